@@ -6,8 +6,11 @@ use bevy::prelude::{
     Window, WindowPlugin,
 };
 
-use egui_chessboard::{ChessUISystems, EguiBoardUIPlugin, HomeMenuUIPlugin, HomeMenuUISystems};
-use games::{components::Game, GameplayPlugin};
+use egui_chessboard::{
+    ChessUISystems, EguiBoardUIPlugin, HomeMenuUIPlugin, HomeMenuUISystems, PieceLibraryFile,
+    SaveFile,
+};
+use games::{components::Game, BotPlugin, GameplayPlugin, VariantDirectory};
 
 fn main() {
     App::default()
@@ -25,6 +28,15 @@ fn main() {
                 ChessUISystems.run_if(any_with_component::<Game>),
             ),
         )
-        .add_plugins((GameplayPlugin, HomeMenuUIPlugin, EguiBoardUIPlugin))
+        // games are saved to the working directory
+        .insert_resource(SaveFile("wildchess-save.ron".into()))
+        .insert_resource(PieceLibraryFile("wildchess-pieces.ron".into()))
+        .insert_resource(VariantDirectory("variants".into()))
+        .add_plugins((
+            GameplayPlugin,
+            BotPlugin,
+            HomeMenuUIPlugin,
+            EguiBoardUIPlugin,
+        ))
         .run();
 }
@@ -8,7 +8,7 @@ use bevy::{
     MinimalPlugins,
 };
 
-use games::{GameplayPlugin, MatchmakingPlugin};
+use games::{BotPlugin, GameplayPlugin, MatchmakingPlugin, VariantDirectory};
 use replication::ReplicationPlugin;
 use transport::server::ServerPlugin as ServerTransportPlugin;
 
@@ -25,10 +25,15 @@ fn main() {
                 ..Default::default()
             },
         ))
+        // variant files are loaded on startup and can be requested by their file name
+        .insert_resource(VariantDirectory(
+            option_env!("VARIANTS_DIR").unwrap_or("variants").into(),
+        ))
         .add_plugins((
             ReplicationPlugin,
             GameplayPlugin,
             MatchmakingPlugin,
+            BotPlugin,
             ServerTransportPlugin {
                 port: option_env!("SERVER_PORT").unwrap_or("7636").to_string(),
                 wt_tokens_port: option_env!("SERVER_TOKENS_PORT")
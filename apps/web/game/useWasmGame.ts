@@ -1,8 +1,22 @@
 import { useState, useMemo, useCallback, useEffect, useRef } from "preact/hooks";
 
 export type NetworkState = "not-connected" | "connected" | "awaiting-game" | "in-game"
-export type GameVariant = "featured-1" | "featured-2" | "featured-3" | "wild"
-export type GameClock = "classical" | "rapid" | "blitz" | "bullet"
+export type GameVariant =
+  | "featured-1"
+  | "featured-2"
+  | "featured-3"
+  | "wild"
+  | "armies"
+  | "horde"
+export type ClockTiming = "increment" | "bronstein" | "delay" | "hourglass" | "per-move"
+// a preset, or a base time and increment (or delay) in seconds
+export type GameClock =
+  | "classical"
+  | "rapid"
+  | "blitz"
+  | "bullet"
+  | { time: number, increment: number, timing: ClockTiming }
+export type BotDifficulty = "easy" | "medium" | "hard"
 
 export type RecvMessage =
   | { kind: 'init' }
@@ -14,18 +28,41 @@ export type RecvMessage =
   | { kind: 'turn', currentTurn: 'white' | 'black' }
   | { kind: 'player-count', count: number }
   | { kind: 'orientation', orientation: 'white' | 'black'}
-  | { kind: 'gameover', winningTeam: 'white' | 'black' }
+  | { kind: 'board-orientation', orientation: 'white' | 'black'}
+  | { kind: 'gameover', winningTeam: 'white' | 'black' | null, description: string | null }
   | { kind: 'clocks', clocks: { white: string, black: string }}
+  | { kind: 'takeback', requestedBy: 'white' | 'black' | null }
+  | { kind: 'draw-offer', offeredBy: 'white' | 'black' | null }
+  | { kind: 'rematch', requestedBy: 'white' | 'black' | null }
+  | { kind: 'can-abort', canAbort: boolean }
+  | { kind: 'history', moves: string[] }
+  | { kind: 'seed', seed: string | null }
+  | { kind: 'premoves', premoves: string[] }
+  | { kind: 'clock-error', message: string | null }
 
 export type SendMessage =
   | { kind: 'init', useDev?: boolean }
-  | { kind: 'online-game', variant: GameVariant | null, clock: GameClock | null }
-  | { kind: 'local-game', variant: GameVariant | null, clock: GameClock | null }
+  | { kind: 'online-game', variant: GameVariant | null, clock: GameClock | null, seed?: string | null }
+  | { kind: 'local-game', variant: GameVariant | null, clock: GameClock | null, seed?: string | null }
+  | { kind: 'bot-game', variant: GameVariant | null, clock: GameClock | null, seed?: string | null, difficulty?: BotDifficulty }
+  | { kind: 'analysis-game', variant: GameVariant | null, clock: GameClock | null, seed?: string | null }
+  | { kind: 'rewind', ply: number }
+  | { kind: 'request-takeback' }
+  | { kind: 'answer-takeback', accept: boolean }
+  | { kind: 'resign' }
+  | { kind: 'abort' }
+  | { kind: 'offer-draw' }
+  | { kind: 'answer-draw', accept: boolean }
+  | { kind: 'request-rematch' }
+  | { kind: 'answer-rematch', accept: boolean }
   | { kind: 'play-move', source: string, target: string }
   | { kind: 'select-promotion', promotionIndex: number }
   | { kind: 'request-targets', source: string }
+  | { kind: 'cancel-premoves' }
+  | { kind: 'flip-board' }
   | { kind: 'remove-board' }
   | { kind: 'leave-game' }
+  | { kind: 'replay-seed' }
 
 export interface GameState {
   currentTurn: "white" | "black"
@@ -34,25 +71,53 @@ export interface GameState {
   icons:  Record<string, string> | null
   targetSquares: string[] | null
   lastMoveSquares: [string, string] | null
+  // the squares of the queued premoves, from and to
+  premoveSquares: string[] | null
   orientation: "white" | "black" | "any"
+  // the side the board is seen from
+  boardOrientation: "white" | "black"
 }
 
 export interface GameActions {
   requestTargets: (source: string) => void
   resetTargets: () => void
   playMove: (source: string, target: string) => boolean
+  cancelPremoves: () => void
+  flipBoard: () => void
 }
 
 export interface GameMenuState {
   netState: NetworkState
   promotionIcons: string[] | null
   winner: "white" | "black" | null
+  // how the game ended, such as "Black wins by resignation"
+  gameResult: string | null
+  takebackRequest: "white" | "black" | null
+  drawOffer: "white" | "black" | null
+  rematchRequest: "white" | "black" | null
+  // whether the game can still be aborted, before the player's first move
+  canAbort: boolean
+  moves: string[]
+  // the seed that generated a wild army, to replay it later
+  seed: string | null
+  // why the requested custom clock was rejected
+  clockError: string | null
 }
 
 export interface GameMenuActions {
-  requestGame: (game: 'online' | 'local', variant: GameVariant | null, clock: GameClock | null) => void
+  requestGame: (game: 'online' | 'local' | 'bot' | 'analysis', variant: GameVariant | null, clock: GameClock | null, seed?: string | null) => void
   leaveGame: () => void
+  // starts the game again with the same variant, opponent and seed
+  replaySeed: () => void
   selectPromotion: (promotionIndex: number) => void
+  requestTakeback: () => void
+  answerTakeback: (accept: boolean) => void
+  resign: () => void
+  abortGame: () => void
+  offerDraw: () => void
+  answerDraw: (accept: boolean) => void
+  requestRematch: () => void
+  answerRematch: (accept: boolean) => void
 }
 
 export interface WasmGameData {
@@ -73,11 +138,21 @@ export default function useWasmGame(useDev: boolean = false): WasmGameData {
   const [currentTurn, setCurrentTurn] = useState<'white' | 'black'>('white');
   const [clocks, setClocks] = useState<{white: string, black: string} | null>(null);
   const [orientation, setOrientation] = useState<"white" | "black">("white");
+  const [boardOrientation, setBoardOrientation] = useState<"white" | "black">("white");
   const [icons, setIcons] = useState<Record<string, string> | null>(null);
   const [targetSquares, setTargetSquares] = useState<string[] | null>(null);
   const [lastMoveSquares, setLastMoveSquares] = useState<[string, string] | null>(null);
+  const [premoveSquares, setPremoveSquares] = useState<string[] | null>(null);
   const [promotionIcons, setPromotionIcons] = useState<string[] | null>(null);
   const [winner, setWinner] = useState<"white" | "black" | null>(null);
+  const [gameResult, setGameResult] = useState<string | null>(null);
+  const [takebackRequest, setTakebackRequest] = useState<"white" | "black" | null>(null);
+  const [drawOffer, setDrawOffer] = useState<"white" | "black" | null>(null);
+  const [rematchRequest, setRematchRequest] = useState<"white" | "black" | null>(null);
+  const [canAbort, setCanAbort] = useState(false);
+  const [moves, setMoves] = useState<string[]>([]);
+  const [seed, setSeed] = useState<string | null>(null);
+  const [clockError, setClockError] = useState<string | null>(null);
 
   const worker = useMemo(() => {
     const worker = new Worker(
@@ -119,18 +194,56 @@ export default function useWasmGame(useDev: boolean = false): WasmGameData {
           setOrientation(event.data.orientation);
           return;
         }
+        case "board-orientation": {
+          setBoardOrientation(event.data.orientation);
+          return;
+        }
         case "require-promotion": {
           setPromotionIcons(event.data.icons);
           return;
         }
         case "gameover": {
           setWinner(event.data.winningTeam);
+          setGameResult(event.data.description);
           return;
         }
         case "clocks": {
           setClocks(event.data.clocks);
           return;
         }
+        case "takeback": {
+          setTakebackRequest(event.data.requestedBy);
+          return;
+        }
+        case "draw-offer": {
+          setDrawOffer(event.data.offeredBy);
+          return;
+        }
+        case "rematch": {
+          setRematchRequest(event.data.requestedBy);
+          return;
+        }
+        case "can-abort": {
+          setCanAbort(event.data.canAbort);
+          return;
+        }
+        case "history": {
+          setMoves(event.data.moves);
+          return;
+        }
+        case "seed": {
+          setSeed(event.data.seed);
+          return;
+        }
+        case "premoves": {
+          const squares = event.data.premoves.flatMap((premove) => [premove.slice(0, 2), premove.slice(2)]);
+          setPremoveSquares(squares.length === 0 ? null : squares);
+          return;
+        }
+        case "clock-error": {
+          setClockError(event.data.message);
+          return;
+        }
         default: {
           assertNever(event.data);
         }
@@ -151,14 +264,18 @@ export default function useWasmGame(useDev: boolean = false): WasmGameData {
     }
   }, [worker, useDev, isInitialized, prevIsInitialized]);
 
-  const requestGame = useCallback((game: 'online' | 'local', variant: GameVariant | null, clock: GameClock | null) => {
-    sendMessage(worker, {kind: `${game}-game`, variant, clock});
+  const requestGame = useCallback((game: 'online' | 'local' | 'bot' | 'analysis', variant: GameVariant | null, clock: GameClock | null, seed: string | null = null) => {
+    sendMessage(worker, {kind: `${game}-game`, variant, clock, seed});
   }, [worker]);
 
   const leaveGame = useCallback(() => {
     sendMessage(worker, {kind: 'leave-game'});
   }, []);
 
+  const replaySeed = useCallback(() => {
+    sendMessage(worker, {kind: 'replay-seed'});
+  }, [worker]);
+
   const requestTargets = useCallback((source: string) => {
     sendMessage(worker, {kind: 'request-targets', source});
   }, [worker])
@@ -172,23 +289,74 @@ export default function useWasmGame(useDev: boolean = false): WasmGameData {
     return targetSquares?.includes(target) ?? false;
   }, [worker]);
 
+  const cancelPremoves = useCallback(() => {
+    sendMessage(worker, {kind: 'cancel-premoves'});
+  }, [worker]);
+
+  const flipBoard = useCallback(() => {
+    sendMessage(worker, {kind: 'flip-board'});
+  }, [worker]);
+
   const selectPromotion = useCallback((promotionIndex: number) => {
     sendMessage(worker, {kind: 'select-promotion', promotionIndex});
   }, [worker]);
 
+  const requestTakeback = useCallback(() => {
+    sendMessage(worker, {kind: 'request-takeback'});
+  }, [worker]);
+
+  const answerTakeback = useCallback((accept: boolean) => {
+    sendMessage(worker, {kind: 'answer-takeback', accept});
+  }, [worker]);
+
+  const resign = useCallback(() => {
+    sendMessage(worker, {kind: 'resign'});
+  }, [worker]);
+
+  const abortGame = useCallback(() => {
+    sendMessage(worker, {kind: 'abort'});
+  }, [worker]);
+
+  const offerDraw = useCallback(() => {
+    sendMessage(worker, {kind: 'offer-draw'});
+  }, [worker]);
+
+  const answerDraw = useCallback((accept: boolean) => {
+    sendMessage(worker, {kind: 'answer-draw', accept});
+  }, [worker]);
+
+  const requestRematch = useCallback(() => {
+    sendMessage(worker, {kind: 'request-rematch'});
+  }, [worker]);
+
+  const answerRematch = useCallback((accept: boolean) => {
+    sendMessage(worker, {kind: 'answer-rematch', accept});
+  }, [worker]);
+
   return {
     boardState: {
-      currentTurn, position, icons, targetSquares, lastMoveSquares, orientation, clocks,
+      currentTurn, position, icons, targetSquares, lastMoveSquares, premoveSquares, orientation, boardOrientation, clocks,
     },
     boardActions: {
-      requestTargets, resetTargets, playMove,
+      requestTargets, resetTargets, playMove, cancelPremoves, flipBoard,
     },
     menuState: {
       netState,
       promotionIcons,
       winner,
+      gameResult,
+      takebackRequest,
+      drawOffer,
+      rematchRequest,
+      canAbort,
+      moves,
+      seed,
+      clockError,
     },
-    menuActions: { requestGame, leaveGame, selectPromotion }
+    menuActions: {
+      requestGame, leaveGame, replaySeed, selectPromotion, requestTakeback, answerTakeback,
+      resign, abortGame, offerDraw, answerDraw, requestRematch, answerRematch,
+    }
   }
 }
 
@@ -9,6 +9,7 @@ interface ChessBoardControls {
   icons: Record<string, string> | null
   orientation: "white" | "black",
   playMove: (source: string, target: string) => boolean,
+  cancelPremoves: () => void,
   requestTargets: (source: string) => void,
   resetTargets: () => void,
 }
@@ -21,12 +22,15 @@ function useChessBoard({
   requestTargets,
   resetTargets,
   playMove,
+  cancelPremoves,
 }: ChessBoardControls): unknown | null {
   const [board, setBoard] = useState(null);
 
+  // clicking an empty square also cancels any premoves
   const handleClickEmptySquare = useCallback(() => {
     resetTargets();
-  }, [resetTargets]);
+    cancelPremoves();
+  }, [resetTargets, cancelPremoves]);
 
   const handleDragStart = useCallback((source: string) => {
     requestTargets(source);
@@ -76,10 +80,12 @@ export default function Board({
   dimensions = [8, 8],
   position,
   icons,
-  orientation,
+  boardOrientation,
   targetSquares,
   lastMoveSquares,
+  premoveSquares,
   playMove,
+  cancelPremoves,
   requestTargets,
   resetTargets,
 }: ChessBoardProps & GameState & GameActions): JSX.Element {
@@ -94,8 +100,9 @@ export default function Board({
     boardRef,
     position,
     icons,
-    orientation: orientation as "white" | "black",
+    orientation: boardOrientation,
     playMove,
+    cancelPremoves,
     requestTargets: selectPiece,
     resetTargets,
   });
@@ -106,6 +113,8 @@ export default function Board({
   useHighlighter(boardRef, board, 'state', targetSquares);
   // and the last move squares
   useHighlighter(boardRef, board, 'state', lastMoveSquares);
+  // and the queued premoves
+  useHighlighter(boardRef, board, 'premove', premoveSquares);
   // and finally the target squares
   // TODO: dots for moves and circles for attacks, instead of backgrounds
   // could use more-transparent circles for unavailable attack squares
@@ -136,9 +145,10 @@ function isSquareLightOrDark(node: Element) {
 const LIGHT_SQUARE_HIGHLIGHT_CLASS = "bg-amber-200";
 const DARK_SQUARE_HIGHLIGHT_CLASS = "bg-amber-300/85";
 const LIGHT_SQUARE_TARGET_CLASS = "bg-blue-200";
+const PREMOVE_CLASS = "bg-violet-300";
 
 type Color = "light" | "dark";
-type HighlightKind = "targets" | "state";
+type HighlightKind = "targets" | "state" | "premove";
 function getHighlightClass(highlight: HighlightKind, square_color: Color): string {
   switch (highlight) {
     case 'state': {
@@ -151,6 +161,9 @@ function getHighlightClass(highlight: HighlightKind, square_color: Color): strin
     case 'targets': {
       return LIGHT_SQUARE_TARGET_CLASS
     }
+    case 'premove': {
+      return PREMOVE_CLASS
+    }
     default: throw new Error("Getting a bad highlight class config: " + highlight);
   }
 }
@@ -20,7 +20,7 @@ export default function GameManager({ description, useDev }: GameManagerProps) {
   }
 
   const { boardState, boardActions, menuState, menuActions } = useWasmGame(useDev);
-  const { clocks, orientation } = boardState;
+  const { clocks, boardOrientation } = boardState;
   const { netState, promotionIcons } = menuState;
   const { requestGame, selectPromotion } =  menuActions;
 
@@ -44,12 +44,19 @@ export default function GameManager({ description, useDev }: GameManagerProps) {
                 )}
             </div>
             {clocks && (
-              <Clock time={clocks[orientation === "white" ? "black" : "white"]} />
+              <Clock time={clocks[boardOrientation === "white" ? "black" : "white"]} />
             )}
             <Board {...boardState} {...boardActions} size={600} />
             {clocks && (
-              <Clock time={clocks[orientation === "any" ? boardState.currentTurn : orientation]} />
+              <Clock time={clocks[boardOrientation]} />
             )}
+            <button
+              type="button"
+              class="shadow rounded p-2 bg-[#fdfbe8]"
+              onClick={() => boardActions.flipBoard()}
+            >
+              Flip Board
+            </button>
           </div>
           <PromotionPieces icons={promotionIcons} selectIcon={selectPromotion} />
           <GameSidebar {...boardState} {...menuState} {...menuActions} />
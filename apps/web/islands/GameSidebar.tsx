@@ -2,32 +2,191 @@ import { JSX } from "preact";
 
 import type { GameState,  GameMenuState, GameMenuActions } from "../game/useWasmGame.ts";
 
-function History() {
+function History({ moves }: Pick<GameMenuState, 'moves'>) {
+  // pair up each white move with the black reply
+  const turns = moves.reduce<string[][]>((turns, move, index) => {
+    if (index % 2 === 0) turns.push([move]);
+    else turns[turns.length - 1].push(move);
+    return turns;
+  }, []);
   return (
     <div>
       <h2 class="text-base font-medium underline">Moves</h2>
-      <p>WIP</p>
+      <ol class="grid grid-cols-[2rem_1fr_1fr] gap-x-2 max-h-48 overflow-y-auto">
+        {turns.map(([white, black], index) => (
+          <li key={index} class="contents">
+            <span class="text-gray-600">{index + 1}.</span>
+            <span class="font-medium">{white}</span>
+            <span class="font-medium">{black ?? ""}</span>
+          </li>
+        ))}
+      </ol>
     </div>
   );
 }
 
-function ActionBar({ leaveGame }: Pick<GameMenuActions, 'leaveGame'>) {
+function Takeback({
+  orientation,
+  takebackRequest,
+  requestTakeback,
+  answerTakeback,
+}: Pick<GameState, 'orientation'> & Pick<GameMenuState, 'takebackRequest'> & Pick<GameMenuActions, 'requestTakeback' | 'answerTakeback'>) {
+  if (takebackRequest === null) {
+    return (
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#fdfbe8]"
+        onClick={() => requestTakeback()}
+      >
+        Takeback
+      </button>
+    );
+  }
+  if (takebackRequest === orientation) {
+    return <p class="italic">Takeback requested...</p>;
+  }
+  return (
+    <div class="flex flex-row gap-1 items-center">
+      <p>{takebackRequest} requests a takeback.</p>
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#efffef]"
+        onClick={() => answerTakeback(true)}
+      >
+        Accept
+      </button>
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#ffefef]"
+        onClick={() => answerTakeback(false)}
+      >
+        Decline
+      </button>
+    </div>
+  );
+}
+
+function Seed({
+  seed,
+  replaySeed,
+}: Pick<GameMenuState, 'seed'> & Pick<GameMenuActions, 'replaySeed'>) {
+  if (seed === null) return null;
+  return (
+    <div class="flex flex-row gap-1 items-center justify-between">
+      <p>Seed: <span class="font-mono select-all">{seed}</span></p>
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#fdfbe8]"
+        onClick={() => replaySeed()}
+      >
+        Replay this seed
+      </button>
+    </div>
+  );
+}
+
+function DrawOffer({
+  orientation,
+  drawOffer,
+  answerDraw,
+}: Pick<GameState, 'orientation'> & Pick<GameMenuState, 'drawOffer'> & Pick<GameMenuActions, 'answerDraw'>) {
+  if (drawOffer === null) return null;
+  if (drawOffer === orientation) {
+    return <p class="italic">Draw offered...</p>;
+  }
+  return (
+    <div class="flex flex-row gap-1 items-center">
+      <p>{drawOffer} offers a draw.</p>
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#efffef]"
+        onClick={() => answerDraw(true)}
+      >
+        Accept
+      </button>
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#ffefef]"
+        onClick={() => answerDraw(false)}
+      >
+        Decline
+      </button>
+    </div>
+  );
+}
+
+function Rematch({
+  orientation,
+  rematchRequest,
+  requestRematch,
+  answerRematch,
+}: Pick<GameState, 'orientation'> & Pick<GameMenuState, 'rematchRequest'> & Pick<GameMenuActions, 'requestRematch' | 'answerRematch'>) {
+  if (rematchRequest === null) {
+    return (
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#fdfbe8]"
+        onClick={() => requestRematch()}
+      >
+        Rematch
+      </button>
+    );
+  }
+  if (rematchRequest === orientation) {
+    return <p class="italic">Rematch requested...</p>;
+  }
+  return (
+    <div class="flex flex-row gap-1 items-center">
+      <p>{rematchRequest} wants a rematch.</p>
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#efffef]"
+        onClick={() => answerRematch(true)}
+      >
+        Accept
+      </button>
+      <button
+        type="button"
+        class="shadow rounded p-2 bg-[#ffefef]"
+        onClick={() => answerRematch(false)}
+      >
+        Decline
+      </button>
+    </div>
+  );
+}
+
+function ActionBar({
+  gameResult,
+  canAbort,
+  leaveGame,
+  resign,
+  abortGame,
+  offerDraw,
+}: Pick<GameMenuState, 'gameResult' | 'canAbort'> & Pick<GameMenuActions, 'leaveGame' | 'resign' | 'abortGame' | 'offerDraw'>) {
+  const isOver = gameResult !== null;
   return (
     <div class="flex flex-row pr-2 justify-between items-center">
       {/* Board actions */}
       <div class="flex flex-row gap-1">
-        {/* Resign */}
+        {/* Resign, or abort before the first move */}
         <button
           type="button"
-          class="shadow rounded p-4 bg-[#fdfbe8] cursor-not-allowed"
-          disabled
-        />
+          class="shadow rounded p-2 bg-[#fdfbe8] disabled:cursor-not-allowed"
+          disabled={isOver}
+          onClick={() => canAbort ? abortGame() : resign()}
+        >
+          {canAbort ? "Abort" : "Resign"}
+        </button>
         {/* Draw offer*/}
         <button
           type="button"
-          class="shadow rounded p-4 bg-[#fdfbe8] cursor-not-allowed"
-          disabled
-        />
+          class="shadow rounded p-2 bg-[#fdfbe8] disabled:cursor-not-allowed"
+          disabled={isOver}
+          onClick={() => offerDraw()}
+        >
+          Offer draw
+        </button>
       </div>
 
       {/* Move history actions */}
@@ -50,7 +209,7 @@ function ActionBar({ leaveGame }: Pick<GameMenuActions, 'leaveGame'>) {
       <button
         type="button"
         class="shadow rounded p-2 bg-[#ffefef]"
-        onClick={() => leaveGame}
+        onClick={() => leaveGame()}
       >
         Leave
       </button>
@@ -80,23 +239,62 @@ function Legend() {
     </div>
   );
 }
-export default function GameSidebar({ orientation, winner, leaveGame }: GameState & GameMenuState & GameMenuActions ): JSX.Element {
-  if (winner) console.log(winner);
+export default function GameSidebar({
+  orientation,
+  gameResult,
+  leaveGame,
+  takebackRequest,
+  requestTakeback,
+  answerTakeback,
+  drawOffer,
+  rematchRequest,
+  canAbort,
+  resign,
+  abortGame,
+  offerDraw,
+  answerDraw,
+  requestRematch,
+  answerRematch,
+  moves,
+  seed,
+  replaySeed,
+}: GameState & GameMenuState & GameMenuActions ): JSX.Element {
   const playerTurnMessage = orientation === "any" ? null : `You are playing ${orientation[0].toUpperCase()}${orientation.slice(1)}.`;
   return (
     <div class="w-[350px] h-min p-4 flex flex-col gap-3 text-sm bg-[#f3edd9] border-2 border-black">
-      <History />
+      <History moves={moves} />
       <hr class="border-black" />
       {playerTurnMessage !== null && (
         <div>
             <h3 class="text-md">{playerTurnMessage}</h3>
         </div>
       )}
-      {winner != null && (<div>
-        <h3 class="text-lg">WINNER: {winner}</h3>
+      {gameResult !== null && (<div>
+        <h3 class="text-lg">{gameResult}</h3>
+        <Rematch
+          orientation={orientation}
+          rematchRequest={rematchRequest}
+          requestRematch={requestRematch}
+          answerRematch={answerRematch}
+        />
       </div>)}
-      {winner != null && (<hr class="border-black" />)}
-      <ActionBar leaveGame={leaveGame} />
+      {gameResult !== null && (<hr class="border-black" />)}
+      <Takeback
+        orientation={orientation}
+        takebackRequest={takebackRequest}
+        requestTakeback={requestTakeback}
+        answerTakeback={answerTakeback}
+      />
+      <DrawOffer orientation={orientation} drawOffer={drawOffer} answerDraw={answerDraw} />
+      <ActionBar
+        gameResult={gameResult}
+        canAbort={canAbort}
+        leaveGame={leaveGame}
+        resign={resign}
+        abortGame={abortGame}
+        offerDraw={offerDraw}
+      />
+      <Seed seed={seed} replaySeed={replaySeed} />
       <hr class="border-black" />
       <Legend />
     </div>
@@ -2,16 +2,28 @@ import { JSX } from "preact";
 import { useState } from "preact/hooks";
 
 import AboutBlurb from "../components/AboutBlurb.tsx";
-import { GameClock, GameMenuActions, GameMenuState, GameVariant } from "../game/useWasmGame.ts";
+import { ClockTiming, GameClock, GameMenuActions, GameMenuState, GameVariant } from "../game/useWasmGame.ts";
 
 import Board from "./Board.tsx";
 
 export default function Lobby({
   netState,
+  clockError,
   requestGame,
 }: GameMenuState & GameMenuActions): JSX.Element {
   const [selectedVariant, setSelectedVariant] = useState<GameVariant | null>(null);
-  const [selectedClock, setSelectedClock] = useState<GameClock | null>(null);
+  const [selectedClock, setSelectedClock] = useState<GameClock | "custom" | null>(null);
+  // the custom time control, in minutes and seconds
+  const [customMinutes, setCustomMinutes] = useState<number>(10);
+  const [customIncrement, setCustomIncrement] = useState<number>(0);
+  const [customTiming, setCustomTiming] = useState<ClockTiming>("increment");
+  const requestedClock: GameClock | null = selectedClock === "custom"
+    ? { time: Math.round(customMinutes * 60), increment: customIncrement, timing: customTiming }
+    : selectedClock;
+  // replays a wild army from its seed; a new one is generated when left empty
+  const [seed, setSeed] = useState<string>("");
+  const isSeeded = selectedVariant === "wild" || selectedVariant === "armies";
+  const requestedSeed = isSeeded && seed.trim() !== "" ? seed.trim() : null;
 
   const isNetDisabled = netState !== 'connected';
   // todo types for empty board
@@ -28,7 +40,7 @@ export default function Lobby({
             role="button"
             class="w-full h-[80px] text-3xl shadow-lg bg-[#6fa6ff] rounded-2xl disabled:opacity-50 disabled:cursor-not-allowed"
             disabled={isNetDisabled}
-            onClick={() => requestGame('online', selectedVariant, selectedClock)}
+            onClick={() => requestGame('online', selectedVariant, requestedClock, requestedSeed)}
           >
             Play Online
           </button>
@@ -41,10 +53,24 @@ export default function Lobby({
         <button
           role="button"
           class="w-full h-[80px] text-3xl shadow-lg bg-[#6fa6ff] rounded-2xl"
-          onClick={() => requestGame('local', selectedVariant, selectedClock)}
+          onClick={() => requestGame('local', selectedVariant, requestedClock, requestedSeed)}
         >
           Play Local
         </button>
+        <button
+          role="button"
+          class="w-full h-[80px] text-3xl shadow-lg bg-[#6fa6ff] rounded-2xl"
+          onClick={() => requestGame('bot', selectedVariant, requestedClock, requestedSeed)}
+        >
+          Play Bot
+        </button>
+        <button
+          role="button"
+          class="w-full h-[80px] text-3xl shadow-lg bg-[#6fa6ff] rounded-2xl"
+          onClick={() => requestGame('analysis', selectedVariant, null, requestedSeed)}
+        >
+          Analysis Board
+        </button>
         <div class="border-2 border-black">
           <div class="text-sm italic m-2 border-b-[1px] border-black">
             <h4>Play featured positions</h4>
@@ -74,6 +100,28 @@ export default function Lobby({
             >
               Wild Position
             </button>
+            <button
+              class={`text-lg shadow rounded-lg py-1 ${selectedClassName(selectedVariant, "armies") ?? DEFAULT_BUTTON_BG}`}
+              onClick={() => setSelectedVariant(setOrToggle<GameVariant>("armies"))}
+            >
+              Different Armies
+            </button>
+            <button
+              class={`text-lg shadow rounded-lg py-1 ${selectedClassName(selectedVariant, "horde") ?? DEFAULT_BUTTON_BG}`}
+              onClick={() => setSelectedVariant(setOrToggle<GameVariant>("horde"))}
+            >
+              Horde
+            </button>
+            {isSeeded && (
+              <input
+                type="text"
+                inputMode="numeric"
+                class="text-sm rounded-lg px-2 py-1 border-[1px] border-black"
+                placeholder="Seed (optional)"
+                value={seed}
+                onInput={(event) => setSeed(event.currentTarget.value)}
+              />
+            )}
           </div>
         </div>
         <div class="border-2 border-black">
@@ -83,16 +131,63 @@ export default function Lobby({
           <div class="flex flex-col gap-2 px-2 pb-2">
             <button
               class={`text-lg shadow rounded-lg py-1 ${selectedClassName(selectedClock, "rapid") ?? DEFAULT_BUTTON_BG}`}
-              onClick={() => setSelectedClock(setOrToggle<GameClock>("rapid"))}
+              onClick={() => setSelectedClock(setOrToggle<GameClock | "custom">("rapid"))}
             >
               Rapid
             </button>
             <button
               class={`text-lg shadow rounded-lg py-1 ${selectedClassName(selectedClock, "blitz") ?? DEFAULT_BUTTON_BG}`}
-              onClick={() => setSelectedClock(setOrToggle<GameClock>("blitz"))}
+              onClick={() => setSelectedClock(setOrToggle<GameClock | "custom">("blitz"))}
             >
               Blitz
             </button>
+            <button
+              class={`text-lg shadow rounded-lg py-1 ${selectedClassName(selectedClock, "custom") ?? DEFAULT_BUTTON_BG}`}
+              onClick={() => setSelectedClock(setOrToggle<GameClock | "custom">("custom"))}
+            >
+              Custom
+            </button>
+            {selectedClock === "custom" && (
+              <div class="flex flex-col gap-1 text-sm">
+                <label class="flex flex-row justify-between gap-2">
+                  Minutes
+                  <input
+                    type="number"
+                    min="0"
+                    step="0.25"
+                    class="w-16 rounded-lg px-2 border-[1px] border-black"
+                    value={customMinutes}
+                    onInput={(event) => setCustomMinutes(Math.max(0, Number(event.currentTarget.value)))}
+                  />
+                </label>
+                {customTiming !== "hourglass" && customTiming !== "per-move" && (
+                  <label class="flex flex-row justify-between gap-2">
+                    {customTiming === "increment" ? "Increment (s)" : "Delay (s)"}
+                    <input
+                      type="number"
+                      min="0"
+                      class="w-16 rounded-lg px-2 border-[1px] border-black"
+                      value={customIncrement}
+                      onInput={(event) => setCustomIncrement(Math.max(0, Math.round(Number(event.currentTarget.value))))}
+                    />
+                  </label>
+                )}
+                <select
+                  class="rounded-lg px-1 border-[1px] border-black"
+                  value={customTiming}
+                  onChange={(event) => setCustomTiming(event.currentTarget.value as ClockTiming)}
+                >
+                  <option value="increment">Increment</option>
+                  <option value="bronstein">Bronstein delay</option>
+                  <option value="delay">Simple delay</option>
+                  <option value="hourglass">Hourglass</option>
+                  <option value="per-move">Per move</option>
+                </select>
+              </div>
+            )}
+            {clockError && (
+              <p class="text-red-500 text-xs">{clockError}</p>
+            )}
           </div>
         </div>
       </div>
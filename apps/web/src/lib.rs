@@ -7,24 +7,34 @@ use wasm_bindgen::prelude::*;
 
 use bevy_app::App;
 use bevy_ecs::{
-    prelude::{Entity, Events, Query, Res, With},
+    prelude::{Entity, Event, Events, Query, Res, ResMut, Resource, With, Without},
     system::RunSystemOnce,
     world::Command,
 };
 
 use games::{
+    can_abort,
     chess::{
         actions::{Actions, LastAction},
+        behavior::BoardThreatsCache,
         board::{Board, Square},
-        pieces::{Mutation, Orientation, PieceIdentity, Position, Royal},
+        pieces::{Mutation, Orientation, PieceIdentity, Position},
         team::Team,
     },
     components::{
-        CurrentTurn, Game, GameBoard, GameOver, GameRequestClock, GameRequestVariant, InGame,
-        Player,
+        ActionHistory, Analysis, ClockConfiguration, CurrentTurn, DrawOffer, Game, GameBoard,
+        GameOver, GameRequestClock, GameRequestVariant, GameSeed, GameSetup, InGame, PieceSet,
+        Player, Ply, Premove, Premoves, RematchRequest, TakebackRequest, VariationTree,
+        WinCondition,
     },
-    Clock, GameOpponent, GameplayPlugin, LeaveGameEvent, MatchmakingPlugin, RequestJoinGameEvent,
-    RequestTurnEvent, RequireMutationEvent,
+    format_duration,
+    layouts::{ShuffleRules, WildConfig, WildPromotion, WildTiers},
+    snapshot_position, AbortGameEvent, AnswerDrawEvent, AnswerRematchEvent, AnswerTakebackEvent,
+    BoardEdit, Bot, BotDifficulty, BotPlugin, Clock, ClockRequestFailedEvent, EditBoardEvent,
+    GameOpponent, GameRecord, GameplayPlugin, ImportGameFailedEvent, LeaveGameEvent,
+    MatchmakingPlugin, OfferDrawEvent, PieceSnapshotQuery, RequestImportGameEvent,
+    RequestJoinGameEvent, RequestRematchEvent, RequestRewindEvent, RequestTakebackEvent,
+    RequestTurnEvent, RequireMutationEvent, ResignGameEvent, Timing,
 };
 use replication::{
     replicon::{
@@ -76,8 +86,10 @@ impl WasmApp {
             ReplicationPlugin,
             GameplayPlugin,
             MatchmakingPlugin,
+            BotPlugin,
             ClientTransportPlugin,
         ));
+        app.init_resource::<BoardView>();
         app.add_plugins(wild_icons::PieceIconPlugin::new(get_orientation));
 
         WasmApp(app)
@@ -114,6 +126,28 @@ impl WasmApp {
         self.request_game(game_request, GameOpponent::Local);
     }
 
+    // difficulty is one of "easy", "medium" or "hard"
+    #[wasm_bindgen]
+    pub fn start_bot_game(&mut self, game_request: WasmGameRequest, difficulty: String) {
+        ClientCommand::Disconnect.apply(self.0.world_mut());
+        #[cfg(feature = "log")]
+        log("Client disconnected!".to_string());
+        let difficulty = match difficulty.as_str() {
+            "easy" => BotDifficulty::Easy,
+            "hard" => BotDifficulty::Hard,
+            _ => BotDifficulty::Medium,
+        };
+        self.request_game(game_request, GameOpponent::AgainstBot(difficulty));
+    }
+
+    #[wasm_bindgen]
+    pub fn start_analysis_game(&mut self, game_request: WasmGameRequest) {
+        ClientCommand::Disconnect.apply(self.0.world_mut());
+        #[cfg(feature = "log")]
+        log("Client disconnected!".to_string());
+        self.request_game(game_request, GameOpponent::Analysis);
+    }
+
     fn request_game(&mut self, game_request: WasmGameRequest, opponent: GameOpponent) {
         #[cfg(feature = "log")]
         log("Requesting game!".to_string());
@@ -121,9 +155,50 @@ impl WasmApp {
             opponent,
             game: game_request.variant,
             clock: game_request.clock,
+            seed: game_request.seed,
         });
     }
 
+    // Requests an analysis game replaying a PGN, returning why it cannot be imported if it fails
+    #[wasm_bindgen]
+    pub fn import_pgn(&mut self, pgn: String) -> Option<String> {
+        if let Err(error) = GameRecord::import(&pgn) {
+            return Some(error.to_string());
+        }
+        self.0
+            .world_mut()
+            .send_event(RequestImportGameEvent { pgn });
+        None
+    }
+
+    // Why the server could not import the last requested PGN, if it failed
+    #[wasm_bindgen]
+    pub fn get_import_error(&mut self) -> Option<String> {
+        let failed_events = self
+            .0
+            .world()
+            .get_resource::<Events<ImportGameFailedEvent>>()?;
+        let mut reader = failed_events.get_reader();
+        reader
+            .read(failed_events)
+            .last()
+            .map(|event| event.reason.clone())
+    }
+
+    // Why the server rejected the clock of the last requested game, if it did
+    #[wasm_bindgen]
+    pub fn get_clock_request_error(&mut self) -> Option<String> {
+        let failed_events = self
+            .0
+            .world()
+            .get_resource::<Events<ClockRequestFailedEvent>>()?;
+        let mut reader = failed_events.get_reader();
+        reader
+            .read(failed_events)
+            .last()
+            .map(|event| event.error.to_string())
+    }
+
     #[wasm_bindgen]
     pub fn leave_game(&mut self) {
         let mut query = self.0.world_mut().query_filtered::<Entity, With<Game>>();
@@ -153,7 +228,8 @@ impl WasmApp {
         query
             .iter(self.0.world())
             .map(|(_, gameover)| WasmGameover {
-                team: *gameover.winner(),
+                team: gameover.winner().copied(),
+                description: gameover.to_string(),
             })
             .next()
     }
@@ -252,31 +328,16 @@ Pieces ({pieces_count}): {pieces:?}"#
     // specifically, returns either "white" or "black"
     // TODO: be less "stringly typed" in a useful way?
     pub fn get_my_team(&mut self) -> Option<String> {
-        let client_id = self
-            .0
-            .world()
-            .get_resource::<RepliconClient>()
-            .and_then(|client| client.id())
-            .unwrap_or(ClientId::SERVER);
+        let controlled_teams = self.controlled_teams();
 
-        let mut query = self
+        // analysis games are played for both teams
+        let mut analysis_query = self
             .0
             .world_mut()
-            .query_filtered::<(&Team, Option<&Client>), With<Player>>();
-        let controlled_teams = query
-            .iter(self.0.world())
-            .filter(|(_, player)| {
-                player.map(|client| client.id).unwrap_or(ClientId::SERVER) == client_id
-            })
-            .map(|(team, _)| *team)
-            .collect::<Vec<_>>();
+            .query_filtered::<(), (With<Game>, With<Analysis>)>();
+        let is_analysis = analysis_query.iter(self.0.world()).count() > 0;
 
-        #[cfg(feature = "log")]
-        log(format!(
-            "ClientID {client_id:?} controls teams {controlled_teams:?}"
-        ));
-
-        if controlled_teams.len() > 1 {
+        if controlled_teams.len() > 1 || (is_analysis && !controlled_teams.is_empty()) {
             Some("any".to_string())
         } else if controlled_teams.len() == 1 {
             let team = match controlled_teams.first().unwrap() {
@@ -289,6 +350,24 @@ Pieces ({pieces_count}): {pieces:?}"#
         }
     }
 
+    // Turns the board around, away from the local player's side or back to it
+    #[wasm_bindgen]
+    pub fn flip_board(&mut self) {
+        let mut view = self.0.world_mut().resource_mut::<BoardView>();
+        view.flipped = !view.flipped;
+    }
+
+    #[wasm_bindgen]
+    // the side the board is seen from, either "white" or "black"
+    pub fn get_board_orientation(&mut self) -> String {
+        // the board is only drawn from White's or Black's side
+        match self.0.world().resource::<BoardView>().orientation {
+            Orientation::Down => "black",
+            _ => "white",
+        }
+        .to_string()
+    }
+
     #[wasm_bindgen]
     // specifically, returns either "white" or "black"
     // TODO: be less "stringly typed" in a useful way?
@@ -346,6 +425,9 @@ Pieces ({pieces_count}): {pieces:?}"#
             .map(|(team, clock)| WasmClock {
                 team: *team,
                 clock: clock.remaining_time(),
+                delay: clock.remaining_delay(),
+                moves_to_next_stage: clock.moves_to_next_stage(),
+                time_control: clock.time_control(),
             })
             .collect::<Vec<_>>()
     }
@@ -367,6 +449,337 @@ Pieces ({pieces_count}): {pieces:?}"#
         )
     }
 
+    // team is either "white" or "black"
+    #[wasm_bindgen]
+    pub fn get_threatened_squares(&mut self, team: String) -> Vec<WasmSquare> {
+        let team = match team.as_str() {
+            "black" => Team::Black,
+            _ => Team::White,
+        };
+        let mut query = self.0.world_mut().query::<&BoardThreatsCache>();
+        query
+            .iter(self.0.world())
+            .flat_map(|threats| threats.threatened_squares(team))
+            .map(WasmSquare)
+            .collect()
+    }
+
+    #[wasm_bindgen]
+    pub fn get_ply(&mut self) -> Option<usize> {
+        let mut query = self.0.world_mut().query_filtered::<&Ply, With<Game>>();
+        query.get_single(self.0.world()).map(Ply::get).ok()
+    }
+
+    // Continues an analysis game from an earlier ply
+    #[wasm_bindgen]
+    pub fn rewind_to_ply(&mut self, ply: usize) {
+        let mut query = self
+            .0
+            .world_mut()
+            .query_filtered::<Entity, With<Analysis>>();
+        let Ok(game) = query.get_single(self.0.world()) else {
+            return;
+        };
+        self.0.world_mut().send_event(RequestRewindEvent {
+            game,
+            ply: Ply::new(ply),
+        });
+    }
+
+    #[wasm_bindgen]
+    pub fn request_takeback(&mut self) {
+        let mut query = self.0.world_mut().query_filtered::<Entity, With<Game>>();
+        let Ok(game) = query.get_single(self.0.world()) else {
+            return;
+        };
+        self.0.world_mut().send_event(RequestTakebackEvent { game });
+    }
+
+    #[wasm_bindgen]
+    pub fn answer_takeback(&mut self, accept: bool) {
+        let mut query = self.0.world_mut().query_filtered::<Entity, With<Game>>();
+        let Ok(game) = query.get_single(self.0.world()) else {
+            return;
+        };
+        self.0
+            .world_mut()
+            .send_event(AnswerTakebackEvent { game, accept });
+    }
+
+    // the team asking for a takeback, either "white" or "black"
+    #[wasm_bindgen]
+    pub fn get_takeback_request(&mut self) -> Option<String> {
+        let mut query = self.0.world_mut().query::<&TakebackRequest>();
+        query
+            .get_single(self.0.world())
+            .map(|request| format!("{:?}", request.team).to_lowercase())
+            .ok()
+    }
+
+    #[wasm_bindgen]
+    pub fn resign(&mut self) {
+        self.send_game_event(|game| ResignGameEvent { game });
+    }
+
+    #[wasm_bindgen]
+    pub fn abort_game(&mut self) {
+        self.send_game_event(|game| AbortGameEvent { game });
+    }
+
+    // whether one of the local player's teams has yet to make its first move
+    #[wasm_bindgen]
+    pub fn can_abort(&mut self) -> bool {
+        let controlled_teams = self.controlled_teams();
+        let mut query = self
+            .0
+            .world_mut()
+            .query_filtered::<(&ActionHistory, &CurrentTurn), (With<Game>, Without<GameOver>)>();
+        let Ok((history, current_turn)) = query.get_single(self.0.world()) else {
+            return false;
+        };
+        controlled_teams
+            .into_iter()
+            .any(|team| can_abort(team, history, current_turn))
+    }
+
+    #[wasm_bindgen]
+    pub fn offer_draw(&mut self) {
+        self.send_game_event(|game| OfferDrawEvent { game });
+    }
+
+    #[wasm_bindgen]
+    pub fn answer_draw(&mut self, accept: bool) {
+        self.send_game_event(|game| AnswerDrawEvent { game, accept });
+    }
+
+    // the team offering a draw, either "white" or "black"
+    #[wasm_bindgen]
+    pub fn get_draw_offer(&mut self) -> Option<String> {
+        let mut query = self.0.world_mut().query::<&DrawOffer>();
+        query
+            .get_single(self.0.world())
+            .map(|offer| format!("{:?}", offer.team).to_lowercase())
+            .ok()
+    }
+
+    #[wasm_bindgen]
+    pub fn request_rematch(&mut self) {
+        self.send_game_event(|game| RequestRematchEvent { game });
+    }
+
+    #[wasm_bindgen]
+    pub fn answer_rematch(&mut self, accept: bool) {
+        self.send_game_event(|game| AnswerRematchEvent { game, accept });
+    }
+
+    // the team asking for a rematch, either "white" or "black"
+    #[wasm_bindgen]
+    pub fn get_rematch_request(&mut self) -> Option<String> {
+        let mut query = self.0.world_mut().query::<&RematchRequest>();
+        query
+            .get_single(self.0.world())
+            .map(|request| format!("{:?}", request.team).to_lowercase())
+            .ok()
+    }
+
+    // the seed that generated the game's random pieces, written in decimal
+    #[wasm_bindgen]
+    pub fn get_seed(&mut self) -> Option<String> {
+        let mut query = self.0.world_mut().query_filtered::<&GameSeed, With<Game>>();
+        query
+            .get_single(self.0.world())
+            .map(|seed| seed.seed.to_string())
+            .ok()
+    }
+
+    // The request with the variant, wild config and seed of the current game, to replay its armies
+    #[wasm_bindgen]
+    pub fn replay_request(&mut self, mut game_request: WasmGameRequest) -> WasmGameRequest {
+        let mut query = self.0.world_mut().query_filtered::<&GameSeed, With<Game>>();
+        if let Ok(seed) = query.get_single(self.0.world()) {
+            game_request.variant = Some(seed.variant.clone());
+            game_request.seed = Some(seed.seed);
+        }
+        game_request
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_piece(&mut self, square: String) {
+        let Ok(square) = Square::try_from(square.as_str()) else {
+            return;
+        };
+        self.edit_board(BoardEdit::RemovePiece(square));
+    }
+
+    #[wasm_bindgen]
+    pub fn switch_turn(&mut self) {
+        let Some(current_turn) = self.current_turn() else {
+            return;
+        };
+        let team = match current_turn.as_str() {
+            "white" => Team::Black,
+            _ => Team::White,
+        };
+        self.edit_board(BoardEdit::SetTurn(team));
+    }
+
+    // the teams played by this client in the current game
+    fn controlled_teams(&mut self) -> Vec<Team> {
+        let client_id = self
+            .0
+            .world()
+            .get_resource::<RepliconClient>()
+            .and_then(|client| client.id())
+            .unwrap_or(ClientId::SERVER);
+
+        let mut query = self
+            .0
+            .world_mut()
+            .query_filtered::<(&Team, Option<&Client>), (With<Player>, Without<Bot>)>();
+        let controlled_teams = query
+            .iter(self.0.world())
+            .filter(|(_, player)| {
+                player.map(|client| client.id).unwrap_or(ClientId::SERVER) == client_id
+            })
+            .map(|(team, _)| *team)
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "log")]
+        log(format!(
+            "ClientID {client_id:?} controls teams {controlled_teams:?}"
+        ));
+
+        controlled_teams
+    }
+
+    fn send_game_event<E: Event>(&mut self, event: impl FnOnce(Entity) -> E) {
+        let mut query = self.0.world_mut().query_filtered::<Entity, With<Game>>();
+        let Ok(game) = query.get_single(self.0.world()) else {
+            return;
+        };
+        self.0.world_mut().send_event(event(game));
+    }
+
+    fn edit_board(&mut self, edit: BoardEdit) {
+        let mut query = self
+            .0
+            .world_mut()
+            .query_filtered::<Entity, With<Analysis>>();
+        let Ok(game) = query.get_single(self.0.world()) else {
+            return;
+        };
+        self.0.world_mut().send_event(EditBoardEvent { game, edit });
+    }
+
+    // The turns leading to the current position, in SAN-style notation
+    #[wasm_bindgen]
+    pub fn get_move_history(&mut self) -> Vec<String> {
+        let mut query = self.0.world_mut().query::<&VariationTree>();
+        let Ok(tree) = query.get_single(self.0.world()) else {
+            return vec![];
+        };
+        tree.path_to(tree.current())
+            .into_iter()
+            .filter_map(|node| tree.get(node))
+            .filter_map(|node| {
+                node.notation.clone().or_else(|| {
+                    node.action
+                        .as_ref()
+                        .map(|action| format!("{}{}", action.movement.from, action.movement.to))
+                })
+            })
+            .collect()
+    }
+
+    // The current game written as PGN
+    #[wasm_bindgen]
+    pub fn export_pgn(&mut self) -> Option<String> {
+        let mut query = self.0.world_mut().query::<(
+            &PieceSet,
+            &GameBoard,
+            Option<&GameSetup>,
+            &WinCondition,
+            Option<&ClockConfiguration>,
+            Option<&GameOver>,
+            &VariationTree,
+        )>();
+        let (piece_set, board, setup, win_condition, clock, game_over, tree) =
+            query.get_single(self.0.world()).ok()?;
+        let record = GameRecord::from_game(
+            piece_set,
+            board,
+            setup,
+            win_condition,
+            clock,
+            game_over,
+            tree,
+        );
+        Some(record.to_string())
+    }
+
+    // Requests a turn written in SAN-style notation, such as `Nf3`, `exd5` or `e8=Q`
+    #[wasm_bindgen]
+    pub fn trigger_move_notation(&mut self, notation: String) -> bool {
+        let world = self.0.world_mut();
+        let mut game_query =
+            world.query_filtered::<(
+                Entity,
+                &CurrentTurn,
+                &Ply,
+                Option<&LastAction>,
+                &PieceSet,
+            ), With<Game>>();
+        let mut board_query = world.query::<(&Board, &InGame)>();
+        let mut piece_query = world.query::<PieceSnapshotQuery>();
+        let mut actions_query = world.query::<&Actions>();
+        let world = self.0.world();
+
+        let Ok((game, current_turn, ply, last_action, piece_set)) = game_query.get_single(world)
+        else {
+            return false;
+        };
+        let Some((board, _)) = board_query
+            .iter(world)
+            .find(|(_, in_game)| in_game.0 == game)
+        else {
+            return false;
+        };
+        let position = snapshot_position(
+            board,
+            current_turn,
+            ply,
+            last_action,
+            piece_query
+                .iter(world)
+                .filter(|piece| piece.in_game.0 == game),
+        );
+        let turn = match piece_set.notation().parse(&position, &notation) {
+            Ok(turn) => turn,
+            Err(_err) => {
+                #[cfg(feature = "log")]
+                error(format!("Warning! {_err}"));
+                return false;
+            }
+        };
+        // request the action calculated by the game rather than the copy found in the position
+        let Some(action) = actions_query
+            .get(world, turn.piece)
+            .ok()
+            .and_then(|actions| actions.get(&turn.action.movement.to))
+            .cloned()
+        else {
+            return false;
+        };
+
+        self.0.world_mut().send_event(RequestTurnEvent {
+            piece: turn.piece,
+            game,
+            action,
+            promotion: turn.mutation,
+        });
+        true
+    }
+
     // Vec should be size 2
     #[wasm_bindgen]
     pub fn get_last_move(&mut self) -> Option<Vec<WasmSquare>> {
@@ -446,6 +859,93 @@ Pieces ({pieces_count}): {pieces:?}"#
         true
     }
 
+    // Queues a move of one of the player's pieces to be played once it is their turn.
+    // The target is only checked against the piece's actions when the premove is played,
+    // and a piece that promotes becomes its option at `promotion_index`.
+    #[wasm_bindgen]
+    pub fn queue_premove(
+        &mut self,
+        piece_square: String,
+        target_square: String,
+        promotion_index: Option<usize>,
+    ) -> bool {
+        let (Ok(from), Ok(to)) = (
+            Square::try_from(piece_square.as_str()),
+            Square::try_from(target_square.as_str()),
+        ) else {
+            return false;
+        };
+        let mut query = self
+            .0
+            .world_mut()
+            .query::<(Entity, &Position, &Team, Option<&Mutation>, &InGame)>();
+        let Some((piece, _, team, maybe_mutations, in_game)) = query
+            .iter(self.0.world())
+            .find(|(_, position, _, _, _)| position.0 == from)
+        else {
+            return false;
+        };
+        let premove = Premove {
+            piece,
+            team: *team,
+            from,
+            to,
+            promotion: maybe_mutations
+                .zip(promotion_index)
+                .and_then(|(mutation, index)| mutation.to_piece.get(index).cloned()),
+        };
+        let game = in_game.0;
+
+        // only pieces of the player's teams that are waiting for their turn can be premoved
+        if !self.controlled_teams().contains(&premove.team)
+            || self
+                .0
+                .world()
+                .get::<CurrentTurn>(game)
+                .is_some_and(|current_turn| current_turn.0 == premove.team)
+        {
+            return false;
+        }
+
+        let Some(mut game) = self.0.world_mut().get_entity_mut(game) else {
+            return false;
+        };
+        match game.get_mut::<Premoves>() {
+            Some(mut premoves) => premoves.push(premove),
+            None => {
+                let mut premoves = Premoves::default();
+                premoves.push(premove);
+                game.insert(premoves);
+            }
+        }
+        true
+    }
+
+    #[wasm_bindgen]
+    pub fn cancel_premoves(&mut self) {
+        let mut query = self
+            .0
+            .world_mut()
+            .query_filtered::<Entity, With<Premoves>>();
+        let games = query.iter(self.0.world()).collect::<Vec<_>>();
+        for game in games {
+            self.0.world_mut().entity_mut(game).remove::<Premoves>();
+        }
+    }
+
+    // the queued premoves in coordinate notation, such as "e2e4"
+    #[wasm_bindgen]
+    pub fn get_premoves(&mut self) -> Vec<String> {
+        let mut query = self.0.world_mut().query::<&Premoves>();
+        let Ok(premoves) = query.get_single(self.0.world()) else {
+            return vec![];
+        };
+        premoves
+            .iter()
+            .map(|premove| format!("{}{}", premove.from, premove.to))
+            .collect()
+    }
+
     #[wasm_bindgen]
     pub fn select_promotion(&mut self, promotions: WasmPromotions, promotion_index: usize) -> bool {
         self.trigger_move(
@@ -465,10 +965,11 @@ Pieces ({pieces_count}): {pieces:?}"#
         let mut reader = mutation_request_events.get_reader();
         // should only be one...
         let event = reader.read(mutation_request_events).last();
+        // the icons are drawn as the pieces on the board are, from the side it is seen from
+        let board_orientation = self.0.world().resource::<BoardView>().orientation;
         event.and_then(|event| {
             let mutation = self.0.world().get::<Mutation>(event.piece)?;
             let team = self.0.world().get::<Team>(event.piece)?;
-            let maybe_royal = self.0.world().get::<Royal>(event.piece);
             let icons = mutation
                 .to_piece
                 .iter()
@@ -480,8 +981,9 @@ Pieces ({pieces_count}): {pieces:?}"#
                         option.behaviors.pattern.as_ref(),
                         option.behaviors.relay.as_ref(),
                         *team,
-                        Orientation::Up,
-                        maybe_royal.is_some(),
+                        board_orientation,
+                        // the promoted piece is crowned only if it is royal itself
+                        option.royal.is_some(),
                     )
                     .source
                 })
@@ -599,6 +1101,9 @@ impl WasmPromotions {
 pub struct WasmClock {
     team: Team,
     clock: Duration,
+    delay: Option<Duration>,
+    moves_to_next_stage: Option<u32>,
+    time_control: String,
 }
 
 #[wasm_bindgen]
@@ -614,10 +1119,25 @@ impl WasmClock {
 
     #[wasm_bindgen]
     pub fn remaining_time(&self) -> String {
-        let total_seconds = self.clock.as_secs();
-        let minutes = total_seconds / 60;
-        let seconds = total_seconds % 60;
-        format!("{}:{:02}", minutes, seconds)
+        format_duration(self.clock)
+    }
+
+    // the seconds left of the turn's delay, before the clock counts down
+    #[wasm_bindgen]
+    pub fn remaining_delay(&self) -> Option<f64> {
+        self.delay.map(|delay| delay.as_secs_f64())
+    }
+
+    // the turns left before the next stage of a multi-stage time control
+    #[wasm_bindgen]
+    pub fn moves_to_next_stage(&self) -> Option<u32> {
+        self.moves_to_next_stage
+    }
+
+    // such as "40 moves in 90:00, then 30:00 + 30s"
+    #[wasm_bindgen]
+    pub fn time_control(&self) -> String {
+        self.time_control.clone()
     }
 }
 
@@ -626,6 +1146,7 @@ impl WasmClock {
 pub struct WasmGameRequest {
     pub(crate) variant: Option<GameRequestVariant>,
     pub(crate) clock: Option<GameRequestClock>,
+    pub(crate) seed: Option<u64>,
 }
 
 #[wasm_bindgen]
@@ -655,7 +1176,65 @@ impl WasmGameRequest {
 
     #[wasm_bindgen]
     pub fn with_wild_game(mut self) -> Self {
-        self.variant = Some(GameRequestVariant::Wild);
+        self.variant = Some(GameRequestVariant::Wild(WildConfig::default()));
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_configured_wild_game(mut self, config: WasmWildConfig) -> Self {
+        self.variant = Some(GameRequestVariant::Wild(config.0));
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_different_armies_game(mut self, config: WasmWildConfig) -> Self {
+        self.variant = Some(GameRequestVariant::DifferentArmies(config.0));
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_horde_game(mut self) -> Self {
+        self.variant = Some(GameRequestVariant::Horde);
+        self
+    }
+
+    // A variant loaded by the server from its variant files,
+    // so only online games can be requested by id
+    #[wasm_bindgen]
+    pub fn with_registered_game(mut self, id: String) -> Self {
+        self.variant = Some(GameRequestVariant::Registered(id));
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_fischer_random_game(mut self, position: u16) -> Self {
+        self.variant = Some(GameRequestVariant::FischerRandom(position));
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_shuffled_wild_game(
+        mut self,
+        position: u16,
+        royal_between_castling_targets: bool,
+        minor2_opposite_colors: bool,
+        config: WasmWildConfig,
+    ) -> Self {
+        self.variant = Some(GameRequestVariant::ShuffledWild(
+            position,
+            ShuffleRules {
+                royal_between_castling_targets,
+                minor2_opposite_colors,
+            },
+            config.0,
+        ));
+        self
+    }
+
+    // seeds are written in decimal, since they may not fit in a javascript number
+    #[wasm_bindgen]
+    pub fn with_seed(mut self, seed: String) -> Self {
+        self.seed = seed.trim().parse().ok();
         self
     }
 
@@ -682,36 +1261,169 @@ impl WasmGameRequest {
         self.clock = Some(GameRequestClock::Bullet);
         self
     }
+
+    // timing is one of "increment", "bronstein", "delay", "hourglass" or "per-move";
+    // the increment is the delay of the delay timings and is ignored by the others
+    #[wasm_bindgen]
+    pub fn with_custom_clock(
+        mut self,
+        time_secs: u32,
+        increment_secs: u32,
+        timing: String,
+    ) -> Self {
+        let increment = Duration::from_secs(increment_secs.into());
+        let timing = match timing.as_str() {
+            "bronstein" => Timing::Bronstein(increment),
+            "delay" => Timing::Delay(increment),
+            "hourglass" => Timing::Hourglass,
+            "per-move" => Timing::PerMove,
+            _ => Timing::Increment(increment),
+        };
+        self.clock = Some(GameRequestClock::custom(
+            Duration::from_secs(time_secs.into()),
+            timing,
+        ));
+        self
+    }
+
+    // Why the server would reject the requested clock, if it would
+    #[wasm_bindgen]
+    pub fn clock_error(&self) -> Option<String> {
+        self.clock?.validate().err().map(|error| error.to_string())
+    }
 }
 
 #[wasm_bindgen]
 pub struct WasmGameover {
-    team: Team,
+    team: Option<Team>,
+    description: String,
 }
 
 #[wasm_bindgen]
 impl WasmGameover {
+    // the winning team, or nothing for draws and aborted games
     #[wasm_bindgen]
-    pub fn get_team(&self) -> String {
-        match self.team {
-            Team::White => "white",
-            Team::Black => "black",
-        }
-        .to_string()
+    pub fn get_team(&self) -> Option<String> {
+        self.team.map(|team| {
+            match team {
+                Team::White => "white",
+                Team::Black => "black",
+            }
+            .to_string()
+        })
+    }
+
+    // how the game ended, such as "Black wins by resignation"
+    #[wasm_bindgen]
+    pub fn get_description(&self) -> String {
+        self.description.clone()
     }
 }
 
+// The side the board is seen from
+#[derive(Clone, Copy, Debug, Default)]
+#[derive(Resource)]
+struct BoardView {
+    // turns the board around from the local player's side
+    flipped: bool,
+    orientation: Orientation,
+}
+
+// The board is seen from the side of the only team the client plays,
+// or from White's side when it plays both teams.
 fn get_orientation(
+    mut view: ResMut<BoardView>,
     client: Option<Res<RepliconClient>>,
-    players: Query<(&Team, Option<&Client>)>,
+    players: Query<(&Orientation, Option<&Client>), (With<Player>, Without<Bot>)>,
 ) -> Orientation {
-    if let Some((team, _)) = client.and_then(|client| client.id()).and_then(|client_id| {
-        players.iter().find(|(_, player)| {
+    let client_id = client
+        .and_then(|client| client.id())
+        .unwrap_or(ClientId::SERVER);
+    let mut local_players = players
+        .iter()
+        .filter(|(_, player)| {
             player.map(|client| client.id).unwrap_or(ClientId::SERVER) == client_id
         })
-    }) {
-        team.orientation()
+        .map(|(orientation, _)| *orientation);
+    let orientation = match (local_players.next(), local_players.next()) {
+        (Some(orientation), None) => orientation,
+        _ => Orientation::Up,
+    };
+    view.orientation = if view.flipped {
+        orientation.flip()
     } else {
-        Orientation::Up
+        orientation
+    };
+    view.orientation
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WasmWildConfig(WildConfig);
+
+#[wasm_bindgen]
+impl WasmWildConfig {
+    #[wasm_bindgen]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the range of the army's value, in percent of the classical army
+    #[wasm_bindgen]
+    pub fn with_budget(mut self, min_budget: u16, max_budget: u16) -> Self {
+        self.0.min_budget = min_budget;
+        self.0.max_budget = max_budget;
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_tiers(
+        mut self,
+        infantry: bool,
+        minor: bool,
+        advanced: bool,
+        major: bool,
+        elite: bool,
+        legendary: bool,
+    ) -> Self {
+        self.0.tiers = WildTiers {
+            infantry,
+            minor,
+            advanced,
+            major,
+            elite,
+            legendary,
+        };
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_random_pawns(mut self, random_pawns: bool) -> Self {
+        self.0.random_pawns = random_pawns;
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_random_kings(mut self, random_kings: bool) -> Self {
+        self.0.random_kings = random_kings;
+        self
+    }
+
+    #[wasm_bindgen]
+    pub fn with_slots(mut self, slots: u8) -> Self {
+        self.0.slots = slots;
+        self
+    }
+
+    // promotion is one of "army", "elite", "classical" or "none"
+    #[wasm_bindgen]
+    pub fn with_promotion(mut self, promotion: String) -> Self {
+        self.0.promotion = match promotion.as_str() {
+            "elite" => WildPromotion::Elite,
+            "classical" => WildPromotion::Classical,
+            "none" => WildPromotion::None,
+            _ => WildPromotion::Army,
+        };
+        self
     }
 }
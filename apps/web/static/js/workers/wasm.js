@@ -33,15 +33,91 @@ function onMessage(event) {
             const gameRequest = makeGameRequest(
                 event.data.variant,
                 event.data.clock,
+                event.data.seed,
             );
+            if (gameRequest == null) {
+                return;
+            }
+            postMessage({ kind: "clock-error", message: null });
+            lastGameRequest = event.data;
             app.start_local_game(gameRequest);
             return;
         }
+        case "bot-game": {
+            const gameRequest = makeGameRequest(
+                event.data.variant,
+                event.data.clock,
+                event.data.seed,
+            );
+            if (gameRequest == null) {
+                return;
+            }
+            postMessage({ kind: "clock-error", message: null });
+            lastGameRequest = event.data;
+            app.start_bot_game(gameRequest, event.data.difficulty ?? "medium");
+            return;
+        }
+        case "analysis-game": {
+            const gameRequest = makeGameRequest(
+                event.data.variant,
+                event.data.clock,
+                event.data.seed,
+            );
+            if (gameRequest == null) {
+                return;
+            }
+            postMessage({ kind: "clock-error", message: null });
+            lastGameRequest = event.data;
+            app.start_analysis_game(gameRequest);
+            return;
+        }
+        case "request-takeback": {
+            app.request_takeback();
+            return;
+        }
+        case "answer-takeback": {
+            app.answer_takeback(event.data.accept);
+            return;
+        }
+        case "resign": {
+            app.resign();
+            return;
+        }
+        case "abort": {
+            app.abort_game();
+            return;
+        }
+        case "offer-draw": {
+            app.offer_draw();
+            return;
+        }
+        case "answer-draw": {
+            app.answer_draw(event.data.accept);
+            return;
+        }
+        case "request-rematch": {
+            app.request_rematch();
+            return;
+        }
+        case "answer-rematch": {
+            app.answer_rematch(event.data.accept);
+            return;
+        }
+        case "rewind": {
+            app.rewind_to_ply(event.data.ply);
+            return;
+        }
         case "online-game": {
             const gameRequest = makeGameRequest(
                 event.data.variant,
                 event.data.clock,
+                event.data.seed,
             );
+            if (gameRequest == null) {
+                return;
+            }
+            postMessage({ kind: "clock-error", message: null });
+            lastGameRequest = event.data;
             app.request_online_game(gameRequest);
             postMessage({ kind: "network-state", state: "awaiting-game" });
             return;
@@ -61,23 +137,53 @@ function onMessage(event) {
             currentPosition = null;
             lastMove = null;
             currentIcons = null;
+            moveHistory = [];
+            currentSeed = null;
+            premoves = [];
             return;
         }
         case "leave-game": {
-            app.leave_game();
-            app.update();
-            postMessage({
-                kind: "position",
-                position: null,
-                lastMove: null,
-            });
-            // reset state
-            connected = false;
-            inGame = false;
-            myTeam = "white";
-            currentPosition = null;
-            lastMove = null;
-            currentIcons = null;
+            leaveGame();
+            return;
+        }
+        case "replay-seed": {
+            // the same kind of game against the same opponent,
+            // with the variant, wild config and seed of the current game
+            if (lastGameRequest == null) {
+                return;
+            }
+            const gameRequest = makeGameRequest(
+                lastGameRequest.variant,
+                lastGameRequest.clock,
+                null,
+            );
+            if (gameRequest == null) {
+                return;
+            }
+            const replayRequest = app.replay_request(gameRequest);
+            leaveGame();
+            switch (lastGameRequest.kind) {
+                case "local-game": {
+                    app.start_local_game(replayRequest);
+                    return;
+                }
+                case "bot-game": {
+                    app.start_bot_game(
+                        replayRequest,
+                        lastGameRequest.difficulty ?? "medium",
+                    );
+                    return;
+                }
+                case "analysis-game": {
+                    app.start_analysis_game(replayRequest);
+                    return;
+                }
+                case "online-game": {
+                    app.request_online_game(replayRequest);
+                    postMessage({ kind: "network-state", state: "awaiting-game" });
+                    return;
+                }
+            }
             return;
         }
         case "request-targets": {
@@ -91,7 +197,6 @@ function onMessage(event) {
             return;
         }
         case "play-move": {
-            // TODO enable premove
             app.update();
 
             console.log(
@@ -112,9 +217,23 @@ function onMessage(event) {
                 );
             console.log({ currentTurn, isMyPiece, isAllowedTarget });
 
+            // while waiting for the opponent, my moves are queued as premoves
+            const isPremove = !isMyTurn && myTeam !== "any" &&
+                app.get_piece_team(event.data.source) === myTeam;
+
             if (isMyTurn && isMyPiece && isAllowedTarget) {
                 // send move event
                 app.trigger_move(event.data.source, event.data.target);
+            } else if (isPremove) {
+                // the player is not asked to choose a promotion once the premove is played,
+                // so premoved pieces promote to their first option
+                app.queue_premove(event.data.source, event.data.target, 0);
+                // the piece stays put until the premove is played
+                postMessage({
+                    kind: "position",
+                    position: currentPosition,
+                    lastMove,
+                });
             } else {
                 // reset board
                 postMessage({
@@ -125,6 +244,14 @@ function onMessage(event) {
             }
             return;
         }
+        case "cancel-premoves": {
+            app.cancel_premoves();
+            return;
+        }
+        case "flip-board": {
+            app.flip_board();
+            return;
+        }
         case "select-promotion": {
             // confirm the context is correct
             if (
@@ -154,6 +281,17 @@ let lastMove = null;
 let currentIcons = null;
 let currentClocks = null;
 let promotionOptions = null;
+let takebackRequest = null;
+let drawOffer = null;
+let rematchRequest = null;
+let canAbort = false;
+let gameResult = null;
+let moveHistory = [];
+let currentSeed = null;
+// the last game requested, to replay its seed against the same opponent
+let lastGameRequest = null;
+let premoves = [];
+let boardOrientation = "white";
 let connectToServerToken = null;
 
 const DEV_IP = "127.0.0.1";
@@ -219,6 +357,13 @@ async function runApp() {
             app.update();
         }
 
+        // the server rejects clocks it will not play
+        const clockRequestError = app.get_clock_request_error();
+        if (clockRequestError != null && !inGame) {
+            postMessage({ kind: "clock-error", message: clockRequestError });
+            postMessage({ kind: "network-state", state: "connected" });
+        }
+
         // track player counts
         const playerCount = app.get_player_count();
         if (playerCount !== lastPlayerCount) {
@@ -282,6 +427,60 @@ async function runApp() {
             postMessage({ kind: "clocks", clocks });
         }
 
+        // track takeback requests
+        const newTakebackRequest = app.get_takeback_request() ?? null;
+        if (newTakebackRequest !== takebackRequest) {
+            takebackRequest = newTakebackRequest;
+            postMessage({ kind: "takeback", requestedBy: takebackRequest });
+        }
+
+        // track draw offers and rematch requests
+        const newDrawOffer = app.get_draw_offer() ?? null;
+        if (newDrawOffer !== drawOffer) {
+            drawOffer = newDrawOffer;
+            postMessage({ kind: "draw-offer", offeredBy: drawOffer });
+        }
+        const newRematchRequest = app.get_rematch_request() ?? null;
+        if (newRematchRequest !== rematchRequest) {
+            rematchRequest = newRematchRequest;
+            postMessage({ kind: "rematch", requestedBy: rematchRequest });
+        }
+
+        // track whether the game can still be aborted
+        const newCanAbort = app.can_abort();
+        if (newCanAbort !== canAbort) {
+            canAbort = newCanAbort;
+            postMessage({ kind: "can-abort", canAbort });
+        }
+
+        // track the moves played
+        const newMoveHistory = app.get_move_history();
+        if (!deepEqual(newMoveHistory, moveHistory)) {
+            moveHistory = newMoveHistory;
+            postMessage({ kind: "history", moves: moveHistory });
+        }
+
+        // track the seed of random armies
+        const newSeed = app.get_seed() ?? null;
+        if (newSeed !== currentSeed) {
+            currentSeed = newSeed;
+            postMessage({ kind: "seed", seed: currentSeed });
+        }
+
+        // track the queued premoves
+        const newPremoves = app.get_premoves();
+        if (!deepEqual(newPremoves, premoves)) {
+            premoves = newPremoves;
+            postMessage({ kind: "premoves", premoves });
+        }
+
+        // track the side the board is seen from
+        const newBoardOrientation = app.get_board_orientation();
+        if (newBoardOrientation !== boardOrientation) {
+            boardOrientation = newBoardOrientation;
+            postMessage({ kind: "board-orientation", orientation: boardOrientation });
+        }
+
         const maybePromotions = app.get_promotion_request();
         if (maybePromotions != null) {
             postMessage({
@@ -293,11 +492,15 @@ async function runApp() {
             promotionOptions = maybePromotions;
         }
 
-        const winningTeam = app.is_game_over();
-        if (winningTeam != null) {
+        // track the result, which is cleared again by a rematch
+        const gameover = app.is_game_over();
+        const newGameResult = gameover?.get_description() ?? null;
+        if (newGameResult !== gameResult) {
+            gameResult = newGameResult;
             postMessage({
                 kind: "gameover",
-                winningTeam: winningTeam.get_team(),
+                winningTeam: gameover?.get_team() ?? null,
+                description: gameResult,
             });
         }
 
@@ -308,7 +511,28 @@ async function runApp() {
     }
 }
 
-function makeGameRequest(variant, clock) {
+// returns null when the server would reject a custom clock
+function leaveGame() {
+    app.leave_game();
+    app.update();
+    postMessage({
+        kind: "position",
+        position: null,
+        lastMove: null,
+    });
+    // reset state
+    connected = false;
+    inGame = false;
+    myTeam = "white";
+    currentPosition = null;
+    lastMove = null;
+    currentIcons = null;
+    moveHistory = [];
+    currentSeed = null;
+    premoves = [];
+}
+
+function makeGameRequest(variant, clock, seed) {
     let gameRequest = wasm_bindgen.WasmGameRequest.new();
     switch (variant) {
         case "featured-1": {
@@ -327,11 +551,34 @@ function makeGameRequest(variant, clock) {
             gameRequest = gameRequest.with_wild_game();
             break;
         }
+        case "armies": {
+            gameRequest = gameRequest.with_different_armies_game(
+                wasm_bindgen.WasmWildConfig.new(),
+            );
+            break;
+        }
+        case "horde": {
+            gameRequest = gameRequest.with_horde_game();
+            break;
+        }
         case null:
             break;
         default:
             throw new Error("Unexpected game request kind: " + variant);
     }
+    if (clock != null && typeof clock === "object") {
+        gameRequest = gameRequest.with_custom_clock(
+            clock.time,
+            clock.increment,
+            clock.timing,
+        );
+        const clockError = gameRequest.clock_error();
+        if (clockError != null) {
+            postMessage({ kind: "clock-error", message: clockError });
+            return null;
+        }
+        clock = null;
+    }
     switch (clock) {
         case "classical": {
             gameRequest = gameRequest.with_classical_clock();
@@ -355,6 +602,9 @@ function makeGameRequest(variant, clock) {
         default:
             throw new Error("Unexpected game clock: " + clock);
     }
+    if (seed != null && seed !== "") {
+        gameRequest = gameRequest.with_seed(seed);
+    }
     return gameRequest;
 }
 
@@ -1,6 +1,6 @@
 #[cfg(feature = "reflect")]
 use bevy_ecs::prelude::ReflectComponent;
-use bevy_ecs::prelude::{Changed, Component, Entity, Query, With};
+use bevy_ecs::prelude::{Changed, Component, Entity, Query, RemovedComponents, With};
 #[cfg(feature = "reflect")]
 use bevy_reflect::Reflect;
 use bevy_utils::{HashMap, HashSet};
@@ -8,8 +8,9 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     actions::Actions,
+    behavior::PatternBehavior,
     board::{Board, OnBoard, Square},
-    pieces::Position,
+    pieces::{Orientation, Position},
     team::Team,
 };
 
@@ -28,7 +29,19 @@ impl BoardPieceCache {
         mut board_query: Query<&mut Self, With<Board>>,
         // Actions should change every move for all pieces
         piece_query: Query<(Entity, &OnBoard, &Team, &Position), Changed<Position>>,
+        mut removed_positions: RemovedComponents<Position>,
     ) {
+        // pieces without a Position (such as captured pieces) no longer occupy a square
+        for piece in removed_positions.read() {
+            for mut cache in board_query.iter_mut() {
+                if let Some(square) = cache.entities.remove(&piece) {
+                    if !cache.entities.values().any(|other| *other == square) {
+                        cache.teams.remove(&square);
+                    }
+                }
+            }
+        }
+
         for (piece, on_board, team, position) in piece_query.iter() {
             let Ok(mut cache) = board_query.get_mut(on_board.0) else {
                 continue;
@@ -65,44 +78,83 @@ pub struct BoardThreat {
 pub struct BoardThreatsCache(HashSet<BoardThreat>);
 
 impl BoardThreatsCache {
-    pub(crate) fn is_threatened(&self, square: Square, team: Team) -> bool {
+    // Whether a piece of `team` on `square` could be captured
+    pub fn is_threatened(&self, square: Square, team: Team) -> bool {
         self.0.contains(&BoardThreat {
             square,
             attacked_team: team,
         })
     }
 
+    // The squares where pieces of `team` could be captured
+    pub fn threatened_squares(&self, team: Team) -> impl Iterator<Item = Square> + '_ {
+        self.0
+            .iter()
+            .filter(move |threat| threat.attacked_team == team)
+            .map(|threat| threat.square)
+    }
+
+    #[allow(clippy::type_complexity)]
     pub(crate) fn track_pieces(
-        mut board_query: Query<&mut Self, With<Board>>,
+        mut board_query: Query<(&mut Self, &Board, &BoardPieceCache)>,
         // Actions should change every move for all pieces
-        piece_query: Query<(Entity, &OnBoard, &Team, &Actions), Changed<Actions>>,
+        piece_query: Query<
+            (
+                &OnBoard,
+                &Team,
+                &Actions,
+                Option<&PatternBehavior>,
+                Option<&Position>,
+                &Orientation,
+            ),
+            Changed<Actions>,
+        >,
     ) {
         // first clear everything
         let mut affected_boards = HashSet::new();
-        for (_piece, on_board, _, _) in piece_query.iter() {
+        for (on_board, _, _, _, _, _) in piece_query.iter() {
             affected_boards.insert(on_board.0);
         }
         for board in affected_boards {
-            let Ok(mut attacked_squares) = board_query.get_mut(board) else {
+            let Ok((mut attacked_squares, _, _)) = board_query.get_mut(board) else {
                 continue;
             };
             attacked_squares.0.clear();
         }
 
         // then instantiate the maps
-        for (_piece, on_board, team, actions) in piece_query.iter() {
-            let Ok(mut attacked_squares) = board_query.get_mut(on_board.0) else {
+        for (on_board, team, actions, pattern, position, orientation) in piece_query.iter() {
+            let Ok((mut attacked_squares, board, pieces)) = board_query.get_mut(on_board.0) else {
                 continue;
             };
-            for &capture_square in actions.0.iter().flat_map(|(_, action)| &action.threats) {
-                attacked_squares.0.insert(BoardThreat {
-                    square: capture_square,
-                    attacked_team: match team {
-                        Team::White => Team::Black,
-                        Team::Black => Team::White,
-                    },
-                });
+            attacked_squares.add_threats(*team, actions);
+            if let (Some(pattern), Some(position)) = (pattern, position) {
+                let threats = pattern.threats(&position.0, orientation, team, board, &pieces.teams);
+                attacked_squares.add_threatened_squares(*team, threats);
             }
         }
     }
+
+    // Records the squares threatened by the actions of a piece on `team`
+    pub(crate) fn add_threats(&mut self, team: Team, actions: &Actions) {
+        let threats = actions
+            .0
+            .values()
+            .flat_map(|action| action.threats.iter().copied());
+        self.add_threatened_squares(team, threats);
+    }
+
+    // Records squares threatened by a piece on `team`, even those it cannot move to
+    pub(crate) fn add_threatened_squares(
+        &mut self,
+        team: Team,
+        squares: impl IntoIterator<Item = Square>,
+    ) {
+        for square in squares {
+            self.0.insert(BoardThreat {
+                square,
+                attacked_team: team.get_next(),
+            });
+        }
+    }
 }
@@ -9,6 +9,7 @@ use bevy_ecs::prelude::ReflectComponent;
 use bevy_log::warn;
 #[cfg(feature = "reflect")]
 use bevy_reflect::Reflect;
+use bevy_utils::HashMap;
 
 use crate::{
     actions::{Action, Actions, Movement},
@@ -31,22 +32,170 @@ pub(crate) fn disable_on_move<T: Component>(
     }
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component))]
 pub struct CastlingTarget;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component))]
 pub struct CastlingBehavior;
 
-// Enable performing whatever Pattern was executed in the last turn
+// The squares after `from` up to and including `to`, along a rank or a file
+fn squares_towards(from: Square, to: Square) -> impl Iterator<Item = Square> {
+    let step = |from: u16, to: u16| match from.cmp(&to) {
+        Ordering::Less => 1,
+        Ordering::Equal => 0,
+        Ordering::Greater => -1,
+    };
+    let (file_step, rank_step) = (step(from.file.0, to.file.0), step(from.rank.0, to.rank.0));
+    let distance = from
+        .file
+        .0
+        .abs_diff(to.file.0)
+        .max(from.rank.0.abs_diff(to.rank.0));
+    (1..=distance as i32).map(move |offset| {
+        Square::new(
+            File((from.file.0 as i32 + file_step * offset) as u16),
+            Rank((from.rank.0 as i32 + rank_step * offset) as u16),
+        )
+    })
+}
+
 impl CastlingBehavior {
+    // Finds the castling actions available to a castler on `position`.
+    // Each target is given as (entity, square, team, orientation).
+    pub(crate) fn search(
+        position: &Square,
+        team: &Team,
+        orientation: &Orientation,
+        targets: impl IntoIterator<Item = (Entity, Square, Team, Orientation)>,
+        pieces: &HashMap<Square, Team>,
+        threats: &BoardThreatsCache,
+    ) -> Actions {
+        let mut actions = Actions::default();
+        for (target_entity, target, target_team, target_orientation) in targets {
+            if *team != target_team {
+                continue;
+            }
+            let (landing_square, is_horizontal, is_position_gt_target) = match (
+                position.file.0.cmp(&target.file.0),
+                position.rank.0.cmp(&target.rank.0),
+            ) {
+                (Ordering::Less, Ordering::Equal) => {
+                    (Square::new(File::G, position.rank), true, false)
+                }
+                (Ordering::Greater, Ordering::Equal) => {
+                    (Square::new(File::C, position.rank), true, true)
+                }
+                (Ordering::Equal, Ordering::Less) => {
+                    (Square::new(position.file, Rank::SIX), false, false)
+                }
+                (Ordering::Equal, Ordering::Greater) => {
+                    (Square::new(position.file, Rank::TWO), false, true)
+                }
+                _ => {
+                    #[cfg(feature = "log")]
+                    warn!("Unexpected castling alignment detected. Castler square: {}, Target square: {}", position, target);
+                    continue;
+                }
+            };
+
+            // the piece could still be on either side of the target, so we have to check
+            let scanned_squares: Vec<_> = if is_horizontal {
+                match position.file.cmp(&landing_square.file) {
+                    Ordering::Less => ((position.file.0 + 1)..=landing_square.file.0)
+                        .map(|file| Square::new(File(file), landing_square.rank))
+                        .collect(),
+                    Ordering::Greater => (landing_square.file.0
+                        ..=position.file.0.saturating_sub(1))
+                        .rev()
+                        .map(|file| Square::new(File(file), landing_square.rank))
+                        .collect(),
+                    Ordering::Equal => {
+                        vec![]
+                    }
+                }
+            } else {
+                match position.rank.cmp(&landing_square.rank) {
+                    Ordering::Less => ((position.rank.0 + 1)..=landing_square.rank.0)
+                        .map(|rank: u16| Square::new(landing_square.file, Rank(rank)))
+                        .collect(),
+                    Ordering::Greater => (landing_square.rank.0
+                        ..=position.rank.0.saturating_sub(1))
+                        .rev()
+                        .map(|rank| Square::new(landing_square.file, Rank(rank)))
+                        .collect(),
+                    Ordering::Equal => {
+                        vec![]
+                    }
+                }
+            };
+
+            // the castle target should appear on the "other" side of the castler
+            let target_landing_square = if is_horizontal {
+                Square::new(
+                    if is_position_gt_target {
+                        File(landing_square.file.0 + 1)
+                    } else {
+                        File(landing_square.file.0 - 1)
+                    },
+                    landing_square.rank,
+                )
+            } else {
+                Square::new(
+                    landing_square.file,
+                    if is_position_gt_target {
+                        Rank(landing_square.rank.0 + 1)
+                    } else {
+                        Rank(landing_square.rank.0 - 1)
+                    },
+                )
+            };
+
+            let is_in_check = threats.is_threatened(*position, *team);
+            let is_forbidden_movement = scanned_squares.iter().any(|scan|
+                // scanned square is check
+                threats.is_threatened(*scan, *team)
+                ||
+                // movement collides with piece (except the rook)
+                *scan != target && pieces.contains_key(scan));
+
+            // the target passes through every square up to its landing square (except the castler)
+            let collides_rook = squares_towards(target, target_landing_square)
+                .any(|scan| scan != *position && pieces.contains_key(&scan));
+
+            if !is_in_check && !is_forbidden_movement && !collides_rook {
+                actions.0.insert(
+                    target,
+                    Action {
+                        movement: Movement {
+                            from: *position,
+                            to: landing_square,
+                            orientation: *orientation,
+                        },
+                        side_effects: vec![(
+                            target_entity,
+                            Movement {
+                                from: target,
+                                to: target_landing_square,
+                                orientation: target_orientation,
+                            },
+                        )],
+                        scanned_squares,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        actions
+    }
+
     pub(crate) fn calculate_actions_system(
         board_query: Query<(&BoardPieceCache, &BoardThreatsCache)>,
         mut castler_query: Query<
@@ -57,121 +206,18 @@ impl CastlingBehavior {
     ) {
         for (pieces, threats) in board_query.iter() {
             for (Position(position), team, orientation, mut actions) in castler_query.iter_mut() {
-                for (target_entity, Position(target), target_team, target_orientation) in
-                    target_query.iter()
-                {
-                    if team != target_team {
-                        continue;
-                    }
-                    let (landing_square, is_horizontal, is_position_gt_target) = match (
-                        position.file.0.cmp(&target.file.0),
-                        position.rank.0.cmp(&target.rank.0),
-                    ) {
-                        (Ordering::Less, Ordering::Equal) => {
-                            (Square::new(File::G, position.rank), true, false)
-                        }
-                        (Ordering::Greater, Ordering::Equal) => {
-                            (Square::new(File::C, position.rank), true, true)
-                        }
-                        (Ordering::Equal, Ordering::Less) => {
-                            (Square::new(position.file, Rank::SIX), false, false)
-                        }
-                        (Ordering::Equal, Ordering::Greater) => {
-                            (Square::new(position.file, Rank::TWO), false, true)
-                        }
-                        _ => {
-                            #[cfg(feature = "log")]
-                            warn!("Unexpected castling alignment detected. Castler square: {}, Target square: {}", position, target);
-                            continue;
-                        }
-                    };
-
-                    // the piece could still be on either side of the target, so we have to check
-                    let scanned_squares: Vec<_> = if is_horizontal {
-                        match position.file.cmp(&landing_square.file) {
-                            Ordering::Less => ((position.file.0 + 1)..=landing_square.file.0)
-                                .map(|file| Square::new(File(file), landing_square.rank))
-                                .collect(),
-                            Ordering::Greater => (landing_square.file.0
-                                ..=position.file.0.saturating_sub(1))
-                                .rev()
-                                .map(|file| Square::new(File(file), landing_square.rank))
-                                .collect(),
-                            Ordering::Equal => {
-                                vec![]
-                            }
-                        }
-                    } else {
-                        match position.rank.cmp(&landing_square.rank) {
-                            Ordering::Less => ((position.rank.0 + 1)..=landing_square.rank.0)
-                                .map(|rank: u16| Square::new(landing_square.file, Rank(rank)))
-                                .collect(),
-                            Ordering::Greater => (landing_square.rank.0
-                                ..=position.rank.0.saturating_sub(1))
-                                .rev()
-                                .map(|rank| Square::new(landing_square.file, Rank(rank)))
-                                .collect(),
-                            Ordering::Equal => {
-                                vec![]
-                            }
-                        }
-                    };
-
-                    // the castle target should appear on the "other" side of the castler
-                    let target_landing_square = if is_horizontal {
-                        Square::new(
-                            if is_position_gt_target {
-                                File(landing_square.file.0 + 1)
-                            } else {
-                                File(landing_square.file.0 - 1)
-                            },
-                            landing_square.rank,
-                        )
-                    } else {
-                        Square::new(
-                            landing_square.file,
-                            if is_position_gt_target {
-                                Rank(landing_square.rank.0 + 1)
-                            } else {
-                                Rank(landing_square.rank.0 - 1)
-                            },
-                        )
-                    };
-
-                    let is_in_check = threats.is_threatened(*position, *team);
-                    let is_forbidden_movement = scanned_squares.iter().any(|scan|
-                        // scanned square is check
-                        threats.is_threatened(*scan, *team)
-                        ||
-                        // movement collides with piece (except the rook)
-                        *scan != *target && pieces.teams.contains_key(scan));
-
-                    let collides_rook = target_landing_square != *target
-                        && pieces.teams.contains_key(&target_landing_square);
-
-                    if !is_in_check && !is_forbidden_movement && !collides_rook {
-                        actions.0.insert(
-                            *target,
-                            Action {
-                                movement: Movement {
-                                    from: *position,
-                                    to: landing_square,
-                                    orientation: *orientation,
-                                },
-                                side_effects: vec![(
-                                    target_entity,
-                                    Movement {
-                                        from: *target,
-                                        to: target_landing_square,
-                                        orientation: *target_orientation,
-                                    },
-                                )],
-                                scanned_squares,
-                                ..Default::default()
-                            },
-                        );
-                    }
-                }
+                actions.extend(Self::search(
+                    position,
+                    team,
+                    orientation,
+                    target_query
+                        .iter()
+                        .map(|(entity, target, team, orientation)| {
+                            (entity, target.0, *team, *orientation)
+                        }),
+                    &pieces.teams,
+                    threats,
+                ));
             }
         }
     }
@@ -187,8 +233,9 @@ mod tests {
 
     use crate::{
         actions::{Action, Actions},
-        behavior::{BoardPieceCache, BoardThreatsCache},
+        behavior::{BoardPieceCache, BoardThreatsCache, PatternBehavior},
         board::{Board, OnBoard, Square},
+        pattern::Pattern,
         pieces::PieceBundle,
         team::Team,
     };
@@ -519,4 +566,42 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_threatened_by_capture_only_pattern() -> Result<()> {
+        let mut app = setup_app();
+        let board = spawn_board(app.world_mut());
+
+        let king = app
+            .world_mut()
+            .spawn((
+                PieceBundle::new(Square::try_from("e1")?.into(), Team::White),
+                OnBoard(board),
+                CastlingBehavior,
+            ))
+            .id();
+        app.world_mut().spawn((
+            PieceBundle::new(Square::try_from("h1")?.into(), Team::White),
+            OnBoard(board),
+            CastlingTarget,
+        ));
+
+        // a pawn on g2 cannot move to f1, but still threatens it
+        app.world_mut().spawn((
+            PieceBundle::new(Square::try_from("g2")?.into(), Team::Black),
+            OnBoard(board),
+            PatternBehavior::default().with_pattern(
+                Pattern::diagonal_forward()
+                    .range(1)
+                    .only_captures_by_displacement(),
+            ),
+        ));
+
+        app.update();
+
+        let actions = app.world().entity(king).get::<Actions>().unwrap();
+        assert_eq!(actions.0.get(&Square::try_from("h1")?), None);
+
+        Ok(())
+    }
 }
@@ -18,7 +18,7 @@ use crate::{
 
 use crate::behavior::Behavior;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
@@ -45,7 +45,7 @@ impl From<EnPassantActionsCache> for Actions {
 // detect whether the piece has "stepped" on an attackable square
 
 impl EnPassantBehavior {
-    fn search(
+    pub(crate) fn search(
         &self,
         origin: &Square,
         orientation: &Orientation,
@@ -5,7 +5,7 @@ use bevy_ecs::prelude::ReflectComponent;
 use bevy_ecs::prelude::{Commands, Component, Entity, Query};
 #[cfg(feature = "reflect")]
 use bevy_reflect::prelude::Reflect;
-use bevy_utils::HashMap;
+use bevy_utils::{HashMap, HashSet};
 
 use crate::{
     actions::{Action, Actions, LastAction},
@@ -65,6 +65,21 @@ impl PatternBehavior {
                 .collect(),
         )
     }
+
+    // The squares threatened by any of the patterns, see `Pattern::threats`
+    pub(crate) fn threats(
+        &self,
+        origin: &Square,
+        orientation: &Orientation,
+        my_team: &Team,
+        board: &Board,
+        pieces: &HashMap<Square, Team>,
+    ) -> HashSet<Square> {
+        self.patterns
+            .iter()
+            .flat_map(|pattern| pattern.threats(origin, orientation, my_team, board, pieces))
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
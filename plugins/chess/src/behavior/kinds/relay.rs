@@ -8,7 +8,7 @@ use bevy_reflect::Reflect;
 use bevy_utils::HashMap;
 
 use crate::{
-    actions::{Actions, LastAction},
+    actions::{Action, Actions, LastAction},
     behavior::BoardPieceCache,
     board::{Board, OnBoard, Square},
     pattern::Pattern,
@@ -51,6 +51,62 @@ impl From<RelayActionsCache> for Actions {
     }
 }
 
+impl RelayBehavior {
+    // Adds the patterns this piece relays to each square it scans
+    pub(crate) fn relay_patterns(
+        &self,
+        origin: &Square,
+        orientation: &Orientation,
+        my_team: &Team,
+        board: &Board,
+        pieces: &HashMap<Square, Team>,
+        relay_pattern_map: &mut HashMap<Square, Vec<(Pattern, Team)>>,
+    ) {
+        for pattern in self.patterns.iter() {
+            for scan_target in pattern
+                .scanner
+                .scan(origin, *orientation, my_team, board, pieces)
+            {
+                if let Some(patterns) = relay_pattern_map.get_mut(&scan_target.target) {
+                    patterns.push((pattern.clone(), *my_team));
+                } else {
+                    relay_pattern_map.insert(scan_target.target, vec![(pattern.clone(), *my_team)]);
+                }
+            }
+        }
+    }
+
+    // Searches the patterns relayed to a piece by its allies
+    pub(crate) fn search_relayed(
+        patterns: Vec<(Pattern, Team)>,
+        origin: &Square,
+        orientation: &Orientation,
+        my_team: &Team,
+        board: &Board,
+        pieces: &HashMap<Square, Team>,
+        last_action: Option<&Action>,
+    ) -> Actions {
+        let patterns = patterns
+            .into_iter()
+            .filter_map(|(pattern, source_team)| {
+                if *my_team == source_team {
+                    Some(pattern)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        PatternBehavior::new(patterns).search(
+            origin,
+            orientation,
+            my_team,
+            board,
+            pieces,
+            last_action,
+        )
+    }
+}
+
 // Enable performing whatever Pattern was executed in the last turn
 impl Behavior for RelayBehavior {
     type ActionsCache = RelayActionsCache;
@@ -78,22 +134,14 @@ impl Behavior for RelayBehavior {
                 .filter(|(_, _, _, _, _, _, on_board)| on_board.0 == board_entity)
             {
                 if let Some(relay_behavior) = relay_behavior {
-                    for pattern in relay_behavior.patterns.iter() {
-                        for scan_target in pattern.scanner.scan(
-                            &position.0,
-                            *orientation,
-                            team,
-                            board,
-                            &pieces.teams,
-                        ) {
-                            if let Some(patterns) = relay_pattern_map.get_mut(&scan_target.target) {
-                                patterns.push((pattern.clone(), *team));
-                            } else {
-                                relay_pattern_map
-                                    .insert(scan_target.target, vec![(pattern.clone(), *team)]);
-                            }
-                        }
-                    }
+                    relay_behavior.relay_patterns(
+                        &position.0,
+                        orientation,
+                        team,
+                        board,
+                        &pieces.teams,
+                        &mut relay_pattern_map,
+                    );
                 }
             }
 
@@ -102,17 +150,8 @@ impl Behavior for RelayBehavior {
                 .filter(|(_, _, _, _, _, _, on_board)| on_board.0 == board_entity)
             {
                 if let Some(patterns) = relay_pattern_map.remove(&position.0) {
-                    let patterns = patterns
-                        .into_iter()
-                        .filter_map(|(pattern, source_team)| {
-                            if *team == source_team {
-                                Some(pattern)
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    let actions = RelayActionsCache::from(PatternBehavior::new(patterns).search(
+                    let actions = RelayActionsCache::from(RelayBehavior::search_relayed(
+                        patterns,
                         &position.0,
                         orientation,
                         team,
@@ -78,7 +78,7 @@ pub trait Behavior {
 //     piece_query: Query<(Option<&mut C>, &Position, &Orientation, &Team)>,
 // ) {}
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 pub struct PieceBehaviors {
@@ -0,0 +1,279 @@
+use thiserror::Error;
+
+use crate::{
+    behavior::PatternBehavior,
+    board::Rank,
+    pattern::{ABSymmetry, Pattern, RSymmetry, Step},
+};
+
+#[derive(Debug, Error)]
+pub enum BetzaError {
+    #[error("`{0}` has no atoms")]
+    Empty(String),
+    #[error("`{0}` is not a supported Betza atom")]
+    UnknownAtom(char),
+    #[error("`{0}` is not a supported Betza modifier")]
+    UnknownModifier(char),
+    #[error("modifiers `{0}` leave no directions to move in")]
+    NoDirections(String),
+}
+
+// The basic moves of Betza's funny notation, by the distance of a single step
+#[derive(Clone, Copy, Debug)]
+enum Atom {
+    // W, D, H
+    Orthogonal(i16),
+    // F, A, G
+    Diagonal(i16),
+    // K and Q: both orthogonal and diagonal
+    Radial(i16),
+    // N, C, Z
+    Oblique(i16, i16),
+}
+
+impl Atom {
+    // the atom and whether it rides by default
+    fn from_char(c: char) -> Option<(Atom, bool)> {
+        Some(match c {
+            'W' => (Atom::Orthogonal(1), false),
+            'D' => (Atom::Orthogonal(2), false),
+            'H' => (Atom::Orthogonal(3), false),
+            'F' => (Atom::Diagonal(1), false),
+            'A' => (Atom::Diagonal(2), false),
+            'G' => (Atom::Diagonal(3), false),
+            'N' => (Atom::Oblique(2, 1), false),
+            'C' => (Atom::Oblique(3, 1), false),
+            'Z' => (Atom::Oblique(3, 2), false),
+            'K' => (Atom::Radial(1), false),
+            'R' => (Atom::Orthogonal(1), true),
+            'B' => (Atom::Diagonal(1), true),
+            'Q' => (Atom::Radial(1), true),
+            _ => return None,
+        })
+    }
+
+    fn step(&self, directions: &str) -> Result<Step, BetzaError> {
+        let no_directions = || BetzaError::NoDirections(directions.to_string());
+        match *self {
+            Atom::Orthogonal(r) => {
+                let symmetry = orthogonal_symmetry(directions);
+                (!symmetry.is_empty())
+                    .then(|| Step::from_r(r, symmetry))
+                    .ok_or_else(no_directions)
+            }
+            Atom::Diagonal(r) => {
+                let symmetry = diagonal_symmetry(directions);
+                (!symmetry.is_empty())
+                    .then(|| Step::from_r(r, symmetry))
+                    .ok_or_else(no_directions)
+            }
+            Atom::Radial(r) => {
+                let symmetry = orthogonal_symmetry(directions) | diagonal_symmetry(directions);
+                (!symmetry.is_empty())
+                    .then(|| Step::from_r(r, symmetry))
+                    .ok_or_else(no_directions)
+            }
+            Atom::Oblique(a, b) => {
+                let symmetry = oblique_symmetry(directions);
+                (!symmetry.is_empty())
+                    .then(|| Step::from_ab(a, b, symmetry))
+                    .ok_or_else(no_directions)
+            }
+        }
+    }
+}
+
+// Reads a piece's movement from a subset of Betza's funny notation, such as `fmWfcF` or `NQ`.
+// Supported atoms are W, F, D, A, H, G, N, C, Z and the shorthands K, Q, R and B.
+// A doubled atom rides, and a number after an atom limits its range (0 is unlimited).
+// Supported modifiers are m (move only), c (capture only), i (only from the local second rank)
+// and the directions f, b, l, r, s and v, which are relative to the piece's orientation.
+pub fn parse(text: &str) -> Result<PatternBehavior, BetzaError> {
+    let mut behavior = PatternBehavior::default();
+    let mut chars = text.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut modifiers = String::new();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_lowercase() {
+            if !"mcifblrsv".contains(c) {
+                return Err(BetzaError::UnknownModifier(c));
+            }
+            modifiers.push(c);
+            continue;
+        }
+        let (atom, mut rides) = Atom::from_char(c).ok_or(BetzaError::UnknownAtom(c))?;
+
+        let mut range = None;
+        if chars.peek() == Some(&c) {
+            chars.next();
+            rides = true;
+        } else {
+            let mut digits = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                digits.push(digit);
+            }
+            range = digits.parse::<usize>().ok();
+        }
+
+        let directions = modifiers
+            .chars()
+            .filter(|c| "fblrsv".contains(*c))
+            .collect::<String>();
+        let mut pattern = Pattern::new(atom.step(&directions)?);
+        pattern = match range {
+            Some(0) => pattern.rider(),
+            Some(range) => pattern.range(range),
+            None if rides => pattern.rider(),
+            None => pattern.leaper(),
+        };
+        pattern = match (modifiers.contains('m'), modifiers.contains('c')) {
+            (true, false) => pattern,
+            (false, true) => pattern.only_captures_by_displacement(),
+            _ => pattern.captures_by_displacement(),
+        };
+        if modifiers.contains('i') {
+            pattern = pattern.only_from_local_rank(Rank::TWO);
+        }
+        behavior = behavior.with_pattern(pattern);
+        modifiers.clear();
+    }
+
+    if behavior.patterns.is_empty() {
+        Err(BetzaError::Empty(text.to_string()))
+    } else {
+        Ok(behavior)
+    }
+}
+
+fn orthogonal_symmetry(directions: &str) -> RSymmetry {
+    if directions.is_empty() {
+        return RSymmetry::orthogonal();
+    }
+    directions
+        .chars()
+        .map(|c| match c {
+            'f' => RSymmetry::FORWARD,
+            'b' => RSymmetry::BACKWARD,
+            'l' => RSymmetry::LEFT,
+            'r' => RSymmetry::RIGHT,
+            's' => RSymmetry::sideways(),
+            _ => RSymmetry::vertical(),
+        })
+        .fold(RSymmetry::empty(), |symmetry, next| symmetry | next)
+}
+
+// a vertical and a horizontal direction together, such as `fl`, name a single diagonal
+fn diagonal_symmetry(directions: &str) -> RSymmetry {
+    if directions.is_empty() {
+        return RSymmetry::diagonal();
+    }
+    let mut symmetry = RSymmetry::empty();
+    let mut chars = directions.chars().peekable();
+    while let Some(c) = chars.next() {
+        let horizontal = matches!(c, 'f' | 'b')
+            .then(|| chars.next_if(|next| matches!(next, 'l' | 'r')))
+            .flatten();
+        symmetry |= match (c, horizontal) {
+            ('f', Some('l')) => RSymmetry::FORWARD_LEFT,
+            ('f', Some(_)) => RSymmetry::FORWARD_RIGHT,
+            ('b', Some('l')) => RSymmetry::BACKWARD_LEFT,
+            ('b', Some(_)) => RSymmetry::BACKWARD_RIGHT,
+            ('f', None) => RSymmetry::diagonal_forward(),
+            ('b', None) => RSymmetry::diagonal_backward(),
+            ('l', _) => RSymmetry::FORWARD_LEFT | RSymmetry::BACKWARD_LEFT,
+            ('r', _) => RSymmetry::FORWARD_RIGHT | RSymmetry::BACKWARD_RIGHT,
+            _ => RSymmetry::diagonal(),
+        };
+    }
+    symmetry
+}
+
+// doubled directions such as `ff` name the narrow moves and `s` the wide ones,
+// while `fl` and similar pairs name the two moves of a quadrant
+fn oblique_symmetry(directions: &str) -> ABSymmetry {
+    if directions.is_empty() {
+        return ABSymmetry::all();
+    }
+    let forward = ABSymmetry::narrow_forward() | ABSymmetry::wide_forward();
+    let backward = ABSymmetry::BACKWARD_BACKWARD_LEFT
+        | ABSymmetry::BACKWARD_BACKWARD_RIGHT
+        | ABSymmetry::BACKWARD_LEFT_LEFT
+        | ABSymmetry::BACKWARD_RIGHT_RIGHT;
+    let left = ABSymmetry::FORWARD_FORWARD_LEFT
+        | ABSymmetry::FORWARD_LEFT_LEFT
+        | ABSymmetry::BACKWARD_BACKWARD_LEFT
+        | ABSymmetry::BACKWARD_LEFT_LEFT;
+    let right = ABSymmetry::FORWARD_FORWARD_RIGHT
+        | ABSymmetry::FORWARD_RIGHT_RIGHT
+        | ABSymmetry::BACKWARD_BACKWARD_RIGHT
+        | ABSymmetry::BACKWARD_RIGHT_RIGHT;
+    let narrow = ABSymmetry::narrow_forward()
+        | ABSymmetry::BACKWARD_BACKWARD_LEFT
+        | ABSymmetry::BACKWARD_BACKWARD_RIGHT;
+    let wide = ABSymmetry::all() - narrow;
+
+    let mut symmetry = ABSymmetry::empty();
+    let mut chars = directions.chars().peekable();
+    while let Some(c) = chars.next() {
+        let single = match c {
+            'f' => forward,
+            'b' => backward,
+            'l' => left,
+            'r' => right,
+            's' => wide,
+            _ => narrow,
+        };
+        symmetry |= match chars.next_if(|next| {
+            matches!(
+                (c, next),
+                ('f' | 'b', 'f' | 'b' | 'l' | 'r' | 's') | ('l', 'l') | ('r', 'r')
+            )
+        }) {
+            Some(next) if next == c => single & narrow,
+            Some('s') => single & wide,
+            Some(next) if "lr".contains(next) => single & if next == 'l' { left } else { right },
+            // `fb` is both directions
+            Some(next) => single | if next == 'f' { forward } else { backward },
+            None => single,
+        };
+    }
+    symmetry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let pawn = PatternBehavior::default()
+            .with_pattern(Pattern::forward().range(1))
+            .with_pattern(
+                Pattern::diagonal_forward()
+                    .range(1)
+                    .only_captures_by_displacement(),
+            )
+            .with_pattern(Pattern::forward().range(2).only_from_local_rank(Rank::TWO));
+        assert_eq!(parse("fmWfcFifmW2").unwrap(), pawn);
+
+        let queen = PatternBehavior::default()
+            .with_pattern(Pattern::radial().rider().captures_by_displacement());
+        assert_eq!(parse("Q").unwrap(), queen);
+        assert_eq!(parse("WW").unwrap(), parse("R").unwrap());
+
+        let archbishop = parse("NB").unwrap();
+        assert_eq!(archbishop.patterns.len(), 2);
+        assert_eq!(
+            archbishop.patterns[0],
+            Pattern::knight().leaper().captures_by_displacement()
+        );
+
+        let shogi_knight = parse("ffN").unwrap();
+        assert_eq!(
+            shogi_knight.patterns[0].scanner.step,
+            Step::forward_leaper(2, 1)
+        );
+
+        assert!(matches!(parse("X"), Err(BetzaError::UnknownAtom('X'))));
+        assert!(matches!(parse("fm"), Err(BetzaError::Empty(_))));
+    }
+}
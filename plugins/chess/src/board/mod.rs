@@ -48,6 +48,11 @@ impl Board {
         }
     }
 
+    // Whether the square is within the bounds of the board
+    pub fn contains(&self, square: Square) -> bool {
+        square.file <= self.size.file && square.rank <= self.size.rank
+    }
+
     pub fn scan(&self, origin: Square, scan_vector: (i16, i16)) -> BoardIterator<'_> {
         BoardIterator {
             board: self,
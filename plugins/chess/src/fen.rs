@@ -0,0 +1,505 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    actions::{Action, Movement},
+    board::{Board, File, Rank, Square},
+    pieces::PieceDefinition,
+    position::{Position, PositionPiece},
+    team::Team,
+};
+
+use bevy_ecs::prelude::Entity;
+
+#[derive(Debug, Error)]
+pub enum FenError {
+    #[error("Invalid piece placement `{0}`")]
+    InvalidPlacement(String),
+    #[error("`{0}` is not in the legend")]
+    UnknownSymbol(char),
+    #[error("The piece on {0} is not in the legend")]
+    MissingSymbol(Square),
+    #[error("Invalid side to move `{0}`")]
+    InvalidSide(String),
+    #[error("Invalid castling rights `{0}`")]
+    InvalidCastling(String),
+    #[error("Invalid en passant squares `{0}`")]
+    InvalidEnPassant(String),
+    #[error("Invalid move counter `{0}`")]
+    InvalidCounter(String),
+    #[error(
+        "The position is {}x{} but the board is {}x{}",
+        .found.file.0 + 1,
+        .found.rank.0 + 1,
+        .expected.file.0 + 1,
+        .expected.rank.0 + 1
+    )]
+    BoardMismatch { expected: Square, found: Square },
+}
+
+// Maps the letters of a position string to pieces.
+// Letters are matched regardless of case: upper case letters are White's pieces
+// and lower case letters are Black's.
+#[derive(Clone, Debug, Default)]
+#[derive(Deserialize, Serialize)]
+pub struct Legend(Vec<(char, PieceDefinition)>);
+
+impl Legend {
+    // Uses a letter for a piece, replacing any piece previously using it
+    #[must_use]
+    pub fn with_piece(mut self, symbol: char, piece: PieceDefinition) -> Self {
+        let symbol = symbol.to_ascii_uppercase();
+        self.0.retain(|(other, _)| *other != symbol);
+        self.0.push((symbol, piece));
+        self
+    }
+
+    pub fn piece(&self, symbol: char) -> Option<&PieceDefinition> {
+        let symbol = symbol.to_ascii_uppercase();
+        self.0
+            .iter()
+            .find(|(other, _)| *other == symbol)
+            .map(|(_, piece)| piece)
+    }
+
+    // The upper case letter of a piece.
+    // Castling behaviors are ignored since they are lost once a piece moves.
+    pub fn symbol(&self, piece: &PieceDefinition) -> Option<char> {
+        let piece = without_castling(piece);
+        self.0
+            .iter()
+            .find(|(_, other)| without_castling(other) == piece)
+            .map(|(symbol, _)| *symbol)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(char, PieceDefinition)> {
+        self.0.iter()
+    }
+}
+
+pub(crate) fn without_castling(piece: &PieceDefinition) -> PieceDefinition {
+    let mut piece = piece.clone();
+    piece.behaviors.castling = None;
+    piece.behaviors.castling_target = None;
+    piece
+}
+
+// Position strings follow FEN, with pieces named by a `Legend`:
+// `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1`
+// Boards can have any size, given by the number of ranks and the length of each rank.
+// Use `Position::from_fen_on` to require the board a game is played on.
+// Castling rights name the castling target of each team: `K` and `Q` for the outermost target
+// on either side of the castling piece, or the file of the target (as in Shredder-FEN).
+// The en passant field lists the squares passed by the last turn, such as `e3`.
+// Fields after the placement are optional.
+impl Position {
+    pub fn from_fen(text: &str, legend: &Legend) -> Result<Self, FenError> {
+        let mut fields = text.split_whitespace();
+        let placement = fields
+            .next()
+            .ok_or_else(|| FenError::InvalidPlacement(text.to_string()))?;
+        let side = fields.next().unwrap_or("w");
+        let castling = fields.next().unwrap_or("-");
+        let en_passant = fields.next().unwrap_or("-");
+        let halfmove_clock = fields.next().unwrap_or("0");
+        let fullmove_number = fields.next().unwrap_or("1");
+
+        let (board, mut pieces) = parse_placement(placement, legend)?;
+        let mut position = Position::new(board);
+        position.side_to_move = match side {
+            "w" => Team::White,
+            "b" => Team::Black,
+            _ => return Err(FenError::InvalidSide(side.to_string())),
+        };
+        apply_castling(castling, &mut pieces)?;
+        position.last_action = parse_en_passant(en_passant, position.side_to_move, &board)?;
+        position.halfmove_clock = halfmove_clock
+            .parse()
+            .map_err(|_| FenError::InvalidCounter(halfmove_clock.to_string()))?;
+        let fullmove_number = fullmove_number
+            .parse::<usize>()
+            .ok()
+            .filter(|number| *number > 0)
+            .ok_or_else(|| FenError::InvalidCounter(fullmove_number.to_string()))?;
+        position.ply =
+            (fullmove_number - 1) * 2 + usize::from(position.side_to_move == Team::Black);
+
+        for piece in pieces {
+            position.insert_piece(piece);
+        }
+        Ok(position)
+    }
+
+    // Reads a position that must be played on `board`
+    pub fn from_fen_on(text: &str, legend: &Legend, board: &Board) -> Result<Self, FenError> {
+        let position = Position::from_fen(text, legend)?;
+        if position.board.size != board.size {
+            return Err(FenError::BoardMismatch {
+                expected: board.size,
+                found: position.board.size,
+            });
+        }
+        Ok(position)
+    }
+
+    pub fn to_fen(&self, legend: &Legend) -> Result<String, FenError> {
+        let mut ranks = vec![];
+        for rank in (0..=self.board.size.rank.0).rev() {
+            let mut text = String::new();
+            let mut empty = 0;
+            for file in 0..=self.board.size.file.0 {
+                let square = Square::new(File(file), Rank(rank));
+                let Some(piece) = self.piece_at(square) else {
+                    empty += 1;
+                    continue;
+                };
+                let symbol = legend
+                    .symbol(&piece.definition)
+                    .ok_or(FenError::MissingSymbol(square))?;
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                text.push(match piece.team {
+                    Team::White => symbol,
+                    Team::Black => symbol.to_ascii_lowercase(),
+                });
+            }
+            if empty > 0 {
+                text.push_str(&empty.to_string());
+            }
+            ranks.push(text);
+        }
+
+        let side = match self.side_to_move {
+            Team::White => "w",
+            Team::Black => "b",
+        };
+        Ok(format!(
+            "{} {side} {} {} {} {}",
+            ranks.join("/"),
+            self.write_castling(),
+            self.write_en_passant(),
+            self.halfmove_clock,
+            self.ply / 2 + 1
+        ))
+    }
+
+    fn write_castling(&self) -> String {
+        let mut text = String::new();
+        for team in [Team::White, Team::Black] {
+            for castler in self
+                .pieces()
+                .filter(|piece| piece.team == team && piece.can_castle)
+            {
+                let Some(origin) = castler.square else {
+                    continue;
+                };
+                let candidates = castling_candidates(self.pieces(), castler);
+                for target in self
+                    .pieces()
+                    .filter(|piece| piece.team == team && piece.is_castling_target)
+                    .filter_map(|piece| piece.square)
+                    .filter(|square| square.rank == origin.rank)
+                {
+                    let outermost = if target.file > origin.file {
+                        candidates.iter().map(|square| square.file).max()
+                    } else {
+                        candidates.iter().map(|square| square.file).min()
+                    };
+                    let symbol = match (outermost == Some(target.file), target.file > origin.file) {
+                        (true, true) => 'K',
+                        (true, false) => 'Q',
+                        _ => char::from(&target.file).to_ascii_uppercase(),
+                    };
+                    let symbol = match team {
+                        Team::White => symbol,
+                        Team::Black => symbol.to_ascii_lowercase(),
+                    };
+                    if !text.contains(symbol) {
+                        text.push(symbol);
+                    }
+                }
+            }
+        }
+        if text.is_empty() {
+            "-".to_string()
+        } else {
+            text
+        }
+    }
+
+    fn write_en_passant(&self) -> String {
+        let Some(action) = &self.last_action else {
+            return "-".to_string();
+        };
+        let can_be_passed = self
+            .piece_at(action.movement.to)
+            .is_some_and(|piece| piece.definition.behaviors.en_passant.is_some());
+        let squares = action
+            .scanned_squares
+            .iter()
+            .filter(|square| **square != action.movement.to)
+            .map(Square::to_string)
+            .collect::<String>();
+        if can_be_passed && !squares.is_empty() {
+            squares
+        } else {
+            "-".to_string()
+        }
+    }
+}
+
+fn parse_placement(
+    placement: &str,
+    legend: &Legend,
+) -> Result<(Board, Vec<PositionPiece>), FenError> {
+    let invalid = || FenError::InvalidPlacement(placement.to_string());
+    let ranks = placement.split('/').collect::<Vec<_>>();
+    let mut pieces = vec![];
+    let mut width = None;
+    for (index, rank_text) in ranks.iter().enumerate() {
+        let rank = Rank((ranks.len() - 1 - index) as u16);
+        let mut file = 0;
+        let mut empty = String::new();
+        for c in rank_text.chars() {
+            if c.is_ascii_digit() {
+                empty.push(c);
+                continue;
+            }
+            file += empty.parse::<u16>().unwrap_or_default();
+            empty.clear();
+            let definition = legend.piece(c).ok_or(FenError::UnknownSymbol(c))?;
+            let team = if c.is_ascii_uppercase() {
+                Team::White
+            } else {
+                Team::Black
+            };
+            let entity = Entity::from_raw(pieces.len() as u32);
+            let square = Square::new(File(file), rank);
+            pieces.push(PositionPiece::new(entity, definition.clone(), square, team));
+            file += 1;
+        }
+        file += empty.parse::<u16>().unwrap_or_default();
+        if width.is_some_and(|width| width != file) || file == 0 {
+            return Err(invalid());
+        }
+        width = Some(file);
+    }
+
+    let width = width.ok_or_else(invalid)?;
+    let board = Board {
+        size: Square::new(File(width - 1), Rank(ranks.len() as u16 - 1)),
+    };
+    Ok((board, pieces))
+}
+
+// The squares of the pieces that could be castled with on the castler's rank
+fn castling_candidates<'a>(
+    pieces: impl Iterator<Item = &'a PositionPiece>,
+    castler: &PositionPiece,
+) -> Vec<Square> {
+    let Some(origin) = castler.square else {
+        return vec![];
+    };
+    pieces
+        .filter(|piece| {
+            piece.team == castler.team && piece.definition.behaviors.castling_target.is_some()
+        })
+        .filter_map(|piece| piece.square)
+        .filter(|square| square.rank == origin.rank && *square != origin)
+        .collect()
+}
+
+fn apply_castling(castling: &str, pieces: &mut [PositionPiece]) -> Result<(), FenError> {
+    for piece in pieces.iter_mut() {
+        piece.can_castle = false;
+        piece.is_castling_target = false;
+    }
+    if castling == "-" {
+        return Ok(());
+    }
+
+    let invalid = || FenError::InvalidCastling(castling.to_string());
+    for c in castling.chars() {
+        let team = if c.is_ascii_uppercase() {
+            Team::White
+        } else {
+            Team::Black
+        };
+        let castlers = pieces
+            .iter()
+            .filter(|piece| piece.team == team && piece.definition.behaviors.castling.is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+        let target = castlers.iter().find_map(|castler| {
+            let origin = castler.square?;
+            let candidates = castling_candidates(pieces.iter(), castler);
+            match c.to_ascii_uppercase() {
+                'K' => candidates
+                    .into_iter()
+                    .filter(|square| square.file > origin.file)
+                    .max_by_key(|square| square.file),
+                'Q' => candidates
+                    .into_iter()
+                    .filter(|square| square.file < origin.file)
+                    .min_by_key(|square| square.file),
+                file => {
+                    let file = File::try_from(file).ok()?;
+                    candidates.into_iter().find(|square| square.file == file)
+                }
+            }
+        });
+        let target = target.ok_or_else(invalid)?;
+
+        for piece in pieces.iter_mut().filter(|piece| piece.team == team) {
+            if piece.square == Some(target) {
+                piece.is_castling_target = true;
+            } else if piece.definition.behaviors.castling.is_some() {
+                piece.can_castle = true;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The last turn is rebuilt from the squares it passed, as though it was played by a piece
+// of the team that is not to move
+fn parse_en_passant(
+    en_passant: &str,
+    side_to_move: Team,
+    board: &Board,
+) -> Result<Option<Action>, FenError> {
+    if en_passant == "-" {
+        return Ok(None);
+    }
+    let invalid = || FenError::InvalidEnPassant(en_passant.to_string());
+    let chars = en_passant.chars().collect::<Vec<_>>();
+    let scanned_squares = chars
+        .chunks(2)
+        .map(|square| match square {
+            [file, rank] => Square::try_from((*file, *rank))
+                .ok()
+                .filter(|square| board.contains(*square)),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)?;
+
+    let orientation = side_to_move.get_next().orientation();
+    let (x, y) = orientation.orient((0, 1));
+    let from = scanned_squares
+        .first()
+        .and_then(|square| square.checked_add(-x, -y, &board.size))
+        .ok_or_else(invalid)?;
+    let to = scanned_squares
+        .last()
+        .and_then(|square| square.checked_add(x, y, &board.size))
+        .ok_or_else(invalid)?;
+    Ok(Some(Action {
+        movement: Movement::new(from, to, orientation),
+        scanned_squares,
+        ..Default::default()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        behavior::{CastlingBehavior, CastlingTarget, EnPassantBehavior, PieceBehaviors},
+        betza,
+        pieces::{PieceIdentity, Royal},
+    };
+
+    use super::*;
+
+    fn legend() -> Legend {
+        let piece = |betza: &str, identity| {
+            PieceDefinition::new(betza::parse(betza).unwrap().into(), identity)
+        };
+        Legend::default()
+            .with_piece(
+                'K',
+                PieceDefinition {
+                    behaviors: PieceBehaviors {
+                        castling: Some(CastlingBehavior),
+                        ..betza::parse("K").unwrap().into()
+                    },
+                    royal: Some(Royal),
+                    ..piece("K", PieceIdentity::King)
+                },
+            )
+            .with_piece(
+                'R',
+                PieceDefinition {
+                    behaviors: PieceBehaviors {
+                        castling_target: Some(CastlingTarget),
+                        ..betza::parse("R").unwrap().into()
+                    },
+                    ..piece("R", PieceIdentity::Rook)
+                },
+            )
+            .with_piece('A', piece("NB", PieceIdentity::Bishop))
+            .with_piece(
+                'P',
+                PieceDefinition {
+                    behaviors: PieceBehaviors {
+                        en_passant: Some(EnPassantBehavior),
+                        ..betza::parse("fmWfcFifmW2").unwrap().into()
+                    },
+                    ..piece("fmWfcFifmW2", PieceIdentity::Pawn)
+                },
+            )
+    }
+
+    #[test]
+    fn test_fen_round_trip() {
+        let legend = legend();
+        let fen = "r3k2r/ppp2ppp/8/3pP3/2A5/8/PPP2PPP/R3K2R w Kq d6 0 12";
+        let position = Position::from_fen(fen, &legend).unwrap();
+        assert_eq!(position.board.size, Square::new(File::H, Rank::EIGHT));
+        assert_eq!(position.side_to_move, Team::White);
+        assert_eq!(position.ply, 22);
+        assert_eq!(position.to_fen(&legend).unwrap(), fen);
+
+        let white_king = position.piece_at(Square::new(File::E, Rank::ONE)).unwrap();
+        assert!(white_king.can_castle);
+        let queenside_rook = position.piece_at(Square::new(File::A, Rank::ONE)).unwrap();
+        assert!(!queenside_rook.is_castling_target);
+
+        // the pawn on e5 can capture in passing
+        let en_passant = position
+            .pseudo_legal_turns()
+            .into_iter()
+            .find(|turn| turn.action.movement.to == Square::new(File::D, Rank::SIX));
+        assert!(en_passant.is_some_and(|turn| turn
+            .action
+            .captures
+            .contains(&Square::new(File::D, Rank::FIVE))));
+
+        // a small board
+        let fen = "1k3/5/5/5/3KA b - - 3 40";
+        let position = Position::from_fen(fen, &legend).unwrap();
+        assert_eq!(position.board.size, Square::new(File::E, Rank::FIVE));
+        assert_eq!(position.to_fen(&legend).unwrap(), fen);
+
+        assert!(matches!(
+            Position::from_fen("8/8/8/8/8/8/8/4X3 w - - 0 1", &legend),
+            Err(FenError::UnknownSymbol('X'))
+        ));
+        assert!(matches!(
+            Position::from_fen("8/8/8/8/8/8/8/4K2 w - - 0 1", &legend),
+            Err(FenError::InvalidPlacement(_))
+        ));
+
+        // boards are not limited in size, but must match the board of the game
+        let fen = "10/10/10/10/10/10/10/10/10/4K5 w - - 0 1";
+        let position = Position::from_fen(fen, &legend).unwrap();
+        assert_eq!(position.board.size, Square::new(File(9), Rank(9)));
+        assert!(Position::from_fen_on(fen, &legend, &position.board).is_ok());
+        assert!(matches!(
+            Position::from_fen_on(fen, &legend, &Board::chess_board()),
+            Err(FenError::BoardMismatch { .. })
+        ));
+    }
+}
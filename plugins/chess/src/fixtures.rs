@@ -0,0 +1,27 @@
+// Simple pieces shared by the unit tests of this crate
+use crate::{
+    behavior::PatternBehavior,
+    pattern::Pattern,
+    pieces::{PieceDefinition, PieceIdentity, Royal},
+};
+
+pub(crate) fn king() -> PieceDefinition {
+    PieceDefinition {
+        royal: Some(Royal),
+        ..PieceDefinition::new(
+            PatternBehavior::default()
+                .with_pattern(Pattern::radial().leaper().captures_by_displacement())
+                .into(),
+            PieceIdentity::King,
+        )
+    }
+}
+
+pub(crate) fn rook() -> PieceDefinition {
+    PieceDefinition::new(
+        PatternBehavior::default()
+            .with_pattern(Pattern::orthogonal().rider().captures_by_displacement())
+            .into(),
+        PieceIdentity::Rook,
+    )
+}
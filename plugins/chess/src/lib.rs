@@ -3,10 +3,17 @@ use bevy_replicon::prelude::AppRuleExt;
 
 pub mod actions;
 pub mod behavior;
+pub mod betza;
 pub mod board;
+pub mod fen;
+#[cfg(test)]
+mod fixtures;
+pub mod notation;
 pub mod pattern;
 pub mod pieces;
+pub mod position;
 pub mod team;
+pub mod value;
 
 pub struct ChessPlugin;
 
@@ -0,0 +1,381 @@
+use std::collections::VecDeque;
+
+use thiserror::Error;
+
+use bevy_utils::HashMap;
+
+use crate::{
+    board::Square,
+    fen::without_castling,
+    pieces::{PieceDefinition, PieceIdentity},
+    position::{Position, PositionPiece, Turn},
+};
+
+#[derive(Debug, Error)]
+pub enum NotationError {
+    #[error("`{0}` does not match any available turn")]
+    NoMatch(String),
+    #[error("`{0}` matches more than one available turn")]
+    Ambiguous(String),
+}
+
+// Writes and reads turns in a SAN-style notation, such as `Nbd7`, `exd5`, `e8=Q+` or `O-O`.
+// Pieces are named by a letter for their `PieceIdentity`, except for pawns which have none.
+// When several distinct pieces share an identity, as in wild armies, each gets its own letter.
+// A piece entering the board from outside of it, such as from a reserve, is written as a drop:
+// `N@e4`.
+#[derive(Clone, Debug)]
+pub struct Notation {
+    symbols: HashMap<PieceIdentity, char>,
+    pieces: Vec<(PieceDefinition, char)>,
+}
+
+impl Default for Notation {
+    fn default() -> Self {
+        Notation {
+            symbols: [
+                (PieceIdentity::King, 'K'),
+                (PieceIdentity::Queen, 'Q'),
+                (PieceIdentity::Rook, 'R'),
+                (PieceIdentity::Bishop, 'B'),
+                (PieceIdentity::Knight, 'N'),
+                (PieceIdentity::Pawn, 'P'),
+            ]
+            .into_iter()
+            .collect(),
+            pieces: vec![],
+        }
+    }
+}
+
+// Letters given to pieces whose identity letter is already taken.
+// Classical letters are left for their identities, and `O` and `X` would read as castling or a
+// capture.
+const SPARE_SYMBOLS: &str = "ACDEFGHIJLMSTUVWYZ";
+
+impl Notation {
+    // Uses a custom letter for pieces with the given identity
+    #[must_use]
+    pub fn with_symbol(mut self, identity: PieceIdentity, symbol: char) -> Self {
+        self.symbols.insert(identity, symbol);
+        self
+    }
+
+    pub fn symbol(&self, identity: PieceIdentity) -> char {
+        self.symbols.get(&identity).copied().unwrap_or('?')
+    }
+
+    // Names every distinct piece of an army, including the pieces they can mutate into.
+    // The first piece of each identity keeps the identity's letter and the others get spare
+    // letters, in the order they are given.
+    #[must_use]
+    pub fn with_pieces<'a>(
+        mut self,
+        pieces: impl IntoIterator<Item = &'a PieceDefinition>,
+    ) -> Self {
+        let mut pending = pieces.into_iter().collect::<VecDeque<_>>();
+        while let Some(piece) = pending.pop_front() {
+            if let Some(mutation) = &piece.mutation {
+                pending.extend(mutation.to_piece.iter());
+            }
+            let piece = without_castling(piece);
+            if self.pieces.iter().any(|(other, _)| *other == piece) {
+                continue;
+            }
+            let identity_symbol = self.symbol(piece.identity);
+            let symbol = if self
+                .pieces
+                .iter()
+                .all(|(_, symbol)| *symbol != identity_symbol)
+            {
+                identity_symbol
+            } else {
+                let Some(spare) = SPARE_SYMBOLS
+                    .chars()
+                    .find(|spare| self.pieces.iter().all(|(_, symbol)| symbol != spare))
+                else {
+                    continue;
+                };
+                spare
+            };
+            self.pieces.push((piece, symbol));
+        }
+        self
+    }
+
+    // The letter of a piece, falling back to the letter of its identity
+    pub fn piece_symbol(&self, piece: &PieceDefinition) -> char {
+        if !self.pieces.is_empty() {
+            let piece = without_castling(piece);
+            if let Some((_, symbol)) = self.pieces.iter().find(|(other, _)| *other == piece) {
+                return *symbol;
+            }
+        }
+        self.symbol(piece.identity)
+    }
+
+    fn is_pawn(&self, piece: &PieceDefinition) -> bool {
+        self.piece_symbol(piece) == self.symbol(PieceIdentity::Pawn)
+    }
+
+    // Writes a turn played from `position`, including whether it checks or mates.
+    // The position is left as it was.
+    pub fn write(&self, position: &mut Position, turn: &Turn) -> String {
+        let mut text = self.write_movement(position, turn);
+        let Some(team) = position.piece(turn.piece).map(|piece| piece.team) else {
+            return text;
+        };
+        if position.make(turn).is_ok() {
+            let opponent = team.get_next();
+            if position.is_royal_capturable(opponent) {
+                if position.legal_actions().is_empty() {
+                    text.push('#');
+                } else {
+                    text.push('+');
+                }
+            }
+            position.unmake();
+        }
+        text
+    }
+
+    // Finds the turn of the side to move in `position` that the text describes.
+    // Check marks and annotations are ignored, and coordinates such as `e2e4` are also accepted.
+    pub fn parse(&self, position: &Position, text: &str) -> Result<Turn, NotationError> {
+        let normalized = normalize(text);
+        let turns = position.pseudo_legal_turns();
+
+        let exact = turns
+            .iter()
+            .filter(|turn| {
+                self.candidates(position, turn)
+                    .iter()
+                    .any(|candidate| normalize(candidate) == normalized)
+            })
+            .collect::<Vec<_>>();
+        if let [turn] = exact.as_slice() {
+            return Ok((*turn).clone());
+        }
+
+        // text missing a needed origin or mutation, such as `Re1` when both rooks can move there
+        let loose = turns
+            .iter()
+            .filter(|turn| self.write_loose(position, turn) == normalized)
+            .count();
+        if exact.len() > 1 || loose > 1 {
+            Err(NotationError::Ambiguous(text.to_string()))
+        } else {
+            Err(NotationError::NoMatch(text.to_string()))
+        }
+    }
+
+    // The ways a turn can be written
+    fn candidates(&self, position: &Position, turn: &Turn) -> [String; 3] {
+        let movement = &turn.action.movement;
+        let coordinates = format!(
+            "{}{}{}",
+            movement.from,
+            movement.to,
+            self.write_mutation(turn)
+        );
+        let prefix = position
+            .piece(turn.piece)
+            .map(|piece| self.piece_symbol(&piece.definition).to_string())
+            .unwrap_or_default();
+        [
+            self.write_movement(position, turn),
+            format!("{prefix}{coordinates}"),
+            coordinates,
+        ]
+    }
+
+    // The normalized notation of a turn without its origin, capture or mutation
+    fn write_loose(&self, position: &Position, turn: &Turn) -> String {
+        let prefix = position
+            .piece(turn.piece)
+            .map(|piece| &piece.definition)
+            .filter(|piece| !self.is_pawn(piece))
+            .map(|piece| self.piece_symbol(piece).to_string())
+            .unwrap_or_default();
+        format!("{prefix}{}", turn.action.movement.to)
+    }
+
+    // The notation of a turn without check marks
+    fn write_movement(&self, position: &Position, turn: &Turn) -> String {
+        let movement = &turn.action.movement;
+        let Some(piece) = position.piece(turn.piece) else {
+            return format!("{}{}", movement.from, movement.to);
+        };
+        let symbol = self.piece_symbol(&piece.definition);
+
+        if !position.board.contains(movement.from) {
+            return format!("{symbol}@{}", movement.to);
+        }
+        if piece.definition.behaviors.castling.is_some() && !turn.action.side_effects.is_empty() {
+            return if movement.to.file > movement.from.file {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            };
+        }
+
+        let is_pawn = self.is_pawn(&piece.definition);
+        let is_capture = !turn.action.captures.is_empty();
+        let mut text = String::new();
+        if !is_pawn {
+            text.push(symbol);
+        }
+        text.push_str(&self.disambiguation(position, piece, movement.to, is_pawn && is_capture));
+        if is_capture {
+            text.push('x');
+        }
+        text.push_str(&movement.to.to_string());
+        text.push_str(&self.write_mutation(turn));
+        text
+    }
+
+    fn write_mutation(&self, turn: &Turn) -> String {
+        turn.mutation
+            .as_ref()
+            .map(|mutation| format!("={}", self.piece_symbol(mutation)))
+            .unwrap_or_default()
+    }
+
+    // The origin file and/or rank needed to tell the piece apart from other pieces of the same kind
+    // that could also move to `target`
+    fn disambiguation(
+        &self,
+        position: &Position,
+        piece: &PositionPiece,
+        target: Square,
+        always_file: bool,
+    ) -> String {
+        let Some(origin) = piece.square else {
+            return String::new();
+        };
+        let rivals = position
+            .pseudo_legal_actions()
+            .into_iter()
+            .filter(|(entity, actions)| *entity != piece.entity && actions.get(&target).is_some())
+            .filter_map(|(entity, _)| position.piece(entity))
+            .filter(|other| {
+                other.team == piece.team
+                    && self.piece_symbol(&other.definition) == self.piece_symbol(&piece.definition)
+            })
+            .filter_map(|other| other.square)
+            .collect::<Vec<_>>();
+        if rivals.is_empty() && !always_file {
+            return String::new();
+        }
+
+        let file = char::from(&origin.file).to_string();
+        let rank = char::from(&origin.rank).to_string();
+        if rivals.iter().all(|square| square.file != origin.file) {
+            file
+        } else if !always_file && rivals.iter().all(|square| square.rank != origin.rank) {
+            rank
+        } else {
+            format!("{file}{rank}")
+        }
+    }
+}
+
+// Drops the parts of the notation that do not identify the turn
+fn normalize(text: &str) -> String {
+    text.trim()
+        .replace('0', "O")
+        .chars()
+        .filter(|c| !matches!(c, '+' | '#' | '!' | '?' | '-' | 'x' | '=' | ' '))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{Board, File, Rank},
+        fixtures::{king, rook},
+        team::Team,
+    };
+
+    use super::*;
+
+    fn find_turn(position: &Position, from: Square, to: Square) -> Turn {
+        position
+            .pseudo_legal_turns()
+            .into_iter()
+            .find(|turn| turn.action.movement.from == from && turn.action.movement.to == to)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_write_and_parse() {
+        let mut position = Position::new(Board::chess_board());
+        position.add_piece(king(), Square::new(File::G, Rank::SIX), Team::White);
+        position.add_piece(rook(), Square::new(File::A, Rank::ONE), Team::White);
+        position.add_piece(rook(), Square::new(File::H, Rank::ONE), Team::White);
+        position.add_piece(king(), Square::new(File::G, Rank::EIGHT), Team::Black);
+        let notation = Notation::default();
+
+        // both rooks can reach e1
+        let turn = find_turn(
+            &position,
+            Square::new(File::A, Rank::ONE),
+            Square::new(File::E, Rank::ONE),
+        );
+        assert_eq!(notation.write(&mut position, &turn), "Rae1");
+
+        let turn = find_turn(
+            &position,
+            Square::new(File::A, Rank::ONE),
+            Square::new(File::A, Rank::EIGHT),
+        );
+        assert_eq!(notation.write(&mut position, &turn), "Ra8#");
+
+        let parsed = notation.parse(&position, "Ra8#").unwrap();
+        assert_eq!(parsed.action, turn.action);
+        let parsed = notation.parse(&position, "a1-a8").unwrap();
+        assert_eq!(parsed.action, turn.action);
+        assert!(matches!(
+            notation.parse(&position, "Re1"),
+            Err(NotationError::Ambiguous(_))
+        ));
+        assert!(matches!(
+            notation.parse(&position, "Rb2"),
+            Err(NotationError::NoMatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_distinct_pieces_with_one_identity() {
+        // a rook-like piece of a wild army that only steps one square
+        let wazir = PieceDefinition {
+            identity: PieceIdentity::Rook,
+            royal: None,
+            ..king()
+        };
+        let mut position = Position::new(Board::chess_board());
+        position.add_piece(king(), Square::new(File::G, Rank::SIX), Team::White);
+        position.add_piece(rook(), Square::new(File::A, Rank::ONE), Team::White);
+        position.add_piece(wazir.clone(), Square::new(File::F, Rank::ONE), Team::White);
+        position.add_piece(king(), Square::new(File::G, Rank::EIGHT), Team::Black);
+        let notation = Notation::default().with_pieces([&king(), &rook(), &wazir]);
+        assert_eq!(notation.piece_symbol(&rook()), 'R');
+        assert_eq!(notation.piece_symbol(&wazir), 'A');
+
+        // both pieces can reach e1, but they are written with different letters
+        let turn = find_turn(
+            &position,
+            Square::new(File::A, Rank::ONE),
+            Square::new(File::E, Rank::ONE),
+        );
+        assert_eq!(notation.write(&mut position, &turn), "Re1");
+        let turn = find_turn(
+            &position,
+            Square::new(File::F, Rank::ONE),
+            Square::new(File::E, Rank::ONE),
+        );
+        assert_eq!(notation.write(&mut position, &turn), "Ae1");
+        let parsed = notation.parse(&position, "Ae1").unwrap();
+        assert_eq!(parsed.action, turn.action);
+    }
+}
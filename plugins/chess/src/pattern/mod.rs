@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "reflect")]
 use bevy_reflect::Reflect;
-use bevy_utils::HashMap;
+use bevy_utils::{HashMap, HashSet};
 
 use crate::{
     actions::{Action, Movement},
@@ -260,21 +260,21 @@ impl Pattern {
         }
     }
 
-    pub fn search(
-        &self,
+    // The scan targets this pattern can act on from `origin`, after its constraints
+    fn scan_targets<'a>(
+        &'a self,
         origin: &Square,
         orientation: &Orientation,
         my_team: &Team,
         board: &Board,
         pieces: &HashMap<Square, Team>,
-        last_action: Option<&Action>,
-    ) -> HashMap<Square, Action> {
+    ) -> impl Iterator<Item = ScanTarget> + 'a {
         if let Some(rank_constraint) = &self.constraints.from_rank {
             let allowed_rank = Square::new(File(rank_constraint.0 .0), rank_constraint.0)
                 .reorient(my_team.orientation(), board)
                 .rank;
             if origin.rank != allowed_rank {
-                return HashMap::new();
+                return Either::Left(std::iter::empty());
             }
         }
 
@@ -282,32 +282,61 @@ impl Pattern {
             .scanner
             .scan(origin, *orientation, my_team, board, pieces);
 
-        if let Some(ForbiddenTargetConstraint(forbidden_squares)) =
-            &self.constraints.forbidden_targets
-        {
-            Either::Right(scan_targets.into_iter().filter_map(|target| {
-                if forbidden_squares.contains(&target.target) {
-                    None
-                } else {
-                    Some(target)
-                }
-            }))
-        } else {
-            Either::Left(scan_targets.into_iter())
-        }
-        // TODO: how do squares with multiple actions merge them?
-        // this is fine for now but we can do better
-        .filter_map(|scan_target| {
-            self.get_action_for_target(
-                scan_target,
-                origin,
-                orientation,
-                my_team,
-                pieces,
-                last_action,
+        Either::Right(scan_targets.into_iter().filter(|target| {
+            !self.constraints.forbidden_targets.as_ref().is_some_and(
+                |ForbiddenTargetConstraint(forbidden_squares)| {
+                    forbidden_squares.contains(&target.target)
+                },
             )
-        })
-        .collect()
+        }))
+    }
+
+    pub fn search(
+        &self,
+        origin: &Square,
+        orientation: &Orientation,
+        my_team: &Team,
+        board: &Board,
+        pieces: &HashMap<Square, Team>,
+        last_action: Option<&Action>,
+    ) -> HashMap<Square, Action> {
+        self.scan_targets(origin, orientation, my_team, board, pieces)
+            // TODO: how do squares with multiple actions merge them?
+            // this is fine for now but we can do better
+            .filter_map(|scan_target| {
+                self.get_action_for_target(
+                    scan_target,
+                    origin,
+                    orientation,
+                    my_team,
+                    pieces,
+                    last_action,
+                )
+            })
+            .collect()
+    }
+
+    // The squares this pattern could capture on, whether or not there is anything to capture.
+    // This includes empty squares that the pattern cannot move to, such as the diagonals
+    // in front of a pawn, which castling cannot pass through.
+    pub fn threats(
+        &self,
+        origin: &Square,
+        orientation: &Orientation,
+        my_team: &Team,
+        board: &Board,
+        pieces: &HashMap<Square, Team>,
+    ) -> HashSet<Square> {
+        let Some(capture) = self.capture else {
+            return HashSet::new();
+        };
+        self.scan_targets(origin, orientation, my_team, board, pieces)
+            .flat_map(|scan_target| {
+                capture
+                    .get_captures(&scan_target, my_team, pieces, None)
+                    .threats
+            })
+            .collect()
     }
 }
 
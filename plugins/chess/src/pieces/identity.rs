@@ -10,7 +10,7 @@ use bevy_reflect::Reflect;
 // but variants often change these.
 // It is mostly useful for supplying contextual information to users, such as displaying a
 // particular icon.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
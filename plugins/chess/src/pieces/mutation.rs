@@ -6,13 +6,17 @@ use bevy_ecs::prelude::ReflectComponent;
 #[cfg(feature = "reflect")]
 use bevy_reflect::Reflect;
 
-use crate::board::Rank;
+use crate::{
+    actions::Action,
+    board::{Board, Rank},
+    team::Team,
+};
 
 use super::PieceDefinition;
 
 // AKA "Promotion", but named Mutation in case of more general purposes
 // TODO: split condition and required into separate component types and systems?
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
@@ -29,7 +33,30 @@ pub struct Mutation {
     pub to_royal: bool,
 }
 
-#[derive(Clone, Debug)]
+impl Mutation {
+    // whether performing this action should mutate a piece on the given team
+    pub fn is_triggered(&self, action: &Action, team: &Team, board: &Board) -> bool {
+        match self.condition {
+            MutationCondition::LocalRank(rank) => {
+                rank == action.movement.to.reorient(team.orientation(), board).rank
+            }
+            MutationCondition::OnCapture => !action.captures.is_empty(),
+        }
+    }
+
+    // The pieces a turn performing this action can mutate into, one of which is always chosen.
+    // Empty when the action does not trigger the mutation.
+    // Both `Position` and the games crate expand turns with this.
+    pub fn options(&self, action: &Action, team: &Team, board: &Board) -> &[PieceDefinition] {
+        if self.is_triggered(action, team, board) {
+            &self.to_piece
+        } else {
+            &[]
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 pub enum MutationCondition {
@@ -46,7 +73,7 @@ impl Default for MutationCondition {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 pub enum MutationRequired {
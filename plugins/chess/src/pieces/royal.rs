@@ -7,7 +7,7 @@ use bevy_ecs::prelude::ReflectComponent;
 use bevy_reflect::Reflect;
 
 // Once all Royal pieces are captured, a player loses the game.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
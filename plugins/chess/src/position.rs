@@ -0,0 +1,711 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use bevy_ecs::{
+    entity::MapEntities,
+    prelude::{Entity, EntityMapper},
+};
+use bevy_utils::{HashMap, HashSet};
+
+use crate::{
+    actions::{Action, Actions},
+    behavior::{BoardThreatsCache, CastlingBehavior, RelayBehavior},
+    board::{Board, Square},
+    pattern::Pattern,
+    pieces::{Orientation, PieceDefinition, PieceIdentity},
+    team::Team,
+};
+
+#[derive(Debug, Error)]
+pub enum PositionError {
+    #[error("Piece {0} is not part of this position")]
+    MissingPiece(Entity),
+}
+
+// Why a position cannot start a game
+#[derive(Debug, Error)]
+pub enum SetupError {
+    #[error("{0:?} has no pieces on the board")]
+    MissingPieces(Team),
+    #[error("{0:?} has no royal piece")]
+    MissingRoyal(Team),
+    #[error("{0:?} is to move and can already capture a royal piece")]
+    RoyalCapturable(Team),
+    #[error("{0} holds more than one piece")]
+    SharedSquare(Square),
+    #[error("{0} is not on the board")]
+    OutOfBoard(Square),
+}
+
+// A piece as tracked by a standalone `Position`.
+// The entity is only an identifier: it is what `Action` side effects refer to, so
+// positions mirroring a game can reuse the piece entities of that game.
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+pub struct PositionPiece {
+    pub entity: Entity,
+    pub definition: PieceDefinition,
+    pub team: Team,
+    pub orientation: Orientation,
+    // None once the piece has been captured
+    pub square: Option<Square>,
+    // castling rights, lost once the piece moves
+    pub can_castle: bool,
+    pub is_castling_target: bool,
+}
+
+impl PositionPiece {
+    pub fn new(entity: Entity, definition: PieceDefinition, square: Square, team: Team) -> Self {
+        PositionPiece {
+            entity,
+            can_castle: definition.behaviors.castling.is_some(),
+            is_castling_target: definition.behaviors.castling_target.is_some(),
+            definition,
+            team,
+            orientation: team.orientation(),
+            square: Some(square),
+        }
+    }
+
+    pub fn is_royal(&self) -> bool {
+        self.definition.royal.is_some()
+    }
+}
+
+// A turn played in a `Position`.
+// This mirrors the data sent with a `PlayTurn` in the games crate.
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+pub struct Turn {
+    pub piece: Entity,
+    pub action: Action,
+    pub mutation: Option<PieceDefinition>,
+}
+
+// The state of a piece before a turn changed it
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+struct PieceUndo {
+    index: usize,
+    square: Option<Square>,
+    can_castle: bool,
+    is_castling_target: bool,
+}
+
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+struct Undo {
+    turn: Turn,
+    pieces: Vec<PieceUndo>,
+    definition: Option<(usize, PieceDefinition)>,
+    last_action: Option<Action>,
+    halfmove_clock: usize,
+}
+
+// A game position that can be evaluated without an ECS `World`.
+// Action generation uses the same searches as `BehaviorsPlugin`, and turns are applied
+// in the same way as the games crate's `PlayTurn` observer.
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+pub struct Position {
+    pub board: Board,
+    pieces: Vec<PositionPiece>,
+    pub side_to_move: Team,
+    pub last_action: Option<Action>,
+    pub ply: usize,
+    // turns since the last capture or pawn move
+    pub halfmove_clock: usize,
+    undo_stack: Vec<Undo>,
+}
+
+impl Position {
+    pub fn new(board: Board) -> Self {
+        Position {
+            board,
+            pieces: vec![],
+            side_to_move: Team::White,
+            last_action: None,
+            ply: 0,
+            halfmove_clock: 0,
+            undo_stack: vec![],
+        }
+    }
+
+    // Adds a new piece to the position, returning the entity used to identify it.
+    // New pieces are identified past every entity in the position, including inserted ones.
+    pub fn add_piece(&mut self, definition: PieceDefinition, square: Square, team: Team) -> Entity {
+        let next_index = self
+            .pieces
+            .iter()
+            .map(|piece| piece.entity.index() + 1)
+            .max()
+            .unwrap_or_default();
+        let entity = Entity::from_raw(next_index);
+        self.insert_piece(PositionPiece::new(entity, definition, square, team));
+        entity
+    }
+
+    // Adds a piece that is already identified, such as a piece entity spawned for a game
+    pub fn insert_piece(&mut self, piece: PositionPiece) {
+        self.pieces.push(piece);
+    }
+
+    pub fn pieces(&self) -> impl Iterator<Item = &PositionPiece> {
+        self.pieces.iter()
+    }
+
+    pub fn piece(&self, entity: Entity) -> Option<&PositionPiece> {
+        self.pieces.iter().find(|piece| piece.entity == entity)
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<&PositionPiece> {
+        self.pieces
+            .iter()
+            .find(|piece| piece.square == Some(square))
+    }
+
+    fn index_of(&self, entity: Entity) -> Option<usize> {
+        self.pieces.iter().position(|piece| piece.entity == entity)
+    }
+
+    fn teams(&self) -> HashMap<Square, Team> {
+        self.pieces
+            .iter()
+            .filter_map(|piece| piece.square.map(|square| (square, piece.team)))
+            .collect()
+    }
+}
+
+// Positions copied from a game refer to its piece entities
+impl MapEntities for Position {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        for piece in self.pieces.iter_mut() {
+            piece.entity = mapper.map_entity(piece.entity);
+        }
+        if let Some(action) = &mut self.last_action {
+            action.map_entities(mapper);
+        }
+        for undo in self.undo_stack.iter_mut() {
+            undo.turn.piece = mapper.map_entity(undo.turn.piece);
+            undo.turn.action.map_entities(mapper);
+            if let Some(action) = &mut undo.last_action {
+                action.map_entities(mapper);
+            }
+        }
+    }
+}
+
+// Action generation
+impl Position {
+    // Calculates the actions of every piece on the board (or only those of `team`),
+    // following the same steps as the `Behavior` systems.
+    fn behavior_actions(&self, team: Option<Team>) -> Vec<(usize, Actions)> {
+        let teams = self.teams();
+        let last_action = self.last_action.as_ref();
+
+        let en_passant_pieces = self
+            .pieces
+            .iter()
+            .filter_map(|piece| {
+                piece
+                    .square
+                    .map(|square| (square, (piece.definition.behaviors.en_passant, piece.team)))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut relay_pattern_map: HashMap<Square, Vec<(Pattern, Team)>> = HashMap::new();
+        for piece in self.pieces.iter() {
+            if let (Some(square), Some(relay)) = (piece.square, &piece.definition.behaviors.relay) {
+                relay.relay_patterns(
+                    &square,
+                    &piece.orientation,
+                    &piece.team,
+                    &self.board,
+                    &teams,
+                    &mut relay_pattern_map,
+                );
+            }
+        }
+
+        self.pieces
+            .iter()
+            .enumerate()
+            .filter(|(_, piece)| team.is_none() || team == Some(piece.team))
+            .filter_map(|(index, piece)| {
+                let square = piece.square?;
+                let behaviors = &piece.definition.behaviors;
+                let mut actions = Actions::default();
+                if let Some(pattern) = &behaviors.pattern {
+                    actions.extend(pattern.search(
+                        &square,
+                        &piece.orientation,
+                        &piece.team,
+                        &self.board,
+                        &teams,
+                        last_action,
+                    ));
+                }
+                if let Some(en_passant) = &behaviors.en_passant {
+                    actions.extend(en_passant.search(
+                        &square,
+                        &piece.orientation,
+                        &piece.team,
+                        &self.board,
+                        &en_passant_pieces,
+                        last_action,
+                    ));
+                }
+                if let Some(patterns) = relay_pattern_map.remove(&square) {
+                    actions.extend(RelayBehavior::search_relayed(
+                        patterns,
+                        &square,
+                        &piece.orientation,
+                        &piece.team,
+                        &self.board,
+                        &teams,
+                        last_action,
+                    ));
+                }
+                Some((index, actions))
+            })
+            .collect()
+    }
+
+    // All actions available to the side to move, including those that leave a royal
+    // piece capturable. This is what the ECS calculates for each piece.
+    pub fn pseudo_legal_actions(&self) -> Vec<(Entity, Actions)> {
+        let mut all_actions = self.behavior_actions(None);
+
+        let teams = self.teams();
+        let mut threats = BoardThreatsCache::default();
+        for (index, actions) in all_actions.iter() {
+            threats.add_threats(self.pieces[*index].team, actions);
+        }
+        for piece in self.pieces.iter() {
+            if let (Some(square), Some(pattern)) =
+                (piece.square, &piece.definition.behaviors.pattern)
+            {
+                let squares = pattern.threats(
+                    &square,
+                    &piece.orientation,
+                    &piece.team,
+                    &self.board,
+                    &teams,
+                );
+                threats.add_threatened_squares(piece.team, squares);
+            }
+        }
+
+        let castling_targets = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.is_castling_target)
+            .filter_map(|piece| {
+                piece
+                    .square
+                    .map(|square| (piece.entity, square, piece.team, piece.orientation))
+            })
+            .collect::<Vec<_>>();
+        for (index, actions) in all_actions.iter_mut() {
+            let piece = &self.pieces[*index];
+            if let (true, Some(square)) = (piece.can_castle, piece.square) {
+                actions.extend(CastlingBehavior::search(
+                    &square,
+                    &piece.team,
+                    &piece.orientation,
+                    castling_targets.iter().copied(),
+                    &teams,
+                    &threats,
+                ));
+            }
+        }
+
+        all_actions
+            .into_iter()
+            .filter(|(index, _)| self.pieces[*index].team == self.side_to_move)
+            .map(|(index, actions)| (self.pieces[index].entity, actions))
+            .collect()
+    }
+
+    // Checks that a set up position can start a game:
+    // every piece is on its own square of the board, each team with royal pieces in its army
+    // (or every team, when the win condition needs them) has one on the board,
+    // and the side to move cannot capture a royal piece of its opponent straight away.
+    // Armies without royal pieces, such as the horde, are lost once all of their pieces are.
+    pub fn validate(&self, royal_required: bool) -> Result<(), SetupError> {
+        let mut squares = HashSet::new();
+        for square in self.pieces.iter().filter_map(|piece| piece.square) {
+            if !self.board.contains(square) {
+                return Err(SetupError::OutOfBoard(square));
+            }
+            if !squares.insert(square) {
+                return Err(SetupError::SharedSquare(square));
+            }
+        }
+        for team in [Team::White, Team::Black] {
+            // a team without pieces would have lost before its first turn
+            if !self
+                .pieces
+                .iter()
+                .any(|piece| piece.team == team && piece.square.is_some())
+            {
+                return Err(SetupError::MissingPieces(team));
+            }
+            let mut royals = self
+                .pieces
+                .iter()
+                .filter(|piece| piece.team == team && piece.is_royal())
+                .peekable();
+            let has_royal_army = royals.peek().is_some();
+            if (royal_required || has_royal_army) && !royals.any(|piece| piece.square.is_some()) {
+                return Err(SetupError::MissingRoyal(team));
+            }
+        }
+        if self.is_royal_capturable(self.side_to_move.get_next()) {
+            return Err(SetupError::RoyalCapturable(self.side_to_move));
+        }
+        Ok(())
+    }
+
+    // Whether any royal piece of `team` could be captured by the other team
+    pub fn is_royal_capturable(&self, team: Team) -> bool {
+        let royal_squares = self
+            .pieces
+            .iter()
+            .filter(|piece| piece.team == team && piece.is_royal())
+            .filter_map(|piece| piece.square)
+            .collect::<Vec<_>>();
+        if royal_squares.is_empty() {
+            return false;
+        }
+        self.behavior_actions(Some(team.get_next()))
+            .iter()
+            .flat_map(|(_, actions)| actions.0.values())
+            .any(|action| {
+                action
+                    .captures
+                    .iter()
+                    .any(|capture| royal_squares.contains(capture))
+            })
+    }
+
+    // The actions of every piece on the board, whichever team is to move.
+    // Castling is not included since it depends on the threats of the other team.
+    pub fn piece_actions(&self) -> impl Iterator<Item = (&PositionPiece, Actions)> {
+        self.behavior_actions(None)
+            .into_iter()
+            .map(|(index, actions)| (&self.pieces[index], actions))
+    }
+
+    // All turns available to the side to move, including those that leave a royal piece
+    // capturable. Each mutation option is a separate turn.
+    pub fn pseudo_legal_turns(&self) -> Vec<Turn> {
+        let mut turns = vec![];
+        for (entity, actions) in self.pseudo_legal_actions() {
+            for action in actions.0.into_values() {
+                self.push_turns(entity, action, &mut turns);
+            }
+        }
+        turns
+    }
+
+    // All turns available to the side to move which do not leave one of its royal pieces
+    // capturable. Each mutation option is a separate turn.
+    pub fn legal_actions(&mut self) -> Vec<Turn> {
+        let team = self.side_to_move;
+        let mut turns = self.pseudo_legal_turns();
+        turns.retain(|turn| {
+            if self.make(turn).is_err() {
+                return false;
+            }
+            let is_legal = !self.is_royal_capturable(team);
+            self.unmake();
+            is_legal
+        });
+        turns
+    }
+
+    fn push_turns(&self, entity: Entity, action: Action, turns: &mut Vec<Turn>) {
+        let options = self
+            .piece(entity)
+            .and_then(|piece| {
+                piece
+                    .definition
+                    .mutation
+                    .as_ref()
+                    .map(|mutation| mutation.options(&action, &piece.team, &self.board))
+            })
+            .unwrap_or_default();
+        if options.is_empty() {
+            turns.push(Turn {
+                piece: entity,
+                action,
+                mutation: None,
+            });
+            return;
+        }
+
+        for mutated_piece in options.iter() {
+            turns.push(Turn {
+                piece: entity,
+                action: action.clone(),
+                mutation: Some(mutated_piece.clone()),
+            });
+        }
+    }
+}
+
+// Playing turns
+impl Position {
+    pub fn make(&mut self, turn: &Turn) -> Result<(), PositionError> {
+        let index = self
+            .index_of(turn.piece)
+            .ok_or(PositionError::MissingPiece(turn.piece))?;
+        let action = &turn.action;
+
+        let mut undo = Undo {
+            turn: turn.clone(),
+            pieces: vec![],
+            definition: None,
+            last_action: self.last_action.clone(),
+            halfmove_clock: self.halfmove_clock,
+        };
+        if action.captures.is_empty()
+            && self.pieces[index].definition.identity != PieceIdentity::Pawn
+        {
+            self.halfmove_clock += 1;
+        } else {
+            self.halfmove_clock = 0;
+        }
+
+        // execute the primary movement
+        undo.pieces.push(self.move_piece(index, action.movement.to));
+
+        // execute side effects
+        for (side_effect_piece, additional_movement) in action.side_effects.iter() {
+            if let Some(side_effect_index) = self.index_of(*side_effect_piece) {
+                undo.pieces
+                    .push(self.move_piece(side_effect_index, additional_movement.to));
+            }
+        }
+
+        // execute captures
+        for capture_square in action.captures.iter() {
+            let captured_piece =
+                self.pieces.iter().enumerate().position(|(other, piece)| {
+                    other != index && piece.square == Some(*capture_square)
+                });
+            if let Some(captured_index) = captured_piece {
+                undo.pieces.push(self.save_piece(captured_index));
+                self.pieces[captured_index].square = None;
+            }
+        }
+
+        // mutate the piece if specified
+        if let Some(mutated_piece) = &turn.mutation {
+            let previous =
+                std::mem::replace(&mut self.pieces[index].definition, mutated_piece.clone());
+            undo.definition = Some((index, previous));
+        }
+
+        self.last_action = Some(action.clone());
+        self.ply += 1;
+        self.side_to_move = self.side_to_move.get_next();
+        self.undo_stack.push(undo);
+        Ok(())
+    }
+
+    // Reverts the last turn made, returning it
+    pub fn unmake(&mut self) -> Option<Turn> {
+        let undo = self.undo_stack.pop()?;
+
+        if let Some((index, definition)) = undo.definition {
+            self.pieces[index].definition = definition;
+        }
+        for piece_undo in undo.pieces.into_iter().rev() {
+            let piece = &mut self.pieces[piece_undo.index];
+            piece.square = piece_undo.square;
+            piece.can_castle = piece_undo.can_castle;
+            piece.is_castling_target = piece_undo.is_castling_target;
+        }
+
+        self.last_action = undo.last_action;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.ply -= 1;
+        self.side_to_move = self.side_to_move.get_next();
+        Some(undo.turn)
+    }
+
+    fn save_piece(&self, index: usize) -> PieceUndo {
+        let piece = &self.pieces[index];
+        PieceUndo {
+            index,
+            square: piece.square,
+            can_castle: piece.can_castle,
+            is_castling_target: piece.is_castling_target,
+        }
+    }
+
+    // moving a piece loses its castling rights, as with `disable_on_move`
+    fn move_piece(&mut self, index: usize, square: Square) -> PieceUndo {
+        let undo = self.save_piece(index);
+        let piece = &mut self.pieces[index];
+        piece.square = Some(square);
+        piece.can_castle = false;
+        piece.is_castling_target = false;
+        undo
+    }
+
+    // Counts the leaf nodes of the legal move tree to the given depth
+    pub fn perft(&mut self, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        let turns = self.legal_actions();
+        if depth == 1 {
+            return turns.len();
+        }
+        let mut nodes = 0;
+        for turn in turns.iter() {
+            if self.make(turn).is_ok() {
+                nodes += self.perft(depth - 1);
+                self.unmake();
+            }
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::{File, Rank},
+        fixtures::{king, rook},
+    };
+
+    use super::*;
+
+    fn sample_position() -> (Position, Entity) {
+        let mut position = Position::new(Board::chess_board());
+        let white_king = position.add_piece(king(), Square::new(File::E, Rank::ONE), Team::White);
+        position.add_piece(rook(), Square::new(File::E, Rank::EIGHT), Team::Black);
+        position.add_piece(king(), Square::new(File::A, Rank::EIGHT), Team::Black);
+        (position, white_king)
+    }
+
+    #[test]
+    fn test_added_pieces_are_unique() {
+        let (mut position, _) = sample_position();
+        let inserted = Entity::from_raw(7);
+        position.insert_piece(PositionPiece::new(
+            inserted,
+            rook(),
+            Square::new(File::H, Rank::ONE),
+            Team::White,
+        ));
+        let added = position.add_piece(rook(), Square::new(File::A, Rank::ONE), Team::White);
+        assert_ne!(added, inserted);
+        assert_eq!(
+            position
+                .pieces()
+                .filter(|piece| piece.entity == added)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_royal_cannot_move_into_capture() {
+        let (mut position, _) = sample_position();
+        let mut squares = position
+            .legal_actions()
+            .into_iter()
+            .map(|turn| turn.action.movement.to)
+            .collect::<Vec<_>>();
+        squares.sort();
+
+        let mut correct = vec![
+            Square::new(File::D, Rank::ONE),
+            Square::new(File::D, Rank::TWO),
+            Square::new(File::F, Rank::ONE),
+            Square::new(File::F, Rank::TWO),
+        ];
+        correct.sort();
+
+        assert_eq!(squares, correct);
+    }
+
+    #[test]
+    fn test_validate() {
+        let (mut position, _) = sample_position();
+        assert!(position.validate(true).is_ok());
+
+        // the white king is attacked by the rook, so Black cannot be to move
+        position.side_to_move = Team::Black;
+        assert!(matches!(
+            position.validate(false),
+            Err(SetupError::RoyalCapturable(Team::Black))
+        ));
+
+        // Black's army has no royal pieces, which only the win condition may require
+        let mut position = Position::new(Board::chess_board());
+        position.add_piece(king(), Square::new(File::E, Rank::ONE), Team::White);
+        position.add_piece(rook(), Square::new(File::E, Rank::EIGHT), Team::Black);
+        assert!(position.validate(false).is_ok());
+        assert!(matches!(
+            position.validate(true),
+            Err(SetupError::MissingRoyal(Team::Black))
+        ));
+
+        // a royal army needs its royal piece on the board
+        let black_king = position.add_piece(king(), Square::new(File::A, Rank::EIGHT), Team::Black);
+        position.pieces.iter_mut().for_each(|piece| {
+            if piece.entity == black_king {
+                piece.square = None;
+            }
+        });
+        assert!(matches!(
+            position.validate(false),
+            Err(SetupError::MissingRoyal(Team::Black))
+        ));
+
+        // both teams need pieces on the board, whatever the win condition
+        let mut position = Position::new(Board::chess_board());
+        assert!(matches!(
+            position.validate(false),
+            Err(SetupError::MissingPieces(Team::White))
+        ));
+        position.add_piece(rook(), Square::new(File::E, Rank::ONE), Team::White);
+        assert!(matches!(
+            position.validate(false),
+            Err(SetupError::MissingPieces(Team::Black))
+        ));
+    }
+
+    #[test]
+    fn test_make_unmake() {
+        let (mut position, white_king) = sample_position();
+        let turn = position
+            .legal_actions()
+            .into_iter()
+            .find(|turn| turn.action.movement.to == Square::new(File::D, Rank::TWO))
+            .unwrap();
+
+        position.make(&turn).unwrap();
+        assert_eq!(position.side_to_move, Team::Black);
+        assert_eq!(position.ply, 1);
+        assert_eq!(
+            position.piece(white_king).and_then(|piece| piece.square),
+            Some(Square::new(File::D, Rank::TWO))
+        );
+
+        position.unmake();
+        assert_eq!(position.side_to_move, Team::White);
+        assert_eq!(position.ply, 0);
+        assert!(position.last_action.is_none());
+        assert_eq!(
+            position.piece(white_king).and_then(|piece| piece.square),
+            Some(Square::new(File::E, Rank::ONE))
+        );
+    }
+}
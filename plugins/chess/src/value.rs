@@ -0,0 +1,172 @@
+use bevy_utils::HashMap;
+
+use crate::{
+    behavior::{PatternBehavior, RelayBehavior},
+    board::{Board, File, Rank, Square},
+    pieces::PieceDefinition,
+    position::Position,
+    team::Team,
+};
+
+// With these weights, the classical pieces are worth roughly
+// knight: 310, bishop: 420, rook: 490, queen: 840
+const BASE_VALUE: f32 = 60.;
+// the value of each square a piece can move to
+const MOBILITY_VALUE: f32 = 50.;
+// the value of each piece a piece can capture, on top of its mobility
+const CAPTURE_VALUE: f32 = 20.;
+// relayed patterns only apply to allies in range, so they are worth less than owned patterns
+const RELAY_DIVISOR: i32 = 2;
+// one in this many squares is occupied in the crowded samples
+const CROWDED_DENSITY: u16 = 4;
+const CROWDED_SAMPLES: u16 = 3;
+
+// An estimate of how strong a piece is
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PieceValue {
+    // the average number of actions over every sampled position
+    pub mobility: f32,
+    // the average number of captures available in crowded positions
+    pub captures: f32,
+    // roughly in hundredths of a pawn
+    pub value: i32,
+}
+
+// Estimates the value of a piece moving with these patterns.
+// The piece is placed on every square of an empty board and of a few crowded boards, where
+// a quarter of the squares hold pieces of both teams. Leapers keep most of their actions in
+// crowded positions while riders are blocked, and patterns that cannot capture gain nothing
+// from the enemy pieces around them.
+pub fn estimate(board: &Board, pattern: &PatternBehavior) -> PieceValue {
+    let definition = PieceDefinition::new(pattern.clone().into(), Default::default());
+    let squares = (0..=board.size.file.0)
+        .flat_map(|file| {
+            (0..=board.size.rank.0).map(move |rank| Square::new(File(file), Rank(rank)))
+        })
+        .collect::<Vec<_>>();
+
+    let mut actions = 0;
+    let mut captures = 0;
+    for square in squares.iter() {
+        for sample in 0..=CROWDED_SAMPLES {
+            let mut position = Position::new(*board);
+            let piece = position.add_piece(definition.clone(), *square, Team::White);
+            // the first sample is an empty board
+            if sample > 0 {
+                let blockers = squares.iter().filter(|blocker| {
+                    *blocker != square
+                        && (blocker.file.0 * 7 + blocker.rank.0 * 3 + sample) % CROWDED_DENSITY == 0
+                });
+                // alternating teams, so that every line holds both allies and enemies
+                for (index, blocker) in blockers.enumerate() {
+                    let team = if index % 2 == 1 {
+                        Team::Black
+                    } else {
+                        Team::White
+                    };
+                    position.add_piece(PieceDefinition::default(), *blocker, team);
+                }
+            }
+            for (_, piece_actions) in position
+                .pseudo_legal_actions()
+                .into_iter()
+                .filter(|(entity, _)| *entity == piece)
+            {
+                actions += piece_actions.0.len();
+                captures += piece_actions
+                    .0
+                    .values()
+                    .filter(|action| !action.captures.is_empty())
+                    .count();
+            }
+        }
+    }
+
+    let empty_samples = squares.len().max(1) as f32;
+    let mobility = actions as f32 / (empty_samples * f32::from(CROWDED_SAMPLES + 1));
+    let captures = captures as f32 / (empty_samples * f32::from(CROWDED_SAMPLES));
+    PieceValue {
+        mobility,
+        captures,
+        value: (BASE_VALUE + MOBILITY_VALUE * mobility + CAPTURE_VALUE * captures) as i32,
+    }
+}
+
+// Caches the estimates of each behavior, since estimating them requires searching many positions
+#[derive(Default)]
+pub struct ValueEstimator {
+    patterns: HashMap<PatternBehavior, PieceValue>,
+    relays: HashMap<RelayBehavior, PieceValue>,
+}
+
+impl ValueEstimator {
+    pub fn pattern_value(&mut self, board: &Board, pattern: &PatternBehavior) -> PieceValue {
+        if let Some(value) = self.patterns.get(pattern) {
+            return *value;
+        }
+        let value = estimate(board, pattern);
+        self.patterns.insert(pattern.clone(), value);
+        value
+    }
+
+    // Relays are valued as though they were patterns of the relaying piece, at a discount
+    pub fn relay_value(&mut self, board: &Board, relay: &RelayBehavior) -> PieceValue {
+        if let Some(value) = self.relays.get(relay) {
+            return *value;
+        }
+        let pattern = PatternBehavior {
+            patterns: relay.patterns.clone(),
+        };
+        let mut value = estimate(board, &pattern);
+        value.value /= RELAY_DIVISOR;
+        self.relays.insert(relay.clone(), value);
+        value
+    }
+
+    pub fn piece_value(&mut self, board: &Board, definition: &PieceDefinition) -> i32 {
+        let pattern_value = definition
+            .behaviors
+            .pattern
+            .as_ref()
+            .map_or(0, |pattern| self.pattern_value(board, pattern).value);
+        let relay_value = definition
+            .behaviors
+            .relay
+            .as_ref()
+            .map_or(0, |relay| self.relay_value(board, relay).value);
+        pattern_value + relay_value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pattern::Pattern;
+
+    use super::*;
+
+    fn value(pattern: Pattern) -> PieceValue {
+        estimate(
+            &Board::chess_board(),
+            &PatternBehavior::default().with_pattern(pattern),
+        )
+    }
+
+    #[test]
+    fn test_classical_piece_values() {
+        let knight = value(Pattern::knight().leaper().captures_by_displacement());
+        let bishop = value(Pattern::diagonal().rider().captures_by_displacement());
+        let rook = value(Pattern::orthogonal().rider().captures_by_displacement());
+        let queen = value(Pattern::radial().rider().captures_by_displacement());
+
+        assert!(knight.value < bishop.value, "{knight:?} < {bishop:?}");
+        assert!(bishop.value < rook.value, "{bishop:?} < {rook:?}");
+        assert!(rook.value < queen.value, "{rook:?} < {queen:?}");
+        assert!((250..350).contains(&knight.value), "{knight:?}");
+        assert!((800..1000).contains(&queen.value), "{queen:?}");
+
+        // without captures, a piece is worth less than the same piece that captures
+        let quiet_rook = value(Pattern::orthogonal().rider());
+        assert_eq!(quiet_rook.captures, 0.);
+        assert!(quiet_rook.value < rook.value);
+    }
+}
@@ -1,26 +1,47 @@
-use itertools::Itertools;
-
 use bevy::{
-    prelude::{Entity, EventWriter, Query, Reflect, Res, ResMut, Resource},
-    utils::HashMap,
+    ecs::system::SystemParam,
+    prelude::{
+        Commands, Entity, EventWriter, Has, Local, Query, Reflect, Res, ResMut, Resource, With,
+        Without,
+    },
+    utils::{HashMap, HashSet},
 };
 
 use bevy_egui::{
-    egui::{CentralPanel, Color32, RichText, ScrollArea, SidePanel, TopBottomPanel, Ui},
+    egui::{
+        CentralPanel, Color32, Key, RichText, ScrollArea, SidePanel, TextEdit, TopBottomPanel, Ui,
+    },
     EguiContexts,
 };
 
 use games::{
-    chess::{board::Square, pieces::PieceDefinition, team::Team},
-    components::{ActionHistory, Clock, CurrentTurn, Ply},
-    RequestTurnEvent,
+    can_abort,
+    chess::{
+        actions::LastAction,
+        behavior::BoardThreatsCache,
+        board::{Board, Square},
+        pieces::{Orientation, PieceDefinition, Position, Royal},
+        team::Team,
+        value::ValueEstimator,
+    },
+    components::{
+        ActionHistory, Analysis, Annotation, Bot, Clock, ClockConfiguration, CurrentTurn,
+        DrawOffer, GameBoard, GameOver, GameSeed, GameSetup, InGame, NodeId, PieceSet, Player, Ply,
+        Premove, Premoves, RematchRequest, TakebackRequest, VariationTree, WinCondition,
+    },
+    AbortGameEvent, AnswerDrawEvent, AnswerRematchEvent, AnswerTakebackEvent, BoardEdit,
+    EditBoardEvent, EditVariationEvent, GameRecord, OfferDrawEvent, RequestRematchEvent,
+    RequestRewindEvent, RequestTakebackEvent, RequestTurnEvent, RequestVariationEvent,
+    ResignGameEvent, SaveGame, VariationEdit,
 };
 use wild_icons::PieceIconSvg;
 
 use crate::{
+    move_input::{CoordinateMove, MoveInput},
     mutation::IntendedMutation,
     query::{PieceData, PieceQuery},
     widgets::{BoardWidget, ClockWidget, PieceInspectorWidget, SquareWidget},
+    SaveFile,
 };
 
 #[derive(Clone, Copy, Debug, Default, Resource, Reflect)]
@@ -32,17 +53,77 @@ pub(crate) struct SelectedHistoricalPly(Option<Ply>);
 #[derive(Clone, Copy, Debug, Default, Resource, Reflect)]
 pub(crate) struct SelectedGame(pub Option<Entity>);
 
+// Which teams' threatened squares are highlighted on the board
+#[derive(Clone, Copy, Debug, Default, Resource, Reflect)]
+pub(crate) struct ShownThreats {
+    white: bool,
+    black: bool,
+}
+
+impl ShownThreats {
+    fn teams(&self) -> impl Iterator<Item = Team> {
+        [(Team::White, self.white), (Team::Black, self.black)]
+            .into_iter()
+            .filter_map(|(team, shown)| shown.then_some(team))
+    }
+}
+
+// The side the board is seen from
+#[derive(Clone, Copy, Debug, Default, Resource, Reflect)]
+pub(crate) struct BoardView {
+    // turns the board around from the local player's side
+    pub(crate) flipped: bool,
+    pub(crate) orientation: Orientation,
+}
+
+// A change to the variation tree made in the history panel
+enum VariationInput {
+    Select(NodeId),
+    Edit(NodeId, VariationEdit),
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub(crate) fn egui_history_panel(
+    mut commands: Commands,
     mut contexts: EguiContexts,
-    games_query: Query<&ActionHistory>,
+    games_query: Query<(&ActionHistory, &VariationTree, Has<Analysis>, Has<GameOver>)>,
+    record_query: Query<(
+        &PieceSet,
+        &GameBoard,
+        Option<&GameSetup>,
+        &WinCondition,
+        Option<&ClockConfiguration>,
+        Option<&GameOver>,
+    )>,
+    mut rewind_writer: EventWriter<RequestRewindEvent>,
+    mut variation_writer: EventWriter<RequestVariationEvent>,
+    mut edit_writer: EventWriter<EditVariationEvent>,
     selected_game: Res<SelectedGame>,
     mut selected_ply: ResMut<SelectedHistoricalPly>,
+    mut comment_buffer: Local<(Option<NodeId>, String)>,
+    save_file: Option<Res<SaveFile>>,
 ) {
-    let Some(history) = selected_game.0.and_then(|game| games_query.get(game).ok()) else {
+    let Some(current_game) = selected_game.0 else {
+        return;
+    };
+    let Ok((history, tree, is_analysis, is_over)) = games_query.get(current_game) else {
         return;
     };
-    let total_count = history.len();
+    // games set up from a position start at a later ply
+    let first_ply = tree.get(tree.root()).map_or(0, |root| root.ply().get());
+    let last_ply = first_ply + history.len();
+    let editable = is_analysis || is_over;
+
+    // the node of the position shown on the board
+    let viewed_node = match selected_ply.0 {
+        Some(ply) => std::iter::once(tree.root())
+            .chain(tree.path_to(tree.current()))
+            .find(|node| tree.get(*node).is_some_and(|node| node.ply() == ply))
+            .unwrap_or(tree.root()),
+        None => tree.current(),
+    };
 
+    let mut input = None;
     TopBottomPanel::top("Move history")
         .resizable(true)
         .show(contexts.ctx_mut(), |ui| {
@@ -53,36 +134,16 @@ pub(crate) fn egui_history_panel(
                 .auto_shrink([false, false])
                 .max_height(280.)
                 .show(ui, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.set_width(150.);
-                        for chunk in &history.iter().enumerate().chunks(2) {
-                            // for now there are only two players
-                            ui.horizontal(|ui| {
-                                for (index, (_entity, action)) in chunk.into_iter() {
-                                    let move_text = format!(
-                                        "{}{}{}",
-                                        action.movement.from,
-                                        if action.captures.is_empty() { "-" } else { "x" },
-                                        action.movement.to,
-                                    );
-                                    let text = RichText::new(move_text)
-                                        .size(28.)
-                                        .strong()
-                                        .color(Color32::BLACK);
-                                    let is_current_ply = index == total_count - 1;
-                                    let selected = selected_ply
-                                        .0
-                                        .is_some_and(|ply| ply == Ply::new(index + 1))
-                                        || (selected_ply.0.is_none() && is_current_ply);
-                                    if ui.selectable_label(selected, text).clicked() && !selected {
-                                        selected_ply.0 = if is_current_ply {
-                                            None
-                                        } else {
-                                            Some(Ply::new(index + 1))
-                                        }
-                                    };
-                                }
-                            });
+                    ui.horizontal_wrapped(|ui| {
+                        if let Some(first) = tree.next(tree.root()) {
+                            render_variation_line(
+                                ui,
+                                tree,
+                                first,
+                                viewed_node,
+                                editable,
+                                &mut input,
+                            );
                         }
                     });
                 });
@@ -93,69 +154,294 @@ pub(crate) fn egui_history_panel(
                     .button(RichText::new("<").size(32.).strong().color(Color32::BLACK))
                     .clicked()
                 {
-                    if let Some(ply) = &mut selected_ply.0 {
+                    if let Some(ply) = selected_ply.0.as_mut().filter(|ply| ply.get() > first_ply) {
                         ply.decrement();
-                    } else if total_count > 0 {
-                        selected_ply.0 = Some(Ply::new(total_count - 1));
+                    } else if selected_ply.0.is_none() && last_ply > first_ply {
+                        selected_ply.0 = Some(Ply::new(last_ply - 1));
                     }
                 }
                 if ui
                     .button(RichText::new(">").size(32.).strong().color(Color32::BLACK))
                     .clicked()
                 {
-                    if selected_ply
-                        .0
-                        .is_some_and(|ply| ply < Ply::new(total_count - 1))
-                    {
+                    if selected_ply.0.is_some_and(|ply| ply.get() + 1 < last_ply) {
                         selected_ply.0.as_mut().unwrap().increment();
                     } else {
                         selected_ply.0 = None;
                     }
                 }
+                // analysis games can continue from any ply, keeping the later turns as a variation
+                if is_analysis {
+                    if let Some(ply) = selected_ply.0 {
+                        if ui
+                            .button(RichText::new("Continue from here").size(24.))
+                            .clicked()
+                        {
+                            rewind_writer.send(RequestRewindEvent {
+                                game: current_game,
+                                ply,
+                            });
+                            selected_ply.0 = None;
+                        }
+                    }
+                }
+                if let Ok((piece_set, board, setup, win_condition, clock, game_over)) =
+                    record_query.get(current_game)
+                {
+                    if ui.button(RichText::new("Copy PGN").size(24.)).clicked() {
+                        let record = GameRecord::from_game(
+                            piece_set,
+                            board,
+                            setup,
+                            win_condition,
+                            clock,
+                            game_over,
+                            tree,
+                        );
+                        ui.output_mut(|output| output.copied_text = record.to_string());
+                    }
+                }
+                if let Some(save_file) = &save_file {
+                    if ui.button(RichText::new("Save Game").size(24.)).clicked() {
+                        commands.trigger(SaveGame {
+                            game: current_game,
+                            path: save_file.0.clone(),
+                        });
+                    }
+                }
             });
 
+            if let Some(node) = tree.get(viewed_node).filter(|_| editable) {
+                if viewed_node != tree.root() {
+                    ui.add_space(10.);
+                    ui.horizontal(|ui| {
+                        for annotation in Annotation::ALL {
+                            let selected = node.annotation == Some(annotation);
+                            if ui
+                                .selectable_label(
+                                    selected,
+                                    RichText::new(annotation.symbol()).size(24.),
+                                )
+                                .clicked()
+                            {
+                                input = Some(VariationInput::Edit(
+                                    viewed_node,
+                                    VariationEdit::SetAnnotation((!selected).then_some(annotation)),
+                                ));
+                            }
+                        }
+                    });
+
+                    if comment_buffer.0 != Some(viewed_node) {
+                        *comment_buffer =
+                            (Some(viewed_node), node.comment.clone().unwrap_or_default());
+                    }
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut comment_buffer.1);
+                        if ui.button("Save comment").clicked() {
+                            let comment = comment_buffer.1.trim();
+                            input = Some(VariationInput::Edit(
+                                viewed_node,
+                                VariationEdit::SetComment(
+                                    (!comment.is_empty()).then(|| comment.to_string()),
+                                ),
+                            ));
+                        }
+                    });
+                }
+            }
+
             ui.add_space(20.);
         });
+
+    match input {
+        Some(VariationInput::Select(node)) => {
+            if tree.is_ancestor(node, tree.current()) {
+                // turns on the current line are browsed without changing the game
+                selected_ply.0 = tree
+                    .get(node)
+                    .map(|node| node.ply())
+                    .filter(|_| node != tree.current());
+            } else if is_analysis {
+                variation_writer.send(RequestVariationEvent {
+                    game: current_game,
+                    node,
+                });
+                selected_ply.0 = None;
+            }
+        }
+        Some(VariationInput::Edit(node, edit)) => {
+            edit_writer.send(EditVariationEvent {
+                game: current_game,
+                node,
+                edit,
+            });
+        }
+        None => {}
+    }
+}
+
+// Renders a line of play PGN-style, with its sidelines in parentheses
+fn render_variation_line(
+    ui: &mut Ui,
+    tree: &VariationTree,
+    start: NodeId,
+    viewed_node: NodeId,
+    editable: bool,
+    input: &mut Option<VariationInput>,
+) {
+    for (index, node_id) in tree.line_from(start).enumerate() {
+        let Some(node) = tree.get(node_id) else {
+            continue;
+        };
+        let Some(action) = &node.action else {
+            continue;
+        };
+
+        // for now there are only two players
+        let turn_index = node.ply().get().saturating_sub(1);
+        let number = turn_index / 2 + 1;
+        let prefix = if turn_index % 2 == 0 {
+            format!("{number}. ")
+        } else if index == 0 {
+            format!("{number}... ")
+        } else {
+            String::new()
+        };
+        // the notation is written by the server shortly after the turn is played
+        let notation = node.notation.clone().unwrap_or_else(|| {
+            format!(
+                "{}{}{}",
+                action.movement.from,
+                if action.captures.is_empty() { "-" } else { "x" },
+                action.movement.to,
+            )
+        });
+        let move_text = format!(
+            "{prefix}{notation}{}",
+            node.annotation
+                .map(|annotation| annotation.symbol())
+                .unwrap_or_default(),
+        );
+        let text = RichText::new(move_text)
+            .size(24.)
+            .strong()
+            .color(Color32::BLACK);
+        let response = ui.selectable_label(node_id == viewed_node, text);
+        if response.clicked() {
+            *input = Some(VariationInput::Select(node_id));
+        }
+        if editable {
+            response.context_menu(|ui| {
+                if !tree.is_main_continuation(node_id) && ui.button("Promote variation").clicked() {
+                    *input = Some(VariationInput::Edit(node_id, VariationEdit::Promote));
+                    ui.close_menu();
+                }
+                // the line leading to the board's position cannot be removed
+                if !tree.is_ancestor(node_id, tree.current())
+                    && ui.button("Remove variation").clicked()
+                {
+                    *input = Some(VariationInput::Edit(node_id, VariationEdit::Remove));
+                    ui.close_menu();
+                }
+            });
+        }
+        if let Some(comment) = &node.comment {
+            ui.label(RichText::new(comment).italics().size(20.));
+        }
+
+        // alternatives to this turn follow it
+        if tree.is_main_continuation(node_id) {
+            for sibling in tree.siblings(node_id) {
+                ui.label(RichText::new("(").size(24.));
+                render_variation_line(ui, tree, sibling, viewed_node, editable, input);
+                ui.label(RichText::new(")").size(24.));
+            }
+        }
+    }
+}
+
+// The requests players make about the game itself, rather than its turns
+#[derive(SystemParam)]
+pub(crate) struct GameRequestWriters<'w> {
+    takeback: EventWriter<'w, RequestTakebackEvent>,
+    answer_takeback: EventWriter<'w, AnswerTakebackEvent>,
+    resign: EventWriter<'w, ResignGameEvent>,
+    abort: EventWriter<'w, AbortGameEvent>,
+    offer_draw: EventWriter<'w, OfferDrawEvent>,
+    answer_draw: EventWriter<'w, AnswerDrawEvent>,
+    rematch: EventWriter<'w, RequestRematchEvent>,
+    answer_rematch: EventWriter<'w, AnswerRematchEvent>,
 }
 
 #[allow(clippy::type_complexity, clippy::too_many_arguments)]
 pub(crate) fn egui_information_panel(
     mut contexts: EguiContexts,
-    game_query: Query<&CurrentTurn>,
+    game_query: Query<(
+        &CurrentTurn,
+        &ActionHistory,
+        Has<Analysis>,
+        Option<&TakebackRequest>,
+        Option<&GameSeed>,
+        Option<&GameOver>,
+        Option<&DrawOffer>,
+        Option<&RematchRequest>,
+    )>,
     piece_query: Query<PieceQuery>,
-    player_query: Query<(&Team, Option<&Clock>)>,
+    player_query: Query<(&Orientation, &InGame, Option<&Clock>), With<Player>>,
+    local_player_query: Query<(&Team, &InGame), (With<Player>, Without<Bot>)>,
     mut mutation_writer: EventWriter<RequestTurnEvent>,
+    mut edit_writer: EventWriter<EditBoardEvent>,
+    mut request_writers: GameRequestWriters,
     mut intended_mutation: ResMut<IntendedMutation>,
+    mut shown_threats: ResMut<ShownThreats>,
+    mut board_view: ResMut<BoardView>,
     selected_square: Res<SelectedSquare>,
     selected_game: Res<SelectedGame>,
+    board_query: Query<&Board>,
+    mut values: Local<ValueEstimator>,
 ) {
     let Some(current_game) = selected_game.0 else {
         return;
     };
-    let Ok(team_with_turn) = game_query.get(current_game) else {
+    let Ok((
+        team_with_turn,
+        history,
+        is_analysis,
+        takeback_request,
+        seed,
+        game_over,
+        draw_offer,
+        rematch_request,
+    )) = game_query.get(current_game)
+    else {
         return;
     };
+    // local players may abort while any of their teams has yet to play
+    let abortable = local_player_query.iter().any(|(team, in_game)| {
+        in_game.0 == current_game && can_abort(*team, history, team_with_turn)
+    });
+    // only the opponents of the team that offered a draw or asked for a rematch can answer
+    let answerable = |offering_team: Team| {
+        local_player_query
+            .iter()
+            .any(|(team, in_game)| in_game.0 == current_game && *team != offering_team)
+    };
+    let draw_answerable = draw_offer.is_some_and(|offer| answerable(offer.team));
+    let rematch_answerable = rematch_request.is_some_and(|request| answerable(request.team));
 
-    let upper_clock = player_query.iter().find_map(
-        |(team, clock)| {
-            if *team == Team::Black {
-                clock
-            } else {
-                None
-            }
-        },
-    );
-
-    let bottom_clock =
-        player_query.iter().find_map(
-            |(team, clock)| {
-                if *team == Team::White {
-                    clock
-                } else {
-                    None
-                }
-            },
-        );
+    // the clock of the player seen at the bottom of the board is shown last
+    let clock = |facing_view: bool| {
+        player_query
+            .iter()
+            .find(|(orientation, in_game, _)| {
+                in_game.0 == current_game
+                    && (**orientation == board_view.orientation) == facing_view
+            })
+            .and_then(|(_, _, clock)| clock)
+    };
+    let upper_clock = clock(false);
+    let bottom_clock = clock(true);
 
     let pieces: HashMap<Square, PieceData> = piece_query
         .into_iter()
@@ -179,9 +465,72 @@ pub(crate) fn egui_information_panel(
                 if let Some(clock) = bottom_clock {
                     ui.add(ClockWidget::new(clock));
                 }
+                if let Some(clock) = bottom_clock.or(upper_clock) {
+                    ui.label(clock.time_control());
+                }
                 ui.add_space(100.);
 
-                ui.label(RichText::new(format!("{:?}'s turn.", team_with_turn)).size(36.));
+                match game_over {
+                    Some(game_over) => {
+                        ui.label(RichText::new(format!("{game_over}.")).size(36.));
+                    }
+                    None => {
+                        ui.label(RichText::new(format!("{:?}'s turn.", team_with_turn)).size(36.));
+                    }
+                }
+                if let Some(seed) = seed {
+                    // the seed reproduces this game's armies, so it can be shared or reported
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Seed: {}", seed.seed));
+                        if ui.button("Copy Seed").clicked() {
+                            ui.output_mut(|output| output.copied_text = seed.seed.to_string());
+                        }
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Flip Board").clicked() {
+                        board_view.flipped = !board_view.flipped;
+                    }
+                    ui.checkbox(&mut shown_threats.white, "Show threats to White");
+                    ui.checkbox(&mut shown_threats.black, "Show threats to Black");
+                });
+
+                if is_analysis {
+                    render_analysis_options(
+                        ui,
+                        current_game,
+                        team_with_turn.0,
+                        selected_square
+                            .0
+                            .filter(|square| pieces.contains_key(square)),
+                        &mut edit_writer,
+                    );
+                } else if game_over.is_some() {
+                    render_rematch_options(
+                        ui,
+                        current_game,
+                        rematch_request,
+                        rematch_answerable,
+                        &mut request_writers,
+                    );
+                } else {
+                    render_takeback_options(
+                        ui,
+                        current_game,
+                        takeback_request,
+                        &mut request_writers.takeback,
+                        &mut request_writers.answer_takeback,
+                    );
+                    render_result_options(
+                        ui,
+                        current_game,
+                        abortable,
+                        draw_offer,
+                        draw_answerable,
+                        &mut request_writers,
+                    );
+                }
 
                 let mut selected_mutation = None;
                 if let Some((_, icons)) = intended_mutation.0.as_ref() {
@@ -199,7 +548,18 @@ pub(crate) fn egui_information_panel(
                 }
 
                 if let Some(piece) = selected_square.0.and_then(|square| pieces.get(&square)) {
-                    ui.add(PieceInspectorWidget::new(piece));
+                    let mut inspector = PieceInspectorWidget::new(piece);
+                    if let Ok(board) = board_query.get(piece.on_board.0) {
+                        inspector = inspector.with_values(
+                            piece
+                                .pattern_behavior
+                                .map(|pattern| values.pattern_value(board, pattern)),
+                            piece
+                                .relay_behavior
+                                .map(|relay| values.relay_value(board, relay)),
+                        );
+                    }
+                    ui.add(inspector);
                 }
             });
     });
@@ -210,22 +570,52 @@ pub(crate) fn egui_information_panel(
 #[allow(clippy::type_complexity)]
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn egui_chessboard(
+    mut commands: Commands,
     mut contexts: EguiContexts,
-    game_query: Query<&CurrentTurn>,
+    game_query: Query<(
+        &CurrentTurn,
+        &Ply,
+        Option<&ActionHistory>,
+        Option<&LastAction>,
+        Option<&Premoves>,
+        Option<&PieceSet>,
+    )>,
     piece_query: Query<PieceQuery>,
+    royal_query: Query<(&Position, &Team, &InGame), With<Royal>>,
+    bot_query: Query<(&Team, &InGame), With<Bot>>,
+    local_player_query: Query<(&Team, &InGame), (With<Player>, Without<Bot>)>,
+    board_query: Query<(&BoardThreatsCache, &InGame)>,
     mut move_writer: EventWriter<RequestTurnEvent>,
     mut intended_mutation: ResMut<IntendedMutation>,
     mut last_selected_square: ResMut<SelectedSquare>,
     selected_game: Res<SelectedGame>,
     selected_ply: Res<SelectedHistoricalPly>,
+    shown_threats: Res<ShownThreats>,
+    board_view: Res<BoardView>,
+    mut move_input: Local<MoveInput>,
 ) {
     let Some(current_game) = selected_game.0 else {
         return;
     };
-    let Ok(team_with_turn) = game_query.get(current_game) else {
+    let Ok((team_with_turn, game_ply, action_history, last_action, premoves, piece_set)) =
+        game_query.get(current_game)
+    else {
         return;
     };
 
+    // the move that led to the position shown,
+    // counting from the first ply of games set up from a position
+    let last_action = match selected_ply.0 {
+        Some(ply) => action_history
+            .and_then(|history| {
+                let first_ply = game_ply.get().checked_sub(history.len())?;
+                let index = ply.get().checked_sub(first_ply + 1)?;
+                history.iter().nth(index)
+            })
+            .map(|(_, action)| action),
+        None => last_action.map(|last_action| &last_action.0),
+    };
+
     let pieces: HashMap<Square, PieceData> = piece_query
         .into_iter()
         .map(|item| {
@@ -244,6 +634,49 @@ pub(crate) fn egui_chessboard(
         })
         .collect();
 
+    // bots play their own turns
+    let is_bot_turn = bot_query
+        .iter()
+        .any(|(team, in_game)| in_game.0 == current_game && *team == team_with_turn.0);
+
+    // a player waiting for their opponent to move can queue premoves,
+    // unless the same player moves for both teams
+    let plays_locally = |team: Team| {
+        local_player_query
+            .iter()
+            .any(|(player_team, in_game)| in_game.0 == current_game && *player_team == team)
+    };
+    let premove_team = Some(team_with_turn.0.get_next())
+        .filter(|team| plays_locally(*team) && !plays_locally(team_with_turn.0));
+    let premove_squares: Vec<(Square, Square)> = premoves
+        .iter()
+        .flat_map(|premoves| premoves.iter())
+        .map(|premove| (premove.from, premove.to))
+        .collect();
+
+    // threats are only known for the current ply
+    let board_threats = board_query
+        .iter()
+        .find(|(_, in_game)| in_game.0 == current_game)
+        .map(|(threats, _)| threats)
+        .filter(|_| selected_ply.0.is_none());
+    let threats: HashSet<Square> = board_threats
+        .iter()
+        .flat_map(|threats| {
+            shown_threats
+                .teams()
+                .flat_map(|team| threats.threatened_squares(team))
+        })
+        .collect();
+    let checks: HashSet<Square> = royal_query
+        .iter()
+        .filter(|(position, team, in_game)| {
+            in_game.0 == current_game
+                && board_threats.is_some_and(|threats| threats.is_threatened(position.0, **team))
+        })
+        .map(|(position, _, _)| position.0)
+        .collect();
+
     let selected_square = if selected_ply.0.is_some() {
         None
     } else {
@@ -254,50 +687,165 @@ pub(crate) fn egui_chessboard(
         .resizable(false)
         .show(contexts.ctx_mut(), |ui| {
             let mut board_selection = None;
+            let mut dropped = None;
 
-            ui.add(BoardWidget::new(
-                &pieces,
-                selected_square,
-                &mut board_selection,
-            ));
+            let mut board = BoardWidget::new(&pieces, selected_square, &mut board_selection)
+                .with_threats(&threats)
+                .with_checks(&checks)
+                .with_last_action(last_action)
+                .with_orientation(board_view.orientation);
+            if selected_ply.0.is_none() {
+                board = board
+                    .with_drop(&mut dropped)
+                    .with_premoves(&premove_squares);
+            }
+            ui.add(board);
+
+            // a dropped piece moves as if its square and then the target had been clicked
+            if let Some((from, to)) = dropped {
+                last_selected_square.0 = Some(from);
+                board_selection = Some(to);
+            }
 
             if selected_ply.0.is_none() {
+                ui.horizontal(|ui| {
+                    ui.label("Move");
+                    let response = ui.add(
+                        TextEdit::singleline(&mut move_input.text)
+                            .hint_text("e2e4, e7e8=Q")
+                            .desired_width(120.),
+                    );
+                    let entered =
+                        response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter));
+                    if entered || ui.button("Play").clicked() {
+                        let notation = piece_set.map(PieceSet::notation).unwrap_or_default();
+                        let coordinate_move = CoordinateMove::parse(&move_input.text)
+                            .ok_or_else(|| format!("`{}` is not a move", move_input.text.trim()));
+                        let input = match premove_team {
+                            Some(team) => coordinate_move
+                                .and_then(|coordinate_move| {
+                                    coordinate_move.premove(&pieces, team, &notation)
+                                })
+                                .map(BoardInput::Premove),
+                            None => coordinate_move
+                                .and_then(|coordinate_move| {
+                                    coordinate_move.resolve(
+                                        current_game,
+                                        &pieces,
+                                        team_with_turn.0,
+                                        &notation,
+                                    )
+                                })
+                                .map(BoardInput::Turn),
+                        };
+                        match input {
+                            Ok(BoardInput::Turn(_)) if is_bot_turn => {
+                                move_input.message = Some("It is the bot's turn".to_string());
+                            }
+                            Ok(input) => {
+                                intended_mutation.0.take();
+                                last_selected_square.0 = None;
+                                move_input.text.clear();
+                                move_input.message = None;
+                                match input {
+                                    BoardInput::Turn(turn_event) => {
+                                        move_writer.send(turn_event);
+                                    }
+                                    BoardInput::Premove(premove) => {
+                                        queue_premove(
+                                            &mut commands,
+                                            current_game,
+                                            premoves,
+                                            premove,
+                                        );
+                                    }
+                                }
+                            }
+                            Err(message) => move_input.message = Some(message),
+                        }
+                        // keep typing moves without clicking the box again
+                        response.request_focus();
+                    }
+                });
+                if let Some(message) = &move_input.message {
+                    ui.colored_label(Color32::RED, message);
+                }
+                if !premove_squares.is_empty() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{} premoves queued", premove_squares.len()));
+                        if ui.button("Cancel premoves").clicked() {
+                            commands.entity(current_game).remove::<Premoves>();
+                        }
+                    });
+                }
+
                 if let Some(selected_square) = board_selection {
                     // remove any mutation selection
                     intended_mutation.0.take();
 
-                    if let Some(turn_event) = handle_clicked_square(
+                    match handle_clicked_square(
                         selected_square,
                         current_game,
                         &mut last_selected_square.0,
                         &pieces,
                         team_with_turn.0,
+                        premove_team,
                     ) {
-                        move_writer.send(turn_event);
+                        Some(BoardInput::Turn(turn_event)) if !is_bot_turn => {
+                            move_writer.send(turn_event);
+                        }
+                        Some(BoardInput::Premove(premove)) => {
+                            queue_premove(&mut commands, current_game, premoves, premove);
+                        }
+                        _ => {}
                     }
                 }
             }
         });
 }
 
+// A move entered on the board
+enum BoardInput {
+    Turn(RequestTurnEvent),
+    Premove(Premove),
+}
+
 fn handle_clicked_square(
     selected_square: Square,
     current_game: Entity,
     last_selected_square: &mut Option<Square>,
     pieces: &HashMap<Square, PieceData>,
     team_with_turn: Team,
-) -> Option<RequestTurnEvent> {
-    if let Some(piece) = (*last_selected_square).and_then(|square| pieces.get(&square)) {
+    premove_team: Option<Team>,
+) -> Option<BoardInput> {
+    if let Some((from, piece)) =
+        (*last_selected_square).and_then(|square| Some((square, pieces.get(&square)?)))
+    {
         if let Some(action) = piece.actions.get(&selected_square) {
             if *piece.team == team_with_turn {
                 *last_selected_square = None;
-                return Some(RequestTurnEvent::new(
+                return Some(BoardInput::Turn(RequestTurnEvent::new(
                     piece.entity,
                     current_game,
                     action.clone(),
-                ));
+                )));
             }
         }
+        // premoves may target any square not held by the piece's own team,
+        // since the position changes before they are played
+        let own_square = pieces
+            .get(&selected_square)
+            .is_some_and(|target| target.team == piece.team);
+        if premove_team == Some(*piece.team) && !own_square {
+            *last_selected_square = None;
+            return Some(BoardInput::Premove(Premove {
+                piece: piece.entity,
+                team: *piece.team,
+                from,
+                to: selected_square,
+                promotion: None,
+            }));
+        }
     }
     if pieces.get(&selected_square).is_some() {
         *last_selected_square = Some(selected_square);
@@ -305,6 +853,141 @@ fn handle_clicked_square(
     None
 }
 
+fn queue_premove(
+    commands: &mut Commands,
+    game: Entity,
+    premoves: Option<&Premoves>,
+    premove: Premove,
+) {
+    let mut premoves = premoves.cloned().unwrap_or_default();
+    premoves.push(premove);
+    commands.entity(game).insert(premoves);
+}
+
+fn render_analysis_options(
+    ui: &mut Ui,
+    current_game: Entity,
+    team_with_turn: Team,
+    selected_square: Option<Square>,
+    edit_writer: &mut EventWriter<EditBoardEvent>,
+) {
+    ui.horizontal(|ui| {
+        if ui.button("Switch turn").clicked() {
+            edit_writer.send(EditBoardEvent {
+                game: current_game,
+                edit: BoardEdit::SetTurn(team_with_turn.get_next()),
+            });
+        }
+        if let Some(square) = selected_square {
+            if ui.button(format!("Remove piece on {square}")).clicked() {
+                edit_writer.send(EditBoardEvent {
+                    game: current_game,
+                    edit: BoardEdit::RemovePiece(square),
+                });
+            }
+        }
+    });
+
+    ui.separator();
+}
+
+fn render_takeback_options(
+    ui: &mut Ui,
+    current_game: Entity,
+    takeback_request: Option<&TakebackRequest>,
+    takeback_writer: &mut EventWriter<RequestTakebackEvent>,
+    answer_writer: &mut EventWriter<AnswerTakebackEvent>,
+) {
+    if let Some(request) = takeback_request {
+        ui.label(RichText::new(format!("{:?} requests a takeback.", request.team)).size(24.));
+        ui.horizontal(|ui| {
+            for (label, accept) in [("Accept", true), ("Decline", false)] {
+                if ui.button(label).clicked() {
+                    answer_writer.send(AnswerTakebackEvent {
+                        game: current_game,
+                        accept,
+                    });
+                }
+            }
+        });
+    } else if ui.button("Request takeback").clicked() {
+        takeback_writer.send(RequestTakebackEvent { game: current_game });
+    }
+
+    ui.separator();
+}
+
+fn render_result_options(
+    ui: &mut Ui,
+    current_game: Entity,
+    abortable: bool,
+    draw_offer: Option<&DrawOffer>,
+    answerable: bool,
+    writers: &mut GameRequestWriters,
+) {
+    if let Some(offer) = draw_offer {
+        ui.label(RichText::new(format!("{:?} offers a draw.", offer.team)).size(24.));
+    }
+    if answerable {
+        ui.horizontal(|ui| {
+            for (label, accept) in [("Accept", true), ("Decline", false)] {
+                if ui.button(label).clicked() {
+                    writers.answer_draw.send(AnswerDrawEvent {
+                        game: current_game,
+                        accept,
+                    });
+                }
+            }
+        });
+    }
+    ui.horizontal(|ui| {
+        if abortable {
+            if ui.button("Abort").clicked() {
+                writers.abort.send(AbortGameEvent { game: current_game });
+            }
+        } else if ui.button("Resign").clicked() {
+            writers.resign.send(ResignGameEvent { game: current_game });
+        }
+        if draw_offer.is_none() && ui.button("Offer draw").clicked() {
+            writers
+                .offer_draw
+                .send(OfferDrawEvent { game: current_game });
+        }
+    });
+
+    ui.separator();
+}
+
+fn render_rematch_options(
+    ui: &mut Ui,
+    current_game: Entity,
+    rematch_request: Option<&RematchRequest>,
+    answerable: bool,
+    writers: &mut GameRequestWriters,
+) {
+    if let Some(request) = rematch_request {
+        ui.label(RichText::new(format!("{:?} wants a rematch.", request.team)).size(24.));
+    }
+    if answerable {
+        ui.horizontal(|ui| {
+            for (label, accept) in [("Accept", true), ("Decline", false)] {
+                if ui.button(label).clicked() {
+                    writers.answer_rematch.send(AnswerRematchEvent {
+                        game: current_game,
+                        accept,
+                    });
+                }
+            }
+        });
+    } else if rematch_request.is_none() && ui.button("Rematch").clicked() {
+        writers
+            .rematch
+            .send(RequestRematchEvent { game: current_game });
+    }
+
+    ui.separator();
+}
+
 fn render_mutation_options(
     ui: &mut Ui,
     selected_mutation: &mut Option<PieceDefinition>,
@@ -1,18 +1,46 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 
 use bevy_egui::{
-    egui::{CentralPanel, Vec2},
+    egui::{CentralPanel, CollapsingHeader, Color32, ComboBox, Slider, TextEdit, Ui, Vec2},
     EguiContexts,
 };
 
-use games::components::SpawnGame;
+use games::{
+    chess::position::Position,
+    components::{
+        Bot, GameBoard, GameRequestClock, GameRequestVariant, GameSeed, PieceSet, SpawnGame,
+        MAX_CLOCK_INCREMENT, MAX_CLOCK_TIME,
+    },
+    BotDifficulty, GameRecord, LoadGame, Timing, VariantRegistry,
+};
 use layouts::*;
 
+use crate::{
+    piece_editor::{load_piece_library, piece_editor_system, PieceEditor, PieceLibrary},
+    setup_editor::{setup_editor_system, SetupEditor},
+    SaveFile,
+};
+
 pub struct HomeMenuUIPlugin;
 
 impl Plugin for HomeMenuUIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, Self::menu_system.in_set(HomeMenuUISystems));
+        app.init_resource::<PieceLibrary>()
+            .add_systems(Startup, load_piece_library)
+            .add_systems(
+                Update,
+                (
+                    Self::menu_system.run_if(
+                        not(resource_exists::<PieceEditor>)
+                            .and_then(not(resource_exists::<SetupEditor>)),
+                    ),
+                    piece_editor_system.run_if(resource_exists::<PieceEditor>),
+                    setup_editor_system.run_if(resource_exists::<SetupEditor>),
+                )
+                    .in_set(HomeMenuUISystems),
+            );
     }
 }
 
@@ -20,34 +48,346 @@ impl Plugin for HomeMenuUIPlugin {
 pub struct HomeMenuUISystems;
 
 impl HomeMenuUIPlugin {
-    pub fn menu_system(mut commands: Commands, mut egui_ctx: EguiContexts) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn menu_system(
+        mut commands: Commands,
+        mut egui_ctx: EguiContexts,
+        // the PGN being imported and the reason it could not be
+        mut import: Local<(String, Option<String>)>,
+        // the position string of a classical game to start from, and the reason it could not be
+        mut setup: Local<(String, Option<String>)>,
+        // the seed of a wild army to replay, and the reason it could not be
+        mut seed: Local<(String, Option<String>)>,
+        mut wild_config: Local<WildConfig>,
+        mut clock_settings: Local<ClockSettings>,
+        save_file: Option<Res<SaveFile>>,
+        variants: Res<VariantRegistry>,
+    ) {
+        let requested_clock = clock_settings.clock;
+        let clock = requested_clock.map(GameRequestClock::to_clock);
         CentralPanel::default().show(egui_ctx.ctx_mut(), |ui| {
             ui.centered_and_justified(|ui| {
                 ui.set_height(300.);
                 ui.set_width(200.);
                 ui.vertical_centered(|ui| {
+                    if let Some(save_file) = save_file.filter(|file| file.0.exists()) {
+                        ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                            if ui.button("Resume Saved Game").clicked() {
+                                commands.trigger(LoadGame {
+                                    path: save_file.0.clone(),
+                                });
+                            }
+                        });
+                    }
                     ui.allocate_ui(Vec2::new(250., 120.), |ui| {
                         if ui.button("Play Traditional Chess").clicked() {
-                            commands.trigger(SpawnGame::new(ClassicalLayout::pieces().into()));
+                            commands.trigger(
+                                SpawnGame::new(ClassicalLayout::pieces().into())
+                                    .with_clock(clock.clone()),
+                            );
+                        }
+                    });
+                    ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                        if ui.button("Play Traditional Chess Against a Bot").clicked() {
+                            let player = commands.spawn(Name::new("Player")).id();
+                            let bot = commands
+                                .spawn((Name::new("Bot"), Bot::new(BotDifficulty::Medium)))
+                                .id();
+                            commands.trigger(
+                                SpawnGame::new(ClassicalLayout::pieces().into())
+                                    .with_players(player, bot)
+                                    .with_clock(clock.clone()),
+                            );
+                        }
+                    });
+                    ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                        if ui.button("Analysis Board").clicked() {
+                            commands.trigger(
+                                SpawnGame::new(ClassicalLayout::pieces().into()).analysis(),
+                            );
                         }
                     });
                     ui.allocate_ui(Vec2::new(250., 120.), |ui| {
                         if ui.button("Play Wild Chess").clicked() {
-                            commands.trigger(SpawnGame::new(ClassicWildLayout::pieces().into()));
+                            commands.trigger(
+                                wild_game(random_seed(), &wild_config).with_clock(clock.clone()),
+                            );
+                        }
+                    });
+                    CollapsingHeader::new("Wild Chess Settings")
+                        .show(ui, |ui| render_wild_config(ui, &mut wild_config));
+                    CollapsingHeader::new("Clock Settings")
+                        .show(ui, |ui| render_clock_settings(ui, &mut clock_settings));
+                    ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                        if ui.button("Play Wild Chess With Different Armies").clicked() {
+                            let seed = random_seed();
+                            let (white, black) =
+                                RandomWildLayout::different_armies(seed, &wild_config);
+                            commands.trigger(
+                                SpawnGame::new(PieceSet::asymmetric(white, black))
+                                    .with_seed(Some(GameSeed {
+                                        seed,
+                                        variant: GameRequestVariant::DifferentArmies(*wild_config),
+                                    }))
+                                    .with_clock(clock.clone()),
+                            );
+                        }
+                    });
+                    ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                        if ui.button("Play Horde Chess").clicked() {
+                            commands.trigger(
+                                SpawnGame::new(PieceSet::asymmetric(
+                                    HordeLayout::pieces(),
+                                    ClassicalLayout::pieces(),
+                                ))
+                                .with_clock(clock.clone()),
+                            );
                         }
                     });
                     ui.allocate_ui(Vec2::new(250., 120.), |ui| {
                         if ui.button("Play Super Relay Chess").clicked() {
-                            commands.trigger(SpawnGame::new(SuperRelayLayout::pieces().into()));
+                            commands.trigger(
+                                SpawnGame::new(SuperRelayLayout::pieces().into())
+                                    .with_clock(clock.clone()),
+                            );
                         }
                     });
                     ui.allocate_ui(Vec2::new(250., 120.), |ui| {
                         if ui.button("Play (Not-Quite) Knight Relay Chess").clicked() {
-                            commands.trigger(SpawnGame::new(KnightRelayLayout::pieces().into()));
+                            commands.trigger(
+                                SpawnGame::new(KnightRelayLayout::pieces().into())
+                                    .with_clock(clock.clone()),
+                            );
                         }
                     });
+                    ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                        if ui.button("Set Up a Position").clicked() {
+                            commands.init_resource::<SetupEditor>();
+                        }
+                    });
+                    ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                        if ui.button("Piece Editor").clicked() {
+                            commands.init_resource::<PieceEditor>();
+                        }
+                    });
+                    for (_, variant) in variants.iter() {
+                        ui.allocate_ui(Vec2::new(250., 120.), |ui| {
+                            let button = ui.button(format!("Play {}", variant.name));
+                            if button.on_hover_text(&variant.description).clicked() {
+                                // variants may play a time control with their own clock
+                                let clock = requested_clock.map(|clock| variant.clock(clock));
+                                commands.trigger(variant.spawn_game().with_clock(clock));
+                            }
+                        });
+                    }
+                    ui.add_space(20.);
+                    ui.add(
+                        TextEdit::multiline(&mut import.0)
+                            .hint_text("Paste a PGN")
+                            .desired_rows(4),
+                    );
+                    if ui.button("Import PGN").clicked() {
+                        match GameRecord::import(&import.0) {
+                            Ok(record) => {
+                                commands.trigger(record.analysis_game());
+                                *import = Default::default();
+                            }
+                            Err(error) => import.1 = Some(error.to_string()),
+                        }
+                    }
+                    if let Some(error) = &import.1 {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                    ui.add_space(20.);
+                    ui.add(TextEdit::singleline(&mut setup.0).hint_text("Paste a FEN"));
+                    if ui.button("Start From Position").clicked() {
+                        let board = GameBoard::Chess.board();
+                        match Position::from_fen_on(&setup.0, &ClassicalLayout::legend(), &board) {
+                            Ok(position) => {
+                                commands.trigger(
+                                    SpawnGame::new(ClassicalLayout::pieces().into())
+                                        .with_setup(position)
+                                        .with_clock(clock.clone()),
+                                );
+                                *setup = Default::default();
+                            }
+                            Err(error) => setup.1 = Some(error.to_string()),
+                        }
+                    }
+                    if let Some(error) = &setup.1 {
+                        ui.colored_label(Color32::RED, error);
+                    }
+                    ui.add_space(20.);
+                    ui.add(TextEdit::singleline(&mut seed.0).hint_text("Wild Chess seed"));
+                    if ui.button("Replay Wild Chess Seed").clicked() {
+                        match seed.0.trim().parse::<u64>() {
+                            Ok(value) => {
+                                commands.trigger(
+                                    wild_game(value, &wild_config).with_clock(clock.clone()),
+                                );
+                                *seed = Default::default();
+                            }
+                            Err(error) => seed.1 = Some(error.to_string()),
+                        }
+                    }
+                    if let Some(error) = &seed.1 {
+                        ui.colored_label(Color32::RED, error);
+                    }
                 });
             });
         });
     }
 }
+
+// A seed replays the same armies only with the same settings
+fn wild_game(seed: u64, config: &WildConfig) -> SpawnGame {
+    SpawnGame::new(RandomWildLayout::configured_pieces(seed, config).into()).with_seed(Some(
+        GameSeed {
+            seed,
+            variant: GameRequestVariant::Wild(*config),
+        },
+    ))
+}
+
+// The time control of the games started from the menu
+pub struct ClockSettings {
+    // None plays without a clock
+    clock: Option<GameRequestClock>,
+    // the custom time control being edited
+    minutes: f32,
+    increment: u64,
+    timing: Timing,
+}
+
+impl Default for ClockSettings {
+    fn default() -> Self {
+        ClockSettings {
+            clock: None,
+            minutes: 10.,
+            increment: 5,
+            timing: Timing::Increment(Duration::from_secs(5)),
+        }
+    }
+}
+
+impl ClockSettings {
+    fn custom_clock(&self) -> GameRequestClock {
+        GameRequestClock::custom(Duration::from_secs_f32(self.minutes * 60.), self.timing)
+    }
+}
+
+fn render_clock_settings(ui: &mut Ui, settings: &mut ClockSettings) {
+    let custom = settings.custom_clock();
+    let is_custom = matches!(settings.clock, Some(GameRequestClock::Custom { .. }));
+    ComboBox::from_label("Clock")
+        .selected_text(match settings.clock {
+            None => "None".to_string(),
+            Some(GameRequestClock::Custom { .. }) => "Custom".to_string(),
+            Some(clock) => format!("{clock:?}"),
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut settings.clock, None, "None");
+            for clock in [
+                GameRequestClock::Bullet,
+                GameRequestClock::Blitz,
+                GameRequestClock::Rapid,
+                GameRequestClock::Classical,
+            ] {
+                ui.selectable_value(&mut settings.clock, Some(clock), format!("{clock:?}"));
+            }
+            if ui.selectable_label(is_custom, "Custom").clicked() {
+                settings.clock = Some(custom);
+            }
+        });
+    if let Some(clock) = settings.clock {
+        ui.label(clock.to_clock().time_control());
+    }
+    if !is_custom {
+        return;
+    }
+
+    // the sliders keep the custom clock within the bounds the server accepts
+    let max_minutes = MAX_CLOCK_TIME.as_secs_f32() / 60.;
+    ui.add(
+        Slider::new(&mut settings.minutes, 0.25..=max_minutes)
+            .logarithmic(true)
+            .text("minutes"),
+    );
+    let increment = Duration::from_secs(settings.increment);
+    ComboBox::from_label("Timing")
+        .selected_text(timing_name(settings.timing))
+        .show_ui(ui, |ui| {
+            for timing in [
+                Timing::Increment(increment),
+                Timing::Bronstein(increment),
+                Timing::Delay(increment),
+                Timing::Hourglass,
+                Timing::PerMove,
+            ] {
+                ui.selectable_value(&mut settings.timing, timing, timing_name(timing));
+            }
+        });
+    if matches!(
+        settings.timing,
+        Timing::Increment(_) | Timing::Bronstein(_) | Timing::Delay(_)
+    ) {
+        let text = match settings.timing {
+            Timing::Increment(_) => "seconds of increment",
+            _ => "seconds of delay",
+        };
+        ui.add(Slider::new(&mut settings.increment, 0..=MAX_CLOCK_INCREMENT.as_secs()).text(text));
+    }
+    let increment = Duration::from_secs(settings.increment);
+    settings.timing = match settings.timing {
+        Timing::Increment(_) => Timing::Increment(increment),
+        Timing::Bronstein(_) => Timing::Bronstein(increment),
+        Timing::Delay(_) => Timing::Delay(increment),
+        timing => timing,
+    };
+    let custom = settings.custom_clock();
+    if let Err(error) = custom.validate() {
+        ui.colored_label(Color32::RED, error.to_string());
+    }
+    settings.clock = Some(custom);
+}
+
+fn timing_name(timing: Timing) -> &'static str {
+    match timing {
+        Timing::Increment(_) => "Increment",
+        Timing::Bronstein(_) => "Bronstein delay",
+        Timing::Delay(_) => "Simple delay",
+        Timing::Hourglass => "Hourglass",
+        Timing::PerMove => "Per move",
+    }
+}
+
+fn render_wild_config(ui: &mut Ui, config: &mut WildConfig) {
+    ui.label("Army value, in percent of the classical army");
+    ui.add(Slider::new(&mut config.min_budget, 50..=200).text("minimum"));
+    ui.add(Slider::new(&mut config.max_budget, 50..=200).text("maximum"));
+    config.max_budget = config.max_budget.max(config.min_budget);
+
+    ui.label("Piece classes");
+    ui.checkbox(&mut config.tiers.infantry, "Infantry");
+    ui.checkbox(&mut config.tiers.minor, "Minor");
+    ui.checkbox(&mut config.tiers.advanced, "Advanced");
+    ui.checkbox(&mut config.tiers.major, "Major");
+    ui.checkbox(&mut config.tiers.elite, "Elite");
+    ui.checkbox(&mut config.tiers.legendary, "Legendary");
+
+    ui.add(Slider::new(&mut config.slots, 1..=WildConfig::MAX_SLOTS).text("different pieces"));
+    ui.checkbox(&mut config.random_pawns, "Random pawns");
+    ui.checkbox(&mut config.random_kings, "Random kings");
+    ComboBox::from_label("Promotion")
+        .selected_text(format!("{:?}", config.promotion))
+        .show_ui(ui, |ui| {
+            for promotion in [
+                WildPromotion::Army,
+                WildPromotion::Elite,
+                WildPromotion::Classical,
+                WildPromotion::None,
+            ] {
+                ui.selectable_value(&mut config.promotion, promotion, format!("{promotion:?}"));
+            }
+        });
+}
@@ -1,25 +1,35 @@
+use std::path::PathBuf;
+
 use bevy::prelude::{
-    Added, App, Entity, IntoSystemConfigs, Plugin, Query, ResMut, SystemSet, Update,
+    Added, App, Entity, IntoSystemConfigs, Plugin, Query, Res, ResMut, Resource, SystemSet, Update,
+    With, Without,
 };
 
 pub use bevy_egui;
 
 use games::{
     chess::pieces::Orientation,
-    components::{Game, History},
+    components::{Bot, Game, History, InGame, Player},
 };
 use wild_icons::{PieceIconCharacter, PieceIconPlugin, PieceIconSvg};
 
+pub(crate) mod move_input;
 pub(crate) mod mutation;
 pub(crate) mod query;
 
 mod home_ui;
 pub use home_ui::{HomeMenuUIPlugin, HomeMenuUISystems};
 
+mod piece_editor;
+pub use piece_editor::{PieceEditor, PieceLibrary, PieceLibraryFile};
+
+mod setup_editor;
+pub use setup_editor::SetupEditor;
+
 mod board_ui;
 use board_ui::{
-    egui_chessboard, egui_history_panel, egui_information_panel, SelectedGame,
-    SelectedHistoricalPly, SelectedSquare,
+    egui_chessboard, egui_history_panel, egui_information_panel, BoardView, SelectedGame,
+    SelectedHistoricalPly, SelectedSquare, ShownThreats,
 };
 
 mod widgets;
@@ -27,6 +37,12 @@ mod widgets;
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, SystemSet)]
 pub struct ChessUISystems;
 
+// The file games are saved to and resumed from.
+// Menus only offer saving and resuming games when this resource exists.
+#[derive(Clone, Debug)]
+#[derive(Resource)]
+pub struct SaveFile(pub PathBuf);
+
 pub struct EguiBoardUIPlugin;
 
 impl Plugin for EguiBoardUIPlugin {
@@ -41,6 +57,10 @@ impl Plugin for EguiBoardUIPlugin {
             .init_resource::<SelectedSquare>()
             .init_resource::<SelectedHistoricalPly>()
             .init_resource::<SelectedGame>()
+            .init_resource::<ShownThreats>()
+            .init_resource::<BoardView>()
+            .observe(History::<PieceIconSvg>::truncate_observer)
+            .observe(History::<PieceIconCharacter>::truncate_observer)
             .add_systems(
                 Update,
                 (
@@ -61,6 +81,26 @@ fn set_game(mut game: ResMut<SelectedGame>, game_query: Query<Entity, Added<Game
     }
 }
 
-fn get_orientation() -> Orientation {
-    Orientation::Up
+// The board is seen from the side of the selected game's only local player,
+// or from White's side when both players share the screen.
+#[allow(clippy::type_complexity)]
+fn get_orientation(
+    mut view: ResMut<BoardView>,
+    game: Res<SelectedGame>,
+    players: Query<(&Orientation, &InGame), (With<Player>, Without<Bot>)>,
+) -> Orientation {
+    let mut local_players = players
+        .iter()
+        .filter(|(_, in_game)| Some(in_game.0) == game.0)
+        .map(|(orientation, _)| *orientation);
+    let orientation = match (local_players.next(), local_players.next()) {
+        (Some(orientation), None) => orientation,
+        _ => Orientation::Up,
+    };
+    view.orientation = if view.flipped {
+        orientation.flip()
+    } else {
+        orientation
+    };
+    view.orientation
 }
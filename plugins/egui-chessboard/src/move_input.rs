@@ -0,0 +1,249 @@
+use bevy::{prelude::Entity, utils::HashMap};
+
+use games::{
+    chess::{board::Square, notation::Notation, pieces::PieceDefinition, team::Team},
+    components::Premove,
+    RequestTurnEvent,
+};
+
+use crate::query::PieceData;
+
+// The text of the move entry box below the board
+#[derive(Default)]
+pub(crate) struct MoveInput {
+    pub(crate) text: String,
+    pub(crate) message: Option<String>,
+}
+
+// A move in coordinate notation, such as `e2e4`, `e2-e4`, `e7e8=Q` or `e7e8q`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct CoordinateMove {
+    pub(crate) from: Square,
+    pub(crate) to: Square,
+    // a piece letter of the game's notation,
+    // or the number of the promotion option counted from 1
+    pub(crate) promotion: Option<char>,
+}
+
+impl CoordinateMove {
+    pub(crate) fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let from = text.get(..2)?.to_lowercase();
+        let rest = text.get(2..)?;
+        let rest = rest.strip_prefix('-').unwrap_or(rest);
+        let to = rest.get(..2)?.to_lowercase();
+        let rest = rest.get(2..)?;
+        let rest = rest.strip_prefix('=').unwrap_or(rest);
+        let mut promotion = rest.chars();
+        let letter = promotion.next();
+        if promotion.next().is_some() {
+            return None;
+        }
+        Some(CoordinateMove {
+            from: from.as_str().try_into().ok()?,
+            to: to.as_str().try_into().ok()?,
+            promotion: letter.map(|letter| letter.to_ascii_uppercase()),
+        })
+    }
+
+    // The turn this move makes in the pieces of a game, or why it cannot be played
+    pub(crate) fn resolve(
+        &self,
+        game: Entity,
+        pieces: &HashMap<Square, PieceData>,
+        team_with_turn: Team,
+        notation: &Notation,
+    ) -> Result<RequestTurnEvent, String> {
+        let piece = self.piece(pieces)?;
+        if *piece.team != team_with_turn {
+            return Err(format!("It is {team_with_turn:?}'s turn"));
+        }
+        let action = piece
+            .actions
+            .get(&self.to)
+            .ok_or_else(|| format!("The piece on {} cannot move to {}", self.from, self.to))?;
+        Ok(match self.promotion(piece, notation)? {
+            Some(promotion) => {
+                RequestTurnEvent::new_with_mutation(piece.entity, game, action.clone(), promotion)
+            }
+            None => RequestTurnEvent::new(piece.entity, game, action.clone()),
+        })
+    }
+
+    // The premove this move queues for a team waiting for its turn
+    pub(crate) fn premove(
+        &self,
+        pieces: &HashMap<Square, PieceData>,
+        team: Team,
+        notation: &Notation,
+    ) -> Result<Premove, String> {
+        let piece = self.piece(pieces)?;
+        if *piece.team != team {
+            return Err(format!("The piece on {} is not {team:?}'s", self.from));
+        }
+        Ok(Premove {
+            piece: piece.entity,
+            team,
+            from: self.from,
+            to: self.to,
+            promotion: self.promotion(piece, notation)?,
+        })
+    }
+
+    fn piece<'a>(
+        &self,
+        pieces: &'a HashMap<Square, PieceData<'a>>,
+    ) -> Result<&'a PieceData<'a>, String> {
+        pieces
+            .get(&self.from)
+            .ok_or_else(|| format!("There is no piece on {}", self.from))
+    }
+
+    fn promotion(
+        &self,
+        piece: &PieceData,
+        notation: &Notation,
+    ) -> Result<Option<PieceDefinition>, String> {
+        let Some(letter) = self.promotion else {
+            return Ok(None);
+        };
+        let options = piece
+            .mutation
+            .map(|mutation| mutation.to_piece.as_slice())
+            .unwrap_or_default();
+        match letter.to_digit(10) {
+            Some(number) => (number as usize)
+                .checked_sub(1)
+                .and_then(|index| options.get(index)),
+            None => options
+                .iter()
+                .find(|option| notation.piece_symbol(option) == letter),
+        }
+        .cloned()
+        .map(Some)
+        .ok_or_else(|| format!("The piece on {} cannot promote to {letter}", self.from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use games::{
+        chess::{
+            actions::{Action, Actions},
+            behavior::PatternBehavior,
+            board::OnBoard,
+            pieces::{Orientation, PieceIdentity},
+        },
+        components::InGame,
+        layouts::ClassicalLayout,
+    };
+
+    use super::*;
+
+    fn square(text: &str) -> Square {
+        text.try_into().unwrap()
+    }
+
+    fn coordinate_move(from: &str, to: &str, promotion: Option<char>) -> CoordinateMove {
+        CoordinateMove {
+            from: square(from),
+            to: square(to),
+            promotion,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            CoordinateMove::parse("e2e4"),
+            Some(coordinate_move("e2", "e4", None))
+        );
+        assert_eq!(
+            CoordinateMove::parse(" E2-e4 "),
+            Some(coordinate_move("e2", "e4", None))
+        );
+        assert_eq!(
+            CoordinateMove::parse("e7e8=Q"),
+            Some(coordinate_move("e7", "e8", Some('Q')))
+        );
+        assert_eq!(
+            CoordinateMove::parse("e7e8q"),
+            Some(coordinate_move("e7", "e8", Some('Q')))
+        );
+        assert_eq!(
+            CoordinateMove::parse("e7e8=2"),
+            Some(coordinate_move("e7", "e8", Some('2')))
+        );
+        for text in ["", "e2", "e2e", "e2e4qq", "z2e4", "e2-", "Nf3"] {
+            assert_eq!(CoordinateMove::parse(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut pawn = ClassicalLayout::named_piece("pawn").unwrap();
+        let mut mutation = pawn.mutation.clone().unwrap();
+        // a wild piece sharing the queen's identity is written with its own letter
+        let wild = PieceDefinition::new(PatternBehavior::default().into(), PieceIdentity::Queen);
+        mutation.to_piece.push(wild.clone());
+        pawn.mutation = Some(mutation.clone());
+        let notation = Notation::default().with_pieces([&pawn]);
+        let wild_symbol = notation.piece_symbol(&wild);
+        assert_ne!(wild_symbol, 'Q');
+
+        let (from, to) = (square("e7"), square("e8"));
+        let actions = Actions::new(
+            [(
+                to,
+                Action::movement(from, to, Orientation::Up, vec![], None),
+            )]
+            .into_iter()
+            .collect(),
+        );
+        let game = Entity::from_raw(0);
+        let (in_game, on_board, team) = (InGame(game), OnBoard(Entity::from_raw(1)), Team::White);
+        let piece = PieceData {
+            entity: Entity::from_raw(2),
+            in_game: &in_game,
+            on_board: &on_board,
+            team: &team,
+            actions: &actions,
+            position: None,
+            pattern_behavior: None,
+            relay_behavior: None,
+            mutation: Some(&mutation),
+            icon: None,
+        };
+        let pieces = [(from, piece)].into_iter().collect::<HashMap<_, _>>();
+
+        let promotion = |text: &str| {
+            CoordinateMove::parse(text)
+                .unwrap()
+                .resolve(game, &pieces, Team::White, &notation)
+                .map(|turn| turn.promotion)
+        };
+        let queen = ClassicalLayout::named_piece("queen");
+        assert_eq!(promotion("e7e8=Q"), Ok(queen.clone()));
+        assert_eq!(promotion("e7e8q"), Ok(queen.clone()));
+        assert_eq!(promotion("e7e8=1"), Ok(mutation.to_piece.first().cloned()));
+        assert_eq!(promotion("e7e8=5"), Ok(Some(wild.clone())));
+        assert_eq!(promotion(&format!("e7e8{wild_symbol}")), Ok(Some(wild)));
+        assert_eq!(promotion("e7e8"), Ok(None));
+
+        assert!(promotion("e7e8=K").is_err());
+        assert!(promotion("e7e8=6").is_err());
+        assert!(promotion("e7e8=0").is_err());
+        assert!(CoordinateMove::parse("e7e7")
+            .unwrap()
+            .resolve(game, &pieces, Team::White, &notation)
+            .is_err());
+        assert!(CoordinateMove::parse("d7d8")
+            .unwrap()
+            .resolve(game, &pieces, Team::White, &notation)
+            .is_err());
+        assert!(CoordinateMove::parse("e7e8=Q")
+            .unwrap()
+            .resolve(game, &pieces, Team::Black, &notation)
+            .is_err());
+    }
+}
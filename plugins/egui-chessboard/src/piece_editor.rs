@@ -0,0 +1,677 @@
+use std::{
+    collections::BTreeMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_egui::{
+    egui::{
+        CentralPanel, Color32, ComboBox, Grid, RichText, ScrollArea, SidePanel, Slider, TextEdit,
+        Ui,
+    },
+    EguiContexts,
+};
+use serde::{Deserialize, Serialize};
+
+use games::chess::{
+    behavior::PatternBehavior,
+    betza,
+    board::{Board, Rank, Square},
+    pattern::{
+        ABSymmetry, CaptureMode, CapturePattern, CaptureRules, ForbiddenTargetConstraint,
+        FromRankConstraint, Pattern, RSymmetry, ScanMode, Step, TargetKind,
+    },
+    pieces::{Orientation, PieceDefinition, PieceIdentity, Royal},
+    team::Team,
+};
+use wild_icons::PieceIconSvg;
+
+use crate::widgets::{describe_pattern, forget_icon, SquareWidget};
+
+// Custom pieces by name, as saved from the piece editor.
+// Each piece can be copied into the `pieces` of a variant file.
+#[derive(Clone, Debug, Default)]
+#[derive(Deserialize, Serialize)]
+#[derive(Resource)]
+pub struct PieceLibrary(pub BTreeMap<String, PieceDefinition>);
+
+impl PieceLibrary {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(std::io::Error::other)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let text =
+            ron::ser::to_string_pretty(self, Default::default()).map_err(std::io::Error::other)?;
+        std::fs::write(path, text)
+    }
+}
+
+// The file custom pieces are saved to and loaded from
+#[derive(Clone, Debug)]
+#[derive(Resource)]
+pub struct PieceLibraryFile(pub PathBuf);
+
+pub(crate) fn load_piece_library(
+    mut library: ResMut<PieceLibrary>,
+    file: Option<Res<PieceLibraryFile>>,
+) {
+    let Some(file) = file.filter(|file| file.0.exists()) else {
+        return;
+    };
+    match PieceLibrary::load(&file.0) {
+        Ok(loaded) => *library = loaded,
+        Err(error) => error!("Failed to load custom pieces: {error}"),
+    }
+}
+
+// What clicking a square of the preview board does
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum ClickMode {
+    #[default]
+    MovePiece,
+    PlaceEnemy,
+    PlaceFriend,
+    ForbidTarget,
+}
+
+// The piece being edited. The home menu shows the editor while this resource exists.
+#[derive(Clone, Debug)]
+#[derive(Resource)]
+pub struct PieceEditor {
+    name: String,
+    identity: PieceIdentity,
+    royal: bool,
+    patterns: Vec<Pattern>,
+    selected_pattern: usize,
+    // the square the piece is previewed from
+    origin: Square,
+    // other pieces on the preview board, to preview collisions and captures
+    blockers: HashMap<Square, Team>,
+    click_mode: ClickMode,
+    betza: String,
+    message: Option<String>,
+}
+
+impl Default for PieceEditor {
+    fn default() -> Self {
+        PieceEditor {
+            name: "Custom Piece".to_string(),
+            identity: PieceIdentity::Queen,
+            royal: false,
+            patterns: vec![Pattern::radial().range(1).captures_by_displacement()],
+            selected_pattern: 0,
+            origin: Square::new(3.into(), 3.into()),
+            blockers: HashMap::new(),
+            click_mode: ClickMode::default(),
+            betza: String::new(),
+            message: None,
+        }
+    }
+}
+
+impl PieceEditor {
+    pub fn piece(&self) -> PieceDefinition {
+        PieceDefinition {
+            royal: self.royal.then_some(Royal),
+            ..PieceDefinition::new(
+                PatternBehavior::new(self.patterns.clone()).into(),
+                self.identity,
+            )
+        }
+    }
+
+    fn edit_piece(&mut self, name: &str, piece: &PieceDefinition) {
+        self.name = name.to_string();
+        self.identity = piece.identity;
+        self.royal = piece.royal.is_some();
+        self.patterns = piece
+            .behaviors
+            .pattern
+            .as_ref()
+            .map(|behavior| behavior.patterns.clone())
+            .unwrap_or_default();
+        self.selected_pattern = 0;
+    }
+
+    fn click(&mut self, square: Square) {
+        match self.click_mode {
+            ClickMode::MovePiece => {
+                self.origin = square;
+                self.blockers.remove(&square);
+            }
+            ClickMode::PlaceEnemy | ClickMode::PlaceFriend if square != self.origin => {
+                let team = if self.click_mode == ClickMode::PlaceEnemy {
+                    Team::Black
+                } else {
+                    Team::White
+                };
+                if self.blockers.get(&square) == Some(&team) {
+                    self.blockers.remove(&square);
+                } else {
+                    self.blockers.insert(square, team);
+                }
+            }
+            ClickMode::ForbidTarget => {
+                let Some(pattern) = self.patterns.get_mut(self.selected_pattern) else {
+                    return;
+                };
+                let forbidden = &mut pattern
+                    .constraints
+                    .forbidden_targets
+                    .get_or_insert_with(|| ForbiddenTargetConstraint(vec![]))
+                    .0;
+                if let Some(index) = forbidden.iter().position(|target| *target == square) {
+                    forbidden.remove(index);
+                } else {
+                    forbidden.push(square);
+                }
+                if forbidden.is_empty() {
+                    pattern.constraints.forbidden_targets = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The squares the piece can move to from the origin, and whether it captures there
+    fn targets(&self, board: &Board) -> HashMap<Square, bool> {
+        let mut pieces = self.blockers.clone();
+        pieces.insert(self.origin, Team::White);
+        let mut targets = HashMap::new();
+        for pattern in self.patterns.iter() {
+            let actions = pattern.search(
+                &self.origin,
+                &Orientation::Up,
+                &Team::White,
+                board,
+                &pieces,
+                None,
+            );
+            for (square, action) in actions {
+                *targets.entry(square).or_default() |= !action.captures.is_empty();
+            }
+        }
+        targets
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn piece_editor_system(
+    mut commands: Commands,
+    mut egui_ctx: EguiContexts,
+    mut editor: ResMut<PieceEditor>,
+    mut library: ResMut<PieceLibrary>,
+    library_file: Option<Res<PieceLibraryFile>>,
+    // the icon of the edited piece, regenerated when the piece changes
+    mut icon: Local<Option<(u64, PieceIconSvg)>>,
+    mut blocker_icons: Local<Option<[(Team, PieceIconSvg); 2]>>,
+) {
+    SidePanel::left("piece_editor_panel")
+        .min_width(480.)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ScrollArea::vertical().show(ui, |ui| {
+                if ui.button("Back").clicked() {
+                    commands.remove_resource::<PieceEditor>();
+                }
+                ui.add_space(10.);
+                render_piece(ui, &mut editor, &mut library, library_file.as_deref());
+                ui.add_space(10.);
+                render_patterns(ui, &mut editor);
+            });
+        });
+
+    let piece = editor.piece();
+    let mut hasher = DefaultHasher::new();
+    (&editor.patterns, piece.identity, editor.royal).hash(&mut hasher);
+    let key = hasher.finish();
+    if icon.as_ref().map(|(icon_key, _)| *icon_key) != Some(key) {
+        // the image of the previous piece is never shown again
+        if let Some((_, old_icon)) = icon.as_ref() {
+            forget_icon(egui_ctx.ctx_mut(), old_icon);
+        }
+        *icon = Some((
+            key,
+            PieceIconSvg::new(
+                piece.identity,
+                "editor",
+                piece.behaviors.pattern.as_ref(),
+                None,
+                Team::White,
+                Orientation::Up,
+                editor.royal,
+            ),
+        ));
+    }
+    let blocker_icons = blocker_icons.get_or_insert_with(|| {
+        let blocker_icon = |team: Team| {
+            PieceIconSvg::new(
+                PieceIdentity::Pawn,
+                "editor-blocker",
+                None,
+                None,
+                team,
+                Orientation::Up,
+                false,
+            )
+        };
+        [
+            (Team::White, blocker_icon(Team::White)),
+            (Team::Black, blocker_icon(Team::Black)),
+        ]
+    });
+
+    CentralPanel::default().show(egui_ctx.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Clicking a square");
+            ui.radio_value(
+                &mut editor.click_mode,
+                ClickMode::MovePiece,
+                "moves the piece",
+            );
+            ui.radio_value(
+                &mut editor.click_mode,
+                ClickMode::PlaceEnemy,
+                "places an enemy",
+            );
+            ui.radio_value(
+                &mut editor.click_mode,
+                ClickMode::PlaceFriend,
+                "places a friend",
+            );
+            ui.radio_value(
+                &mut editor.click_mode,
+                ClickMode::ForbidTarget,
+                "forbids a target of the selected pattern",
+            );
+        });
+        let board = Board::chess_board();
+        let targets = editor.targets(&board);
+        let forbidden = editor
+            .patterns
+            .get(editor.selected_pattern)
+            .and_then(|pattern| pattern.constraints.forbidden_targets.as_ref())
+            .map(|forbidden| forbidden.0.iter().copied().collect::<HashSet<_>>())
+            .unwrap_or_default();
+        let mut clicked = None;
+        Grid::new("piece_editor_board").show(ui, |ui| {
+            for y in (0..board.size.rank.0 + 1).rev() {
+                for x in 0..board.size.file.0 + 1 {
+                    let square = Square::new(x.into(), y.into());
+                    let square_icon = if square == editor.origin {
+                        icon.as_ref().map(|(_, icon)| icon)
+                    } else {
+                        editor.blockers.get(&square).and_then(|team| {
+                            blocker_icons
+                                .iter()
+                                .find(|(icon_team, _)| icon_team == team)
+                                .map(|(_, icon)| icon)
+                        })
+                    };
+                    let mut widget = SquareWidget::new(square, square_icon).with_width(80.);
+                    if square == editor.origin {
+                        widget = widget.selected();
+                    } else if let Some(captures) = targets.get(&square) {
+                        widget = widget.with_target(*captures);
+                    } else if forbidden.contains(&square) {
+                        widget = widget.with_threat(true);
+                    }
+                    if ui.add(widget).clicked() {
+                        clicked = Some(square);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(square) = clicked {
+            editor.click(square);
+        }
+    });
+}
+
+fn render_piece(
+    ui: &mut Ui,
+    editor: &mut PieceEditor,
+    library: &mut PieceLibrary,
+    library_file: Option<&PieceLibraryFile>,
+) {
+    ui.add(TextEdit::singleline(&mut editor.name).hint_text("Piece name"));
+    ComboBox::from_label("Identity")
+        .selected_text(format!("{:?}", editor.identity))
+        .show_ui(ui, |ui| {
+            for identity in [
+                PieceIdentity::King,
+                PieceIdentity::Queen,
+                PieceIdentity::Rook,
+                PieceIdentity::Bishop,
+                PieceIdentity::Knight,
+                PieceIdentity::Pawn,
+            ] {
+                ui.selectable_value(&mut editor.identity, identity, format!("{identity:?}"));
+            }
+        });
+    ui.checkbox(&mut editor.royal, "Royal");
+
+    ui.horizontal(|ui| {
+        if ui.button("Save Piece").clicked() {
+            library.0.insert(editor.name.clone(), editor.piece());
+            editor.message = Some(match library_file {
+                Some(file) => match library.save(&file.0) {
+                    Ok(()) => format!("Saved {}", editor.name),
+                    Err(error) => format!("Failed to save pieces: {error}"),
+                },
+                None => format!("Saved {} until the app closes", editor.name),
+            });
+        }
+        if ui.button("Copy RON").clicked() {
+            match ron::ser::to_string_pretty(&editor.piece(), Default::default()) {
+                Ok(text) => ui.output_mut(|output| output.copied_text = text),
+                Err(error) => editor.message = Some(error.to_string()),
+            }
+        }
+    });
+    if !library.0.is_empty() {
+        let mut edited = None;
+        ComboBox::from_label("Edit a saved piece")
+            .selected_text("")
+            .show_ui(ui, |ui| {
+                for (name, piece) in library.0.iter() {
+                    if ui.selectable_label(false, name).clicked() {
+                        edited = Some((name.clone(), piece.clone()));
+                    }
+                }
+            });
+        if let Some((name, piece)) = edited {
+            editor.edit_piece(&name, &piece);
+        }
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(TextEdit::singleline(&mut editor.betza).hint_text("Betza, such as WN"));
+        if ui.button("Import Betza").clicked() {
+            match betza::parse(&editor.betza) {
+                Ok(behavior) => {
+                    editor.patterns = behavior.patterns;
+                    editor.selected_pattern = 0;
+                    editor.message = None;
+                }
+                Err(error) => editor.message = Some(error.to_string()),
+            }
+        }
+    });
+    if let Some(message) = &editor.message {
+        ui.colored_label(Color32::LIGHT_YELLOW, message);
+    }
+}
+
+fn render_patterns(ui: &mut Ui, editor: &mut PieceEditor) {
+    ui.label(RichText::new("Patterns").size(20.));
+    for index in 0..editor.patterns.len() {
+        let label = format!("Pattern {}", index + 1);
+        if ui
+            .selectable_label(editor.selected_pattern == index, label)
+            .clicked()
+        {
+            editor.selected_pattern = index;
+        }
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Add Pattern").clicked() {
+            editor.patterns.push(Pattern::orthogonal());
+            editor.selected_pattern = editor.patterns.len() - 1;
+        }
+        if ui.button("Remove Pattern").clicked() && editor.selected_pattern < editor.patterns.len()
+        {
+            editor.patterns.remove(editor.selected_pattern);
+            editor.selected_pattern = editor.selected_pattern.saturating_sub(1);
+        }
+    });
+
+    let Some(pattern) = editor.patterns.get_mut(editor.selected_pattern) else {
+        return;
+    };
+    ui.label(describe_pattern(pattern, &Team::White).size(16.));
+    ui.separator();
+    render_step(ui, &mut pattern.scanner.step);
+    ui.separator();
+    render_scanner(ui, pattern);
+    ui.separator();
+    render_capture(ui, &mut pattern.capture);
+    ui.separator();
+    render_constraints(ui, pattern);
+}
+
+fn render_step(ui: &mut Ui, step: &mut Step) {
+    ui.horizontal(|ui| {
+        let is_leap = matches!(step, Step::TwoDim(..));
+        if ui.radio(!is_leap, "Straight steps").clicked() && is_leap {
+            *step = Step::from_r(1, RSymmetry::orthogonal());
+        }
+        if ui.radio(is_leap, "Leaps").clicked() && !is_leap {
+            *step = Step::from_ab(2, 1, ABSymmetry::ALL);
+        }
+    });
+    match step {
+        Step::OneDim(r, symmetry) => {
+            ui.add(Slider::new(r, 1..=7).text("squares per step"));
+            // the directions, arranged around the piece
+            let directions = [
+                [
+                    Some(RSymmetry::FORWARD_LEFT),
+                    Some(RSymmetry::FORWARD),
+                    Some(RSymmetry::FORWARD_RIGHT),
+                ],
+                [Some(RSymmetry::LEFT), None, Some(RSymmetry::RIGHT)],
+                [
+                    Some(RSymmetry::BACKWARD_LEFT),
+                    Some(RSymmetry::BACKWARD),
+                    Some(RSymmetry::BACKWARD_RIGHT),
+                ],
+            ];
+            Grid::new("piece_editor_r_symmetry").show(ui, |ui| {
+                for row in directions {
+                    for direction in row {
+                        match direction {
+                            Some(direction) => {
+                                let mut enabled = symmetry.contains(direction);
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    symmetry.set(direction, enabled);
+                                }
+                            }
+                            None => {
+                                ui.label("•");
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+        Step::TwoDim(a, b, symmetry) => {
+            ui.add(Slider::new(a, 2..=7).text("squares along"));
+            ui.add(Slider::new(b, 1..=6).text("squares across"));
+            // a leap is always longer along than across
+            *b = (*b).min(*a - 1);
+            // the leaps, arranged around the piece as for a knight
+            let directions = [
+                [
+                    None,
+                    Some(ABSymmetry::FORWARD_FORWARD_LEFT),
+                    None,
+                    Some(ABSymmetry::FORWARD_FORWARD_RIGHT),
+                    None,
+                ],
+                [
+                    Some(ABSymmetry::FORWARD_LEFT_LEFT),
+                    None,
+                    None,
+                    None,
+                    Some(ABSymmetry::FORWARD_RIGHT_RIGHT),
+                ],
+                [None, None, None, None, None],
+                [
+                    Some(ABSymmetry::BACKWARD_LEFT_LEFT),
+                    None,
+                    None,
+                    None,
+                    Some(ABSymmetry::BACKWARD_RIGHT_RIGHT),
+                ],
+                [
+                    None,
+                    Some(ABSymmetry::BACKWARD_BACKWARD_LEFT),
+                    None,
+                    Some(ABSymmetry::BACKWARD_BACKWARD_RIGHT),
+                    None,
+                ],
+            ];
+            Grid::new("piece_editor_ab_symmetry").show(ui, |ui| {
+                for (y, row) in directions.into_iter().enumerate() {
+                    for (x, direction) in row.into_iter().enumerate() {
+                        match direction {
+                            Some(direction) => {
+                                let mut enabled = symmetry.contains(direction);
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    symmetry.set(direction, enabled);
+                                }
+                            }
+                            None if x == 2 && y == 2 => {
+                                ui.label("•");
+                            }
+                            None => {
+                                ui.label("");
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+    }
+}
+
+fn render_scanner(ui: &mut Ui, pattern: &mut Pattern) {
+    let mut limited = pattern.scanner.range.is_some();
+    if ui.checkbox(&mut limited, "Limited range").changed() {
+        pattern.scanner.range = limited.then_some(1);
+    }
+    if let Some(range) = &mut pattern.scanner.range {
+        ui.add(Slider::new(range, 1..=7).text("steps"));
+    }
+
+    let mode = &mut pattern.scanner.mode;
+    ComboBox::from_label("Collisions")
+        .selected_text(match mode {
+            ScanMode::Walk => "Stops at a piece",
+            ScanMode::Pierce => "Passes through pieces",
+            ScanMode::Hop { .. } => "Hops over a piece",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(mode, ScanMode::Walk, "Stops at a piece");
+            ui.selectable_value(mode, ScanMode::Pierce, "Passes through pieces");
+            let is_hop = matches!(mode, ScanMode::Hop { .. });
+            if ui.selectable_label(is_hop, "Hops over a piece").clicked() && !is_hop {
+                *mode = ScanMode::Hop {
+                    max_steps_after_hop: 1,
+                    allowed_hops: TargetKind::Any,
+                };
+            }
+        });
+    if let ScanMode::Hop {
+        max_steps_after_hop,
+        allowed_hops,
+    } = mode
+    {
+        ui.add(Slider::new(max_steps_after_hop, 1..=7).text("steps after the hop"));
+        render_target_kind(ui, "Hops over", allowed_hops);
+    }
+}
+
+fn render_capture(ui: &mut Ui, capture: &mut Option<CaptureRules>) {
+    let mut captures = capture.is_some();
+    if ui.checkbox(&mut captures, "Captures").changed() {
+        *capture = captures.then(CaptureRules::default);
+    }
+    let Some(capture) = capture else {
+        return;
+    };
+    ComboBox::from_label("Capture mode")
+        .selected_text(format!("{:?}", capture.mode))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut capture.mode, CaptureMode::CanCapture, "CanCapture");
+            ui.selectable_value(&mut capture.mode, CaptureMode::MustCapture, "MustCapture");
+        });
+    ComboBox::from_label("Capture pattern")
+        .selected_text(format!("{:?}", capture.pattern))
+        .show_ui(ui, |ui| {
+            for pattern in [
+                CapturePattern::CaptureByDisplacement,
+                CapturePattern::CaptureInPassing,
+                CapturePattern::CaptureByOvertake,
+                CapturePattern::CaptureAtRange,
+            ] {
+                ui.selectable_value(&mut capture.pattern, pattern, format!("{pattern:?}"));
+            }
+        });
+    render_target_kind(ui, "Captures", &mut capture.target);
+}
+
+fn render_target_kind(ui: &mut Ui, label: &str, target: &mut TargetKind) {
+    ComboBox::from_label(label)
+        .selected_text(format!("{target:?}"))
+        .show_ui(ui, |ui| {
+            for kind in [TargetKind::Enemy, TargetKind::Friendly, TargetKind::Any] {
+                ui.selectable_value(target, kind, format!("{kind:?}"));
+            }
+        });
+}
+
+fn render_constraints(ui: &mut Ui, pattern: &mut Pattern) {
+    let constraints = &mut pattern.constraints;
+    let mut from_rank = constraints.from_rank.is_some();
+    if ui.checkbox(&mut from_rank, "Only from a rank").changed() {
+        constraints.from_rank = from_rank.then_some(FromRankConstraint(Rank::TWO));
+    }
+    if let Some(FromRankConstraint(rank)) = &mut constraints.from_rank {
+        let mut number = rank.0 + 1;
+        ui.add(Slider::new(&mut number, 1..=8).text("rank, from the piece's side"));
+        rank.0 = number - 1;
+    }
+    if let Some(ForbiddenTargetConstraint(squares)) = &constraints.forbidden_targets {
+        let squares = squares
+            .iter()
+            .map(|square| square.to_string())
+            .collect::<Vec<_>>();
+        ui.horizontal(|ui| {
+            ui.label(format!("Forbidden targets: {}", squares.join(", ")));
+            if ui.button("Clear").clicked() {
+                constraints.forbidden_targets = None;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_library() {
+        let mut library = PieceLibrary::default();
+        library
+            .0
+            .insert("Custom Piece".to_string(), PieceEditor::default().piece());
+        let knight = PieceDefinition::new(betza::parse("N").unwrap().into(), PieceIdentity::Knight);
+        library.0.insert("Knight".to_string(), knight);
+
+        let path = std::env::temp_dir().join(format!("piece-library-{}.ron", std::process::id()));
+        library.save(&path).unwrap();
+        let loaded = PieceLibrary::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().0, library.0);
+    }
+}
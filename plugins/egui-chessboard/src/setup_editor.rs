@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_egui::{
+    egui::{CentralPanel, Color32, Grid, RichText, ScrollArea, SidePanel},
+    EguiContexts,
+};
+
+use games::{
+    chess::{
+        board::{Board, Square},
+        pieces::{Orientation, PieceDefinition},
+        position::{Position, PositionPiece},
+        team::Team,
+    },
+    components::{PieceSet, SpawnGame, WinCondition},
+};
+use layouts::{ClassicalLayout, FeaturedWildLayout, PieceSpecification};
+use wild_icons::PieceIconSvg;
+
+use crate::{piece_editor::PieceLibrary, widgets::SquareWidget};
+
+// A piece placed on the setup board
+#[derive(Clone, Debug)]
+struct SetupPiece {
+    // the index of the piece in the palette
+    kind: usize,
+    team: Team,
+    can_castle: bool,
+    is_castling_target: bool,
+    can_mutate: bool,
+}
+
+// The position being set up. The home menu shows the setup editor while this resource exists.
+#[derive(Clone, Debug)]
+#[derive(Resource)]
+pub struct SetupEditor {
+    // the pieces that can be placed, by name
+    palette: Vec<(String, PieceDefinition)>,
+    pieces: BTreeMap<Square, SetupPiece>,
+    // the palette piece placed by clicking an empty square
+    placing: Option<usize>,
+    placing_team: Team,
+    side_to_move: Team,
+    // the board is shown from Black's side
+    flipped: bool,
+    selected: Option<Square>,
+    message: Option<String>,
+}
+
+impl FromWorld for SetupEditor {
+    fn from_world(world: &mut World) -> Self {
+        let mut palette = ["king", "queen", "rook", "bishop", "knight", "pawn"]
+            .into_iter()
+            .filter_map(|name| {
+                ClassicalLayout::named_piece(name).map(|piece| (capitalize(name), piece))
+            })
+            .collect::<Vec<_>>();
+        for (label, layout) in [
+            ("I", FeaturedWildLayout::One),
+            ("II", FeaturedWildLayout::Two),
+            ("III", FeaturedWildLayout::Three),
+        ] {
+            let set = layout.piece_set();
+            for (name, piece) in [
+                ("King", set.king),
+                ("Elite", set.elite),
+                ("Major", set.major),
+                ("Minor", set.minor1),
+                ("Second Minor", set.minor2),
+                ("Pawn", set.pawn),
+            ] {
+                palette.push((format!("Featured {label} {name}"), piece));
+            }
+        }
+        if let Some(library) = world.get_resource::<PieceLibrary>() {
+            palette.extend(
+                library
+                    .0
+                    .iter()
+                    .map(|(name, piece)| (name.clone(), piece.clone())),
+            );
+        }
+        SetupEditor {
+            palette,
+            pieces: BTreeMap::new(),
+            placing: Some(0),
+            placing_team: Team::White,
+            side_to_move: Team::White,
+            flipped: false,
+            selected: None,
+            message: None,
+        }
+    }
+}
+
+impl SetupEditor {
+    fn place(&mut self, kind: usize, square: Square, team: Team) {
+        let Some((_, piece)) = self.palette.get(kind) else {
+            return;
+        };
+        self.pieces.insert(
+            square,
+            SetupPiece {
+                kind,
+                team,
+                can_castle: piece.behaviors.castling.is_some(),
+                is_castling_target: piece.behaviors.castling_target.is_some(),
+                can_mutate: piece.mutation.is_some(),
+            },
+        );
+    }
+
+    fn classical_start(&mut self) {
+        let board = Board::chess_board();
+        self.pieces.clear();
+        for team in [Team::White, Team::Black] {
+            for spec in ClassicalLayout::pieces() {
+                let Some(kind) = self
+                    .palette
+                    .iter()
+                    .position(|(_, piece)| *piece == spec.piece)
+                else {
+                    continue;
+                };
+                let square = spec.start_square.reorient(team.orientation(), &board);
+                self.place(kind, square, team);
+            }
+        }
+        self.side_to_move = Team::White;
+    }
+
+    fn definition(&self, piece: &SetupPiece) -> PieceDefinition {
+        let mut definition = self.palette[piece.kind].1.clone();
+        if !piece.can_mutate {
+            definition.mutation = None;
+        }
+        definition
+    }
+
+    pub fn position(&self) -> Position {
+        let mut position = Position::new(Board::chess_board());
+        for (index, (square, piece)) in self.pieces.iter().enumerate() {
+            let mut position_piece = PositionPiece::new(
+                Entity::from_raw(index as u32),
+                self.definition(piece),
+                *square,
+                piece.team,
+            );
+            position_piece.can_castle &= piece.can_castle;
+            position_piece.is_castling_target &= piece.is_castling_target;
+            position.insert_piece(position_piece);
+        }
+        position.side_to_move = self.side_to_move;
+        position
+    }
+
+    // The pieces of each team as they were set up, recorded with the game
+    fn piece_set(&self) -> PieceSet {
+        let board = Board::chess_board();
+        let specifications = |team: Team| {
+            self.pieces
+                .iter()
+                .filter(|(_, piece)| piece.team == team)
+                .map(|(square, piece)| {
+                    PieceSpecification::new(
+                        self.definition(piece),
+                        square.reorient(team.orientation(), &board),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        PieceSet::asymmetric(specifications(Team::White), specifications(Team::Black))
+    }
+}
+
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+pub(crate) fn setup_editor_system(
+    mut commands: Commands,
+    mut egui_ctx: EguiContexts,
+    mut editor: ResMut<SetupEditor>,
+    // the icons of the palette pieces by team and board orientation
+    mut icons: Local<HashMap<(usize, Team, bool), PieceIconSvg>>,
+) {
+    if editor.is_added() {
+        icons.clear();
+    }
+
+    SidePanel::left("setup_editor_panel")
+        .min_width(320.)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ScrollArea::vertical().show(ui, |ui| {
+                if ui.button("Back").clicked() {
+                    commands.remove_resource::<SetupEditor>();
+                }
+                ui.add_space(10.);
+                ui.horizontal(|ui| {
+                    ui.label("To move");
+                    ui.radio_value(&mut editor.side_to_move, Team::White, "White");
+                    ui.radio_value(&mut editor.side_to_move, Team::Black, "Black");
+                });
+                ui.checkbox(&mut editor.flipped, "View from Black's side");
+                ui.horizontal(|ui| {
+                    if ui.button("Classical Start").clicked() {
+                        editor.classical_start();
+                        editor.selected = None;
+                    }
+                    if ui.button("Clear Board").clicked() {
+                        editor.pieces.clear();
+                        editor.selected = None;
+                    }
+                });
+                if ui.button("Start Game").clicked() {
+                    let position = editor.position();
+                    match position.validate(WinCondition::default().requires_royal()) {
+                        Ok(()) => {
+                            commands.trigger(
+                                SpawnGame::new(editor.piece_set()).with_setup(position),
+                            );
+                            commands.remove_resource::<SetupEditor>();
+                        }
+                        Err(error) => editor.message = Some(error.to_string()),
+                    }
+                }
+                if let Some(message) = &editor.message {
+                    ui.colored_label(Color32::RED, message);
+                }
+                ui.add_space(10.);
+
+                let editor = &mut *editor;
+                if let Some(square) = editor.selected {
+                    if let Some(piece) = editor.pieces.get_mut(&square) {
+                        let (name, definition) = &editor.palette[piece.kind];
+                        ui.label(
+                            RichText::new(format!("{:?} {name} on {square}", piece.team))
+                                .size(16.),
+                        );
+                        if definition.behaviors.castling.is_some() {
+                            ui.checkbox(&mut piece.can_castle, "Can castle");
+                        }
+                        if definition.behaviors.castling_target.is_some() {
+                            ui.checkbox(&mut piece.is_castling_target, "Can be castled with");
+                        }
+                        if definition.mutation.is_some() {
+                            ui.checkbox(&mut piece.can_mutate, "Can promote");
+                        }
+                        if ui.button("Remove").clicked() {
+                            editor.pieces.remove(&square);
+                            editor.selected = None;
+                        }
+                        ui.add_space(10.);
+                    }
+                }
+
+                ui.label(RichText::new("Pieces").size(20.));
+                ui.label("Click an empty square to place a piece, click a piece to edit it, or right click a piece to remove it.");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut editor.placing_team, Team::White, "White");
+                    ui.radio_value(&mut editor.placing_team, Team::Black, "Black");
+                });
+                for (index, (name, _)) in editor.palette.iter().enumerate() {
+                    ui.selectable_value(&mut editor.placing, Some(index), name);
+                }
+            });
+        });
+
+    let orientation = if editor.flipped {
+        Orientation::Down
+    } else {
+        Orientation::Up
+    };
+    for piece in editor.pieces.values() {
+        let (name, definition) = &editor.palette[piece.kind];
+        icons
+            .entry((piece.kind, piece.team, editor.flipped))
+            .or_insert_with(|| {
+                PieceIconSvg::new(
+                    definition.identity,
+                    format!(
+                        "setup-{}-{name}-{:?}-{:?}",
+                        piece.kind, piece.team, orientation
+                    ),
+                    definition.behaviors.pattern.as_ref(),
+                    definition.behaviors.relay.as_ref(),
+                    piece.team,
+                    orientation,
+                    definition.royal.is_some(),
+                )
+            });
+    }
+
+    CentralPanel::default().show(egui_ctx.ctx_mut(), |ui| {
+        let board = Board::chess_board();
+        let mut ranks = (0..board.size.rank.0 + 1).rev().collect::<Vec<_>>();
+        let mut files = (0..board.size.file.0 + 1).collect::<Vec<_>>();
+        if editor.flipped {
+            ranks.reverse();
+            files.reverse();
+        }
+        let mut clicked = None;
+        let mut removed = None;
+        Grid::new("setup_editor_board").show(ui, |ui| {
+            for rank in ranks.iter() {
+                for file in files.iter() {
+                    let square = Square::new((*file).into(), (*rank).into());
+                    let icon = editor
+                        .pieces
+                        .get(&square)
+                        .and_then(|piece| icons.get(&(piece.kind, piece.team, editor.flipped)));
+                    let mut widget = SquareWidget::new(square, icon).with_width(80.);
+                    if editor.selected == Some(square) {
+                        widget = widget.selected();
+                    }
+                    let response = ui.add(widget);
+                    if response.clicked() {
+                        clicked = Some(square);
+                    } else if response.secondary_clicked() {
+                        removed = Some(square);
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(square) = clicked {
+            editor.message = None;
+            if editor.pieces.contains_key(&square) {
+                editor.selected = Some(square);
+            } else if let Some(kind) = editor.placing {
+                let team = editor.placing_team;
+                editor.place(kind, square, team);
+                editor.selected = Some(square);
+            }
+        }
+        if let Some(square) = removed {
+            editor.message = None;
+            editor.pieces.remove(&square);
+            if editor.selected == Some(square) {
+                editor.selected = None;
+            }
+        }
+    });
+}
@@ -1,18 +1,32 @@
-use bevy::utils::HashMap;
+use bevy::utils::{HashMap, HashSet};
 
-use bevy_egui::egui::{self, Response, Ui, Widget};
+use bevy_egui::egui::{
+    self, Area, Color32, Id, Order, Rect, Response, Sense, Shape, Stroke, Ui, Widget,
+};
 
-use games::chess::board::Square;
+use games::chess::{actions::Action, board::Square, pieces::Orientation};
 
-use crate::{query::PieceData, widgets::SquareWidget};
+use crate::{
+    query::PieceData,
+    widgets::{icon_image, SquareWidget},
+};
 
 pub struct BoardWidget<'a> {
     pieces: &'a HashMap<Square, PieceData<'a>>,
     previous_selection: Option<Square>,
     selection: &'a mut Option<Square>,
+    dropped: Option<&'a mut Option<(Square, Square)>>,
+    threats: Option<&'a HashSet<Square>>,
+    checks: Option<&'a HashSet<Square>>,
+    last_action: Option<&'a Action>,
+    premoves: &'a [(Square, Square)],
+    orientation: Orientation,
 }
 
 impl<'a> BoardWidget<'a> {
+    const PATH_COLOR: Color32 = Color32::from_rgba_premultiplied(30, 70, 140, 200);
+    const PREMOVE_COLOR: Color32 = Color32::from_rgba_premultiplied(100, 40, 130, 180);
+
     pub fn new(
         pieces: &'a HashMap<Square, PieceData<'a>>,
         previous_selection: Option<Square>,
@@ -22,35 +36,199 @@ impl<'a> BoardWidget<'a> {
             selection,
             previous_selection,
             pieces,
+            dropped: None,
+            threats: None,
+            checks: None,
+            last_action: None,
+            premoves: &[],
+            orientation: Orientation::Up,
         }
     }
+
+    // Lets pieces be dragged, writing the squares a piece is dragged from and dropped on
+    #[must_use]
+    pub fn with_drop(mut self, dropped: &'a mut Option<(Square, Square)>) -> Self {
+        self.dropped = Some(dropped);
+        self
+    }
+
+    // Highlights the given squares as threatened
+    #[must_use]
+    pub fn with_threats(mut self, threats: &'a HashSet<Square>) -> Self {
+        self.threats = Some(threats);
+        self
+    }
+
+    // Rings the royal pieces on the given squares as capturable
+    #[must_use]
+    pub fn with_checks(mut self, checks: &'a HashSet<Square>) -> Self {
+        self.checks = Some(checks);
+        self
+    }
+
+    // Shows the board from the side of a player facing `orientation`
+    #[must_use]
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    // The square shown at a row from the top and a column from the left
+    fn square_at(&self, row: u16, column: u16) -> Square {
+        let (file, rank) = match self.orientation {
+            Orientation::Up => (column, 7 - row),
+            Orientation::Down => (7 - column, row),
+            Orientation::Left => (row, column),
+            Orientation::Right => (7 - row, 7 - column),
+        };
+        Square::new(file.into(), rank.into())
+    }
+
+    // Draws the queued premoves, from the square of each piece to its target
+    #[must_use]
+    pub fn with_premoves(mut self, premoves: &'a [(Square, Square)]) -> Self {
+        self.premoves = premoves;
+        self
+    }
+
+    // Marks the squares the last move started and ended on
+    #[must_use]
+    pub fn with_last_action(mut self, last_action: Option<&'a Action>) -> Self {
+        self.last_action = last_action;
+        self
+    }
 }
 
 impl<'a> Widget for BoardWidget<'a> {
     fn ui(self, ui: &mut Ui) -> Response {
-        egui::Grid::new("board_grid")
+        // the square of the piece being dragged, kept across frames until it is dropped
+        let drag_id = Id::new("board_drag");
+        let dragging = if self.dropped.is_some() {
+            ui.data(|data| data.get_temp::<Square>(drag_id))
+        } else {
+            None
+        };
+        let selected_piece_data = dragging
+            .or(self.previous_selection)
+            .and_then(|square| self.pieces.get(&square));
+        let last_move = self
+            .last_action
+            .map(|action| [action.movement.from, action.movement.to]);
+
+        let mut square_rects: HashMap<Square, Rect> = HashMap::default();
+        let mut released = false;
+        let response = egui::Grid::new("board_grid")
             .show(ui, |ui| {
-                let selected_piece_data = self
-                    .previous_selection
-                    .and_then(|square| self.pieces.get(&square));
-
-                for y in (0..=7).rev() {
-                    for x in 0..=7 {
-                        let square = Square::new(x.into(), y.into());
-                        if ui
-                            .add(SquareWidget::new_from_context(
-                                square,
-                                self.pieces.get(&square).and_then(|piece| piece.icon),
-                                selected_piece_data,
-                            ))
-                            .clicked()
-                        {
+                for row in 0..=7 {
+                    for column in 0..=7 {
+                        let square = self.square_at(row, column);
+                        let threatened = self
+                            .threats
+                            .is_some_and(|threats| threats.contains(&square));
+                        let in_check = self.checks.is_some_and(|checks| checks.contains(&square));
+                        let moved = last_move.is_some_and(|squares| squares.contains(&square));
+                        // the dragged piece follows the pointer instead
+                        let icon = self
+                            .pieces
+                            .get(&square)
+                            .and_then(|piece| piece.icon)
+                            .filter(|_| dragging != Some(square));
+                        let response = ui
+                            .add(
+                                SquareWidget::new_from_context(square, icon, selected_piece_data)
+                                    .with_threat(threatened)
+                                    .with_last_move(moved)
+                                    .with_check(in_check),
+                            )
+                            .interact(Sense::click_and_drag());
+                        if response.clicked() {
                             *self.selection = Some(square);
-                        };
+                        }
+                        if response.drag_started()
+                            && self.dropped.is_some()
+                            && self.pieces.contains_key(&square)
+                        {
+                            ui.data_mut(|data| data.insert_temp(drag_id, square));
+                        }
+                        if response.drag_stopped() {
+                            released = true;
+                        }
+                        square_rects.insert(square, response.rect);
                     }
                     ui.end_row();
                 }
             })
-            .response
+            .response;
+
+        // other squares are not hovered while a piece is dragged, so the pointer is used instead
+        let hovered = ui.ctx().pointer_latest_pos().and_then(|pointer| {
+            square_rects
+                .iter()
+                .find(|(_, rect)| rect.contains(pointer))
+                .map(|(square, _)| *square)
+        });
+
+        if let Some(from) = dragging {
+            let piece = self.pieces.get(&from);
+            if released {
+                ui.data_mut(|data| data.remove::<Square>(drag_id));
+                if let Some(dropped) = self.dropped {
+                    *dropped = hovered.map(|to| (from, to));
+                }
+            } else if let Some((icon, pointer)) = piece
+                .and_then(|piece| piece.icon)
+                .zip(ui.ctx().pointer_latest_pos())
+            {
+                let size = SquareWidget::WIDTH * 0.8;
+                Area::new(Id::new("dragged_piece"))
+                    .order(Order::Tooltip)
+                    .interactable(false)
+                    .fixed_pos(pointer - egui::vec2(size, size) / 2.)
+                    .show(ui.ctx(), |ui| {
+                        ui.add(icon_image(icon).fit_to_exact_size(egui::vec2(size, size)));
+                    });
+            }
+            // outline the square the piece would be dropped on
+            let target_rect = hovered
+                .filter(|target| piece.is_some_and(|piece| piece.actions.get(target).is_some()))
+                .and_then(|target| square_rects.get(&target));
+            if let Some(rect) = target_rect.filter(|_| !released) {
+                ui.painter()
+                    .rect_stroke(*rect, 0., Stroke::new(4., Self::PATH_COLOR));
+            }
+        }
+
+        for (from, to) in self.premoves.iter() {
+            if let Some((from, to)) = square_rects.get(from).zip(square_rects.get(to)) {
+                let painter = ui.painter();
+                painter.line_segment(
+                    [from.center(), to.center()],
+                    Stroke::new(6., Self::PREMOVE_COLOR),
+                );
+                painter.circle_filled(to.center(), 10., Self::PREMOVE_COLOR);
+            }
+        }
+
+        // the path to the hovered target of the selected piece, through each square it scans
+        let path = selected_piece_data
+            .zip(hovered)
+            .and_then(|(piece, target)| {
+                let origin = piece.position?.0;
+                let action = piece.actions.get(&target)?;
+                std::iter::once(origin)
+                    .chain(action.scanned_squares.iter().copied())
+                    .chain(std::iter::once(target))
+                    .map(|square| square_rects.get(&square).map(|rect| rect.center()))
+                    .collect::<Option<Vec<_>>>()
+            });
+        if let Some(points) = path {
+            let painter = ui.painter();
+            for point in points.iter().skip(1) {
+                painter.circle_filled(*point, 6., Self::PATH_COLOR);
+            }
+            painter.add(Shape::line(points, Stroke::new(4., Self::PATH_COLOR)));
+        }
+
+        response
     }
 }
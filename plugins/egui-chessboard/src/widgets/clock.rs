@@ -1,5 +1,5 @@
 use bevy_egui::egui::{Button, Color32, Response, RichText, Stroke, Ui, Widget};
-use games::components::Clock;
+use games::{components::Clock, format_duration};
 
 pub struct ClockWidget<'a> {
     clock: &'a Clock,
@@ -20,10 +20,7 @@ impl<'a> ClockWidget<'a> {
 
 impl<'a> Widget for ClockWidget<'a> {
     fn ui(self, ui: &mut Ui) -> Response {
-        let remaining_seconds = self.clock.remaining_time().as_secs();
-        let minutes = remaining_seconds / 60;
-        let seconds = remaining_seconds % 60;
-        let text = RichText::new(format!("{}:{:0>2}", minutes, seconds))
+        let text = RichText::new(format_duration(self.clock.remaining_time()))
             .size(Self::FONT_SIZE)
             .strong()
             .color(Color32::BLACK);
@@ -31,6 +28,20 @@ impl<'a> Widget for ClockWidget<'a> {
         let button = Button::new(text)
             .fill(Self::BG_COLOR)
             .stroke(Stroke::new(Self::STROKE_WIDTH, Self::STROKE_COLOR));
-        ui.add_sized([Self::WIDTH, Self::HEIGHT], button)
+        ui.vertical(|ui| {
+            ui.add_sized([Self::WIDTH, Self::HEIGHT], button);
+            // the clock only counts down once the delay has passed
+            if let Some(delay) = self
+                .clock
+                .remaining_delay()
+                .filter(|delay| !delay.is_zero())
+            {
+                ui.label(format!("Delay: {:.1}s", delay.as_secs_f32()));
+            }
+            if let Some(moves) = self.clock.moves_to_next_stage() {
+                ui.label(format!("{moves} moves to the next time control"));
+            }
+        })
+        .response
     }
 }
@@ -5,7 +5,9 @@ mod clock;
 pub use clock::ClockWidget;
 
 mod piece_inspector;
+pub(crate) use piece_inspector::describe_pattern;
 pub use piece_inspector::PieceInspectorWidget;
 
 mod square;
 pub use square::SquareWidget;
+pub(crate) use square::{forget_icon, icon_image};
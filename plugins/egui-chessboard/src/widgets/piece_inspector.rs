@@ -3,17 +3,36 @@ use bevy_egui::egui::{Color32, Response, RichText, Style, Ui, Visuals, Widget};
 use games::chess::{
     pattern::{CaptureMode, CaptureRules, Pattern, RSymmetry, ScanMode, Step},
     team::Team,
+    value::PieceValue,
 };
 
 use crate::query::PieceData;
 
 pub struct PieceInspectorWidget<'a> {
     piece: &'a PieceData<'a>,
+    pattern_value: Option<PieceValue>,
+    relay_value: Option<PieceValue>,
 }
 
 impl<'a> PieceInspectorWidget<'a> {
     pub fn new(piece: &'a PieceData<'a>) -> Self {
-        Self { piece }
+        Self {
+            piece,
+            pattern_value: None,
+            relay_value: None,
+        }
+    }
+
+    // The estimated values of the piece's patterns and relays
+    #[must_use]
+    pub fn with_values(
+        mut self,
+        pattern_value: Option<PieceValue>,
+        relay_value: Option<PieceValue>,
+    ) -> Self {
+        self.pattern_value = pattern_value;
+        self.relay_value = relay_value;
+        self
     }
 }
 
@@ -29,6 +48,19 @@ impl<'a> Widget for PieceInspectorWidget<'a> {
             });
 
             ui.label(RichText::new("Selected piece:").size(24.));
+            if self.pattern_value.is_some() || self.relay_value.is_some() {
+                let total = self.pattern_value.map_or(0, |value| value.value)
+                    + self.relay_value.map_or(0, |value| value.value);
+                ui.label(
+                    RichText::new(format!("Estimated value: {:.1}", total as f32 / 100.)).size(24.),
+                );
+            }
+            if let Some(value) = self.pattern_value {
+                ui.label(describe_value("Moves", value));
+            }
+            if let Some(value) = self.relay_value {
+                ui.label(describe_value("Relays", value));
+            }
             if let Some(patterns) = self.piece.pattern_behavior {
                 ui.label(RichText::new("Piece move patterns:").size(24.));
                 for pattern in patterns.patterns.iter() {
@@ -47,7 +79,14 @@ impl<'a> Widget for PieceInspectorWidget<'a> {
     }
 }
 
-fn describe_pattern(pattern: &Pattern, _team: &Team) -> RichText {
+fn describe_value(kind: &str, value: PieceValue) -> RichText {
+    RichText::new(format!(
+        "{kind}: {:.1} actions, {:.1} captures on average",
+        value.mobility, value.captures
+    ))
+}
+
+pub(crate) fn describe_pattern(pattern: &Pattern, _team: &Team) -> RichText {
     RichText::new(format!(
         "- {} {}{}{}{}.",
         match pattern.capture {
@@ -1,5 +1,5 @@
 use bevy_egui::egui::{
-    Button, Color32, Image, ImageSource, Response, RichText, Stroke, Ui, Widget,
+    Button, Color32, Context, Image, ImageSource, Response, RichText, Stroke, Ui, Widget,
 };
 use egui_extras::install_image_loaders;
 
@@ -12,6 +12,7 @@ enum SquareHighlight {
     Selected,
     Targetable,
     CaptureTargetable,
+    Threatened,
 }
 
 impl SquareHighlight {
@@ -41,7 +42,9 @@ pub struct SquareWidget<'a> {
     square: Square,
     icon: Option<&'a PieceIconSvg>,
     highlight: Option<SquareHighlight>,
-    // TODO: scale: f32,
+    last_move: bool,
+    in_check: bool,
+    width: f32,
 }
 
 impl<'a> SquareWidget<'a> {
@@ -50,16 +53,73 @@ impl<'a> SquareWidget<'a> {
     const DARK_BG: Color32 = Color32::from_rgb(181, 136, 99);
     const LIGHT_BG: Color32 = Color32::from_rgb(240, 217, 181);
 
+    pub fn new(square: Square, icon: Option<&'a PieceIconSvg>) -> Self {
+        SquareWidget {
+            square,
+            icon,
+            highlight: None,
+            last_move: false,
+            in_check: false,
+            width: Self::WIDTH,
+        }
+    }
+
     pub fn new_from_context(
         square: Square,
         icon: Option<&'a PieceIconSvg>,
         selected_piece: Option<&'a PieceData<'a>>,
     ) -> Self {
         SquareWidget {
-            square,
-            icon,
             highlight: SquareHighlight::from_context(square, selected_piece),
+            ..Self::new(square, icon)
+        }
+    }
+
+    #[must_use]
+    pub fn with_width(mut self, width: f32) -> Self {
+        self.width = width;
+        self
+    }
+
+    #[must_use]
+    pub fn selected(mut self) -> Self {
+        self.highlight = Some(SquareHighlight::Selected);
+        self
+    }
+
+    // Highlights the square as one the piece can move to, or capture on
+    #[must_use]
+    pub fn with_target(mut self, captures: bool) -> Self {
+        self.highlight = Some(if captures {
+            SquareHighlight::CaptureTargetable
+        } else {
+            SquareHighlight::Targetable
+        });
+        self
+    }
+
+    // Highlights the square as one where a piece could be captured,
+    // unless it is already highlighted for the selected piece
+    #[must_use]
+    pub fn with_threat(mut self, threatened: bool) -> Self {
+        if threatened && self.highlight.is_none() {
+            self.highlight = Some(SquareHighlight::Threatened);
         }
+        self
+    }
+
+    // Marks the square as one the last move started or ended on
+    #[must_use]
+    pub fn with_last_move(mut self, last_move: bool) -> Self {
+        self.last_move = last_move;
+        self
+    }
+
+    // Rings a royal piece on the square that could be captured
+    #[must_use]
+    pub fn with_check(mut self, in_check: bool) -> Self {
+        self.in_check = in_check;
+        self
     }
 
     fn background_color(&self) -> Color32 {
@@ -68,6 +128,13 @@ impl<'a> SquareWidget<'a> {
             Some(SquareHighlight::CaptureTargetable) => {
                 Color32::from_rgba_unmultiplied(180, 70, 70, 130)
             }
+            Some(SquareHighlight::Threatened) => {
+                if self.square.is_even() {
+                    Color32::from_rgb(200, 110, 80)
+                } else {
+                    Color32::from_rgb(235, 160, 130)
+                }
+            }
             _ => {
                 if self.square.is_even() {
                     Self::DARK_BG
@@ -86,23 +153,29 @@ impl<'a> SquareWidget<'a> {
     }
 }
 
+// The image of a piece icon
+pub(crate) fn icon_image(icon: &PieceIconSvg) -> Image<'static> {
+    Image::new(ImageSource::Bytes {
+        uri: icon.source.clone().into(),
+        bytes: icon.source.bytes().collect::<Vec<u8>>().into(),
+    })
+}
+
+// Drops the cached image of a piece icon that is no longer shown
+pub(crate) fn forget_icon(ctx: &Context, icon: &PieceIconSvg) {
+    ctx.forget_image(&icon.source);
+}
+
 impl<'a> Widget for SquareWidget<'a> {
     fn ui(self, ui: &mut Ui) -> Response {
         install_image_loaders(ui.ctx());
         let background_color = self.background_color();
         let mut button = match self.icon {
-            Some(PieceIconSvg { source, .. }) => {
-                let image = ImageSource::Bytes {
-                    uri: source.clone().into(),
-                    bytes: source.bytes().collect::<Vec<u8>>().into(),
-                };
+            Some(icon) => {
                 // TODO: why is this not * 2.?
-                const R: f32 = SquareWidget::WIDTH - SquareWidget::STROKE_WIDTH * 3.;
-                Button::image_and_text(
-                    Image::new(image.clone()).fit_to_exact_size((R, R).into()),
-                    "",
-                )
-                .fill(background_color)
+                let r = self.width - SquareWidget::STROKE_WIDTH * 3.;
+                Button::image_and_text(icon_image(icon).fit_to_exact_size((r, r).into()), "")
+                    .fill(background_color)
             }
             None => {
                 let text = RichText::new("").size(86.).strong().color(Color32::BLACK);
@@ -120,6 +193,23 @@ impl<'a> Widget for SquareWidget<'a> {
             button = button.stroke(Stroke::new(Self::STROKE_WIDTH, stroke_color));
         }
 
-        ui.add_sized([Self::WIDTH, Self::WIDTH], button)
+        let response = ui.add_sized([self.width, self.width], button);
+        // overlays are drawn over the piece, so they are kept translucent
+        let painter = ui.painter();
+        if self.last_move {
+            painter.rect_filled(
+                response.rect.shrink(Self::STROKE_WIDTH),
+                0.,
+                Color32::from_rgba_unmultiplied(205, 210, 60, 70),
+            );
+        }
+        if self.in_check {
+            painter.circle_stroke(
+                response.rect.center(),
+                self.width * 0.42,
+                Stroke::new(Self::STROKE_WIDTH, Color32::from_rgb(220, 30, 30)),
+            );
+        }
+        response
     }
 }
@@ -0,0 +1,74 @@
+use chess::{
+    board::Board, pieces::PieceDefinition, position::Position, team::Team, value::ValueEstimator,
+};
+
+// the value of each available action
+const MOBILITY_VALUE: i32 = 4;
+
+// Evaluates positions with any kind of piece, using the material of each team and the
+// number of actions available to them.
+// Piece values are estimated from their mobility with `chess::value`, so the evaluator
+// does not need to know anything about the pieces in advance.
+#[derive(Default)]
+pub struct Evaluator {
+    values: ValueEstimator,
+}
+
+impl Evaluator {
+    // The score of a position from the perspective of the team to move
+    pub fn evaluate(&mut self, position: &Position) -> i32 {
+        let mut score = 0;
+        for piece in position.pieces() {
+            if piece.square.is_none() || piece.is_royal() {
+                continue;
+            }
+            let value = self.piece_value(&position.board, &piece.definition);
+            score += perspective(piece.team, position.side_to_move) * value;
+        }
+        for (piece, actions) in position.piece_actions() {
+            score += perspective(piece.team, position.side_to_move)
+                * MOBILITY_VALUE
+                * actions.0.len() as i32;
+        }
+        score
+    }
+
+    pub fn piece_value(&mut self, board: &Board, definition: &PieceDefinition) -> i32 {
+        self.values.piece_value(board, definition)
+    }
+}
+
+fn perspective(team: Team, side_to_move: Team) -> i32 {
+    if team == side_to_move {
+        1
+    } else {
+        -1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{behavior::PatternBehavior, pattern::Pattern};
+
+    use super::*;
+
+    fn value(pattern: Pattern) -> i32 {
+        let definition = PieceDefinition::new(
+            PatternBehavior::default().with_pattern(pattern).into(),
+            Default::default(),
+        );
+        Evaluator::default().piece_value(&Board::chess_board(), &definition)
+    }
+
+    #[test]
+    fn test_classical_piece_values() {
+        let knight = value(Pattern::knight().leaper().captures_by_displacement());
+        let bishop = value(Pattern::diagonal().rider().captures_by_displacement());
+        let rook = value(Pattern::orthogonal().rider().captures_by_displacement());
+        let queen = value(Pattern::radial().rider().captures_by_displacement());
+
+        assert!(knight < bishop, "{knight} < {bishop}");
+        assert!(bishop < rook, "{bishop} < {rook}");
+        assert!(rook < queen, "{rook} < {queen}");
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use bevy_app::prelude::{App, Plugin, Update};
+#[cfg(feature = "reflect")]
+use bevy_ecs::prelude::ReflectComponent;
+use bevy_ecs::prelude::{Component, IntoSystemConfigs, IntoSystemSetConfigs, SystemSet};
+#[cfg(feature = "reflect")]
+use bevy_reflect::prelude::Reflect;
+
+use bevy_replicon::prelude::{has_authority, AppRuleExt, ClientId};
+
+use crate::{Clock, GameSystems};
+
+mod evaluation;
+pub use evaluation::Evaluator;
+mod search;
+pub use search::{search, SearchLimits};
+mod systems;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(SystemSet)]
+pub struct BotSystems;
+
+// Plays turns for every `Player` with a `Bot` component.
+// Bots run wherever games are authoritative: on the server, or in local games.
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        app.replicate::<Bot>()
+            .configure_sets(
+                Update,
+                BotSystems.before(GameSystems::All).run_if(has_authority),
+            )
+            .add_systems(
+                Update,
+                (systems::start_thinking, systems::play_turns)
+                    .chain()
+                    .in_set(BotSystems),
+            );
+
+        #[cfg(feature = "reflect")]
+        app.register_type::<Bot>();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum BotDifficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl BotDifficulty {
+    pub fn limits(&self) -> SearchLimits {
+        match self {
+            BotDifficulty::Easy => SearchLimits {
+                max_depth: 1,
+                max_time: Duration::from_millis(250),
+            },
+            BotDifficulty::Medium => SearchLimits {
+                max_depth: 3,
+                max_time: Duration::from_secs(1),
+            },
+            BotDifficulty::Hard => SearchLimits {
+                max_depth: 64,
+                max_time: Duration::from_secs(5),
+            },
+        }
+    }
+
+    // Limits the search so that the bot does not run out of time.
+    // This spends about 1/30th of the remaining time plus half of the increment per turn.
+    pub fn limits_with_clock(&self, clock: Option<&Clock>) -> SearchLimits {
+        let mut limits = self.limits();
+        if let Some(clock) = clock {
+            let budget = clock.remaining_time() / 30 + clock.increment() / 2;
+            limits.max_time = limits.max_time.min(budget);
+        }
+        limits
+    }
+}
+
+// A player controlled by the search engine
+#[derive(Clone, Copy, Debug, Default)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct Bot {
+    pub difficulty: BotDifficulty,
+}
+
+impl Bot {
+    pub fn new(difficulty: BotDifficulty) -> Self {
+        Bot { difficulty }
+    }
+
+    // The client that bots request their turns as, which no connection is given.
+    // Only bots can move for the teams of bot players, even in local games that share
+    // `ClientId::SERVER`.
+    pub fn client_id() -> ClientId {
+        ClientId::new(u64::MAX)
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::Duration;
+
+use bevy_utils::Instant;
+
+use chess::position::{Position, Turn};
+
+use super::evaluation::Evaluator;
+
+const INFINITY: i32 = 10_000_000;
+// the score of capturing a royal piece, reduced by the number of plies needed to do so
+const ROYAL_CAPTURE: i32 = 1_000_000;
+const MAX_PLY: i32 = 1_000;
+// how many nodes to search between checks of the time budget
+const TIME_CHECK_INTERVAL: usize = 256;
+
+#[derive(Clone, Copy, Debug)]
+pub struct SearchLimits {
+    pub max_depth: usize,
+    pub max_time: Duration,
+}
+
+// Finds the best turn for the side to move with an iterative deepening alpha-beta search.
+// The deepest completed iteration decides the turn, so the search can stop at any time.
+pub fn search(position: &mut Position, limits: SearchLimits) -> Option<Turn> {
+    let mut turns = position.legal_actions();
+    if turns.is_empty() {
+        // every turn loses a royal piece, but a turn still has to be played
+        turns = position.pseudo_legal_turns();
+    }
+    if turns.len() <= 1 {
+        return turns.pop();
+    }
+    order_turns(&mut turns);
+
+    let mut search = Search {
+        evaluator: Evaluator::default(),
+        deadline: Instant::now() + limits.max_time,
+        root_ply: position.ply,
+        next_time_check: TIME_CHECK_INTERVAL,
+        nodes: 0,
+        aborted: false,
+    };
+
+    for depth in 1..=limits.max_depth.max(1) {
+        let mut alpha = -INFINITY;
+        let mut best = None;
+        for (index, turn) in turns.iter().enumerate() {
+            if position.make(turn).is_err() {
+                continue;
+            }
+            let score = -search.negamax(position, depth - 1, -INFINITY, -alpha);
+            position.unmake();
+            if search.aborted {
+                break;
+            }
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some(index);
+            }
+        }
+        if search.aborted {
+            break;
+        }
+        // search the best turn first in the next iteration
+        if let Some(index) = best {
+            let turn = turns.remove(index);
+            turns.insert(0, turn);
+        }
+        // stop early once a forced royal capture is found
+        if alpha.abs() >= ROYAL_CAPTURE - MAX_PLY {
+            break;
+        }
+    }
+
+    turns.into_iter().next()
+}
+
+struct Search {
+    evaluator: Evaluator,
+    deadline: Instant,
+    root_ply: usize,
+    next_time_check: usize,
+    nodes: usize,
+    aborted: bool,
+}
+
+impl Search {
+    fn negamax(&mut self, position: &mut Position, depth: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.nodes >= self.next_time_check {
+            self.next_time_check += TIME_CHECK_INTERVAL;
+            self.aborted = Instant::now() >= self.deadline;
+        }
+        if self.aborted {
+            return 0;
+        }
+
+        // turns that lose a royal piece are searched like any other turn,
+        // since capturing a royal piece is how games are won
+        let mut turns = position.pseudo_legal_turns();
+        if turns.iter().any(|turn| captures_royal(position, turn)) {
+            let distance = (position.ply - self.root_ply) as i32;
+            return ROYAL_CAPTURE - distance;
+        }
+        if depth == 0 {
+            return self.evaluator.evaluate(position);
+        }
+        if turns.is_empty() {
+            return 0;
+        }
+        order_turns(&mut turns);
+
+        for turn in turns.iter() {
+            if position.make(turn).is_err() {
+                continue;
+            }
+            let score = -self.negamax(position, depth - 1, -beta, -alpha);
+            position.unmake();
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+fn captures_royal(position: &Position, turn: &Turn) -> bool {
+    turn.action.captures.iter().any(|square| {
+        position
+            .piece_at(*square)
+            .is_some_and(|piece| piece.is_royal() && piece.team != position.side_to_move)
+    })
+}
+
+// searching captures and mutations first makes alpha-beta cutoffs more likely
+fn order_turns(turns: &mut [Turn]) {
+    turns.sort_by_key(|turn| (turn.action.captures.is_empty(), turn.mutation.is_none()));
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::{
+        board::{Board, File, Rank, Square},
+        pieces::PieceDefinition,
+        team::Team,
+    };
+    use layouts::ClassicalLayout;
+
+    use super::*;
+
+    fn piece(name: &str) -> PieceDefinition {
+        ClassicalLayout::named_piece(name).unwrap()
+    }
+
+    #[test]
+    fn test_finds_back_rank_mate() {
+        let mut position = Position::new(Board::chess_board());
+        position.add_piece(piece("king"), Square::new(File::G, Rank::SIX), Team::White);
+        position.add_piece(piece("rook"), Square::new(File::A, Rank::ONE), Team::White);
+        position.add_piece(
+            piece("king"),
+            Square::new(File::G, Rank::EIGHT),
+            Team::Black,
+        );
+
+        let turn = search(
+            &mut position,
+            SearchLimits {
+                max_depth: 3,
+                max_time: Duration::from_secs(60),
+            },
+        )
+        .unwrap();
+
+        // any rook move to the back rank is mate
+        assert_eq!(turn.action.movement.to.rank, Rank::EIGHT);
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+
+use bevy_ecs::prelude::{
+    Commands, Component, DetectChanges, Entity, EventWriter, Query, Ref, With,
+};
+use bevy_tasks::AsyncComputeTaskPool;
+
+use bevy_replicon::prelude::FromClient;
+
+use chess::{
+    actions::LastAction,
+    board::{Board, OnBoard},
+    position::Turn,
+    team::Team,
+};
+
+use crate::{
+    components::{CurrentTurn, InGame, IsActiveGame, Player, Ply},
+    snapshot_position, Clock, PieceSnapshotQuery, RequestTurnEvent,
+};
+
+use super::{search, Bot};
+
+// The result of a search: None until the search completes
+type SearchResult = Arc<Mutex<Option<Option<Turn>>>>;
+
+// A search running in the background for the turn at `ply` of a game
+#[derive(Component)]
+pub(super) struct BotThinking {
+    game: Entity,
+    ply: Ply,
+    result: SearchResult,
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn start_thinking(
+    mut commands: Commands,
+    bot_query: Query<
+        (
+            Entity,
+            &Bot,
+            &Team,
+            &InGame,
+            &OnBoard,
+            Option<&Clock>,
+            Option<&BotThinking>,
+        ),
+        With<Player>,
+    >,
+    game_query: Query<(&CurrentTurn, Ref<Ply>, Option<&LastAction>), IsActiveGame>,
+    board_query: Query<&Board>,
+    piece_query: Query<PieceSnapshotQuery>,
+) {
+    for (bot_entity, bot, team, in_game, on_board, clock, thinking) in bot_query.iter() {
+        let Ok((current_turn, ply, last_action)) = game_query.get(in_game.0) else {
+            continue;
+        };
+        if current_turn.0 != *team {
+            continue;
+        }
+        // already thinking about this turn,
+        // unless the game was rewound to it after a takeback
+        if thinking.is_some_and(|thinking| thinking.game == in_game.0 && thinking.ply == *ply)
+            && !ply.is_changed()
+        {
+            continue;
+        }
+        let Ok(board) = board_query.get(on_board.0) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Failed to find board {}", on_board.0);
+            continue;
+        };
+
+        let mut position = snapshot_position(
+            board,
+            current_turn,
+            &ply,
+            last_action,
+            piece_query
+                .iter()
+                .filter(|piece| piece.in_game.0 == in_game.0),
+        );
+        let limits = bot.difficulty.limits_with_clock(clock);
+
+        let result: SearchResult = Arc::default();
+        let task_result = result.clone();
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                let turn = search(&mut position, limits);
+                if let Ok(mut result) = task_result.lock() {
+                    *result = Some(turn);
+                }
+            })
+            .detach();
+
+        #[cfg(feature = "log")]
+        bevy_log::info!("Bot {bot_entity} is thinking about {:?}'s turn", team);
+        commands.entity(bot_entity).insert(BotThinking {
+            game: in_game.0,
+            ply: *ply,
+            result,
+        });
+    }
+}
+
+// Requests the turns found by finished searches, as `Bot::client_id`
+pub(super) fn play_turns(
+    mut commands: Commands,
+    bot_query: Query<(Entity, &Team, &BotThinking)>,
+    game_query: Query<(&CurrentTurn, &Ply), IsActiveGame>,
+    mut turn_writer: EventWriter<FromClient<RequestTurnEvent>>,
+) {
+    for (bot_entity, team, thinking) in bot_query.iter() {
+        let Some(turn) = thinking
+            .result
+            .lock()
+            .ok()
+            .and_then(|mut result| result.take())
+        else {
+            continue;
+        };
+        // the search is over, so the bot can think again whenever it has to move
+        commands.entity(bot_entity).remove::<BotThinking>();
+
+        let Some(Turn {
+            piece,
+            action,
+            mutation,
+        }) = turn
+        else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Bot {bot_entity} has no turns to play");
+            continue;
+        };
+        // the game may have moved on while the bot was thinking
+        let Ok((current_turn, ply)) = game_query.get(thinking.game) else {
+            continue;
+        };
+        if current_turn.0 != *team || *ply != thinking.ply {
+            continue;
+        }
+
+        turn_writer.send(FromClient {
+            client_id: Bot::client_id(),
+            event: match mutation {
+                Some(mutation) => {
+                    RequestTurnEvent::new_with_mutation(piece, thinking.game, action, mutation)
+                }
+                None => RequestTurnEvent::new(piece, thinking.game, action),
+            },
+        });
+    }
+}
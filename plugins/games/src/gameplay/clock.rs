@@ -5,34 +5,141 @@ use std::time::Duration;
 use bevy_app::{App, Plugin, Update};
 #[cfg(feature = "reflect")]
 use bevy_ecs::prelude::ReflectComponent;
-use bevy_ecs::prelude::{Commands, Component, Entity, IntoSystemConfigs, Query, Res, SystemSet};
+use bevy_ecs::prelude::{
+    not, Commands, Component, Entity, Event, EventReader, EventWriter, IntoSystemConfigs, Local,
+    Query, Res, ResMut, Resource, SystemSet,
+};
 #[cfg(feature = "reflect")]
 use bevy_reflect::prelude::Reflect;
-use bevy_time::{Stopwatch, Time};
+use bevy_time::{Real, Stopwatch, Time};
 
-use bevy_replicon::prelude::AppRuleExt;
+use bevy_replicon::prelude::{
+    client_connected, has_authority, server_running, AppRuleExt, ChannelKind, ClientEventAppExt,
+    FromClient, SendMode, ServerEventAppExt, ToClients,
+};
+use replication::Client;
 
-use crate::components::{GameOver, InGame, IsActiveGame};
+use crate::components::{GameOver, GameOverReason, InGame, IsActiveGame};
 
+// The most time given back to a player for the latency of each turn
+pub const MAX_LAG_COMPENSATION: Duration = Duration::from_millis(500);
+// How often the server measures the latency of its clients
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+
+// How a clock gains or withholds time around each turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum Timing {
+    // Fischer: the increment is added after each turn. No increment is sudden death.
+    Increment(Duration),
+    // The time spent on a turn is added back after it, up to the delay
+    Bronstein(Duration),
+    // Simple (US) delay: the clock only counts down once the delay of each turn has passed
+    Delay(Duration),
+    // The time spent on a turn is added to the opponent's clock
+    Hourglass,
+    // Every turn must be played within the stage's time, which resets after each turn
+    PerMove,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Timing::Increment(Duration::ZERO)
+    }
+}
+
+// A period of a time control, such as 40 moves in 90 minutes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct TimeStage {
+    // the time added to the clock when the stage starts
+    pub time: Duration,
+    // the number of turns to play before the next stage, or None for the rest of the game
+    pub moves: Option<u32>,
+    pub timing: Timing,
+}
+
+impl TimeStage {
+    pub fn new(time: Duration, timing: Timing) -> Self {
+        TimeStage {
+            time,
+            moves: None,
+            timing,
+        }
+    }
+
+    // Ends the stage after `moves` turns
+    #[must_use]
+    pub fn with_moves(mut self, moves: u32) -> Self {
+        self.moves = Some(moves);
+        self
+    }
+}
+
+impl std::fmt::Display for TimeStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(moves) = self.moves {
+            write!(f, "{moves} moves in ")?;
+        }
+        write!(f, "{}", format_duration(self.time))?;
+        match self.timing {
+            Timing::Increment(increment) if increment.is_zero() => Ok(()),
+            Timing::Increment(increment) => write!(f, " + {}s", increment.as_secs()),
+            Timing::Bronstein(delay) => write!(f, " Bronstein {}s", delay.as_secs()),
+            Timing::Delay(delay) => write!(f, " delay {}s", delay.as_secs()),
+            Timing::Hourglass => write!(f, " hourglass"),
+            Timing::PerMove => write!(f, " per move"),
+        }
+    }
+}
+
+// Writes a duration as minutes and seconds, such as 5:00
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+// A player's clock. Its stopwatch measures the turn being played.
 #[derive(Clone, Debug, Default)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component))]
 pub struct Clock {
+    // the time left when the current turn started
     duration: Duration,
-    increment: Duration,
     stopwatch: Stopwatch,
+    stages: Vec<TimeStage>,
+    stage: usize,
+    // the turns played in the current stage
+    stage_moves: u32,
+    // whether a client has caught this snapshot up with the latency of the server
+    #[serde(skip)]
+    caught_up: bool,
 }
 
 impl Clock {
     pub fn new(duration: Duration, increment: Duration) -> Self {
+        Self::with_timing(duration, Timing::Increment(increment))
+    }
+
+    pub fn with_timing(duration: Duration, timing: Timing) -> Self {
+        Self::from_stages(vec![TimeStage::new(duration, timing)])
+    }
+
+    // A multi-stage time control. The clock starts with the time of the first stage.
+    pub fn from_stages(stages: Vec<TimeStage>) -> Self {
         let mut stopwatch = Stopwatch::new();
         stopwatch.pause();
         Clock {
-            duration,
-            increment,
+            duration: stages.first().map(|stage| stage.time).unwrap_or_default(),
             stopwatch,
+            stages,
+            stage: 0,
+            stage_moves: 0,
+            caught_up: false,
         }
     }
 
@@ -41,25 +148,165 @@ impl Clock {
         self.stopwatch.tick(dt);
     }
 
-    // if there is increment, apply it on pause
-    pub fn pause(&mut self) {
-        self.duration += self.increment;
+    // Gives back the time a turn spent in transit to the server
+    pub fn compensate(&mut self, lag: Duration) {
+        let elapsed = self.stopwatch.elapsed();
+        self.stopwatch.set_elapsed(elapsed.saturating_sub(lag));
+    }
+
+    // Ends the turn, applying the timing of the current stage.
+    // Returns the time handed to the opponent, as with an hourglass.
+    pub fn pause(&mut self) -> Duration {
+        let spent = self.stopwatch.elapsed();
+        let stage = self.current_stage();
+        self.duration = self.remaining_time();
         self.stopwatch.pause();
+        self.stopwatch.reset();
+
+        let handed_over = match stage.timing {
+            Timing::Increment(increment) => {
+                self.duration += increment;
+                Duration::ZERO
+            }
+            Timing::Bronstein(delay) => {
+                self.duration += spent.min(delay);
+                Duration::ZERO
+            }
+            Timing::Delay(_) => Duration::ZERO,
+            Timing::Hourglass => spent,
+            Timing::PerMove => {
+                self.duration = stage.time;
+                Duration::ZERO
+            }
+        };
+
+        // the time of the next stage is added once the moves of this one are played
+        self.stage_moves += 1;
+        if stage
+            .moves
+            .is_some_and(|moves| self.stage_moves >= moves && self.stage + 1 < self.stages.len())
+        {
+            self.stage += 1;
+            self.stage_moves = 0;
+            self.duration += self.current_stage().time;
+        }
+        handed_over
     }
 
     pub fn unpause(&mut self) {
         self.stopwatch.unpause();
     }
 
+    // Adds time to the clock, such as the time an hourglass opponent spent
+    pub fn add_time(&mut self, time: Duration) {
+        self.duration += time;
+    }
+
     pub fn remaining_time(&self) -> Duration {
-        self.duration.saturating_sub(self.stopwatch.elapsed())
+        self.duration.saturating_sub(self.charged_time())
+    }
+
+    // The time left of the current turn's delay, before the clock counts down
+    pub fn remaining_delay(&self) -> Option<Duration> {
+        match self.current_stage().timing {
+            Timing::Delay(delay) => Some(delay.saturating_sub(self.stopwatch.elapsed())),
+            _ => None,
+        }
+    }
+
+    // The time of the current turn that counts against the clock
+    fn charged_time(&self) -> Duration {
+        let elapsed = self.stopwatch.elapsed();
+        match self.current_stage().timing {
+            Timing::Delay(delay) => elapsed.saturating_sub(delay),
+            _ => elapsed,
+        }
+    }
+
+    // The time added or given back after each turn of the current stage
+    pub fn increment(&self) -> Duration {
+        match self.current_stage().timing {
+            Timing::Increment(time) | Timing::Bronstein(time) | Timing::Delay(time) => time,
+            Timing::Hourglass | Timing::PerMove => Duration::ZERO,
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.current_stage().timing
+    }
+
+    pub fn stages(&self) -> &[TimeStage] {
+        &self.stages
+    }
+
+    pub fn current_stage(&self) -> TimeStage {
+        self.stages.get(self.stage).copied().unwrap_or_default()
+    }
+
+    // The turns left to play before the next stage of the time control
+    pub fn moves_to_next_stage(&self) -> Option<u32> {
+        if self.stage + 1 >= self.stages.len() {
+            return None;
+        }
+        self.current_stage()
+            .moves
+            .map(|moves| moves.saturating_sub(self.stage_moves))
+    }
+
+    // The time control, such as "40 moves in 90:00, then 30:00 + 30s"
+    pub fn time_control(&self) -> String {
+        self.stages
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", then ")
     }
 
     pub fn is_flagged(&self) -> bool {
         self.remaining_time().is_zero()
     }
+
+    // How long the clock has been out of time
+    pub fn overtime(&self) -> Duration {
+        self.charged_time().saturating_sub(self.duration)
+    }
+}
+
+// The round trip between the server and a player's client, as measured by the server
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Component)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct Latency(pub Duration);
+
+impl Latency {
+    // The time given back for each turn, which is also the grace before flagging
+    pub fn compensation(&self) -> Duration {
+        self.0.min(MAX_LAG_COMPENSATION)
+    }
 }
 
+// Sent by the server to measure the round trip to a client, with the last measurement
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct ClockPing {
+    sent_at: Duration,
+    latency: Duration,
+}
+
+// A client's answer to a `ClockPing`
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct ClockPong {
+    sent_at: Duration,
+}
+
+// The round trip to the server, as last measured by the server
+#[derive(Clone, Copy, Debug, Default)]
+#[derive(Resource)]
+pub struct ServerLatency(pub Duration);
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[derive(SystemSet)]
 pub struct ClockSystems;
@@ -68,32 +315,206 @@ pub struct ClockPlugin;
 
 impl Plugin for ClockPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, Self::tick.in_set(ClockSystems));
+        app.init_resource::<ServerLatency>()
+            .add_server_event::<ClockPing>(ChannelKind::Unreliable)
+            .add_client_event::<ClockPong>(ChannelKind::Unreliable)
+            .add_systems(
+                Update,
+                (
+                    // only the server runs the clocks and flags players
+                    Self::tick.run_if(has_authority),
+                    Self::interpolate.run_if(not(has_authority)),
+                    (Self::send_pings, Self::receive_pongs).run_if(server_running),
+                    Self::answer_pings.run_if(client_connected),
+                )
+                    .in_set(ClockSystems),
+            );
         app.replicate::<Clock>();
         #[cfg(feature = "reflect")]
-        app.register_type::<Clock>();
+        app.register_type::<Clock>().register_type::<Latency>();
     }
 }
 
 impl ClockPlugin {
-    // ticks clocks for all active games
+    // ticks clocks for all active games.
+    // A player only flags once out of time for longer than their latency is compensated,
+    // since their move may still be in transit.
     fn tick(
         mut commands: Commands,
-        mut clocks: Query<(&mut Clock, &Team, &InGame)>,
+        mut clocks: Query<(&mut Clock, &Team, &InGame, Option<&Latency>)>,
         games: Query<Entity, IsActiveGame>,
         time: Res<Time>,
     ) {
-        for (mut clock, team, in_game) in clocks.iter_mut() {
+        for (mut clock, team, in_game, latency) in clocks.iter_mut() {
             let Ok(game) = games.get(in_game.0) else {
                 continue;
             };
             clock.tick(time.delta());
-            if clock.is_flagged() {
-                commands.entity(game).insert(GameOver::new(match team {
-                    Team::White => Team::Black,
-                    Team::Black => Team::White,
-                }));
+            let grace = latency.map(Latency::compensation).unwrap_or_default();
+            if clock.is_flagged() && clock.overtime() >= grace {
+                commands.entity(game).insert(GameOver::win(
+                    match team {
+                        Team::White => Team::Black,
+                        Team::Black => Team::White,
+                    },
+                    GameOverReason::Timeout,
+                ));
             }
         }
     }
+
+    // Clients display the clocks replicated by the server, running them between snapshots.
+    // Each snapshot is caught up by the time it took to arrive.
+    fn interpolate(
+        mut clocks: Query<(&mut Clock, &InGame)>,
+        games: Query<Entity, IsActiveGame>,
+        latency: Res<ServerLatency>,
+        time: Res<Time>,
+    ) {
+        for (mut clock, in_game) in clocks.iter_mut() {
+            if !games.contains(in_game.0) {
+                continue;
+            }
+            if !clock.caught_up {
+                clock.caught_up = true;
+                clock.tick(latency.0.min(MAX_LAG_COMPENSATION) / 2);
+            }
+            clock.tick(time.delta());
+        }
+    }
+
+    fn send_pings(
+        players: Query<(&Client, Option<&Latency>)>,
+        mut ping_writer: EventWriter<ToClients<ClockPing>>,
+        mut last_ping: Local<Option<Duration>>,
+        time: Res<Time<Real>>,
+    ) {
+        let now = time.elapsed();
+        if last_ping.is_some_and(|last_ping| now - last_ping < PING_INTERVAL) {
+            return;
+        }
+        *last_ping = Some(now);
+        for (client, latency) in players.iter() {
+            ping_writer.send(ToClients {
+                mode: SendMode::Direct(client.id),
+                event: ClockPing {
+                    sent_at: now,
+                    latency: latency.map(|latency| latency.0).unwrap_or_default(),
+                },
+            });
+        }
+    }
+
+    fn receive_pongs(
+        mut commands: Commands,
+        mut pong_reader: EventReader<FromClient<ClockPong>>,
+        players: Query<(Entity, &Client, Option<&Latency>)>,
+        time: Res<Time<Real>>,
+    ) {
+        for FromClient { client_id, event } in pong_reader.read() {
+            let Some((player, _, latency)) = players
+                .iter()
+                .find(|(_, client, _)| client.id == *client_id)
+            else {
+                continue;
+            };
+            let round_trip = time.elapsed().saturating_sub(event.sent_at);
+            // smooth out the jitter of single measurements
+            let latency = match latency {
+                Some(latency) => (latency.0 * 3 + round_trip) / 4,
+                None => round_trip,
+            };
+            commands.entity(player).insert(Latency(latency));
+        }
+    }
+
+    fn answer_pings(
+        mut ping_reader: EventReader<ClockPing>,
+        mut pong_writer: EventWriter<ClockPong>,
+        mut latency: ResMut<ServerLatency>,
+    ) {
+        for ping in ping_reader.read() {
+            latency.0 = ping.latency;
+            pong_writer.send(ClockPong {
+                sent_at: ping.sent_at,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_turn(clock: &mut Clock, seconds: u64) -> Duration {
+        clock.unpause();
+        clock.tick(Duration::from_secs(seconds));
+        clock.pause()
+    }
+
+    #[test]
+    fn test_timing() {
+        let secs = Duration::from_secs;
+
+        let mut fischer = Clock::new(secs(60), secs(2));
+        play_turn(&mut fischer, 10);
+        assert_eq!(fischer.remaining_time(), secs(52));
+
+        let mut bronstein = Clock::with_timing(secs(60), Timing::Bronstein(secs(5)));
+        play_turn(&mut bronstein, 3);
+        assert_eq!(bronstein.remaining_time(), secs(60));
+        play_turn(&mut bronstein, 10);
+        assert_eq!(bronstein.remaining_time(), secs(55));
+
+        let mut delay = Clock::with_timing(secs(60), Timing::Delay(secs(5)));
+        delay.unpause();
+        delay.tick(secs(3));
+        assert_eq!(delay.remaining_time(), secs(60));
+        assert_eq!(delay.remaining_delay(), Some(secs(2)));
+        delay.tick(secs(7));
+        delay.pause();
+        assert_eq!(delay.remaining_time(), secs(55));
+
+        let mut hourglass = Clock::with_timing(secs(60), Timing::Hourglass);
+        assert_eq!(play_turn(&mut hourglass, 10), secs(10));
+        assert_eq!(hourglass.remaining_time(), secs(50));
+
+        let mut per_move = Clock::with_timing(secs(30), Timing::PerMove);
+        play_turn(&mut per_move, 20);
+        assert_eq!(per_move.remaining_time(), secs(30));
+    }
+
+    #[test]
+    fn test_stages() {
+        let secs = Duration::from_secs;
+        let mut clock = Clock::from_stages(vec![
+            TimeStage::new(secs(5400), Timing::default()).with_moves(2),
+            TimeStage::new(secs(1800), Timing::Increment(secs(30))),
+        ]);
+        assert_eq!(clock.time_control(), "2 moves in 90:00, then 30:00 + 30s");
+
+        play_turn(&mut clock, 100);
+        assert_eq!(clock.moves_to_next_stage(), Some(1));
+        play_turn(&mut clock, 100);
+        assert_eq!(clock.moves_to_next_stage(), None);
+        assert_eq!(clock.remaining_time(), secs(5400 - 200 + 1800));
+        play_turn(&mut clock, 100);
+        assert_eq!(clock.remaining_time(), secs(5400 - 300 + 1800 + 30));
+    }
+
+    #[test]
+    fn test_lag_compensation() {
+        let secs = Duration::from_secs;
+        let mut clock = Clock::new(secs(10), Duration::ZERO);
+        clock.unpause();
+        clock.tick(secs(12));
+        assert!(clock.is_flagged());
+        assert_eq!(clock.overtime(), secs(2));
+
+        let latency = Latency(secs(3));
+        assert_eq!(latency.compensation(), MAX_LAG_COMPENSATION);
+        clock.compensate(secs(3));
+        clock.pause();
+        assert_eq!(clock.remaining_time(), secs(1));
+    }
 }
@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use serde::{Deserialize, Serialize};
 
 use bevy_core::Name;
@@ -7,6 +9,7 @@ use bevy_ecs::{
     event::Event,
     observer::Trigger,
     prelude::{Commands, Component, Entity},
+    system::EntityCommands,
 };
 #[cfg(feature = "reflect")]
 use bevy_reflect::Reflect;
@@ -14,15 +17,22 @@ use bevy_reflect::Reflect;
 use bevy_replicon::prelude::Replicated;
 
 use chess::{
-    behavior::{BoardPieceCache, BoardThreatsCache},
+    actions::LastAction,
+    behavior::{
+        BoardPieceCache, BoardThreatsCache, CastlingBehavior, CastlingTarget, PieceBehaviorsBundle,
+    },
     board::{Board, OnBoard, Rank, Square},
-    pieces::{PieceBundle, Position, Royal},
+    fen::Legend,
+    notation::Notation,
+    pieces::{Mutation, PieceBundle, PieceDefinition, PieceIdentity, Position, Royal},
+    position,
     team::Team,
 };
+use itertools::Itertools;
 use layouts::PieceSpecification;
 
 use crate::{
-    components::{ActionHistory, History, Ply},
+    components::{ActionHistory, GameRequestVariant, History, Ply, VariationTree},
     Clock,
 };
 
@@ -41,18 +51,120 @@ pub struct Game;
 pub enum GameBoard {
     #[default]
     Chess,
+    // A rectangular board of any size up to 9x9, given by its last square
+    Custom(Square),
     // Shogi,    // TODO
     // Checkers, // TODO
 }
 
+impl GameBoard {
+    pub fn board(&self) -> Board {
+        match self {
+            GameBoard::Chess => Board::chess_board(),
+            GameBoard::Custom(size) => Board { size: *size },
+        }
+    }
+
+    pub fn from_board(board: &Board) -> Self {
+        if board.size == Board::chess_board().size {
+            GameBoard::Chess
+        } else {
+            GameBoard::Custom(board.size)
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 #[derive(Deserialize, Serialize)]
 #[derive(Component)]
-pub struct PieceSet(pub Vec<PieceSpecification>);
+pub struct PieceSet {
+    // the pieces of both teams, unless Black fields its own army
+    pub pieces: Vec<PieceSpecification>,
+    #[serde(default)]
+    pub black_pieces: Option<Vec<PieceSpecification>>,
+}
+
+impl PieceSet {
+    pub fn new(pieces: Vec<PieceSpecification>) -> Self {
+        Self {
+            pieces,
+            black_pieces: None,
+        }
+    }
+
+    // Each team fields its own army, as in Chess with Different Armies or Horde chess
+    pub fn asymmetric(
+        white_pieces: Vec<PieceSpecification>,
+        black_pieces: Vec<PieceSpecification>,
+    ) -> Self {
+        Self {
+            pieces: white_pieces,
+            black_pieces: Some(black_pieces),
+        }
+    }
+
+    // The pieces of a team, with start squares relative to the team's orientation
+    pub fn team(&self, team: Team) -> &[PieceSpecification] {
+        match (team, &self.black_pieces) {
+            (Team::Black, Some(black_pieces)) => black_pieces,
+            _ => &self.pieces,
+        }
+    }
+
+    // The position before the first turn, with the pieces of both teams on their start squares
+    pub fn starting_position(&self, board: &Board) -> position::Position {
+        let mut position = position::Position::new(*board);
+        for team in [Team::White, Team::Black].into_iter() {
+            for PieceSpecification {
+                piece,
+                start_square,
+            } in self.team(team).iter()
+            {
+                let start_square = start_square.reorient(team.orientation(), board);
+                position.add_piece(piece.clone(), start_square, team);
+            }
+        }
+        position
+    }
+
+    // The notation naming each distinct piece of both armies with its own letter
+    pub fn notation(&self) -> Notation {
+        let black_pieces = self.black_pieces.iter().flatten();
+        Notation::default().with_pieces(
+            self.pieces
+                .iter()
+                .chain(black_pieces)
+                .map(|specification| &specification.piece),
+        )
+    }
+
+    // Names the pieces of both armies in position strings with their notation letters
+    pub fn legend(&self) -> Legend {
+        let notation = self.notation();
+        let black_pieces = self.black_pieces.iter().flatten();
+        let mut pending = self
+            .pieces
+            .iter()
+            .chain(black_pieces)
+            .map(|specification| &specification.piece)
+            .collect::<VecDeque<_>>();
+        let mut legend = Legend::default();
+        while let Some(piece) = pending.pop_front() {
+            if let Some(mutation) = &piece.mutation {
+                pending.extend(mutation.to_piece.iter());
+            }
+            let symbol = notation.piece_symbol(piece);
+            if legend.piece(symbol).is_none() {
+                legend = legend.with_piece(symbol, piece.clone());
+            }
+        }
+        legend
+    }
+}
 
 impl From<Vec<PieceSpecification>> for PieceSet {
     fn from(pieces: Vec<PieceSpecification>) -> Self {
-        Self(pieces)
+        Self::new(pieces)
     }
 }
 
@@ -77,6 +189,13 @@ pub struct Crazyhouse;
 #[derive(Component)]
 pub struct AntiGame;
 
+// A game used to study positions rather than to compete.
+// A single player plays for both teams without a clock, and can rewind turns or edit the board.
+#[derive(Clone, Copy, Debug, Default)]
+#[derive(Deserialize, Serialize)]
+#[derive(Component)]
+pub struct Analysis;
+
 // The set of win conditions for the board
 #[derive(Clone, Debug, Default)]
 #[derive(Deserialize, Serialize)]
@@ -96,6 +215,18 @@ pub enum WinCondition {
     RaceToRegion(Vec<Square>),
 }
 
+impl WinCondition {
+    // Whether every team needs a royal piece, even when its army has none:
+    // armies without royal pieces lose by capture once all of their pieces are captured,
+    // but only royal pieces can race
+    pub fn requires_royal(&self) -> bool {
+        matches!(
+            self,
+            WinCondition::RaceToRank(_) | WinCondition::RaceToRegion(_)
+        )
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 #[derive(Deserialize, Serialize)]
 #[derive(Component)]
@@ -112,6 +243,24 @@ pub struct ClockConfiguration {
 #[cfg_attr(feature = "reflect", reflect(Component))]
 pub struct CurrentTurn(pub Team);
 
+// The seed that generated the game's random pieces, and the variant that generated them.
+// Requesting the same variant with the same seed generates the same armies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+#[derive(Component)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct GameSeed {
+    pub seed: u64,
+    pub variant: GameRequestVariant,
+}
+
+// The position a game was started from, for games not started from the piece set's start squares
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+#[derive(Component)]
+pub struct GameSetup(pub position::Position);
+
 // TODO: revisit this API
 // perhaps use the blueprints lib
 #[derive(Clone, Default)]
@@ -128,6 +277,10 @@ pub struct SpawnGame {
     atomic: Option<Atomic>,
     crazyhouse: Option<Crazyhouse>,
     anti: Option<AntiGame>,
+    analysis: Option<Analysis>,
+    moves: Vec<String>,
+    setup: Option<position::Position>,
+    seed: Option<GameSeed>,
 }
 
 impl SpawnGame {
@@ -144,6 +297,10 @@ impl SpawnGame {
             atomic: None,
             crazyhouse: None,
             anti: None,
+            analysis: None,
+            moves: vec![],
+            setup: None,
+            seed: None,
         }
     }
 
@@ -153,12 +310,51 @@ impl SpawnGame {
         self
     }
 
+    // Black fields its own army instead of the same pieces as White
+    #[must_use]
+    pub fn with_black_pieces(mut self, pieces: Vec<PieceSpecification>) -> Self {
+        self.piece_set.black_pieces = Some(pieces);
+        self
+    }
+
     #[must_use]
     pub fn with_board(mut self, board: GameBoard) -> Self {
         self.board = board;
         self
     }
 
+    #[must_use]
+    pub fn with_win_condition(mut self, win_condition: WinCondition) -> Self {
+        self.win_condition = win_condition;
+        self
+    }
+
+    // Moves to play once the game is spawned, written in `chess::notation::Notation`
+    #[must_use]
+    pub fn with_moves(mut self, moves: Vec<String>) -> Self {
+        self.moves = moves;
+        self
+    }
+
+    // Starts the game from a position instead of the piece set's start squares,
+    // such as one read with `chess::position::Position::from_fen`.
+    // The side to move, castling rights, en passant captures and the move counters
+    // are taken from the position, so the game's ply continues from the position's ply.
+    #[must_use]
+    pub fn with_setup(mut self, setup: position::Position) -> Self {
+        self.board = GameBoard::from_board(&setup.board);
+        self.turn = CurrentTurn(setup.side_to_move);
+        self.setup = Some(setup);
+        self
+    }
+
+    // Records the seed and variant that generated the piece set
+    #[must_use]
+    pub fn with_seed(mut self, seed: Option<GameSeed>) -> Self {
+        self.seed = seed;
+        self
+    }
+
     #[must_use]
     pub fn with_clock(mut self, clock: Option<Clock>) -> Self {
         self.clock = clock.map(|clock| ClockConfiguration { clock });
@@ -183,6 +379,12 @@ impl SpawnGame {
         self
     }
 
+    #[must_use]
+    pub fn analysis(mut self) -> Self {
+        self.analysis = Some(Analysis);
+        self
+    }
+
     pub fn name(&self) -> Name {
         Name::new(format!("{:?} Game", self.board))
     }
@@ -195,6 +397,7 @@ impl SpawnGame {
 impl SpawnGame {
     pub(crate) fn observer(trigger: Trigger<Self>, mut commands: Commands) {
         let spawner = trigger.event();
+        let ply = Ply::new(spawner.setup.as_ref().map_or(0, |setup| setup.ply));
 
         // first spawn the game entity
         // this has all the information about the game instance's configuration
@@ -206,8 +409,9 @@ impl SpawnGame {
             spawner.piece_set.clone(),
             spawner.win_condition.clone(),
             Replicated,
-            Ply::default(),
+            ply,
             ActionHistory::default(),
+            VariationTree::new(ply),
         ));
         if let Some(clock) = &spawner.clock {
             builder.insert(clock.clone());
@@ -221,22 +425,42 @@ impl SpawnGame {
         if spawner.anti.is_some() {
             builder.insert(AntiGame);
         }
+        if spawner.analysis.is_some() {
+            builder.insert(Analysis);
+        }
+        if let Some(seed) = &spawner.seed {
+            builder.insert(seed.clone());
+        }
+        if let Some(setup) = &spawner.setup {
+            builder.insert(GameSetup(setup.clone()));
+        }
+        if !spawner.moves.is_empty() {
+            builder.insert(PendingMoves(spawner.moves.iter().cloned().collect()));
+        }
+        let last_action = spawner
+            .setup
+            .as_ref()
+            .and_then(|setup| setup.last_action.clone())
+            .map(LastAction);
+        if let Some(last_action) = &last_action {
+            builder.insert(last_action.clone());
+        }
         let game = builder.id();
 
         // next spawn a board entity that will track board state
-        let board_data = match spawner.board {
-            GameBoard::Chess => Board::chess_board(),
-        };
-        let board = commands
-            .spawn((
-                board_data,
-                InGame(game),
-                Name::new(format!("Board (Game {:?})", game)),
-                BoardPieceCache::default(),
-                BoardThreatsCache::default(),
-                Replicated,
-            ))
-            .id();
+        let board_data = spawner.board.board();
+        let mut board_builder = commands.spawn((
+            board_data,
+            InGame(game),
+            Name::new(format!("Board (Game {:?})", game)),
+            BoardPieceCache::default(),
+            BoardThreatsCache::default(),
+            Replicated,
+        ));
+        if let Some(last_action) = last_action {
+            board_builder.insert(last_action);
+        }
+        let board = board_builder.id();
 
         // next find or spawn our players and associate them with the game instance and board
         let (player1, player2) = if let Some((player1, player2)) = spawner.players {
@@ -248,7 +472,11 @@ impl SpawnGame {
             )
         };
 
-        for (player, team) in [(player1, Team::White), (player2, Team::Black)] {
+        // a single player can play for both teams, such as in analysis games
+        for (player, team) in [(player1, Team::White), (player2, Team::Black)]
+            .into_iter()
+            .dedup_by(|(player1, _), (player2, _)| player1 == player2)
+        {
             commands
                 .entity(player)
                 .insert((
@@ -265,47 +493,35 @@ impl SpawnGame {
         }
 
         // finally, spawn all game pieces
-        for team in [Team::White, Team::Black].into_iter() {
-            for PieceSpecification {
-                piece,
-                start_square,
-            } in spawner.piece_set.0.iter()
-            {
-                let start_square = start_square.reorient(team.orientation(), &board_data);
-                let name = Name::new(format!("{:?} {}-{:?}", team, start_square, piece.identity));
-                #[cfg(feature = "log")]
-                bevy_log::info!("...spawning piece: {}", name);
-
-                let mut piece_builder = commands.spawn((
-                    name,
-                    piece.identity,
-                    PieceBundle::new(start_square.into(), team),
-                    InGame(game),
-                    OnBoard(board),
-                    History::<Position>::default(),
-                    Replicated,
-                ));
-
-                if piece.royal.is_some() {
-                    piece_builder.insert(Royal);
+        if let Some(setup) = &spawner.setup {
+            for piece in setup.pieces() {
+                let Some(square) = piece.square else {
+                    continue;
+                };
+                let entity = spawn_piece(
+                    &mut commands,
+                    game,
+                    board,
+                    &piece.definition,
+                    square,
+                    piece.team,
+                );
+                if !piece.can_castle {
+                    commands.entity(entity).remove::<CastlingBehavior>();
                 }
-                if let Some(mutation) = &piece.mutation {
-                    piece_builder.insert(mutation.clone());
+                if !piece.is_castling_target {
+                    commands.entity(entity).remove::<CastlingTarget>();
                 }
-                if let Some(behavior) = &piece.behaviors.pattern {
-                    piece_builder.insert(behavior.clone());
-                }
-                if let Some(behavior) = &piece.behaviors.relay {
-                    piece_builder.insert(behavior.clone());
-                }
-                if let Some(behavior) = piece.behaviors.en_passant {
-                    piece_builder.insert(behavior);
-                }
-                if let Some(behavior) = piece.behaviors.castling {
-                    piece_builder.insert(behavior);
-                }
-                if let Some(behavior) = piece.behaviors.castling_target {
-                    piece_builder.insert(behavior);
+            }
+        } else {
+            for team in [Team::White, Team::Black].into_iter() {
+                for PieceSpecification {
+                    piece,
+                    start_square,
+                } in spawner.piece_set.team(team).iter()
+                {
+                    let start_square = start_square.reorient(team.orientation(), &board_data);
+                    spawn_piece(&mut commands, game, board, piece, start_square, team);
                 }
             }
         }
@@ -314,3 +530,67 @@ impl SpawnGame {
         bevy_log::info!("Spawned game {game} with players {player1}, {player2} on board {board}");
     }
 }
+
+// The moves left to play in a newly spawned game, one move per frame
+#[derive(Clone, Debug, Default)]
+#[derive(Component)]
+pub(crate) struct PendingMoves(pub(crate) VecDeque<String>);
+
+// Spawns a piece into a game
+pub(crate) fn spawn_piece(
+    commands: &mut Commands,
+    game: Entity,
+    board: Entity,
+    piece: &PieceDefinition,
+    square: Square,
+    team: Team,
+) -> Entity {
+    let name = Name::new(format!("{:?} {}-{:?}", team, square, piece.identity));
+    #[cfg(feature = "log")]
+    bevy_log::info!("...spawning piece: {}", name);
+
+    let mut piece_builder = commands.spawn((
+        name,
+        PieceBundle::new(square.into(), team),
+        InGame(game),
+        OnBoard(board),
+        History::<Position>::default(),
+        Replicated,
+    ));
+    insert_piece_definition(&mut piece_builder, piece);
+    piece_builder.id()
+}
+
+// Inserts the components describing a piece: its identity, behaviors, mutation and royalty
+pub(crate) fn insert_piece_definition(piece_builder: &mut EntityCommands, piece: &PieceDefinition) {
+    piece_builder.insert(piece.identity);
+    if piece.royal.is_some() {
+        piece_builder.insert(Royal);
+    }
+    if let Some(mutation) = &piece.mutation {
+        piece_builder.insert(mutation.clone());
+    }
+    if let Some(behavior) = &piece.behaviors.pattern {
+        piece_builder.insert(behavior.clone());
+    }
+    if let Some(behavior) = &piece.behaviors.relay {
+        piece_builder.insert(behavior.clone());
+    }
+    if let Some(behavior) = piece.behaviors.en_passant {
+        piece_builder.insert(behavior);
+    }
+    if let Some(behavior) = piece.behaviors.castling {
+        piece_builder.insert(behavior);
+    }
+    if let Some(behavior) = piece.behaviors.castling_target {
+        piece_builder.insert(behavior);
+    }
+}
+
+// Removes the components inserted by `insert_piece_definition`
+pub(crate) fn remove_piece_definition(piece_builder: &mut EntityCommands) {
+    piece_builder
+        .remove::<PieceBehaviorsBundle>()
+        .remove::<(CastlingBehavior, CastlingTarget)>()
+        .remove::<(PieceIdentity, Mutation, Royal)>();
+}
@@ -10,12 +10,17 @@ use bevy_ecs::{
 use bevy_reflect::prelude::Reflect;
 
 mod game;
+pub(crate) use game::{
+    insert_piece_definition, remove_piece_definition, spawn_piece, PendingMoves,
+};
 pub use game::{
-    AntiGame, Atomic, ClockConfiguration, Crazyhouse, CurrentTurn, Game, GameBoard, PieceSet,
-    SpawnGame, WinCondition,
+    Analysis, AntiGame, Atomic, ClockConfiguration, Crazyhouse, CurrentTurn, Game, GameBoard,
+    GameSeed, GameSetup, PieceSet, SpawnGame, WinCondition,
 };
 mod turns;
-pub use turns::{ActionHistory, History, Ply};
+pub use turns::{ActionHistory, History, Ply, Premove, Premoves, TakebackRequest, TruncateHistory};
+mod variations;
+pub use variations::{Annotation, NodeId, VariationNode, VariationTree};
 
 #[derive(Clone, Debug)]
 #[derive(Component)]
@@ -37,23 +42,105 @@ impl MapEntities for InGame {
     }
 }
 
+// How a game ended
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum GameOverReason {
+    // The game's `WinCondition` was met
+    #[default]
+    WinCondition,
+    Timeout,
+    Resignation,
+    DrawAgreed,
+    // The game ended before both teams played, and has no result
+    Aborted,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Component))]
 pub struct GameOver {
-    winner: chess::team::Team,
+    // no team wins drawn or aborted games
+    winner: Option<chess::team::Team>,
+    #[serde(default)]
+    reason: GameOverReason,
 }
 
 impl GameOver {
+    // A win by the game's `WinCondition`
     pub fn new(winner: chess::team::Team) -> Self {
-        Self { winner }
+        Self::win(winner, GameOverReason::WinCondition)
+    }
+
+    pub fn win(winner: chess::team::Team, reason: GameOverReason) -> Self {
+        Self {
+            winner: Some(winner),
+            reason,
+        }
+    }
+
+    pub fn draw(reason: GameOverReason) -> Self {
+        Self {
+            winner: None,
+            reason,
+        }
+    }
+
+    pub fn aborted() -> Self {
+        Self::draw(GameOverReason::Aborted)
+    }
+
+    pub fn winner(&self) -> Option<&chess::team::Team> {
+        self.winner.as_ref()
+    }
+
+    pub fn reason(&self) -> GameOverReason {
+        self.reason
     }
 
-    pub fn winner(&self) -> &chess::team::Team {
-        &self.winner
+    pub fn is_draw(&self) -> bool {
+        self.winner.is_none() && self.reason != GameOverReason::Aborted
     }
 }
 
+impl std::fmt::Display for GameOver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.winner, self.reason) {
+            (_, GameOverReason::Aborted) => write!(f, "Game aborted"),
+            (Some(winner), GameOverReason::WinCondition) => write!(f, "{winner:?} wins"),
+            (Some(winner), GameOverReason::Timeout) => write!(f, "{winner:?} wins on time"),
+            (Some(winner), GameOverReason::Resignation) => {
+                write!(f, "{winner:?} wins by resignation")
+            }
+            (_, GameOverReason::DrawAgreed) => write!(f, "Draw by agreement"),
+            (None, _) => write!(f, "Draw"),
+        }
+    }
+}
+
+// A draw offered by `team`, which the opponent must accept.
+// The offer expires at `expires_at`, once the opponent has played a turn.
+#[derive(Clone, Copy, Debug)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct DrawOffer {
+    pub team: chess::team::Team,
+    pub expires_at: Ply,
+}
+
+// A request from `team` to play a finished game again with colors swapped
+#[derive(Clone, Copy, Debug)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct RematchRequest {
+    pub team: chess::team::Team,
+}
+
 pub type IsActiveGame = (With<Game>, Without<GameOver>);
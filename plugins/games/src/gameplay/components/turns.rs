@@ -1,16 +1,19 @@
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
 
 #[cfg(feature = "reflect")]
 use bevy_ecs::prelude::ReflectComponent;
 use bevy_ecs::{
     entity::MapEntities,
-    prelude::{Changed, Commands, Component, Entity, EntityMapper, Query, RemovedComponents, With},
+    observer::Trigger,
+    prelude::{
+        Changed, Commands, Component, Entity, EntityMapper, Event, Query, RemovedComponents, With,
+    },
 };
 #[cfg(feature = "reflect")]
 use bevy_reflect::Reflect;
 
-use chess::actions::Action;
+use chess::{actions::Action, board::Square, pieces::PieceDefinition, team::Team};
 
 use super::{Game, InGame};
 
@@ -31,6 +34,10 @@ impl Ply {
         Ply(ply)
     }
 
+    pub fn get(&self) -> usize {
+        self.0
+    }
+
     pub fn decrement(&mut self) {
         self.0 = self.0.saturating_sub(1);
     }
@@ -40,6 +47,68 @@ impl Ply {
     }
 }
 
+// A request from `team` to take back its last turn, which the opponent must accept.
+// Accepting rewinds the game to `ply`; the request expires once another turn is played.
+#[derive(Clone, Copy, Debug)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct TakebackRequest {
+    pub team: Team,
+    pub ply: Ply,
+    pub requested_at: Ply,
+}
+
+// A turn queued by a player while waiting for the opponent.
+// It is requested once it is `team`'s turn, if the piece can still move to `to`.
+#[derive(Clone, Debug, PartialEq)]
+#[derive(Deserialize, Serialize)]
+pub struct Premove {
+    pub piece: Entity,
+    pub team: Team,
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceDefinition>,
+}
+
+// The premoves queued on a game by the local players, played in order.
+// They only live on the client that queued them and are not replicated.
+#[derive(Clone, Debug, Default)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+pub struct Premoves(VecDeque<Premove>);
+
+impl Premoves {
+    pub fn push(&mut self, premove: Premove) {
+        self.0.push_back(premove);
+    }
+
+    pub fn front(&self) -> Option<&Premove> {
+        self.0.front()
+    }
+
+    pub fn pop_front(&mut self) -> Option<Premove> {
+        self.0.pop_front()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Premove> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl MapEntities for Premoves {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        for premove in self.0.iter_mut() {
+            premove.piece = mapper.map_entity(premove.piece);
+        }
+    }
+}
+
 // A vector using Ply as an index.
 // It tracks the action made each ply.
 #[derive(Clone, Debug, Default)]
@@ -73,6 +142,11 @@ impl ActionHistory {
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
+
+    // Keeps only the first `len` actions
+    pub fn truncate(&mut self, len: usize) {
+        self.0.truncate(len);
+    }
 }
 
 impl MapEntities for ActionHistory {
@@ -85,6 +159,15 @@ impl MapEntities for ActionHistory {
     }
 }
 
+// Removes the values recorded after `ply` from every `History` of a game,
+// such as when the game is rewound.
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+pub struct TruncateHistory {
+    pub game: Entity,
+    pub ply: Ply,
+}
+
 // A sparse vector using Ply as an index.
 // It is kept sparse in order to minimize cloning.
 #[derive(Clone, Debug)]
@@ -122,6 +205,11 @@ impl<T> History<T> {
         self.0.get(&latest_ply).and_then(|value| value.as_ref())
     }
 
+    // Removes every value recorded after `ply`
+    pub fn truncate(&mut self, ply: Ply) {
+        self.0.retain(|index, _| *index <= ply);
+    }
+
     // When retrieving the most up-to-date value, rely on BTreeMap methods instead.
     pub fn get_latest(&self) -> Option<&T> {
         self.0
@@ -167,6 +255,20 @@ impl<T> History<T> {
     }
 }
 
+impl<T: Component> History<T> {
+    pub fn truncate_observer(
+        trigger: Trigger<TruncateHistory>,
+        mut history_query: Query<(&InGame, &mut History<T>)>,
+    ) {
+        let TruncateHistory { game, ply } = trigger.event();
+        for (in_game, mut history) in history_query.iter_mut() {
+            if in_game.0 == *game {
+                history.truncate(*ply);
+            }
+        }
+    }
+}
+
 impl<T> Default for History<T> {
     fn default() -> Self {
         History::new()
@@ -0,0 +1,357 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "reflect")]
+use bevy_ecs::prelude::ReflectComponent;
+use bevy_ecs::{
+    entity::MapEntities,
+    prelude::{Component, Entity, EntityMapper},
+};
+#[cfg(feature = "reflect")]
+use bevy_reflect::Reflect;
+
+use chess::{actions::Action, pieces::PieceDefinition};
+
+use super::Ply;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct NodeId(usize);
+
+// The usual move annotations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum Annotation {
+    Brilliant,
+    Good,
+    Interesting,
+    Dubious,
+    Mistake,
+    Blunder,
+}
+
+impl Annotation {
+    pub const ALL: [Annotation; 6] = [
+        Annotation::Brilliant,
+        Annotation::Good,
+        Annotation::Interesting,
+        Annotation::Dubious,
+        Annotation::Mistake,
+        Annotation::Blunder,
+    ];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Annotation::Brilliant => "!!",
+            Annotation::Good => "!",
+            Annotation::Interesting => "!?",
+            Annotation::Dubious => "?!",
+            Annotation::Mistake => "?",
+            Annotation::Blunder => "??",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Annotation::ALL
+            .into_iter()
+            .find(|annotation| annotation.symbol() == symbol)
+    }
+}
+
+// A turn in the variation tree.
+// The root node has no turn and stands for the starting position.
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct VariationNode {
+    pub piece: Option<Entity>,
+    pub action: Option<Action>,
+    pub mutation: Option<PieceDefinition>,
+    // the turn in SAN-style notation, written once the turn has been played
+    pub notation: Option<String>,
+    pub comment: Option<String>,
+    pub annotation: Option<Annotation>,
+    parent: Option<NodeId>,
+    // the first child continues the main line of this node
+    children: Vec<NodeId>,
+    ply: Ply,
+}
+
+impl VariationNode {
+    fn root(ply: Ply) -> Self {
+        VariationNode {
+            piece: None,
+            action: None,
+            mutation: None,
+            notation: None,
+            comment: None,
+            annotation: None,
+            parent: None,
+            children: vec![],
+            ply,
+        }
+    }
+
+    // The ply of the position reached by this node's turn
+    pub fn ply(&self) -> Ply {
+        self.ply
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+// Every line of play explored in a game: the main line and any sidelines.
+// `current` is the node whose position is on the board.
+#[derive(Clone, Debug)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Component))]
+pub struct VariationTree {
+    // removed nodes leave a gap so that ids stay valid
+    nodes: Vec<Option<VariationNode>>,
+    current: NodeId,
+}
+
+impl Default for VariationTree {
+    fn default() -> Self {
+        VariationTree::new(Ply::default())
+    }
+}
+
+impl VariationTree {
+    // A tree whose root is the position at `ply`, such as a game started from a position
+    pub fn new(ply: Ply) -> Self {
+        VariationTree {
+            nodes: vec![Some(VariationNode::root(ply))],
+            current: NodeId(0),
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn current(&self) -> NodeId {
+        self.current
+    }
+
+    pub fn get(&self, node: NodeId) -> Option<&VariationNode> {
+        self.nodes.get(node.0).and_then(|node| node.as_ref())
+    }
+
+    pub fn get_mut(&mut self, node: NodeId) -> Option<&mut VariationNode> {
+        self.nodes.get_mut(node.0).and_then(|node| node.as_mut())
+    }
+
+    // The main continuation of a node
+    pub fn next(&self, node: NodeId) -> Option<NodeId> {
+        self.get(node)?.children.first().copied()
+    }
+
+    pub fn previous(&self, node: NodeId) -> Option<NodeId> {
+        self.get(node)?.parent
+    }
+
+    // The alternatives to a node's turn, not including the node itself
+    pub fn siblings(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.previous(node)
+            .and_then(|parent| self.get(parent))
+            .into_iter()
+            .flat_map(|parent| parent.children.iter().copied())
+            .filter(move |sibling| *sibling != node)
+    }
+
+    // Whether the node continues the main line of its parent
+    pub fn is_main_continuation(&self, node: NodeId) -> bool {
+        !self
+            .previous(node)
+            .and_then(|parent| self.next(parent))
+            .is_some_and(|main| main != node)
+    }
+
+    // Follows the main continuation from `node` until the end of the line
+    pub fn line_from(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(Some(node), |node| self.next(*node))
+    }
+
+    pub fn mainline(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.line_from(self.root()).skip(1)
+    }
+
+    // The nodes leading from the root to `node`, not including the root
+    pub fn path_to(&self, node: NodeId) -> Vec<NodeId> {
+        let mut path = std::iter::successors(Some(node), |node| self.previous(*node))
+            .filter(|node| *node != self.root())
+            .collect::<Vec<_>>();
+        path.reverse();
+        path
+    }
+
+    // Whether `ancestor` is on the path from the root to `node`
+    pub fn is_ancestor(&self, ancestor: NodeId, node: NodeId) -> bool {
+        std::iter::successors(Some(node), |node| self.previous(*node)).any(|node| node == ancestor)
+    }
+
+    // The last node shared by the paths to both nodes
+    pub fn common_ancestor(&self, node: NodeId, other: NodeId) -> NodeId {
+        std::iter::successors(Some(node), |node| self.previous(*node))
+            .find(|ancestor| self.is_ancestor(*ancestor, other))
+            .unwrap_or(self.root())
+    }
+
+    // Adds a turn after `parent`, reusing the node if the turn was already explored.
+    // New turns start a sideline unless the parent has no continuation yet.
+    pub fn add_turn(
+        &mut self,
+        parent: NodeId,
+        piece: Entity,
+        action: Action,
+        mutation: Option<PieceDefinition>,
+    ) -> Option<NodeId> {
+        let parent_node = self.get(parent)?;
+        if let Some(existing) = parent_node.children.iter().copied().find(|child| {
+            self.get(*child).is_some_and(|child| {
+                child.piece == Some(piece)
+                    && child.action == Some(action.clone())
+                    && child.mutation.as_ref().map(|mutation| mutation.identity)
+                        == mutation.as_ref().map(|mutation| mutation.identity)
+            })
+        }) {
+            return Some(existing);
+        }
+
+        let mut ply = parent_node.ply;
+        ply.increment();
+        let node = NodeId(self.nodes.len());
+        self.nodes.push(Some(VariationNode {
+            piece: Some(piece),
+            action: Some(action),
+            mutation,
+            notation: None,
+            comment: None,
+            annotation: None,
+            parent: Some(parent),
+            children: vec![],
+            ply,
+        }));
+        self.get_mut(parent)?.children.push(node);
+        Some(node)
+    }
+
+    // Records a turn played in the game from the current node
+    pub fn play(&mut self, piece: Entity, action: Action, mutation: Option<PieceDefinition>) {
+        if let Some(node) = self.add_turn(self.current, piece, action, mutation) {
+            self.current = node;
+        }
+    }
+
+    // Moves the current node back to the one reached at `ply`
+    pub fn rewind(&mut self, ply: Ply) {
+        while self.get(self.current).is_some_and(|node| node.ply > ply) {
+            let Some(parent) = self.previous(self.current) else {
+                break;
+            };
+            self.current = parent;
+        }
+    }
+
+    // Makes the line through `node` the main continuation of its parent
+    pub fn promote(&mut self, node: NodeId) {
+        let Some(parent) = self.previous(node) else {
+            return;
+        };
+        if let Some(parent) = self.get_mut(parent) {
+            parent.children.retain(|child| *child != node);
+            parent.children.insert(0, node);
+        }
+    }
+
+    // Removes `node` and every turn after it.
+    // The root and the nodes leading to the current position cannot be removed.
+    pub fn remove(&mut self, node: NodeId) -> bool {
+        if node == self.root() || self.is_ancestor(node, self.current) {
+            return false;
+        }
+        let Some(parent) = self.previous(node) else {
+            return false;
+        };
+        if let Some(parent) = self.get_mut(parent) {
+            parent.children.retain(|child| *child != node);
+        }
+        let mut removed = vec![node];
+        while let Some(node) = removed.pop() {
+            if let Some(node) = self.nodes.get_mut(node.0).and_then(Option::take) {
+                removed.extend(node.children);
+            }
+        }
+        true
+    }
+}
+
+impl MapEntities for VariationTree {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        for node in self.nodes.iter_mut().flatten() {
+            node.piece = node.piece.map(|piece| mapper.map_entity(piece));
+            if let Some(action) = &mut node.action {
+                action.map_entities(mapper);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::board::Square;
+
+    use super::*;
+
+    fn action(from: &str, to: &str) -> Action {
+        Action::movement(
+            Square::try_from(from).unwrap(),
+            Square::try_from(to).unwrap(),
+            Default::default(),
+            vec![],
+            None,
+        )
+    }
+
+    #[test]
+    fn test_variations() {
+        let piece = Entity::from_raw(0);
+        let mut tree = VariationTree::default();
+        tree.play(piece, action("e2", "e4"), None);
+        tree.play(piece, action("e7", "e5"), None);
+        let e5 = tree.current();
+
+        // take back the last turn and explore another one
+        tree.rewind(Ply::new(1));
+        tree.play(piece, action("c7", "c5"), None);
+        let c5 = tree.current();
+        assert_eq!(tree.mainline().count(), 2);
+        assert!(!tree.is_main_continuation(c5));
+        assert_eq!(tree.siblings(c5).collect::<Vec<_>>(), vec![e5]);
+
+        // replaying a turn reuses its node
+        tree.rewind(Ply::new(1));
+        tree.play(piece, action("c7", "c5"), None);
+        assert_eq!(tree.current(), c5);
+
+        tree.promote(c5);
+        assert!(tree.is_main_continuation(c5));
+        assert_eq!(tree.common_ancestor(c5, e5), tree.path_to(c5)[0]);
+
+        // the current line cannot be removed
+        assert!(!tree.remove(c5));
+        assert!(tree.remove(e5));
+        assert!(tree.get(e5).is_none());
+        assert_eq!(tree.siblings(c5).count(), 0);
+    }
+}
@@ -5,3 +5,15 @@ pub mod components;
 
 mod plugin;
 pub use plugin::*;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod pgn;
+pub use pgn::*;
+
+mod save;
+pub use save::*;
+
+mod variants;
+pub use variants::*;
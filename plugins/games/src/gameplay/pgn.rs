@@ -0,0 +1,689 @@
+use std::{fmt, time::Duration};
+
+use thiserror::Error;
+
+use chess::{
+    notation::NotationError,
+    position::{self, Turn},
+    team::Team,
+};
+use layouts::ClassicalLayout;
+
+use crate::{
+    components::{
+        ClockConfiguration, GameBoard, GameOver, GameSetup, PieceSet, SpawnGame, VariationNode,
+        VariationTree, WinCondition,
+    },
+    Clock, TimeStage, Timing,
+};
+
+#[derive(Debug, Error)]
+pub enum PgnError {
+    #[error("Malformed header `{0}`")]
+    MalformedHeader(String),
+    #[error("Invalid `{header}` header: {reason}")]
+    InvalidHeader { header: String, reason: String },
+    #[error("Illegal move {} `{text}`: {source}", move_number(*.ply))]
+    IllegalMove {
+        ply: usize,
+        text: String,
+        #[source]
+        source: NotationError,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    #[default]
+    Ongoing,
+}
+
+impl GameResult {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Ongoing => "*",
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Ongoing),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<&GameOver>> for GameResult {
+    fn from(game_over: Option<&GameOver>) -> Self {
+        match game_over {
+            Some(game_over) if game_over.is_draw() => GameResult::Draw,
+            Some(game_over) => match game_over.winner() {
+                Some(Team::White) => GameResult::WhiteWins,
+                Some(Team::Black) => GameResult::BlackWins,
+                // aborted games have no result
+                None => GameResult::Ongoing,
+            },
+            None => GameResult::Ongoing,
+        }
+    }
+}
+
+// A game written as PGN.
+// Besides the usual headers, the `GameBoard`, `PieceSet` and `WinCondition` are embedded as RON
+// so that games with wild pieces or on other boards can be replayed.
+// Games without a `PieceSet` header are played with the classical pieces.
+// Games started from a position have `SetUp` and `FEN` headers, with pieces named by
+// `PieceSet::legend`.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    pub board: GameBoard,
+    pub setup: Option<position::Position>,
+    pub piece_set: PieceSet,
+    pub win_condition: WinCondition,
+    pub clock: Option<Clock>,
+    pub result: GameResult,
+    pub moves: Vec<String>,
+}
+
+impl Default for GameRecord {
+    fn default() -> Self {
+        GameRecord {
+            white: "?".to_string(),
+            black: "?".to_string(),
+            board: GameBoard::default(),
+            setup: None,
+            piece_set: PieceSet::new(ClassicalLayout::pieces()),
+            win_condition: WinCondition::default(),
+            clock: None,
+            result: GameResult::default(),
+            moves: vec![],
+        }
+    }
+}
+
+impl GameRecord {
+    // Records the turns leading to the current node of a game's `VariationTree`
+    pub fn from_game(
+        piece_set: &PieceSet,
+        board: &GameBoard,
+        setup: Option<&GameSetup>,
+        win_condition: &WinCondition,
+        clock: Option<&ClockConfiguration>,
+        game_over: Option<&GameOver>,
+        variation_tree: &VariationTree,
+    ) -> Self {
+        // the tree refers to the game's piece entities, so turns are matched by their squares
+        let notation = piece_set.notation();
+        let setup = setup.map(|setup| setup.0.clone());
+        let mut position = setup
+            .clone()
+            .unwrap_or_else(|| piece_set.starting_position(&board.board()));
+        let mut moves = vec![];
+        for node in variation_tree
+            .path_to(variation_tree.current())
+            .into_iter()
+            .filter_map(|node| variation_tree.get(node))
+        {
+            let Some(turn) = find_turn(&position, node) else {
+                #[cfg(feature = "log")]
+                bevy_log::warn!("Failed to find turn {:?} of the recorded game", node.action);
+                break;
+            };
+            moves.push(notation.write(&mut position, &turn));
+            if position.make(&turn).is_err() {
+                break;
+            }
+        }
+
+        GameRecord {
+            board: *board,
+            setup,
+            piece_set: piece_set.clone(),
+            win_condition: win_condition.clone(),
+            clock: clock.map(|configuration| configuration.clock.clone()),
+            result: game_over.into(),
+            moves,
+            ..Default::default()
+        }
+    }
+
+    #[must_use]
+    pub fn with_players(mut self, white: impl Into<String>, black: impl Into<String>) -> Self {
+        self.white = white.into();
+        self.black = black.into();
+        self
+    }
+
+    pub fn parse(text: &str) -> Result<Self, PgnError> {
+        let mut record = GameRecord::default();
+        let mut fen = None;
+        let mut timings = None;
+        let mut movetext = String::new();
+        for line in text.lines().map(str::trim) {
+            if !line.starts_with('[') {
+                movetext.push_str(line);
+                movetext.push('\n');
+                continue;
+            }
+            let (key, value) = parse_header(line)?;
+            let invalid = |reason: String| PgnError::InvalidHeader {
+                header: key.to_string(),
+                reason,
+            };
+            match key {
+                "White" => record.white = value,
+                "Black" => record.black = value,
+                "Result" => {
+                    record.result = GameResult::from_symbol(&value)
+                        .ok_or_else(|| invalid(format!("unknown result `{value}`")))?;
+                }
+                "TimeControl" => {
+                    record.clock = parse_time_control(&value).map_err(invalid)?;
+                }
+                "TimeControlType" => timings = Some(value),
+                "WinCondition" => {
+                    record.win_condition =
+                        ron::from_str(&value).map_err(|error| invalid(error.to_string()))?;
+                }
+                "PieceSet" => {
+                    record.piece_set =
+                        ron::from_str(&value).map_err(|error| invalid(error.to_string()))?;
+                }
+                "Board" => {
+                    record.board =
+                        ron::from_str(&value).map_err(|error| invalid(error.to_string()))?;
+                }
+                "FEN" => fen = Some(value),
+                _ => {}
+            }
+        }
+        // the timings of the stages replace the increments they are written with
+        if let (Some(timings), Some(clock)) = (timings, &record.clock) {
+            let clock =
+                parse_timings(clock, &timings).map_err(|reason| PgnError::InvalidHeader {
+                    header: "TimeControlType".to_string(),
+                    reason,
+                })?;
+            record.clock = Some(clock);
+        }
+        // the position is read once the piece set naming its pieces and the board are known
+        if let Some(fen) = fen {
+            let legend = record.piece_set.legend();
+            let setup = position::Position::from_fen_on(&fen, &legend, &record.board.board())
+                .map_err(|error| PgnError::InvalidHeader {
+                    header: "FEN".to_string(),
+                    reason: error.to_string(),
+                })?;
+            record.setup = Some(setup);
+        }
+
+        // results, move numbers, comments, variations and NAGs do not identify moves
+        let mut depth = 0;
+        let mut in_comment = false;
+        let mut cleaned = String::new();
+        for line in movetext.lines() {
+            for c in line.chars() {
+                match c {
+                    _ if in_comment => in_comment = c != '}',
+                    '{' => in_comment = true,
+                    ';' if depth == 0 => break,
+                    '(' => depth += 1,
+                    ')' => depth = usize::max(depth, 1) - 1,
+                    _ if depth > 0 => {}
+                    _ => cleaned.push(c),
+                }
+            }
+            cleaned.push(' ');
+        }
+        record.moves = cleaned
+            .split_whitespace()
+            .filter(|token| GameResult::from_symbol(token).is_none())
+            .map(strip_move_number)
+            .filter(|token| !token.is_empty() && !token.starts_with('$'))
+            .map(str::to_string)
+            .collect();
+        Ok(record)
+    }
+
+    // Parses a game, checking that each of its moves can be played
+    pub fn import(text: &str) -> Result<Self, PgnError> {
+        let record = GameRecord::parse(text)?;
+        record.replay()?;
+        Ok(record)
+    }
+
+    // An analysis game that replays the recorded moves
+    pub fn analysis_game(self) -> SpawnGame {
+        let spawn_game = SpawnGame::new(self.piece_set)
+            .with_board(self.board)
+            .with_win_condition(self.win_condition)
+            .with_moves(self.moves)
+            .analysis();
+        match self.setup {
+            Some(setup) => spawn_game.with_setup(setup),
+            None => spawn_game,
+        }
+    }
+
+    // The position before the first recorded move
+    pub fn starting_position(&self) -> position::Position {
+        self.setup
+            .clone()
+            .unwrap_or_else(|| self.piece_set.starting_position(&self.board.board()))
+    }
+
+    // Plays every move from the starting position, failing at the first illegal move
+    pub fn replay(&self) -> Result<position::Position, PgnError> {
+        let notation = self.piece_set.notation();
+        let mut position = self.starting_position();
+        let first_ply = position.ply;
+        for (ply, text) in self.moves.iter().enumerate() {
+            let turn = notation
+                .parse(&position, text)
+                .map_err(|source| PgnError::IllegalMove {
+                    ply: first_ply + ply,
+                    text: text.clone(),
+                    source,
+                })?;
+            // turns found in the position are always made
+            let _ = position.make(&turn);
+        }
+        Ok(position)
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time_control = self
+            .clock
+            .as_ref()
+            .map(|clock| {
+                clock
+                    .stages()
+                    .iter()
+                    .map(write_time_stage)
+                    .collect::<Vec<_>>()
+                    .join(":")
+            })
+            .unwrap_or_else(|| "-".to_string());
+        let mut headers = vec![
+            ("Event", "Wild Chess Game".to_string()),
+            ("White", self.white.clone()),
+            ("Black", self.black.clone()),
+            ("Result", self.result.symbol().to_string()),
+        ];
+        if let Some(setup) = &self.setup {
+            let fen = setup
+                .to_fen(&self.piece_set.legend())
+                .map_err(|_| fmt::Error)?;
+            headers.extend([("SetUp", "1".to_string()), ("FEN", fen)]);
+        }
+        headers.push(("TimeControl", time_control));
+        // timings that PGN has no notation for are named by stage
+        if let Some(clock) = self.clock.as_ref().filter(|clock| {
+            clock
+                .stages()
+                .iter()
+                .any(|stage| !matches!(stage.timing, Timing::Increment(_) | Timing::Hourglass))
+        }) {
+            let timings = clock
+                .stages()
+                .iter()
+                .map(|stage| timing_name(stage.timing))
+                .collect::<Vec<_>>()
+                .join(":");
+            headers.push(("TimeControlType", timings));
+        }
+        headers.extend([
+            (
+                "Board",
+                ron::to_string(&self.board).map_err(|_| fmt::Error)?,
+            ),
+            (
+                "WinCondition",
+                ron::to_string(&self.win_condition).map_err(|_| fmt::Error)?,
+            ),
+            (
+                "PieceSet",
+                ron::to_string(&self.piece_set).map_err(|_| fmt::Error)?,
+            ),
+        ]);
+        for (key, value) in headers {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{key} \"{value}\"]")?;
+        }
+        writeln!(f)?;
+
+        // keep lines of movetext short
+        let mut line_length = 0;
+        let first_ply = self.setup.as_ref().map_or(0, |setup| setup.ply);
+        let tokens = self
+            .moves
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let ply = first_ply + index;
+                // a game set up with Black to move starts with a number such as `12...`
+                match ply % 2 {
+                    1 if index > 0 => text.clone(),
+                    _ => format!("{} {text}", move_number(ply)),
+                }
+            })
+            .chain(std::iter::once(self.result.symbol().to_string()));
+        for token in tokens {
+            if line_length > 0 && line_length + token.len() >= 80 {
+                writeln!(f)?;
+                line_length = 0;
+            } else if line_length > 0 {
+                write!(f, " ")?;
+                line_length += 1;
+            }
+            write!(f, "{token}")?;
+            line_length += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+// The prefix of a move in movetext, such as `12.` for White or `12...` for Black
+fn move_number(ply: usize) -> String {
+    let dots = if ply % 2 == 1 { "..." } else { "." };
+    format!("{}{dots}", ply / 2 + 1)
+}
+
+// Removes a leading move number such as `12.` or `12...`, which may be attached to the move
+fn strip_move_number(token: &str) -> &str {
+    let rest = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() < token.len() && rest.starts_with('.') {
+        rest.trim_start_matches('.')
+    } else {
+        token
+    }
+}
+
+fn find_turn(position: &position::Position, node: &VariationNode) -> Option<Turn> {
+    let action = node.action.as_ref()?;
+    position.pseudo_legal_turns().into_iter().find(|turn| {
+        turn.action.movement.from == action.movement.from
+            && turn.action.movement.to == action.movement.to
+            && turn.mutation.as_ref().map(|mutation| mutation.identity)
+                == node.mutation.as_ref().map(|mutation| mutation.identity)
+    })
+}
+
+fn parse_header(line: &str) -> Result<(&str, String), PgnError> {
+    let malformed = || PgnError::MalformedHeader(line.to_string());
+    let inner = line
+        .strip_prefix('[')
+        .and_then(|line| line.strip_suffix(']'))
+        .ok_or_else(malformed)?;
+    let (key, value) = inner
+        .split_once(char::is_whitespace)
+        .ok_or_else(malformed)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(malformed)?;
+
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            unescaped.extend(chars.next());
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Ok((key, unescaped))
+}
+
+// Writes a stage as `moves/seconds+increment`, or `*seconds` for an hourglass.
+// PGN has no notation for delays or time per move, which are written as increments
+// and named by the `TimeControlType` header.
+fn write_time_stage(stage: &TimeStage) -> String {
+    let moves = stage
+        .moves
+        .map(|moves| format!("{moves}/"))
+        .unwrap_or_default();
+    let seconds = stage.time.as_secs();
+    match stage.timing {
+        Timing::Hourglass => format!("{moves}*{seconds}"),
+        Timing::Increment(increment) | Timing::Bronstein(increment) | Timing::Delay(increment) => {
+            format!("{moves}{seconds}+{}", increment.as_secs())
+        }
+        Timing::PerMove => format!("{moves}{seconds}"),
+    }
+}
+
+// Time controls are written as stages separated by `:`, such as `40/5400:1800+30`,
+// or `-` for untimed games
+fn parse_time_control(value: &str) -> Result<Option<Clock>, String> {
+    if value == "-" || value == "?" {
+        return Ok(None);
+    }
+    let parse = |seconds: &str| {
+        seconds
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .map_err(|_| format!("expected `seconds+increment`, found `{value}`"))
+    };
+    let stages = value
+        .split(':')
+        .map(|stage| {
+            let (moves, stage) = match stage.split_once('/') {
+                Some((moves, stage)) => (
+                    Some(
+                        moves
+                            .parse::<u32>()
+                            .map_err(|_| format!("expected a number of moves, found `{moves}`"))?,
+                    ),
+                    stage,
+                ),
+                None => (None, stage),
+            };
+            let (time, timing) = match stage.strip_prefix('*') {
+                Some(time) => (parse(time)?, Timing::Hourglass),
+                None => {
+                    let (time, increment) = stage.split_once('+').unwrap_or((stage, "0"));
+                    (parse(time)?, Timing::Increment(parse(increment)?))
+                }
+            };
+            Ok(TimeStage {
+                time,
+                moves,
+                timing,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Some(Clock::from_stages(stages)))
+}
+
+// The name of a timing in the `TimeControlType` header
+fn timing_name(timing: Timing) -> &'static str {
+    match timing {
+        Timing::Increment(_) => "increment",
+        Timing::Bronstein(_) => "bronstein",
+        Timing::Delay(_) => "delay",
+        Timing::Hourglass => "hourglass",
+        Timing::PerMove => "per-move",
+    }
+}
+
+// Applies the timings of the `TimeControlType` header, such as `delay:increment`,
+// to the stages of a clock read from the `TimeControl` header
+fn parse_timings(clock: &Clock, value: &str) -> Result<Clock, String> {
+    let names = value.split(':').collect::<Vec<_>>();
+    if names.len() != clock.stages().len() {
+        return Err(format!(
+            "expected {} timings, found `{value}`",
+            clock.stages().len()
+        ));
+    }
+    let stages = clock
+        .stages()
+        .iter()
+        .zip(names)
+        .map(|(stage, name)| {
+            let bonus = match stage.timing {
+                Timing::Increment(bonus) => bonus,
+                _ => Duration::ZERO,
+            };
+            let timing = match name {
+                "increment" => Timing::Increment(bonus),
+                "bronstein" => Timing::Bronstein(bonus),
+                "delay" => Timing::Delay(bonus),
+                "hourglass" => Timing::Hourglass,
+                "per-move" => Timing::PerMove,
+                _ => return Err(format!("unknown timing `{name}`")),
+            };
+            Ok(TimeStage { timing, ..*stage })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Clock::from_stages(stages))
+}
+
+#[cfg(test)]
+mod tests {
+    use layouts::RandomWildLayout;
+
+    use crate::VariantDefinition;
+
+    use super::*;
+
+    #[test]
+    fn test_pgn_round_trip() {
+        let piece_set = PieceSet::new(RandomWildLayout::pieces(0));
+        let notation = piece_set.notation();
+        let mut position = piece_set.starting_position(&GameBoard::Chess.board());
+        let mut moves = vec![];
+        for _ in 0..4 {
+            let turn = position.pseudo_legal_turns().remove(0);
+            moves.push(notation.write(&mut position, &turn));
+            position.make(&turn).unwrap();
+        }
+
+        let record = GameRecord {
+            piece_set,
+            clock: Some(Clock::new(Duration::from_secs(300), Duration::from_secs(2))),
+            result: GameResult::WhiteWins,
+            moves,
+            ..Default::default()
+        }
+        .with_players("Alice \"A\"", "Bob");
+        let text = record.to_string();
+
+        let parsed = GameRecord::parse(&text).unwrap();
+        assert_eq!(parsed.white, "Alice \"A\"");
+        assert_eq!(parsed.black, "Bob");
+        assert_eq!(parsed.result, GameResult::WhiteWins);
+        assert_eq!(parsed.moves, record.moves);
+        assert_eq!(parsed.piece_set.pieces.len(), record.piece_set.pieces.len());
+        assert!(parsed.replay().is_ok());
+        let clock = parsed.clock.unwrap();
+        assert_eq!(clock.remaining_time(), Duration::from_secs(300));
+        assert_eq!(clock.increment(), Duration::from_secs(2));
+
+        let clock = parse_time_control("40/5400:1800+30").unwrap().unwrap();
+        assert_eq!(clock.time_control(), "40 moves in 90:00, then 30:00 + 30s");
+        assert_eq!(
+            clock
+                .stages()
+                .iter()
+                .map(write_time_stage)
+                .collect::<Vec<_>>(),
+            ["40/5400+0", "1800+30"]
+        );
+
+        let parsed = GameRecord::parse("1. e4 {best by test} e5 (1... c5) 2. Ke3 Ke7 1-0").unwrap();
+        assert_eq!(parsed.moves, vec!["e4", "e5", "Ke3", "Ke7"]);
+        assert!(matches!(
+            parsed.replay(),
+            Err(PgnError::IllegalMove { ply: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_timing_round_trip() {
+        let bonus = Duration::from_secs(3);
+        for timing in [
+            Timing::Increment(bonus),
+            Timing::Increment(Duration::ZERO),
+            Timing::Bronstein(bonus),
+            Timing::Delay(bonus),
+            Timing::Hourglass,
+            Timing::PerMove,
+        ] {
+            let record = GameRecord {
+                clock: Some(Clock::from_stages(vec![
+                    TimeStage::new(Duration::from_secs(5400), timing).with_moves(40),
+                    TimeStage::new(Duration::from_secs(1800), timing),
+                ])),
+                ..Default::default()
+            };
+            let text = record.to_string();
+            let parsed = GameRecord::parse(&text).unwrap();
+            assert_eq!(
+                parsed.clock.as_ref().map(Clock::stages),
+                record.clock.as_ref().map(Clock::stages),
+                "{text}"
+            );
+        }
+
+        assert!(matches!(
+            GameRecord::parse("[TimeControl \"300\"]\n[TimeControlType \"delay:delay\"]"),
+            Err(PgnError::InvalidHeader { .. })
+        ));
+        assert!(matches!(
+            GameRecord::parse("[TimeControl \"300\"]\n[TimeControlType \"sandglass\"]"),
+            Err(PgnError::InvalidHeader { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pgn_board_and_setup() {
+        let text = include_str!("../../../../variants/los_alamos.ron");
+        let variant = VariantDefinition::from_ron(text).unwrap().build().unwrap();
+        let record = GameRecord {
+            board: variant.board,
+            piece_set: variant.piece_set,
+            moves: vec!["b3".to_string()],
+            ..Default::default()
+        };
+        let parsed = GameRecord::import(&record.to_string()).unwrap();
+        assert_eq!(parsed.board.board().size, variant.board.board().size);
+
+        // a position with Black to move in the twelfth move
+        let piece_set = PieceSet::new(ClassicalLayout::pieces());
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 3 12";
+        let setup = position::Position::from_fen(fen, &piece_set.legend()).unwrap();
+        let record = GameRecord {
+            setup: Some(setup),
+            piece_set,
+            moves: vec!["Kd7".to_string(), "e4".to_string()],
+            ..Default::default()
+        };
+        let text = record.to_string();
+        assert!(text.contains("[SetUp \"1\"]"));
+        assert!(text.contains(&format!("[FEN \"{fen}\"]")));
+        assert!(text.contains("12... Kd7 13. e4"));
+
+        let parsed = GameRecord::import(&text).unwrap();
+        let setup = parsed.setup.as_ref().unwrap();
+        assert_eq!(setup.side_to_move, Team::Black);
+        assert_eq!(setup.ply, 23);
+        assert_eq!(setup.halfmove_clock, 3);
+        assert_eq!(parsed.moves, record.moves);
+    }
+}
@@ -0,0 +1,159 @@
+use bevy_ecs::{
+    change_detection::DetectChangesMut,
+    prelude::{Commands, Entity, EventReader, Query, With, Without},
+};
+
+use bevy_replicon::prelude::{ClientId, FromClient};
+
+use chess::{actions::LastAction, board::Board, pieces::Position};
+use replication::Client;
+
+use crate::{
+    components::{
+        insert_piece_definition, remove_piece_definition, spawn_piece, ActionHistory, Analysis,
+        CurrentTurn, Game, GameOver, InGame, Player, Ply, TruncateHistory, VariationTree,
+    },
+    PieceSnapshotQuery,
+};
+
+use super::{BoardEdit, EditBoardEvent, RequestRewindEvent, RewindGame};
+
+// Whether the client plays in the analysis game
+pub(super) fn can_analyze(
+    client_id: ClientId,
+    game: Entity,
+    player_query: &Query<(&InGame, Option<&Client>), With<Player>>,
+) -> bool {
+    player_query.iter().any(|(in_game, client)| {
+        in_game.0 == game && client.map(|client| client.id).unwrap_or(ClientId::SERVER) == client_id
+    })
+}
+
+pub(super) fn handle_rewind_requests(
+    mut commands: Commands,
+    game_query: Query<(), (With<Game>, With<Analysis>)>,
+    player_query: Query<(&InGame, Option<&Client>), With<Player>>,
+    mut rewind_requests: EventReader<FromClient<RequestRewindEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: RequestRewindEvent { game, ply },
+    } in rewind_requests.read()
+    {
+        if game_query.get(*game).is_err() {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} is not an analysis game and cannot be rewound freely");
+            continue;
+        }
+        if !can_analyze(*client_id, *game, &player_query) {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} is not analyzing game {game}");
+            continue;
+        }
+        commands.trigger(RewindGame {
+            game: *game,
+            ply: *ply,
+        });
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn handle_board_edits(
+    mut commands: Commands,
+    mut game_query: Query<
+        (
+            &mut Ply,
+            &mut CurrentTurn,
+            &mut ActionHistory,
+            &mut VariationTree,
+        ),
+        (With<Game>, With<Analysis>),
+    >,
+    board_query: Query<(Entity, &Board, &InGame), Without<Game>>,
+    player_query: Query<(&InGame, Option<&Client>), With<Player>>,
+    piece_query: Query<PieceSnapshotQuery>,
+    mut edit_requests: EventReader<FromClient<EditBoardEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: EditBoardEvent { game, edit },
+    } in edit_requests.read()
+    {
+        let Ok((mut ply, mut current_turn, mut action_history, mut variation_tree)) =
+            game_query.get_mut(*game)
+        else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} is not an analysis game and cannot be edited");
+            continue;
+        };
+        if !can_analyze(*client_id, *game, &player_query) {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} is not analyzing game {game}");
+            continue;
+        }
+        let Some((board, board_data, _)) = board_query
+            .iter()
+            .find(|(_, _, in_game)| in_game.0 == *game)
+        else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Failed to find board for game {game}");
+            continue;
+        };
+        let occupant = |square| {
+            piece_query
+                .iter()
+                .find(|piece| piece.in_game.0 == *game && piece.position == Some(&Position(square)))
+                .map(|piece| piece.entity)
+        };
+
+        let mut removed_piece = None;
+        match edit {
+            BoardEdit::AddPiece {
+                square,
+                team,
+                piece,
+            } => {
+                if !board_data.contains(*square) {
+                    #[cfg(feature = "log")]
+                    bevy_log::warn!("Square {square} is not on board {board}");
+                    continue;
+                }
+                removed_piece = occupant(*square);
+                spawn_piece(&mut commands, *game, board, piece, *square, *team);
+            }
+            BoardEdit::RemovePiece(square) => {
+                removed_piece = occupant(*square);
+            }
+            BoardEdit::SetTurn(team) => {
+                current_turn.0 = *team;
+            }
+        }
+
+        // the edited position is the start of a new game:
+        // captured pieces are forgotten, and the other pieces are inserted again
+        // so that their current state is recorded as the start of their history
+        for piece in piece_query.iter().filter(|piece| piece.in_game.0 == *game) {
+            let Some(position) = piece
+                .position
+                .filter(|_| removed_piece != Some(piece.entity))
+            else {
+                commands.entity(piece.entity).despawn();
+                continue;
+            };
+            let mut piece_builder = commands.entity(piece.entity);
+            remove_piece_definition(&mut piece_builder);
+            insert_piece_definition(&mut piece_builder, &piece.definition());
+            piece_builder.remove::<Position>().insert(position.clone());
+        }
+        *ply = Ply::default();
+        current_turn.set_changed();
+        action_history.truncate(0);
+        *variation_tree = VariationTree::default();
+        commands.entity(*game).remove::<(GameOver, LastAction)>();
+        commands.entity(board).remove::<LastAction>();
+        commands.trigger(TruncateHistory {
+            game: *game,
+            ply: Ply::default(),
+        });
+    }
+}
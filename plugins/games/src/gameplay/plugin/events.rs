@@ -5,7 +5,9 @@ use bevy_ecs::{
     prelude::{Entity, EntityMapper, Event},
 };
 
-use chess::{actions::Action, pieces::PieceDefinition};
+use chess::{actions::Action, board::Square, pieces::PieceDefinition, team::Team};
+
+use crate::components::{Annotation, NodeId, Ply};
 
 #[derive(Clone)]
 #[derive(Event)]
@@ -76,3 +78,206 @@ impl MapEntities for RequireMutationEvent {
             .collect();
     }
 }
+
+// Requests that an analysis game is rewound to an earlier ply
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct RequestRewindEvent {
+    pub game: Entity,
+    pub ply: Ply,
+}
+
+impl MapEntities for RequestRewindEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+pub enum BoardEdit {
+    // Places a new piece, replacing any piece on the square
+    AddPiece {
+        square: Square,
+        team: Team,
+        piece: PieceDefinition,
+    },
+    RemovePiece(Square),
+    SetTurn(Team),
+}
+
+// Requests a change to the position of an analysis game.
+// Setting up a position starts the game over from it, clearing its history.
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct EditBoardEvent {
+    pub game: Entity,
+    pub edit: BoardEdit,
+}
+
+impl MapEntities for EditBoardEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Requests to take back the last turn of the requesting player
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct RequestTakebackEvent {
+    pub game: Entity,
+}
+
+impl MapEntities for RequestTakebackEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Accepts or declines the opponent's `TakebackRequest`
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct AnswerTakebackEvent {
+    pub game: Entity,
+    pub accept: bool,
+}
+
+impl MapEntities for AnswerTakebackEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Resigns the game for the requesting player
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct ResignGameEvent {
+    pub game: Entity,
+}
+
+impl MapEntities for ResignGameEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Ends the game without a result, before the requesting player's first turn
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct AbortGameEvent {
+    pub game: Entity,
+}
+
+impl MapEntities for AbortGameEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Offers a draw, which the opponent must accept
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct OfferDrawEvent {
+    pub game: Entity,
+}
+
+impl MapEntities for OfferDrawEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Accepts or declines the opponent's `DrawOffer`
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct AnswerDrawEvent {
+    pub game: Entity,
+    pub accept: bool,
+}
+
+impl MapEntities for AnswerDrawEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Requests to play a finished game again with colors swapped,
+// the same pieces and the same clock
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct RequestRematchEvent {
+    pub game: Entity,
+}
+
+impl MapEntities for RequestRematchEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Accepts or declines the opponent's `RematchRequest`
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct AnswerRematchEvent {
+    pub game: Entity,
+    pub accept: bool,
+}
+
+impl MapEntities for AnswerRematchEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+// Requests that an analysis game continues from a node of its `VariationTree`
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct RequestVariationEvent {
+    pub game: Entity,
+    pub node: NodeId,
+}
+
+impl MapEntities for RequestVariationEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
+
+#[derive(Clone)]
+#[derive(Deserialize, Serialize)]
+pub enum VariationEdit {
+    SetComment(Option<String>),
+    SetAnnotation(Option<Annotation>),
+    // Makes the variation the main line of its parent
+    Promote,
+    // Removes the variation and every turn after it
+    Remove,
+}
+
+// Requests a change to a node of a game's `VariationTree`.
+// Variations can be edited in analysis games and once a game is over.
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct EditVariationEvent {
+    pub game: Entity,
+    pub node: NodeId,
+    pub edit: VariationEdit,
+}
+
+impl MapEntities for EditVariationEvent {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        self.game = mapper.map_entity(self.game);
+    }
+}
@@ -1,4 +1,4 @@
-use bevy_app::prelude::{App, Plugin, Update};
+use bevy_app::prelude::{App, Plugin, Startup, Update};
 use bevy_ecs::prelude::{
     Added, Changed, Component, Condition, IntoSystemConfigs, IntoSystemSetConfigs, Query, SystemSet,
 };
@@ -11,22 +11,32 @@ use chess::{
 };
 
 use bevy_replicon::prelude::*;
-use turns::PlayTurn;
+pub(crate) use turns::PlayTurn;
 
 use crate::{
     components::{
-        ActionHistory, AntiGame, Atomic, ClockConfiguration, Crazyhouse, Game, GameBoard, GameOver,
-        History, InGame, Ply, WinCondition,
+        ActionHistory, Analysis, AntiGame, Atomic, ClockConfiguration, Crazyhouse, DrawOffer, Game,
+        GameBoard, GameOver, GameSeed, GameSetup, History, InGame, PieceSet, Ply, RematchRequest,
+        TakebackRequest, VariationTree, WinCondition,
     },
-    ClockPlugin, MatchmakingSystems,
+    load_variants, ClockPlugin, LoadGame, MatchmakingSystems, SaveGame, VariantRegistry,
 };
 
 use super::components::{CurrentTurn, Player, SpawnGame};
 
+mod analysis;
 mod events;
 pub use events::*;
+mod notation;
+mod premoves;
+mod results;
+pub use results::can_abort;
+mod rewind;
+pub use rewind::{GameSnapshot, GameSnapshots, RewindGame};
 mod systems;
+mod takeback;
 mod turns;
+mod variations;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[derive(SystemSet)]
@@ -54,22 +64,44 @@ impl Plugin for GameplayPlugin {
             .configure_sets(Update, GameSystems::All.after(MatchmakingSystems))
             .add_mapped_client_event::<RequestTurnEvent>(ChannelKind::Ordered)
             .add_mapped_server_event::<RequireMutationEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<RequestRewindEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<EditBoardEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<RequestTakebackEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<AnswerTakebackEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<RequestVariationEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<EditVariationEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<ResignGameEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<AbortGameEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<OfferDrawEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<AnswerDrawEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<RequestRematchEvent>(ChannelKind::Ordered)
+            .add_mapped_client_event::<AnswerRematchEvent>(ChannelKind::Ordered)
             .replicate::<Ply>()
             .replicate_mapped::<InGame>()
             .replicate::<Game>()
             .replicate::<Player>()
             .replicate::<CurrentTurn>()
+            .replicate::<GameSeed>()
             .replicate::<GameOver>()
             .replicate::<GameBoard>()
+            .replicate::<PieceSet>()
+            .replicate::<GameSetup>()
             .replicate::<Atomic>()
             .replicate::<Crazyhouse>()
             .replicate::<AntiGame>()
+            .replicate::<Analysis>()
+            .replicate::<TakebackRequest>()
+            .replicate::<DrawOffer>()
+            .replicate::<RematchRequest>()
             .replicate::<WinCondition>()
             .replicate::<ClockConfiguration>()
             .replicate_mapped::<ActionHistory>()
+            .replicate_mapped::<VariationTree>()
             .replicate::<History<Position>>()
             .replicate::<History<PatternBehavior>>()
             .replicate::<History<RelayBehavior>>()
+            .init_resource::<VariantRegistry>()
+            .add_systems(Startup, load_variants)
             .configure_sets(
                 Update,
                 (
@@ -94,7 +126,36 @@ impl Plugin for GameplayPlugin {
             )
             .add_systems(
                 Update,
-                systems::trigger_turns.in_set(GameSystems::TriggerTurn),
+                (
+                    takeback::expire_takeback_requests,
+                    takeback::handle_takeback_requests,
+                    takeback::handle_takeback_answers,
+                    results::expire_draw_offers,
+                    results::handle_resignations,
+                    results::handle_aborts,
+                    results::handle_draw_offers,
+                    results::handle_draw_answers,
+                    results::handle_rematch_requests,
+                    results::handle_rematch_answers,
+                    analysis::handle_rewind_requests,
+                    analysis::handle_board_edits,
+                    notation::play_pending_moves,
+                    variations::replay_variations,
+                    variations::handle_variation_requests,
+                    variations::handle_variation_edits,
+                    systems::trigger_turns,
+                )
+                    .chain()
+                    .in_set(GameSystems::TriggerTurn),
+            )
+            // premoves are requested by clients, so they run without authority
+            .add_systems(Update, premoves::play_premoves.after(BehaviorsSystems))
+            .add_systems(
+                Update,
+                (rewind::record_snapshots, notation::record_notation)
+                    .chain()
+                    .after(BehaviorsSystems)
+                    .run_if(has_authority),
             )
             .add_systems(
                 Update,
@@ -104,6 +165,12 @@ impl Plugin for GameplayPlugin {
 
         app.observe(SpawnGame::observer);
         app.observe(PlayTurn::observer);
+        app.observe(RewindGame::observer);
+        app.observe(SaveGame::observer);
+        app.observe(LoadGame::observer);
+        app.observe(History::<Position>::truncate_observer);
+        app.observe(History::<PatternBehavior>::truncate_observer);
+        app.observe(History::<RelayBehavior>::truncate_observer);
 
         #[cfg(feature = "reflect")]
         app.register_type::<InGame>()
@@ -111,7 +178,11 @@ impl Plugin for GameplayPlugin {
             .register_type::<WinCondition>()
             .register_type::<Ply>()
             .register_type::<ClockConfiguration>()
-            .register_type::<ActionHistory>();
+            .register_type::<ActionHistory>()
+            .register_type::<VariationTree>()
+            .register_type::<TakebackRequest>()
+            .register_type::<DrawOffer>()
+            .register_type::<RematchRequest>();
     }
 }
 
@@ -188,7 +259,7 @@ mod tests {
         app.add_plugins(GameplayPlugin);
 
         app.world_mut()
-            .trigger(SpawnGame::new(PieceSet(RandomWildLayout::pieces())));
+            .trigger(SpawnGame::new(PieceSet::new(RandomWildLayout::pieces(0))));
 
         app.update();
 
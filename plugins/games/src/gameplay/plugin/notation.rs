@@ -0,0 +1,111 @@
+use bevy_ecs::prelude::{Changed, Commands, Entity, Query, With};
+
+use chess::{actions::LastAction, board::Board, position::Turn};
+
+use crate::{
+    components::{CurrentTurn, Game, InGame, PendingMoves, PieceSet, Ply, VariationTree},
+    snapshot_position, PieceSnapshotQuery,
+};
+
+use super::{turns::PlayTurn, GameSnapshots};
+
+// Plays the moves a game was spawned with, one move per frame.
+// The remaining moves are dropped if one of them cannot be played.
+#[allow(clippy::type_complexity)]
+pub(super) fn play_pending_moves(
+    mut commands: Commands,
+    mut game_query: Query<
+        (
+            Entity,
+            &CurrentTurn,
+            &Ply,
+            Option<&LastAction>,
+            &PieceSet,
+            &mut PendingMoves,
+        ),
+        With<Game>,
+    >,
+    board_query: Query<(Entity, &Board, &InGame)>,
+    piece_query: Query<PieceSnapshotQuery>,
+) {
+    for (game, current_turn, ply, last_action, piece_set, mut pending) in game_query.iter_mut() {
+        let Some((board, board_data, _)) =
+            board_query.iter().find(|(_, _, in_game)| in_game.0 == game)
+        else {
+            continue;
+        };
+        let Some(text) = pending.0.pop_front() else {
+            commands.entity(game).remove::<PendingMoves>();
+            continue;
+        };
+
+        let position = snapshot_position(
+            board_data,
+            current_turn,
+            ply,
+            last_action,
+            piece_query.iter().filter(|piece| piece.in_game.0 == game),
+        );
+        let turn = match piece_set.notation().parse(&position, &text) {
+            Ok(turn) => turn,
+            Err(_error) => {
+                #[cfg(feature = "log")]
+                bevy_log::warn!("Failed to play move {text} in game {game}: {_error}");
+                commands.entity(game).remove::<PendingMoves>();
+                continue;
+            }
+        };
+        commands.trigger(PlayTurn {
+            ply: *ply,
+            piece: turn.piece,
+            board,
+            game,
+            action: turn.action,
+            mutation: turn.mutation,
+        });
+        if pending.0.is_empty() {
+            commands.entity(game).remove::<PendingMoves>();
+        }
+    }
+}
+
+// Writes the notation of each turn once it is played.
+// The position before the turn is taken from the game's snapshots.
+#[allow(clippy::type_complexity)]
+pub(super) fn record_notation(
+    mut game_query: Query<
+        (&Ply, &PieceSet, &GameSnapshots, &mut VariationTree),
+        (With<Game>, Changed<Ply>),
+    >,
+) {
+    for (ply, piece_set, snapshots, mut variation_tree) in game_query.iter_mut() {
+        let current = variation_tree.current();
+        let Some(node) = variation_tree.get(current) else {
+            continue;
+        };
+        if node.ply() != *ply || node.notation.is_some() {
+            continue;
+        }
+        let (Some(piece), Some(action)) = (node.piece, node.action.clone()) else {
+            continue;
+        };
+        let Some(snapshot) = ply
+            .get()
+            .checked_sub(1)
+            .and_then(|previous| snapshots.get(Ply::new(previous)))
+        else {
+            continue;
+        };
+
+        let turn = Turn {
+            piece,
+            action,
+            mutation: node.mutation.clone(),
+        };
+        let mut position = snapshot.position.clone();
+        let notation = piece_set.notation().write(&mut position, &turn);
+        if let Some(node) = variation_tree.get_mut(current) {
+            node.notation = Some(notation);
+        }
+    }
+}
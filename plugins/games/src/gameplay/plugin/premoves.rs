@@ -0,0 +1,59 @@
+use bevy_ecs::prelude::{Commands, DetectChanges, Entity, EventWriter, Has, Query, Ref, With};
+
+use chess::{actions::Actions, pieces::Position};
+
+use crate::components::{CurrentTurn, Game, GameOver, Premoves};
+
+use super::RequestTurnEvent;
+
+// Requests the next premove of a game once it is the turn of the team that queued it.
+// If it cannot be played in the new position, every premove of the game is cancelled.
+#[allow(clippy::type_complexity)]
+pub(super) fn play_premoves(
+    mut commands: Commands,
+    mut game_query: Query<(Entity, Ref<CurrentTurn>, Has<GameOver>, &mut Premoves), With<Game>>,
+    piece_query: Query<(&Position, &Actions)>,
+    mut turn_writer: EventWriter<RequestTurnEvent>,
+) {
+    for (game, current_turn, is_over, mut premoves) in game_query.iter_mut() {
+        if is_over || premoves.is_empty() {
+            commands.entity(game).remove::<Premoves>();
+            continue;
+        }
+        // Actions are only up to date once the turn has changed
+        if !current_turn.is_changed() {
+            continue;
+        }
+        if !premoves
+            .front()
+            .is_some_and(|premove| premove.team == current_turn.0)
+        {
+            continue;
+        }
+        let Some(premove) = premoves.pop_front() else {
+            continue;
+        };
+
+        let action = piece_query
+            .get(premove.piece)
+            .ok()
+            .filter(|(position, _)| position.0 == premove.from)
+            .and_then(|(_, actions)| actions.get(&premove.to));
+        let Some(action) = action else {
+            #[cfg(feature = "log")]
+            bevy_log::info!(
+                "Cancelled premoves of game {game}: {} to {} cannot be played",
+                premove.from,
+                premove.to
+            );
+            commands.entity(game).remove::<Premoves>();
+            continue;
+        };
+        turn_writer.send(RequestTurnEvent {
+            game,
+            piece: premove.piece,
+            action: action.clone(),
+            promotion: premove.promotion,
+        });
+    }
+}
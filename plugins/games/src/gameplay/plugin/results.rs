@@ -0,0 +1,594 @@
+use bevy_ecs::prelude::{Commands, Entity, EventReader, Has, Query, With, Without};
+
+use bevy_replicon::prelude::FromClient;
+
+use chess::team::Team;
+
+use crate::components::{
+    ActionHistory, Analysis, AntiGame, Atomic, ClockConfiguration, Crazyhouse, CurrentTurn,
+    DrawOffer, Game, GameBoard, GameOver, GameOverReason, GameSeed, GameSetup, InGame,
+    IsActiveGame, PieceSet, Player, Ply, RematchRequest, SpawnGame, WinCondition,
+};
+
+use super::{
+    takeback::{client_teams, PlayerQuery},
+    AbortGameEvent, AnswerDrawEvent, AnswerRematchEvent, OfferDrawEvent, RequestRematchEvent,
+    ResignGameEvent,
+};
+
+type ActiveGameQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Ply,
+        &'static ActionHistory,
+        &'static CurrentTurn,
+        Option<&'static DrawOffer>,
+    ),
+    (IsActiveGame, Without<Analysis>),
+>;
+
+type RematchQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static PieceSet,
+        &'static GameBoard,
+        &'static WinCondition,
+        Option<&'static ClockConfiguration>,
+        Option<&'static GameSeed>,
+        Option<&'static GameSetup>,
+        Has<Atomic>,
+        Has<Crazyhouse>,
+        Has<AntiGame>,
+    ),
+    (With<Game>, With<GameOver>, Without<Analysis>),
+>;
+
+// Whether a team can still abort the game: each team can until it plays its first turn.
+// Turns are counted from the start of the game, which may be set up at a later ply.
+pub fn can_abort(team: Team, history: &ActionHistory, current_turn: &CurrentTurn) -> bool {
+    let turns_played = history.len();
+    // the team that played first, counting back from the team to move
+    let first_team = match turns_played % 2 {
+        0 => current_turn.0,
+        _ => current_turn.0.get_next(),
+    };
+    let first_turn = if team == first_team { 0 } else { 1 };
+    turns_played <= first_turn
+}
+
+// The team a client acts for, which is the team to move when it plays both teams
+fn acting_team(teams: &[Team], current_turn: Team) -> Option<Team> {
+    if teams.contains(&current_turn) {
+        Some(current_turn)
+    } else {
+        teams.first().copied()
+    }
+}
+
+fn opponent_is_bot(team: Team, game: Entity, player_query: &PlayerQuery) -> bool {
+    player_query
+        .iter()
+        .any(|(player_team, in_game, _, is_bot)| {
+            in_game.0 == game && *player_team != team && is_bot
+        })
+}
+
+// Offers expire once the opponent has played, and when the game ends
+pub(super) fn expire_draw_offers(
+    mut commands: Commands,
+    game_query: Query<(Entity, &Ply, &DrawOffer, Has<GameOver>)>,
+) {
+    for (game, ply, offer, is_over) in game_query.iter() {
+        if is_over || *ply >= offer.expires_at {
+            commands.entity(game).remove::<DrawOffer>();
+        }
+    }
+}
+
+pub(super) fn handle_resignations(
+    mut commands: Commands,
+    game_query: ActiveGameQuery,
+    player_query: PlayerQuery,
+    mut resignations: EventReader<FromClient<ResignGameEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: ResignGameEvent { game },
+    } in resignations.read()
+    {
+        let Ok((_, _, current_turn, _)) = game_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} is not being played");
+            continue;
+        };
+        let teams = client_teams(*client_id, *game, &player_query);
+        let Some(team) = acting_team(&teams, current_turn.0) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} is not playing in game {game}");
+            continue;
+        };
+        #[cfg(feature = "log")]
+        bevy_log::info!("{team:?} resigned game {game}");
+        commands
+            .entity(*game)
+            .insert(GameOver::win(team.get_next(), GameOverReason::Resignation));
+    }
+}
+
+pub(super) fn handle_aborts(
+    mut commands: Commands,
+    game_query: ActiveGameQuery,
+    player_query: PlayerQuery,
+    mut aborts: EventReader<FromClient<AbortGameEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: AbortGameEvent { game },
+    } in aborts.read()
+    {
+        let Ok((_, history, current_turn, _)) = game_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} is not being played");
+            continue;
+        };
+        let teams = client_teams(*client_id, *game, &player_query);
+        if !teams
+            .iter()
+            .any(|team| can_abort(*team, history, current_turn))
+        {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} cannot abort game {game}");
+            continue;
+        }
+        #[cfg(feature = "log")]
+        bevy_log::info!("Game {game} aborted");
+        commands.entity(*game).insert(GameOver::aborted());
+    }
+}
+
+pub(super) fn handle_draw_offers(
+    mut commands: Commands,
+    game_query: ActiveGameQuery,
+    player_query: PlayerQuery,
+    mut draw_offers: EventReader<FromClient<OfferDrawEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: OfferDrawEvent { game },
+    } in draw_offers.read()
+    {
+        let Ok((ply, _, current_turn, offer)) = game_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} is not being played");
+            continue;
+        };
+        let teams = client_teams(*client_id, *game, &player_query);
+        let Some(team) = acting_team(&teams, current_turn.0) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} is not playing in game {game}");
+            continue;
+        };
+
+        // offering a draw to an opponent who offered one accepts it,
+        // as do players offering themselves in local games
+        if offer.is_some_and(|offer| offer.team != team) || teams.contains(&team.get_next()) {
+            #[cfg(feature = "log")]
+            bevy_log::info!("Draw agreed in game {game}");
+            commands
+                .entity(*game)
+                .insert(GameOver::draw(GameOverReason::DrawAgreed));
+        } else if opponent_is_bot(team, *game, &player_query) {
+            // bots play on
+            #[cfg(feature = "log")]
+            bevy_log::info!("The bot declined {team:?}'s draw offer in game {game}");
+        } else {
+            // the offer stands until the opponent plays their next turn
+            let turns_left = if current_turn.0 == team { 2 } else { 1 };
+            #[cfg(feature = "log")]
+            bevy_log::info!("{team:?} offered a draw in game {game}");
+            commands.entity(*game).insert(DrawOffer {
+                team,
+                expires_at: Ply::new(ply.get() + turns_left),
+            });
+        }
+    }
+}
+
+pub(super) fn handle_draw_answers(
+    mut commands: Commands,
+    game_query: ActiveGameQuery,
+    player_query: PlayerQuery,
+    mut draw_answers: EventReader<FromClient<AnswerDrawEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: AnswerDrawEvent { game, accept },
+    } in draw_answers.read()
+    {
+        let Ok((_, _, _, Some(offer))) = game_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} has no draw offer to answer");
+            continue;
+        };
+        // only the opponent of the offering team can answer
+        if !client_teams(*client_id, *game, &player_query)
+            .iter()
+            .any(|team| *team != offer.team)
+        {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} cannot answer the draw offer");
+            continue;
+        }
+
+        commands.entity(*game).remove::<DrawOffer>();
+        if *accept {
+            #[cfg(feature = "log")]
+            bevy_log::info!("Draw agreed in game {game}");
+            commands
+                .entity(*game)
+                .insert(GameOver::draw(GameOverReason::DrawAgreed));
+        }
+    }
+}
+
+pub(super) fn handle_rematch_requests(
+    mut commands: Commands,
+    request_query: Query<Option<&RematchRequest>, (With<GameOver>, Without<Analysis>)>,
+    rematch_query: RematchQuery,
+    player_query: PlayerQuery,
+    game_entities: Query<(Entity, &InGame, Option<&Team>, Has<Player>)>,
+    mut rematch_requests: EventReader<FromClient<RequestRematchEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: RequestRematchEvent { game },
+    } in rematch_requests.read()
+    {
+        let Ok(request) = request_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} is not over");
+            continue;
+        };
+        let teams = client_teams(*client_id, *game, &player_query);
+        let Some(team) = teams.first().copied() else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} did not play in game {game}");
+            continue;
+        };
+
+        // requesting a rematch from an opponent who requested one accepts it;
+        // bots always accept, as do players asking themselves in local games
+        if request.is_some_and(|request| request.team != team)
+            || teams.contains(&team.get_next())
+            || opponent_is_bot(team, *game, &player_query)
+        {
+            start_rematch(&mut commands, *game, &rematch_query, &game_entities);
+        } else {
+            #[cfg(feature = "log")]
+            bevy_log::info!("{team:?} requested a rematch of game {game}");
+            commands.entity(*game).insert(RematchRequest { team });
+        }
+    }
+}
+
+pub(super) fn handle_rematch_answers(
+    mut commands: Commands,
+    request_query: Query<&RematchRequest, With<GameOver>>,
+    rematch_query: RematchQuery,
+    player_query: PlayerQuery,
+    game_entities: Query<(Entity, &InGame, Option<&Team>, Has<Player>)>,
+    mut rematch_answers: EventReader<FromClient<AnswerRematchEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: AnswerRematchEvent { game, accept },
+    } in rematch_answers.read()
+    {
+        let Ok(request) = request_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} has no rematch request to answer");
+            continue;
+        };
+        // only the opponent of the requester can answer
+        if !client_teams(*client_id, *game, &player_query)
+            .iter()
+            .any(|team| *team != request.team)
+        {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} cannot answer the rematch request");
+            continue;
+        }
+
+        commands.entity(*game).remove::<RematchRequest>();
+        if *accept {
+            start_rematch(&mut commands, *game, &rematch_query, &game_entities);
+        }
+    }
+}
+
+// Spawns the same game from the same position with the players' colors swapped,
+// and despawns the finished game
+fn start_rematch(
+    commands: &mut Commands,
+    game: Entity,
+    rematch_query: &RematchQuery,
+    game_entities: &Query<(Entity, &InGame, Option<&Team>, Has<Player>)>,
+) {
+    let Ok((
+        piece_set,
+        board,
+        win_condition,
+        clock,
+        seed,
+        setup,
+        is_atomic,
+        is_crazyhouse,
+        is_anti,
+    )) = rematch_query.get(game)
+    else {
+        #[cfg(feature = "log")]
+        bevy_log::warn!("Failed to find game data for {game}");
+        return;
+    };
+    let player = |team: Team| {
+        game_entities
+            .iter()
+            .find(|(_, in_game, player_team, is_player)| {
+                *is_player && in_game.0 == game && *player_team == Some(&team)
+            })
+            .map(|(player, _, _, _)| player)
+    };
+    let (Some(white), Some(black)) = (player(Team::White), player(Team::Black)) else {
+        #[cfg(feature = "log")]
+        bevy_log::warn!("Failed to find the players of game {game}");
+        return;
+    };
+
+    let mut spawn_game = SpawnGame::new(piece_set.clone())
+        .with_players(black, white)
+        .with_board(*board)
+        .with_win_condition(win_condition.clone())
+        .with_seed(seed.cloned())
+        .with_clock(clock.map(|clock| clock.clock.clone()));
+    if let Some(setup) = setup {
+        spawn_game = spawn_game.with_setup(setup.0.clone());
+    }
+    if is_atomic {
+        spawn_game = spawn_game.atomic();
+    }
+    if is_crazyhouse {
+        spawn_game = spawn_game.crazyhouse();
+    }
+    if is_anti {
+        spawn_game = spawn_game.anti_game();
+    }
+
+    // the players join the new game, and everything else of the old game is despawned
+    for (entity, in_game, _, is_player) in game_entities.iter() {
+        if in_game.0 == game && !is_player {
+            commands.entity(entity).despawn();
+        }
+    }
+    commands.entity(game).despawn();
+
+    #[cfg(feature = "log")]
+    bevy_log::info!("Starting a rematch of game {game}");
+    commands.trigger(spawn_game);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::prelude::{Event, Events, IntoSystemConfigs, Schedule, World};
+    use bevy_replicon::prelude::ClientId;
+    use chess::{actions::Action, board::Board, position::Position};
+    use replication::Client;
+
+    use super::*;
+
+    fn white_client() -> ClientId {
+        ClientId::new(1)
+    }
+
+    fn black_client() -> ClientId {
+        ClientId::new(2)
+    }
+
+    struct ResultsTest {
+        world: World,
+        schedule: Schedule,
+        white: Entity,
+        black: Entity,
+    }
+
+    impl ResultsTest {
+        // A game between two clients, run by the result systems
+        fn new(spawn_game: SpawnGame) -> Self {
+            let mut world = World::new();
+            world.observe(SpawnGame::observer);
+            world.init_resource::<Events<FromClient<ResignGameEvent>>>();
+            world.init_resource::<Events<FromClient<AbortGameEvent>>>();
+            world.init_resource::<Events<FromClient<OfferDrawEvent>>>();
+            world.init_resource::<Events<FromClient<AnswerDrawEvent>>>();
+            world.init_resource::<Events<FromClient<RequestRematchEvent>>>();
+            world.init_resource::<Events<FromClient<AnswerRematchEvent>>>();
+            let white = world.spawn(Client { id: white_client() }).id();
+            let black = world.spawn(Client { id: black_client() }).id();
+            world.trigger(spawn_game.with_players(white, black));
+            world.flush();
+
+            let mut schedule = Schedule::default();
+            schedule.add_systems(
+                (
+                    expire_draw_offers,
+                    handle_resignations,
+                    handle_aborts,
+                    handle_draw_offers,
+                    handle_draw_answers,
+                    handle_rematch_requests,
+                    handle_rematch_answers,
+                )
+                    .chain(),
+            );
+            Self {
+                world,
+                schedule,
+                white,
+                black,
+            }
+        }
+
+        fn game(&mut self) -> Entity {
+            self.world
+                .query_filtered::<Entity, With<Game>>()
+                .single(&self.world)
+        }
+
+        fn send<E: Event>(&mut self, client_id: ClientId, event: E) {
+            self.world.send_event(FromClient { client_id, event });
+            self.schedule.run(&mut self.world);
+            self.world.flush();
+        }
+
+        fn play_turns(&mut self, turns: usize) {
+            let game = self.game();
+            let mut ply = self.world.get_mut::<Ply>(game).unwrap();
+            let next = Ply::new(ply.get() + turns);
+            *ply = next;
+            self.schedule.run(&mut self.world);
+        }
+    }
+
+    #[test]
+    fn test_can_abort() {
+        // a history of the given number of turns
+        let history = |turns: usize| {
+            let mut history = ActionHistory::default();
+            for _ in 0..turns {
+                history.push(Entity::PLACEHOLDER, Action::default());
+            }
+            history
+        };
+        let white_to_move = CurrentTurn(Team::White);
+        let black_to_move = CurrentTurn(Team::Black);
+        assert!(can_abort(Team::White, &history(0), &white_to_move));
+        assert!(can_abort(Team::Black, &history(0), &white_to_move));
+        assert!(!can_abort(Team::White, &history(1), &black_to_move));
+        assert!(can_abort(Team::Black, &history(1), &black_to_move));
+        assert!(!can_abort(Team::Black, &history(2), &white_to_move));
+
+        // games set up with Black to move
+        assert!(!can_abort(Team::Black, &history(1), &white_to_move));
+        assert!(can_abort(Team::White, &history(1), &white_to_move));
+    }
+
+    #[test]
+    fn test_resign() {
+        let mut test = ResultsTest::new(SpawnGame::new(PieceSet::default()));
+        let game = test.game();
+
+        // clients can only resign the games they play in
+        test.send(ClientId::new(3), ResignGameEvent { game });
+        assert!(test.world.get::<GameOver>(game).is_none());
+
+        // either team can resign, on its turn or not
+        test.send(black_client(), ResignGameEvent { game });
+        assert_eq!(
+            test.world.get::<GameOver>(game),
+            Some(&GameOver::win(Team::White, GameOverReason::Resignation))
+        );
+    }
+
+    #[test]
+    fn test_draw_offers() {
+        let mut test = ResultsTest::new(SpawnGame::new(PieceSet::default()));
+        let game = test.game();
+
+        // White's offer stands while it plays its turn, and expires once Black plays
+        test.send(white_client(), OfferDrawEvent { game });
+        assert_eq!(test.world.get::<DrawOffer>(game).unwrap().team, Team::White);
+        test.play_turns(1);
+        assert!(test.world.get::<DrawOffer>(game).is_some());
+        test.play_turns(1);
+        assert!(test.world.get::<DrawOffer>(game).is_none());
+
+        // the offering team cannot accept its own offer, and a declined offer is removed
+        test.send(white_client(), OfferDrawEvent { game });
+        test.send(white_client(), AnswerDrawEvent { game, accept: true });
+        assert!(test.world.get::<GameOver>(game).is_none());
+        test.send(
+            black_client(),
+            AnswerDrawEvent {
+                game,
+                accept: false,
+            },
+        );
+        assert!(test.world.get::<DrawOffer>(game).is_none());
+        assert!(test.world.get::<GameOver>(game).is_none());
+
+        // the opponent accepts by answering the offer
+        test.send(white_client(), OfferDrawEvent { game });
+        test.send(black_client(), AnswerDrawEvent { game, accept: true });
+        let game_over = test.world.get::<GameOver>(game).unwrap();
+        assert!(game_over.is_draw());
+        assert_eq!(game_over.reason(), GameOverReason::DrawAgreed);
+    }
+
+    #[test]
+    fn test_draw_offered_back() {
+        let mut test = ResultsTest::new(SpawnGame::new(PieceSet::default()));
+        let game = test.game();
+
+        // offering a draw to the team that offered one accepts it
+        test.send(white_client(), OfferDrawEvent { game });
+        test.send(black_client(), OfferDrawEvent { game });
+        assert_eq!(
+            test.world.get::<GameOver>(game),
+            Some(&GameOver::draw(GameOverReason::DrawAgreed))
+        );
+        // and the offer expires with the game
+        test.schedule.run(&mut test.world);
+        assert!(test.world.get::<DrawOffer>(game).is_none());
+    }
+
+    #[test]
+    fn test_rematch() {
+        let mut setup = Position::new(Board::default());
+        setup.side_to_move = Team::Black;
+        setup.ply = 5;
+        let mut test =
+            ResultsTest::new(SpawnGame::new(PieceSet::default()).with_setup(setup.clone()));
+        let game = test.game();
+
+        // rematches can only be requested once the game is over
+        test.send(white_client(), RequestRematchEvent { game });
+        assert!(test.world.get::<RematchRequest>(game).is_none());
+        test.world
+            .entity_mut(game)
+            .insert(GameOver::new(Team::White));
+
+        test.send(white_client(), RequestRematchEvent { game });
+        assert_eq!(
+            test.world.get::<RematchRequest>(game).unwrap().team,
+            Team::White
+        );
+        test.send(black_client(), AnswerRematchEvent { game, accept: true });
+
+        // the players swap colors in a new game from the same position
+        let rematch = test.game();
+        assert_ne!(rematch, game);
+        assert_eq!(test.world.get::<Team>(test.white), Some(&Team::Black));
+        assert_eq!(test.world.get::<Team>(test.black), Some(&Team::White));
+        for player in [test.white, test.black] {
+            assert_eq!(test.world.get::<InGame>(player).unwrap().0, rematch);
+        }
+        assert_eq!(
+            test.world.get::<CurrentTurn>(rematch).unwrap().0,
+            setup.side_to_move
+        );
+        assert_eq!(test.world.get::<Ply>(rematch).unwrap().get(), setup.ply);
+        assert!(test.world.get::<GameSetup>(rematch).is_some());
+        assert!(test.world.get::<GameOver>(rematch).is_none());
+    }
+}
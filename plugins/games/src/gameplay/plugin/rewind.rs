@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+
+use bevy_ecs::{
+    entity::MapEntities,
+    observer::Trigger,
+    prelude::{Changed, Commands, Component, Entity, EntityMapper, Event, Query, With, Without},
+};
+
+use chess::{
+    actions::LastAction,
+    board::Board,
+    pieces::{PieceDefinition, Position},
+    position,
+};
+
+use crate::{
+    components::{
+        insert_piece_definition, remove_piece_definition, ActionHistory, CurrentTurn, Game,
+        GameOver, InGame, Player, Ply, TruncateHistory, VariationTree,
+    },
+    snapshot_position, Clock, PieceSnapshotQuery,
+};
+
+// The state of a game at the start of a ply
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+pub struct GameSnapshot {
+    pub position: position::Position,
+    pub clocks: Vec<(Entity, Clock)>,
+}
+
+// A copy of the game at every ply played so far, starting from the ply of the first snapshot,
+// which is not zero for games started from a position.
+// Only kept where games are authoritative, since it is used to rewind games.
+#[derive(Clone, Debug, Default)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+pub struct GameSnapshots(Vec<GameSnapshot>);
+
+impl GameSnapshots {
+    pub fn get(&self, ply: Ply) -> Option<&GameSnapshot> {
+        let index = ply.get().checked_sub(self.first_ply().get())?;
+        self.0.get(index)
+    }
+
+    // The ply the game started from
+    pub fn first_ply(&self) -> Ply {
+        Ply::new(self.0.first().map_or(0, |snapshot| snapshot.position.ply))
+    }
+}
+
+impl MapEntities for GameSnapshots {
+    fn map_entities<M: EntityMapper>(&mut self, mapper: &mut M) {
+        for snapshot in self.0.iter_mut() {
+            snapshot.position.map_entities(mapper);
+            for (player, _) in snapshot.clocks.iter_mut() {
+                *player = mapper.map_entity(*player);
+            }
+        }
+    }
+}
+
+// Records a snapshot every time the ply of a game changes.
+// This runs after the behaviors systems so that lost castling rights are part of the snapshot.
+#[allow(clippy::type_complexity)]
+pub(super) fn record_snapshots(
+    mut commands: Commands,
+    mut game_query: Query<
+        (
+            Entity,
+            &Ply,
+            &CurrentTurn,
+            Option<&LastAction>,
+            Option<&mut GameSnapshots>,
+        ),
+        (With<Game>, Changed<Ply>),
+    >,
+    board_query: Query<(&Board, &InGame)>,
+    piece_query: Query<PieceSnapshotQuery>,
+    clock_query: Query<(Entity, &InGame, &Clock), With<Player>>,
+) {
+    for (game, ply, current_turn, last_action, snapshots) in game_query.iter_mut() {
+        let Some((board, _)) = board_query.iter().find(|(_, in_game)| in_game.0 == game) else {
+            continue;
+        };
+        let snapshot = GameSnapshot {
+            position: snapshot_position(
+                board,
+                current_turn,
+                ply,
+                last_action,
+                piece_query.iter().filter(|piece| piece.in_game.0 == game),
+            ),
+            clocks: clock_query
+                .iter()
+                .filter(|(_, in_game, _)| in_game.0 == game)
+                .map(|(player, _, clock)| (player, clock.clone()))
+                .collect(),
+        };
+        if let Some(mut snapshots) = snapshots {
+            // any snapshots after this ply belong to a line that was abandoned,
+            // and a ply before the first snapshot starts the game over, as when editing a position
+            let index = ply.get().saturating_sub(snapshots.first_ply().get());
+            snapshots.0.truncate(index);
+            if snapshots.0.len() == index {
+                snapshots.0.push(snapshot);
+            }
+        } else {
+            commands.entity(game).insert(GameSnapshots(vec![snapshot]));
+        }
+    }
+}
+
+// Restores a game to the state it was in at `ply`.
+// Turns played after `ply` are forgotten, so playing a turn afterwards starts a new line.
+// The game's `VariationTree` keeps the abandoned turns as a variation.
+#[derive(Clone, Copy, Debug)]
+#[derive(Event)]
+pub struct RewindGame {
+    pub game: Entity,
+    pub ply: Ply,
+}
+
+impl RewindGame {
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn observer(
+        trigger: Trigger<RewindGame>,
+        mut commands: Commands,
+        mut game_query: Query<
+            (
+                &mut Ply,
+                &mut CurrentTurn,
+                &mut ActionHistory,
+                Option<&mut VariationTree>,
+                Option<&mut LastAction>,
+                &GameSnapshots,
+            ),
+            With<Game>,
+        >,
+        mut board_query: Query<
+            (Entity, &InGame, Option<&mut LastAction>),
+            (With<Board>, Without<Game>),
+        >,
+        piece_query: Query<PieceSnapshotQuery>,
+        mut clock_query: Query<&mut Clock, With<Player>>,
+    ) {
+        let RewindGame { game, ply } = *trigger.event();
+
+        let Ok((
+            mut game_ply,
+            mut current_turn,
+            mut action_history,
+            variation_tree,
+            game_last_action,
+            snapshots,
+        )) = game_query.get_mut(game)
+        else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Failed to find game {game}");
+            return;
+        };
+        let Some(GameSnapshot {
+            position: snapshot,
+            clocks,
+        }) = snapshots.get(ply)
+        else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} has no snapshot of ply {ply:?}");
+            return;
+        };
+
+        #[cfg(feature = "log")]
+        bevy_log::info!("Rewinding game {game} from {:?} to {ply:?}", *game_ply);
+
+        for piece in piece_query.iter().filter(|piece| piece.in_game.0 == game) {
+            let Some(snapshot_piece) = snapshot.piece(piece.entity) else {
+                // the piece had been captured by then
+                if piece.position.is_some() {
+                    commands.entity(piece.entity).remove::<Position>();
+                }
+                continue;
+            };
+            let Some(square) = snapshot_piece.square else {
+                continue;
+            };
+
+            let definition_changed =
+                !same_definition(&piece.definition(), &snapshot_piece.definition);
+            if definition_changed {
+                let mut piece_builder = commands.entity(piece.entity);
+                remove_piece_definition(&mut piece_builder);
+                insert_piece_definition(&mut piece_builder, &snapshot_piece.definition);
+            }
+            // Position is re-inserted rather than changed so that restored pieces do not
+            // lose their castling rights, and so that the change is replicated
+            if definition_changed || piece.position != Some(&Position(square)) {
+                commands
+                    .entity(piece.entity)
+                    .remove::<Position>()
+                    .insert(Position(square));
+            }
+        }
+
+        for (player, snapshot_clock) in clocks.iter() {
+            if let Ok(mut clock) = clock_query.get_mut(*player) {
+                *clock = snapshot_clock.clone();
+            }
+        }
+
+        action_history.truncate(ply.get() - snapshots.first_ply().get());
+        *game_ply = ply;
+        current_turn.0 = snapshot.side_to_move;
+        // the turns after `ply` are kept in the variation tree
+        if let Some(mut variation_tree) = variation_tree {
+            variation_tree.rewind(ply);
+        }
+        commands.entity(game).remove::<GameOver>();
+
+        match (&snapshot.last_action, game_last_action) {
+            (Some(action), Some(mut last_action)) => last_action.0 = action.clone(),
+            (Some(action), None) => {
+                commands.entity(game).insert(LastAction(action.clone()));
+            }
+            (None, _) => {
+                commands.entity(game).remove::<LastAction>();
+            }
+        }
+        for (board, in_game, board_last_action) in board_query.iter_mut() {
+            if in_game.0 != game {
+                continue;
+            }
+            match (&snapshot.last_action, board_last_action) {
+                (Some(action), Some(mut last_action)) => last_action.0 = action.clone(),
+                (Some(action), None) => {
+                    commands.entity(board).insert(LastAction(action.clone()));
+                }
+                (None, _) => {
+                    commands.entity(board).remove::<LastAction>();
+                }
+            }
+        }
+
+        commands.trigger(TruncateHistory { game, ply });
+    }
+}
+
+// Whether two pieces would behave the same way
+fn same_definition(definition: &PieceDefinition, other: &PieceDefinition) -> bool {
+    definition.identity == other.identity
+        && definition.royal.is_some() == other.royal.is_some()
+        && definition.mutation.is_some() == other.mutation.is_some()
+        && definition.behaviors.pattern == other.behaviors.pattern
+        && definition.behaviors.relay == other.behaviors.relay
+        && definition.behaviors.en_passant.is_some() == other.behaviors.en_passant.is_some()
+        && definition.behaviors.castling.is_some() == other.behaviors.castling.is_some()
+        && definition.behaviors.castling_target.is_some()
+            == other.behaviors.castling_target.is_some()
+}
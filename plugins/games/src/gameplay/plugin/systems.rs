@@ -1,16 +1,17 @@
-use bevy_ecs::prelude::{Commands, Entity, EventReader, EventWriter, Query, With};
+use bevy_ecs::prelude::{Commands, Entity, EventReader, EventWriter, Has, Query, With};
 
 use bevy_replicon::prelude::{ClientId, FromClient, SendMode, ToClients};
 
 use chess::{
     board::{Board, OnBoard},
-    pieces::{Mutation, MutationCondition, Position, Royal},
+    pieces::{Mutation, PieceIdentity, Position, Royal},
     team::Team,
 };
 use replication::Client;
 
 use crate::{
-    components::{CurrentTurn, InGame, IsActiveGame, Ply, WinCondition},
+    bot::Bot,
+    components::{Analysis, CurrentTurn, InGame, IsActiveGame, Ply, WinCondition},
     gameplay::components::GameOver,
 };
 
@@ -18,9 +19,10 @@ use super::{PlayTurn, RequestTurnEvent, RequireMutationEvent};
 
 pub(super) fn trigger_turns(
     mut commands: Commands,
-    game_query: Query<(&Ply, &CurrentTurn), IsActiveGame>,
+    game_query: Query<(&Ply, &CurrentTurn, Has<Analysis>), IsActiveGame>,
     board_query: Query<&Board>,
-    player_query: Query<(&Team, &InGame, Option<&Client>)>,
+    // bots request their turns as `Bot::client_id`, see `BotPlugin`
+    player_query: Query<(&Team, &InGame, Option<&Client>, Has<Bot>)>,
     piece_query: Query<(&Team, &OnBoard, Option<&Mutation>)>,
     mut requested_turns: EventReader<FromClient<RequestTurnEvent>>,
     mut require_mutation_writer: EventWriter<ToClients<RequireMutationEvent>>,
@@ -37,7 +39,7 @@ pub(super) fn trigger_turns(
     } in requested_turns.read()
     {
         // is there a game instance?
-        let Ok((ply, current_turn)) = game_query.get(*game) else {
+        let Ok((ply, current_turn, is_analysis)) = game_query.get(*game) else {
             #[cfg(feature = "log")]
             bevy_log::warn!("Failed to find game data for {game}");
             continue;
@@ -49,11 +51,23 @@ pub(super) fn trigger_turns(
             continue;
         };
         // get the player data
-        let Some((player_team, in_game, player)) =
-            player_query.iter().find(|(player_team, _, player)| {
-                player.map(|client| client.id).unwrap_or(ClientId::SERVER) == *client_id
-                    && **player_team == current_turn.0
-            })
+        // in analysis games, the player plays for both teams
+        let Some((_, in_game, player, _)) =
+            player_query
+                .iter()
+                .find(|(player_team, in_game, player, is_bot)| {
+                    let player_id = match (player, is_bot) {
+                        (_, true) => Bot::client_id(),
+                        (Some(client), false) => client.id,
+                        (None, false) => ClientId::SERVER,
+                    };
+                    player_id == *client_id
+                        && if is_analysis {
+                            in_game.0 == *game
+                        } else {
+                            **player_team == current_turn.0
+                        }
+                })
         else {
             #[cfg(feature = "log")]
             bevy_log::warn!(
@@ -63,103 +77,102 @@ pub(super) fn trigger_turns(
             continue;
         };
         // is the piece owned by the player?
-        if piece_team != player_team {
+        if *piece_team != current_turn.0 {
             #[cfg(feature = "log")]
             bevy_log::warn!(
-                "Piece {piece} is owned by team {piece_team:?}, not team {player_team:?}",
+                "Piece {piece} is owned by team {piece_team:?}, not team {:?}",
+                current_turn.0
             );
             continue;
         }
 
-        let mut turn = None;
-        if let Some(mutation) = mutation {
-            let Ok(board) = board_query.get(on_board.0) else {
-                #[cfg(feature = "log")]
-                bevy_log::warn!("Failed to find board {}", on_board.0);
+        // a triggered mutation is always applied, as in `Position`
+        let options = match mutation {
+            Some(mutation) => {
+                let Ok(board) = board_query.get(on_board.0) else {
+                    #[cfg(feature = "log")]
+                    bevy_log::warn!("Failed to find board {}", on_board.0);
+                    continue;
+                };
+                mutation.options(action, piece_team, board)
+            }
+            None => &[],
+        };
+        let mutated_piece = match (options, promotion) {
+            ([], _) => None,
+            ([only], _) => Some(only.clone()),
+            (_, Some(promotion)) => Some(promotion.clone()),
+            (_, None) => {
+                require_mutation_writer.send(ToClients {
+                    mode: player
+                        .map(|player| SendMode::Direct(player.id))
+                        .unwrap_or(SendMode::Broadcast),
+                    event: RequireMutationEvent {
+                        piece: *piece,
+                        game: *game,
+                        action: action.clone(),
+                    },
+                });
                 continue;
-            };
-            match mutation.condition {
-                MutationCondition::LocalRank(rank) => {
-                    let reoriented_rank = action
-                        .movement
-                        .to
-                        .reorient(piece_team.orientation(), board)
-                        .rank;
-                    if rank != reoriented_rank {
-                        turn = Some(PlayTurn::action(
-                            *ply,
-                            *piece,
-                            on_board.0,
-                            in_game.0,
-                            action.clone(),
-                        ));
-                    } else if mutation.to_piece.len() == 1 {
-                        turn = Some(PlayTurn::mutation(
-                            *ply,
-                            *piece,
-                            on_board.0,
-                            in_game.0,
-                            action.clone(),
-                            mutation.to_piece.first().unwrap().clone(),
-                        ));
-                    } else if let Some(promotion) = promotion {
-                        turn = Some(PlayTurn::mutation(
-                            *ply,
-                            *piece,
-                            on_board.0,
-                            in_game.0,
-                            action.clone(),
-                            promotion.clone(),
-                        ));
-                    } else {
-                        require_mutation_writer.send(ToClients {
-                            mode: player
-                                .map(|player| SendMode::Direct(player.id))
-                                .unwrap_or(SendMode::Broadcast),
-                            event: RequireMutationEvent {
-                                piece: *piece,
-                                game: *game,
-                                action: action.clone(),
-                            },
-                        });
-                    }
-                }
-                MutationCondition::OnCapture => {
-                    unimplemented!("OnCapture promotion not yet implemented");
-                }
             }
-        } else {
-            turn = Some(PlayTurn::action(
-                *ply,
-                *piece,
-                on_board.0,
-                in_game.0,
-                action.clone(),
-            ));
-        }
+        };
 
-        if let Some(turn) = turn {
-            commands.trigger(turn);
-        }
+        let action = action.clone();
+        commands.trigger(match mutated_piece {
+            Some(mutated_piece) => {
+                PlayTurn::mutation(*ply, *piece, on_board.0, in_game.0, action, mutated_piece)
+            }
+            None => PlayTurn::action(*ply, *piece, on_board.0, in_game.0, action),
+        });
     }
 }
 
+type PieceQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static InGame,
+        &'static Team,
+        Option<&'static Position>,
+        Has<Royal>,
+    ),
+    With<PieceIdentity>,
+>;
+
 pub(super) fn detect_gameover(
     mut commands: Commands,
     game_query: Query<(Entity, &WinCondition), IsActiveGame>,
-    royal_query: Query<(&InGame, &Team, Option<&Position>), With<Royal>>,
+    piece_query: PieceQuery,
 ) {
     for (game_entity, win_condition) in game_query.iter() {
+        // an army without royal pieces is lost once all of its pieces are captured
+        let all_pieces_captured = |current_team: Team| {
+            let pieces = || {
+                piece_query.iter().filter(move |(in_game, team, _, _)| {
+                    in_game.0 == game_entity && **team == current_team
+                })
+            };
+            if pieces().any(|(_, _, _, is_royal)| is_royal) {
+                None
+            } else {
+                Some(pieces().all(|(_, _, position, _)| position.is_none()))
+            }
+        };
         match win_condition {
             WinCondition::RoyalCaptureAll => {
                 let all_captured = |current_team: Team| {
-                    royal_query
-                        .iter()
-                        .filter(|(in_game, team, position)| {
-                            in_game.0 == game_entity && **team == current_team && position.is_some()
-                        })
-                        .count()
-                        == 0
+                    all_pieces_captured(current_team).unwrap_or_else(|| {
+                        piece_query
+                            .iter()
+                            .filter(|(in_game, team, position, is_royal)| {
+                                in_game.0 == game_entity
+                                    && **team == current_team
+                                    && *is_royal
+                                    && position.is_some()
+                            })
+                            .count()
+                            == 0
+                    })
                 };
                 if all_captured(Team::White) {
                     #[cfg(feature = "log")]
@@ -178,13 +191,18 @@ pub(super) fn detect_gameover(
             }
             WinCondition::RoyalCapture => {
                 let any_captured = |current_team: Team| {
-                    royal_query
-                        .iter()
-                        .filter(|(in_game, team, position)| {
-                            in_game.0 == game_entity && **team == current_team && position.is_none()
-                        })
-                        .count()
-                        > 0
+                    all_pieces_captured(current_team).unwrap_or_else(|| {
+                        piece_query
+                            .iter()
+                            .filter(|(in_game, team, position, is_royal)| {
+                                in_game.0 == game_entity
+                                    && **team == current_team
+                                    && *is_royal
+                                    && position.is_none()
+                            })
+                            .count()
+                            > 0
+                    })
                 };
                 if any_captured(Team::White) {
                     #[cfg(feature = "log")]
@@ -0,0 +1,163 @@
+use bevy_ecs::prelude::{Commands, Entity, EventReader, Has, Query, With};
+
+use bevy_replicon::prelude::{ClientId, FromClient};
+
+use chess::team::Team;
+use replication::Client;
+
+use crate::{
+    components::{
+        ActionHistory, CurrentTurn, GameOver, InGame, IsActiveGame, Player, Ply, TakebackRequest,
+    },
+    Bot,
+};
+
+use super::{AnswerTakebackEvent, RequestTakebackEvent, RewindGame};
+
+pub(super) type PlayerQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Team,
+        &'static InGame,
+        Option<&'static Client>,
+        Has<Bot>,
+    ),
+    With<Player>,
+>;
+
+// The teams played by the client in the game.
+// Bots play on behalf of the server, but they never ask for takebacks.
+pub(super) fn client_teams(
+    client_id: ClientId,
+    game: Entity,
+    player_query: &PlayerQuery,
+) -> Vec<Team> {
+    player_query
+        .iter()
+        .filter(|(_, in_game, client, is_bot)| {
+            in_game.0 == game
+                && !is_bot
+                && client.map(|client| client.id).unwrap_or(ClientId::SERVER) == client_id
+        })
+        .map(|(team, _, _, _)| *team)
+        .collect()
+}
+
+// Requests expire once another turn is played or the game ends
+pub(super) fn expire_takeback_requests(
+    mut commands: Commands,
+    game_query: Query<(Entity, &Ply, &TakebackRequest, Has<GameOver>)>,
+) {
+    for (game, ply, request, is_over) in game_query.iter() {
+        if is_over || request.requested_at != *ply {
+            commands.entity(game).remove::<TakebackRequest>();
+        }
+    }
+}
+
+pub(super) fn handle_takeback_requests(
+    mut commands: Commands,
+    // finished games stay finished, since rewinding would bring them back to life
+    game_query: Query<(&Ply, &ActionHistory, &CurrentTurn), IsActiveGame>,
+    player_query: PlayerQuery,
+    mut takeback_requests: EventReader<FromClient<RequestTakebackEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: RequestTakebackEvent { game },
+    } in takeback_requests.read()
+    {
+        let Ok((ply, history, current_turn)) = game_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Failed to find active game {game}");
+            continue;
+        };
+        let teams = client_teams(*client_id, *game, &player_query);
+        if teams.is_empty() {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} is not playing in game {game}");
+            continue;
+        }
+
+        // take back the last turn played by the requester,
+        // along with the opponent's reply if there was one
+        let last_team = current_turn.0.get_next();
+        let (team, plies) = if teams.contains(&last_team) {
+            (last_team, 1)
+        } else {
+            (current_turn.0, 2)
+        };
+        // games set up from a position cannot be taken back past their start
+        let Some(target) = ply
+            .get()
+            .checked_sub(plies)
+            .filter(|_| plies <= history.len())
+        else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("{team:?} has no turn to take back in game {game}");
+            continue;
+        };
+        let request = TakebackRequest {
+            team,
+            ply: Ply::new(target),
+            requested_at: *ply,
+        };
+
+        // bots always accept, as do players asking themselves in local games
+        let opponent_accepts = player_query
+            .iter()
+            .filter(|(player_team, in_game, _, _)| in_game.0 == *game && **player_team != team)
+            .all(|(player_team, _, _, is_bot)| is_bot || teams.contains(player_team));
+        if opponent_accepts {
+            #[cfg(feature = "log")]
+            bevy_log::info!("Taking back {team:?}'s turn in game {game}");
+            commands.trigger(RewindGame {
+                game: *game,
+                ply: request.ply,
+            });
+        } else {
+            #[cfg(feature = "log")]
+            bevy_log::info!("{team:?} requested a takeback in game {game}");
+            commands.entity(*game).insert(request);
+        }
+    }
+}
+
+pub(super) fn handle_takeback_answers(
+    mut commands: Commands,
+    game_query: Query<(&Ply, &TakebackRequest), IsActiveGame>,
+    player_query: PlayerQuery,
+    mut takeback_answers: EventReader<FromClient<AnswerTakebackEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: AnswerTakebackEvent { game, accept },
+    } in takeback_answers.read()
+    {
+        let Ok((ply, request)) = game_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Active game {game} has no takeback request to answer");
+            continue;
+        };
+        // only the opponent of the requester can answer
+        if !client_teams(*client_id, *game, &player_query)
+            .iter()
+            .any(|team| *team != request.team)
+        {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} cannot answer the takeback request");
+            continue;
+        }
+
+        commands.entity(*game).remove::<TakebackRequest>();
+        if *accept && request.requested_at == *ply {
+            #[cfg(feature = "log")]
+            bevy_log::info!("Takeback accepted in game {game}");
+            commands.trigger(RewindGame {
+                game: *game,
+                ply: request.ply,
+            });
+        }
+    }
+}
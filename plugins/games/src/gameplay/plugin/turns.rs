@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use bevy_ecs::{
     observer::Trigger,
@@ -13,7 +14,9 @@ use chess::{
     team::Team,
 };
 
-use crate::components::{ActionHistory, Clock, CurrentTurn, Game, InGame, Player, Ply};
+use crate::components::{
+    ActionHistory, Clock, CurrentTurn, Game, InGame, Latency, Player, Ply, VariationTree,
+};
 
 use chess::{actions::Action, pieces::PieceDefinition};
 
@@ -61,6 +64,7 @@ impl PlayTurn {
 }
 
 impl PlayTurn {
+    #[allow(clippy::type_complexity)]
     pub(crate) fn observer(
         trigger: Trigger<PlayTurn>,
         mut commands: Commands,
@@ -69,12 +73,16 @@ impl PlayTurn {
                 &mut CurrentTurn,
                 &mut Ply,
                 &mut ActionHistory,
+                Option<&mut VariationTree>,
                 Option<&mut LastAction>,
             ),
             With<Game>,
         >,
         mut boards: Query<Option<&mut LastAction>, (With<Board>, Without<Game>)>,
-        mut players: Query<(Entity, &Team, Option<&mut Clock>, &InGame), With<Player>>,
+        mut players: Query<
+            (Entity, &Team, Option<&mut Clock>, &InGame, Option<&Latency>),
+            With<Player>,
+        >,
         mut pieces: Query<(Entity, &Team, &mut Position, &OnBoard), With<PieceIdentity>>,
     ) {
         let PlayTurn {
@@ -87,8 +95,13 @@ impl PlayTurn {
         } = trigger.event();
 
         // get the game instance
-        let Ok((mut game_turn, mut game_ply, mut game_action_history, game_last_action)) =
-            games.get_mut(*game)
+        let Ok((
+            mut game_turn,
+            mut game_ply,
+            mut game_action_history,
+            variation_tree,
+            game_last_action,
+        )) = games.get_mut(*game)
         else {
             #[cfg(feature = "log")]
             bevy_log::warn!("Failed to find game {game}");
@@ -231,25 +244,36 @@ impl PlayTurn {
             commands.entity(*board).insert(LastAction(action.clone()));
         }
 
-        // update clocks
-        for (_, team, clock, in_game) in players.iter_mut() {
-            if *game != in_game.0 {
-                continue;
+        // update clocks: stop the mover's clock before starting the opponent's,
+        // which may receive the time the mover spent.
+        // The mover's clock stopped when the server received the turn, less its time in transit.
+        let mut handed_over = Duration::ZERO;
+        for (_, team, clock, in_game, latency) in players.iter_mut() {
+            if *game == in_game.0 && game_turn.0 == *team {
+                if let Some(mut clock) = clock {
+                    if let Some(latency) = latency {
+                        clock.compensate(latency.compensation());
+                    }
+                    handed_over += clock.pause();
+                }
             }
-            if game_turn.0 == *team {
+        }
+        for (_, team, clock, in_game, _) in players.iter_mut() {
+            if *game == in_game.0 && game_turn.0 != *team {
                 if let Some(mut clock) = clock {
-                    clock.pause();
+                    clock.add_time(handed_over);
+                    clock.unpause();
                 }
-            } else if let Some(mut clock) = clock {
-                clock.unpause();
             }
-            game_turn.0 = game_turn.0.get_next();
         }
 
         // TODO: in a future with 4-player, does this lead to bugs?
         if *game_ply == *ply {
             game_action_history.push(*piece, action.clone());
             game_ply.increment();
+            if let Some(mut variation_tree) = variation_tree {
+                variation_tree.play(*piece, action.clone(), mutation.clone());
+            }
         } else {
             #[cfg(feature = "log")]
             bevy_log::warn!(
@@ -0,0 +1,160 @@
+use bevy_ecs::prelude::{Commands, Component, Entity, EventReader, Has, Query, With};
+
+use bevy_replicon::prelude::FromClient;
+
+use chess::board::Board;
+use replication::Client;
+
+use crate::components::{Analysis, Game, GameOver, InGame, NodeId, Player, Ply, VariationTree};
+
+use super::{
+    analysis::can_analyze, turns::PlayTurn, EditVariationEvent, RequestVariationEvent, RewindGame,
+    VariationEdit,
+};
+
+// The turns left to replay to reach a requested variation, one turn per frame
+#[derive(Clone, Debug)]
+#[derive(Component)]
+pub(super) struct PendingVariation(Vec<NodeId>);
+
+pub(super) fn handle_variation_requests(
+    mut commands: Commands,
+    game_query: Query<&VariationTree, (With<Game>, With<Analysis>)>,
+    player_query: Query<(&InGame, Option<&Client>), With<Player>>,
+    mut variation_requests: EventReader<FromClient<RequestVariationEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: RequestVariationEvent { game, node },
+    } in variation_requests.read()
+    {
+        let Ok(variation_tree) = game_query.get(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} is not an analysis game and cannot switch variations");
+            continue;
+        };
+        if !can_analyze(*client_id, *game, &player_query) {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} is not analyzing game {game}");
+            continue;
+        }
+        if variation_tree.get(*node).is_none() {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Game {game} has no variation node {node:?}");
+            continue;
+        }
+
+        // go back to where the lines split, then play the turns leading to the node
+        let ancestor = variation_tree.common_ancestor(variation_tree.current(), *node);
+        let Some(ancestor_ply) = variation_tree.get(ancestor).map(|node| node.ply()) else {
+            continue;
+        };
+        let turns = variation_tree
+            .path_to(*node)
+            .into_iter()
+            .filter(|node| {
+                variation_tree
+                    .get(*node)
+                    .is_some_and(|node| node.ply() > ancestor_ply)
+            })
+            .collect::<Vec<_>>();
+        commands.trigger(RewindGame {
+            game: *game,
+            ply: ancestor_ply,
+        });
+        if turns.is_empty() {
+            commands.entity(*game).remove::<PendingVariation>();
+        } else {
+            commands.entity(*game).insert(PendingVariation(turns));
+        }
+    }
+}
+
+pub(super) fn replay_variations(
+    mut commands: Commands,
+    mut game_query: Query<(Entity, &Ply, &VariationTree, &mut PendingVariation), With<Game>>,
+    board_query: Query<(Entity, &InGame), With<Board>>,
+) {
+    for (game, ply, variation_tree, mut pending) in game_query.iter_mut() {
+        let Some(next) = pending.0.first().copied() else {
+            commands.entity(game).remove::<PendingVariation>();
+            continue;
+        };
+        // stop replaying if the game moved on some other way
+        let Some(node) = variation_tree
+            .get(next)
+            .filter(|node| node.parent() == Some(variation_tree.current()))
+        else {
+            commands.entity(game).remove::<PendingVariation>();
+            continue;
+        };
+        let (Some(piece), Some(action)) = (node.piece, node.action.clone()) else {
+            commands.entity(game).remove::<PendingVariation>();
+            continue;
+        };
+        let Some((board, _)) = board_query.iter().find(|(_, in_game)| in_game.0 == game) else {
+            continue;
+        };
+
+        commands.trigger(PlayTurn {
+            ply: *ply,
+            piece,
+            board,
+            game,
+            action,
+            mutation: node.mutation.clone(),
+        });
+        pending.0.remove(0);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(super) fn handle_variation_edits(
+    mut game_query: Query<(&mut VariationTree, Has<Analysis>, Has<GameOver>), With<Game>>,
+    player_query: Query<(&InGame, Option<&Client>), With<Player>>,
+    mut edit_requests: EventReader<FromClient<EditVariationEvent>>,
+) {
+    for FromClient {
+        client_id,
+        event: EditVariationEvent { game, node, edit },
+    } in edit_requests.read()
+    {
+        let Ok((mut variation_tree, is_analysis, is_over)) = game_query.get_mut(*game) else {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Failed to find variations of game {game}");
+            continue;
+        };
+        if !(is_analysis || is_over) {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("Variations of game {game} cannot be edited while it is played");
+            continue;
+        }
+        if !can_analyze(*client_id, *game, &player_query) {
+            #[cfg(feature = "log")]
+            bevy_log::warn!("ClientId {client_id:?} is not playing in game {game}");
+            continue;
+        }
+
+        match edit {
+            VariationEdit::SetComment(comment) => {
+                if let Some(node) = variation_tree.get_mut(*node) {
+                    node.comment.clone_from(comment);
+                }
+            }
+            VariationEdit::SetAnnotation(annotation) => {
+                if let Some(node) = variation_tree.get_mut(*node) {
+                    node.annotation = *annotation;
+                }
+            }
+            VariationEdit::Promote => {
+                variation_tree.promote(*node);
+            }
+            VariationEdit::Remove => {
+                if !variation_tree.remove(*node) {
+                    #[cfg(feature = "log")]
+                    bevy_log::warn!("Cannot remove the line leading to the current position");
+                }
+            }
+        }
+    }
+}
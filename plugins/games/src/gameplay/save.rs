@@ -0,0 +1,577 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use bevy_core::Name;
+use bevy_ecs::{
+    entity::{EntityHashMap, MapEntities},
+    observer::Trigger,
+    prelude::{Commands, Entity, EntityMapper, Event, With, World},
+};
+
+use bevy_replicon::prelude::Replicated;
+
+use chess::{
+    actions::{Actions, LastAction},
+    behavior::{BoardPieceCache, BoardThreatsCache, PatternBehavior, RelayBehavior},
+    board::{Board, OnBoard},
+    pieces::{Orientation, PieceDefinition, Position},
+    team::Team,
+};
+
+use crate::{
+    components::{
+        insert_piece_definition, ActionHistory, Analysis, AntiGame, Atomic, Bot,
+        ClockConfiguration, Crazyhouse, CurrentTurn, DrawOffer, Game, GameBoard, GameOver,
+        GameSeed, GameSetup, History, InGame, PieceSet, Player, Ply, Premoves, RematchRequest,
+        TakebackRequest, VariationTree, WinCondition,
+    },
+    Clock, GameSnapshots, PieceSnapshotQuery,
+};
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Game {0} does not exist")]
+    MissingGame(Entity),
+    #[error("Could not access the save file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not write the game: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("Could not read the game: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+    #[error("The game was saved in version {0} of the format, which is not supported")]
+    UnsupportedVersion(u32),
+}
+
+// The version of the format written by `SavedGame`, increased whenever it changes
+pub const SAVE_VERSION: u32 = 1;
+
+// The components of a game entity that are saved
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+struct SavedRules {
+    name: String,
+    board: GameBoard,
+    piece_set: PieceSet,
+    win_condition: WinCondition,
+    clock: Option<ClockConfiguration>,
+    turn: CurrentTurn,
+    ply: Ply,
+    atomic: Option<Atomic>,
+    crazyhouse: Option<Crazyhouse>,
+    anti: Option<AntiGame>,
+    analysis: Option<Analysis>,
+    seed: Option<GameSeed>,
+    game_over: Option<GameOver>,
+    last_action: Option<LastAction>,
+    action_history: ActionHistory,
+    variation_tree: VariationTree,
+    snapshots: Option<GameSnapshots>,
+    setup: Option<GameSetup>,
+    premoves: Option<Premoves>,
+    takeback_request: Option<TakebackRequest>,
+    draw_offer: Option<DrawOffer>,
+    rematch_request: Option<RematchRequest>,
+}
+
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+struct SavedBoard {
+    entity: Entity,
+    name: String,
+    board: Board,
+}
+
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+struct SavedPlayer {
+    entity: Entity,
+    name: String,
+    team: Team,
+    orientation: Orientation,
+    clock: Option<Clock>,
+    bot: Option<Bot>,
+}
+
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+struct SavedPiece {
+    entity: Entity,
+    name: String,
+    team: Team,
+    orientation: Orientation,
+    // None once the piece has been captured
+    position: Option<Position>,
+    definition: PieceDefinition,
+    position_history: Option<History<Position>>,
+    pattern_history: Option<History<PatternBehavior>>,
+    relay_history: Option<History<RelayBehavior>>,
+}
+
+// A copy of a game and every entity in it, which can be written to a file and
+// spawned again later. Entities are stored as they were when the game was saved,
+// and are replaced by newly spawned entities when the game is restored.
+// Only the components listed in `SavedRules`, `SavedBoard`, `SavedPlayer` and `SavedPiece`
+// are saved; caches such as `Actions` and the board caches are rebuilt once restored.
+#[derive(Clone, Debug)]
+#[derive(Deserialize, Serialize)]
+pub struct SavedGame {
+    version: u32,
+    game: Entity,
+    rules: SavedRules,
+    board: SavedBoard,
+    players: Vec<SavedPlayer>,
+    pieces: Vec<SavedPiece>,
+}
+
+impl SavedGame {
+    pub fn capture(world: &mut World, game: Entity) -> Result<Self, SaveError> {
+        let missing = || SaveError::MissingGame(game);
+        let entity = world
+            .get_entity(game)
+            .filter(|entity| entity.contains::<Game>())
+            .ok_or_else(missing)?;
+        let rules = SavedRules {
+            name: name(entity.get::<Name>()),
+            board: entity.get::<GameBoard>().copied().unwrap_or_default(),
+            piece_set: entity.get::<PieceSet>().cloned().unwrap_or_default(),
+            win_condition: entity.get::<WinCondition>().cloned().unwrap_or_default(),
+            clock: entity.get::<ClockConfiguration>().cloned(),
+            turn: entity.get::<CurrentTurn>().copied().unwrap_or_default(),
+            ply: entity.get::<Ply>().copied().unwrap_or_default(),
+            atomic: entity.get::<Atomic>().cloned(),
+            crazyhouse: entity.get::<Crazyhouse>().cloned(),
+            anti: entity.get::<AntiGame>().cloned(),
+            analysis: entity.get::<Analysis>().copied(),
+            seed: entity.get::<GameSeed>().cloned(),
+            game_over: entity.get::<GameOver>().copied(),
+            last_action: entity.get::<LastAction>().cloned(),
+            action_history: entity.get::<ActionHistory>().cloned().unwrap_or_default(),
+            variation_tree: entity.get::<VariationTree>().cloned().unwrap_or_default(),
+            snapshots: entity.get::<GameSnapshots>().cloned(),
+            setup: entity.get::<GameSetup>().cloned(),
+            premoves: entity.get::<Premoves>().cloned(),
+            takeback_request: entity.get::<TakebackRequest>().copied(),
+            draw_offer: entity.get::<DrawOffer>().copied(),
+            rematch_request: entity.get::<RematchRequest>().copied(),
+        };
+
+        let board = world
+            .query::<(Entity, &Board, &InGame, Option<&Name>)>()
+            .iter(world)
+            .find(|(_, _, in_game, _)| in_game.0 == game)
+            .map(|(entity, board, _, name)| SavedBoard {
+                entity,
+                name: self::name(name),
+                board: *board,
+            })
+            .ok_or_else(missing)?;
+
+        let players = world
+            .query_filtered::<(
+                Entity,
+                &InGame,
+                &Team,
+                &Orientation,
+                Option<&Name>,
+                Option<&Clock>,
+                Option<&Bot>,
+            ), With<Player>>()
+            .iter(world)
+            .filter(|(_, in_game, ..)| in_game.0 == game)
+            .map(
+                |(entity, _, team, orientation, name, clock, bot)| SavedPlayer {
+                    entity,
+                    name: self::name(name),
+                    team: *team,
+                    orientation: *orientation,
+                    clock: clock.cloned(),
+                    bot: bot.copied(),
+                },
+            )
+            .collect();
+
+        let pieces = world
+            .query::<(
+                PieceSnapshotQuery,
+                Option<&Name>,
+                Option<&History<Position>>,
+                Option<&History<PatternBehavior>>,
+                Option<&History<RelayBehavior>>,
+            )>()
+            .iter(world)
+            .filter(|(piece, ..)| piece.in_game.0 == game)
+            .map(
+                |(piece, name, position_history, pattern_history, relay_history)| SavedPiece {
+                    entity: piece.entity,
+                    name: self::name(name),
+                    team: *piece.team,
+                    orientation: *piece.orientation,
+                    position: piece.position.cloned(),
+                    definition: piece.definition(),
+                    position_history: position_history.cloned(),
+                    pattern_history: pattern_history.cloned(),
+                    relay_history: relay_history.cloned(),
+                },
+            )
+            .collect();
+
+        Ok(SavedGame {
+            version: SAVE_VERSION,
+            game,
+            rules,
+            board,
+            players,
+            pieces,
+        })
+    }
+
+    // Spawns the saved game, returning the new game entity
+    pub fn restore(&self, commands: &mut Commands) -> Entity {
+        let mut entities = EntityHashMap::default();
+        for entity in [self.game, self.board.entity]
+            .into_iter()
+            .chain(self.players.iter().map(|player| player.entity))
+            .chain(self.pieces.iter().map(|piece| piece.entity))
+        {
+            entities.insert(entity, commands.spawn_empty().id());
+        }
+        let mut mapper = SavedEntityMapper(&entities);
+        let game = mapper.map_entity(self.game);
+        let board = mapper.map_entity(self.board.entity);
+
+        let rules = &self.rules;
+        let mut builder = commands.entity(game);
+        builder.insert((
+            Name::new(rules.name.clone()),
+            rules.turn,
+            Game,
+            rules.board,
+            rules.piece_set.clone(),
+            rules.win_condition.clone(),
+            Replicated,
+            rules.ply,
+            mapped(&rules.action_history, &mut mapper),
+            mapped(&rules.variation_tree, &mut mapper),
+        ));
+        if let Some(clock) = &rules.clock {
+            builder.insert(clock.clone());
+        }
+        if let Some(atomic) = &rules.atomic {
+            builder.insert(atomic.clone());
+        }
+        if let Some(crazyhouse) = &rules.crazyhouse {
+            builder.insert(crazyhouse.clone());
+        }
+        if let Some(anti) = &rules.anti {
+            builder.insert(anti.clone());
+        }
+        if let Some(analysis) = rules.analysis {
+            builder.insert(analysis);
+        }
+        if let Some(seed) = &rules.seed {
+            builder.insert(seed.clone());
+        }
+        if let Some(game_over) = rules.game_over {
+            builder.insert(game_over);
+        }
+        if let Some(last_action) = &rules.last_action {
+            builder.insert(mapped(last_action, &mut mapper));
+        }
+        if let Some(snapshots) = &rules.snapshots {
+            builder.insert(mapped(snapshots, &mut mapper));
+        }
+        if let Some(setup) = &rules.setup {
+            builder.insert(setup.clone());
+        }
+        if let Some(premoves) = &rules.premoves {
+            builder.insert(mapped(premoves, &mut mapper));
+        }
+        if let Some(takeback_request) = rules.takeback_request {
+            builder.insert(takeback_request);
+        }
+        if let Some(draw_offer) = rules.draw_offer {
+            builder.insert(draw_offer);
+        }
+        if let Some(rematch_request) = rules.rematch_request {
+            builder.insert(rematch_request);
+        }
+
+        let mut builder = commands.entity(board);
+        builder.insert((
+            self.board.board,
+            InGame(game),
+            Name::new(self.board.name.clone()),
+            BoardPieceCache::default(),
+            BoardThreatsCache::default(),
+            Replicated,
+        ));
+        if let Some(last_action) = &rules.last_action {
+            builder.insert(mapped(last_action, &mut mapper));
+        }
+
+        for player in self.players.iter() {
+            let mut builder = commands.entity(mapper.map_entity(player.entity));
+            builder.insert((
+                Name::new(player.name.clone()),
+                Player,
+                InGame(game),
+                OnBoard(board),
+                player.team,
+                player.orientation,
+                Replicated,
+            ));
+            if let Some(clock) = &player.clock {
+                builder.insert(clock.clone());
+            }
+            if let Some(bot) = player.bot {
+                builder.insert(bot);
+            }
+        }
+
+        for piece in self.pieces.iter() {
+            let mut builder = commands.entity(mapper.map_entity(piece.entity));
+            builder.insert((
+                Name::new(piece.name.clone()),
+                piece.team,
+                piece.orientation,
+                Actions::default(),
+                InGame(game),
+                OnBoard(board),
+                Replicated,
+            ));
+            insert_piece_definition(&mut builder, &piece.definition);
+            if let Some(position) = &piece.position {
+                builder.insert(position.clone());
+            }
+            if let Some(history) = &piece.position_history {
+                builder.insert(history.clone());
+            }
+            if let Some(history) = &piece.pattern_history {
+                builder.insert(history.clone());
+            }
+            if let Some(history) = &piece.relay_history {
+                builder.insert(history.clone());
+            }
+        }
+
+        #[cfg(feature = "log")]
+        bevy_log::info!("Restored game {} as {game}", self.game);
+        game
+    }
+
+    pub fn to_ron(&self) -> Result<String, SaveError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, SaveError> {
+        let saved: Self = ron::from_str(text)?;
+        if saved.version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(saved.version));
+        }
+        Ok(saved)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), SaveError> {
+        std::fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn read(path: &Path) -> Result<Self, SaveError> {
+        Self::from_ron(&std::fs::read_to_string(path)?)
+    }
+}
+
+fn name(name: Option<&Name>) -> String {
+    name.map(|name| name.as_str().to_string())
+        .unwrap_or_default()
+}
+
+fn mapped<T: Clone + MapEntities>(value: &T, mapper: &mut SavedEntityMapper) -> T {
+    let mut value = value.clone();
+    value.map_entities(mapper);
+    value
+}
+
+// Maps the entities of a saved game to the entities spawned for it.
+// Entities that were not part of the game become placeholders.
+struct SavedEntityMapper<'a>(&'a EntityHashMap<Entity>);
+
+impl EntityMapper for SavedEntityMapper<'_> {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        self.0.get(&entity).copied().unwrap_or(Entity::PLACEHOLDER)
+    }
+}
+
+// Writes a game to a file, from which it can be resumed with `LoadGame`
+#[derive(Clone, Debug)]
+#[derive(Event)]
+pub struct SaveGame {
+    pub game: Entity,
+    pub path: PathBuf,
+}
+
+impl SaveGame {
+    pub(crate) fn observer(trigger: Trigger<Self>, mut commands: Commands) {
+        let SaveGame { game, path } = trigger.event().clone();
+        commands.add(move |world: &mut World| {
+            match SavedGame::capture(world, game).and_then(|saved| saved.write(&path)) {
+                Ok(()) => {
+                    #[cfg(feature = "log")]
+                    bevy_log::info!("Saved game {game} to {}", path.display());
+                }
+                Err(_error) => {
+                    #[cfg(feature = "log")]
+                    bevy_log::error!("Failed to save game {game}: {_error}");
+                }
+            }
+        });
+    }
+}
+
+// Spawns the game saved in a file by `SaveGame`
+#[derive(Clone, Debug)]
+#[derive(Event)]
+pub struct LoadGame {
+    pub path: PathBuf,
+}
+
+impl LoadGame {
+    pub(crate) fn observer(trigger: Trigger<Self>, mut commands: Commands) {
+        let path = &trigger.event().path;
+        match SavedGame::read(path) {
+            Ok(saved) => {
+                saved.restore(&mut commands);
+            }
+            Err(_error) => {
+                #[cfg(feature = "log")]
+                bevy_log::error!("Failed to load game from {}: {_error}", path.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::board::Square;
+    use layouts::ClassicalLayout;
+
+    use crate::components::{Premove, SpawnGame};
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_restore() {
+        let mut world = World::new();
+        world.observe(SpawnGame::observer);
+        world.flush();
+        world.trigger(SpawnGame::new(ClassicalLayout::pieces().into()));
+        world.flush();
+
+        let game = world.query_filtered::<Entity, With<Game>>().single(&world);
+        let e2 = Square::try_from("e2").unwrap();
+        let e4 = Square::try_from("e4").unwrap();
+        let pawn = world
+            .query::<(Entity, &Position)>()
+            .iter(&world)
+            .find(|(_, position)| position.0 == e2)
+            .map(|(entity, _)| entity)
+            .unwrap();
+        let mut premoves = Premoves::default();
+        premoves.push(Premove {
+            piece: pawn,
+            team: Team::White,
+            from: e2,
+            to: e4,
+            promotion: None,
+        });
+        world.entity_mut(game).insert((
+            Ply::new(3),
+            CurrentTurn(Team::Black),
+            premoves,
+            DrawOffer {
+                team: Team::White,
+                expires_at: Ply::new(5),
+            },
+        ));
+        let text = SavedGame::capture(&mut world, game)
+            .unwrap()
+            .to_ron()
+            .unwrap();
+
+        let mut restored_world = World::new();
+        // offset the new entities so that reusing the saved entities would be caught
+        restored_world.spawn_batch((0..100).map(|_| Name::new("Unrelated")));
+        let saved = SavedGame::from_ron(&text).unwrap();
+        let restored = saved.restore(&mut restored_world.commands());
+        restored_world.flush();
+
+        assert_eq!(restored_world.get::<Ply>(restored), Some(&Ply::new(3)));
+        assert!(restored_world
+            .get::<DrawOffer>(restored)
+            .is_some_and(|offer| offer.team == Team::White && offer.expires_at == Ply::new(5)));
+        let premove = restored_world
+            .get::<Premoves>(restored)
+            .and_then(|premoves| premoves.front())
+            .cloned()
+            .unwrap();
+        assert_eq!(
+            restored_world.get::<Position>(premove.piece).map(|p| p.0),
+            Some(e2)
+        );
+        assert_eq!(
+            restored_world
+                .get::<CurrentTurn>(restored)
+                .map(|turn| turn.0),
+            Some(Team::Black)
+        );
+        let pieces = restored_world
+            .query::<(&InGame, &OnBoard, &Position)>()
+            .iter(&restored_world)
+            .filter(|(in_game, on_board, _)| {
+                in_game.0 == restored && restored_world.get::<Board>(on_board.0).is_some()
+            })
+            .count();
+        assert_eq!(pieces, 32);
+        let e1 = Square::try_from("e1").unwrap();
+        assert!(restored_world
+            .query::<(&Position, &chess::behavior::CastlingBehavior)>()
+            .iter(&restored_world)
+            .any(|(position, _)| position.0 == e1));
+        let players = restored_world
+            .query_filtered::<&InGame, With<Player>>()
+            .iter(&restored_world)
+            .filter(|in_game| in_game.0 == restored)
+            .count();
+        assert_eq!(players, 2);
+    }
+
+    #[test]
+    fn test_save_versions() {
+        let mut world = World::new();
+        world.observe(SpawnGame::observer);
+        world.flush();
+        world.trigger(SpawnGame::new(ClassicalLayout::pieces().into()));
+        world.flush();
+        let game = world.query_filtered::<Entity, With<Game>>().single(&world);
+        let text = SavedGame::capture(&mut world, game)
+            .unwrap()
+            .to_ron()
+            .unwrap();
+        assert!(text.contains(&format!("version: {SAVE_VERSION}")));
+
+        let newer = text.replacen(
+            &format!("version: {SAVE_VERSION},"),
+            &format!("version: {},", SAVE_VERSION + 1),
+            1,
+        );
+        assert!(matches!(
+            SavedGame::from_ron(&newer),
+            Err(SaveError::UnsupportedVersion(_))
+        ));
+    }
+}
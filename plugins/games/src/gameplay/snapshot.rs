@@ -0,0 +1,79 @@
+use bevy_ecs::{prelude::Entity, query::QueryData};
+
+use chess::{
+    actions::LastAction,
+    behavior::{
+        CastlingBehavior, CastlingTarget, EnPassantBehavior, PatternBehavior, PieceBehaviors,
+        RelayBehavior,
+    },
+    board::Board,
+    pieces::{Mutation, Orientation, PieceDefinition, PieceIdentity, Position, Royal},
+    position::{self, PositionPiece},
+    team::Team,
+};
+
+use crate::components::{CurrentTurn, InGame, Ply};
+
+// The components needed to copy a piece of a game into a standalone `position::Position`
+#[derive(QueryData)]
+pub struct PieceSnapshotQuery {
+    pub entity: Entity,
+    pub in_game: &'static InGame,
+    pub team: &'static Team,
+    pub orientation: &'static Orientation,
+    pub position: Option<&'static Position>,
+    pub identity: &'static PieceIdentity,
+    pub pattern: Option<&'static PatternBehavior>,
+    pub en_passant: Option<&'static EnPassantBehavior>,
+    pub relay: Option<&'static RelayBehavior>,
+    pub castling: Option<&'static CastlingBehavior>,
+    pub castling_target: Option<&'static CastlingTarget>,
+    pub mutation: Option<&'static Mutation>,
+    pub royal: Option<&'static Royal>,
+}
+
+impl PieceSnapshotQueryItem<'_> {
+    pub fn definition(&self) -> PieceDefinition {
+        PieceDefinition {
+            behaviors: PieceBehaviors {
+                pattern: self.pattern.cloned(),
+                en_passant: self.en_passant.copied(),
+                relay: self.relay.cloned(),
+                castling: self.castling.copied(),
+                castling_target: self.castling_target.copied(),
+            },
+            identity: *self.identity,
+            mutation: self.mutation.cloned(),
+            royal: self.royal.copied(),
+        }
+    }
+
+    // None if the piece has been captured
+    pub fn position_piece(&self) -> Option<PositionPiece> {
+        let square = self.position?.0;
+        let mut piece = PositionPiece::new(self.entity, self.definition(), square, *self.team);
+        piece.orientation = *self.orientation;
+        Some(piece)
+    }
+}
+
+// Copies the current state of a game into a `position::Position`.
+// Pieces keep their entities so that turns found in the position can be requested in the game.
+pub fn snapshot_position<'a>(
+    board: &Board,
+    current_turn: &CurrentTurn,
+    ply: &Ply,
+    last_action: Option<&LastAction>,
+    pieces: impl IntoIterator<Item = PieceSnapshotQueryItem<'a>>,
+) -> position::Position {
+    let mut snapshot = position::Position::new(*board);
+    snapshot.side_to_move = current_turn.0;
+    snapshot.ply = ply.get();
+    snapshot.last_action = last_action.map(|action| action.0.clone());
+    for piece in pieces {
+        if let Some(piece) = piece.position_piece() {
+            snapshot.insert_piece(piece);
+        }
+    }
+    snapshot
+}
@@ -0,0 +1,362 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use bevy_ecs::prelude::{Res, ResMut, Resource};
+
+use chess::{
+    board::{File, Rank, Square},
+    fen::Legend,
+    pieces::{Mutation, MutationCondition, PieceDefinition},
+};
+use layouts::{parse_legend, ClassicalLayout, LegendError, PieceSpecification};
+
+use crate::{
+    components::{GameBoard, GameRequestClock, PieceSet, SpawnGame, WinCondition},
+    Clock,
+};
+
+#[derive(Debug, Error)]
+pub enum VariantError {
+    #[error("No variant is registered as `{0}`")]
+    Unknown(String),
+    #[error("Could not read {0}: {1}")]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("Could not parse {0}: {1}")]
+    Parse(PathBuf, #[source] ron::error::SpannedError),
+    #[error("Invalid legend: {0}")]
+    Legend(#[from] LegendError),
+    #[error("`{0}` is not a piece of the legend")]
+    UnknownPiece(char),
+    #[error("The layout does not fit on the board at rank {0}")]
+    OutOfBoard(usize),
+}
+
+// A rule component of `SpawnGame`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+pub enum GameRule {
+    Atomic,
+    Crazyhouse,
+    AntiGame,
+}
+
+// A clock in seconds, as written in variant files
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+pub struct ClockPreset {
+    pub time: u64,
+    #[serde(default)]
+    pub increment: u64,
+}
+
+impl ClockPreset {
+    pub fn to_clock(self) -> Clock {
+        Clock::new(
+            Duration::from_secs(self.time),
+            Duration::from_secs(self.increment),
+        )
+    }
+}
+
+// Where a piece promotes, and the letters of the pieces it may promote to
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+pub struct PromotionRule {
+    // counted from 1, as in notation, and local to the team's orientation
+    pub rank: u16,
+    pub pieces: String,
+}
+
+// A variant as written in a RON file, for example:
+// (
+//     name: "Classical With Archbishops",
+//     legend: "A=NB:bishop",
+//     promotions: { 'P': (rank: 8, pieces: "QRAN") },
+//     layout: ["RNAQKANR", "PPPPPPPP"],
+//     clocks: { Blitz: (time: 180, increment: 2) },
+// )
+#[derive(Clone, Debug, Default)]
+#[derive(Deserialize, Serialize)]
+pub struct VariantDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub board: GameBoard,
+    // Letters for pieces in addition to the classical ones, such as `A=NB M=fmWfcF:pawn`.
+    // See `layouts::parse_legend`.
+    #[serde(default)]
+    pub legend: String,
+    // Letters for pieces that a legend cannot describe
+    #[serde(default)]
+    pub pieces: BTreeMap<char, PieceDefinition>,
+    // Replaces the promotions of the pieces of the legend
+    #[serde(default)]
+    pub promotions: BTreeMap<char, PromotionRule>,
+    // White's ranks from its back rank forward, written with the letters of the legend.
+    // As in FEN, digits skip empty squares.
+    pub layout: Vec<String>,
+    // Black's ranks from its own back rank forward, if Black fields a different army
+    #[serde(default)]
+    pub black_layout: Option<Vec<String>>,
+    #[serde(default)]
+    pub win_condition: WinCondition,
+    #[serde(default)]
+    pub rules: Vec<GameRule>,
+    // Clocks replacing the usual time controls of matchmaking
+    #[serde(default)]
+    pub clocks: HashMap<GameRequestClock, ClockPreset>,
+}
+
+impl VariantDefinition {
+    pub fn from_ron(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    pub fn legend(&self) -> Result<Legend, VariantError> {
+        let mut legend = ClassicalLayout::legend();
+        for (symbol, piece) in parse_legend(&self.legend)?.iter() {
+            legend = legend.with_piece(*symbol, piece.clone());
+        }
+        for (symbol, piece) in self.pieces.iter() {
+            legend = legend.with_piece(*symbol, piece.clone());
+        }
+        // promotions choose among the pieces of the legend before any of them promote
+        let pieces = legend.clone();
+        let piece = |symbol: char| {
+            pieces
+                .piece(symbol)
+                .cloned()
+                .ok_or(VariantError::UnknownPiece(symbol))
+        };
+        for (symbol, promotion) in self.promotions.iter() {
+            let mut promoting = piece(*symbol)?;
+            promoting.mutation = Some(Mutation {
+                condition: MutationCondition::LocalRank(Rank(promotion.rank.saturating_sub(1))),
+                to_piece: promotion
+                    .pieces
+                    .chars()
+                    .map(piece)
+                    .collect::<Result<_, _>>()?,
+                ..Default::default()
+            });
+            legend = legend.with_piece(*symbol, promoting);
+        }
+        Ok(legend)
+    }
+
+    pub fn build(self) -> Result<Variant, VariantError> {
+        let legend = self.legend()?;
+        let board = self.board.board();
+        let read_layout = |layout: &[String]| -> Result<Vec<PieceSpecification>, VariantError> {
+            let mut pieces = vec![];
+            for (rank, row) in layout.iter().enumerate() {
+                let mut file = 0;
+                for symbol in row.chars() {
+                    if let Some(skip) = symbol.to_digit(10) {
+                        file += skip as u16;
+                        continue;
+                    }
+                    let piece = legend
+                        .piece(symbol)
+                        .ok_or(VariantError::UnknownPiece(symbol))?;
+                    let square = Square::new(File(file), Rank(rank as u16));
+                    if !board.contains(square) {
+                        return Err(VariantError::OutOfBoard(rank + 1));
+                    }
+                    pieces.push(PieceSpecification::new(piece.clone(), square));
+                    file += 1;
+                }
+            }
+            Ok(pieces)
+        };
+        let piece_set = match &self.black_layout {
+            Some(black_layout) => {
+                PieceSet::asymmetric(read_layout(&self.layout)?, read_layout(black_layout)?)
+            }
+            None => PieceSet::new(read_layout(&self.layout)?),
+        };
+        Ok(Variant {
+            name: self.name,
+            description: self.description,
+            board: self.board,
+            piece_set,
+            win_condition: self.win_condition,
+            rules: self.rules,
+            clocks: self.clocks,
+        })
+    }
+}
+
+// A variant ready to be played
+#[derive(Clone, Debug)]
+pub struct Variant {
+    pub name: String,
+    pub description: String,
+    pub board: GameBoard,
+    pub piece_set: PieceSet,
+    pub win_condition: WinCondition,
+    pub rules: Vec<GameRule>,
+    pub clocks: HashMap<GameRequestClock, ClockPreset>,
+}
+
+impl Variant {
+    pub fn spawn_game(&self) -> SpawnGame {
+        let mut spawn_game = SpawnGame::new(self.piece_set.clone())
+            .with_board(self.board)
+            .with_win_condition(self.win_condition.clone());
+        for rule in self.rules.iter() {
+            spawn_game = match rule {
+                GameRule::Atomic => spawn_game.atomic(),
+                GameRule::Crazyhouse => spawn_game.crazyhouse(),
+                GameRule::AntiGame => spawn_game.anti_game(),
+            };
+        }
+        spawn_game
+    }
+
+    // The variant's own clock for a time control, if it has one
+    pub fn clock(&self, clock: GameRequestClock) -> Clock {
+        self.clocks
+            .get(&clock)
+            .map(|preset| preset.to_clock())
+            .unwrap_or_else(|| clock.to_clock())
+    }
+}
+
+// The variants loaded from files, by id
+#[derive(Clone, Debug, Default)]
+#[derive(Resource)]
+pub struct VariantRegistry(BTreeMap<String, Variant>);
+
+impl VariantRegistry {
+    #[must_use]
+    pub fn with_variant(mut self, id: impl Into<String>, variant: Variant) -> Self {
+        self.0.insert(id.into(), variant);
+        self
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Variant> {
+        self.0.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Variant)> {
+        self.0.iter()
+    }
+
+    // Registers a variant file, named by its file stem
+    pub fn load_file(&mut self, path: &Path) -> Result<String, VariantError> {
+        let text =
+            std::fs::read_to_string(path).map_err(|error| VariantError::Io(path.into(), error))?;
+        let variant = VariantDefinition::from_ron(&text)
+            .map_err(|error| VariantError::Parse(path.into(), error))?
+            .build()?;
+        let id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.0.insert(id.clone(), variant);
+        Ok(id)
+    }
+
+    // Registers every `.ron` file of a directory.
+    // Files that cannot be loaded are skipped, and their errors returned.
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<VariantError> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) => return vec![VariantError::Io(dir.into(), error)],
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+            .filter_map(|path| self.load_file(&path).err())
+            .collect()
+    }
+}
+
+// The directory of variant files to load on startup
+#[derive(Clone, Debug)]
+#[derive(Resource)]
+pub struct VariantDirectory(pub PathBuf);
+
+pub(crate) fn load_variants(
+    directory: Option<Res<VariantDirectory>>,
+    mut registry: ResMut<VariantRegistry>,
+) {
+    let Some(directory) = directory else {
+        return;
+    };
+    for _error in registry.load_dir(&directory.0) {
+        #[cfg(feature = "log")]
+        bevy_log::error!("Failed to load variant: {_error}");
+    }
+    #[cfg(feature = "log")]
+    bevy_log::info!("Loaded {} variants", registry.0.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_variant() {
+        let text = r#"(
+            name: "Classical",
+            layout: ["RNBQKBNR", "PPPPPPPP"],
+            clocks: { Blitz: (time: 180, increment: 2) },
+        )"#;
+        let variant = VariantDefinition::from_ron(text).unwrap().build().unwrap();
+        let board = variant.board.board();
+        let legend = ClassicalLayout::legend();
+        assert_eq!(
+            variant
+                .piece_set
+                .starting_position(&board)
+                .to_fen(&legend)
+                .unwrap(),
+            PieceSet::new(ClassicalLayout::pieces())
+                .starting_position(&board)
+                .to_fen(&legend)
+                .unwrap()
+        );
+        assert_eq!(
+            variant.clock(GameRequestClock::Blitz).increment(),
+            Duration::from_secs(2)
+        );
+
+        let text = r#"(name: "Too Wide", layout: ["RNBQKBNRR"])"#;
+        assert!(matches!(
+            VariantDefinition::from_ron(text).unwrap().build(),
+            Err(VariantError::OutOfBoard(1))
+        ));
+    }
+
+    #[test]
+    fn test_variant_files() {
+        let mut registry = VariantRegistry::default();
+        let errors =
+            registry.load_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../../variants"));
+        assert!(errors.is_empty(), "{errors:?}");
+
+        let los_alamos = registry.get("los_alamos").unwrap();
+        let pawn = los_alamos
+            .piece_set
+            .pieces
+            .iter()
+            .find(|spec| spec.start_square == "a2".try_into().unwrap())
+            .unwrap();
+        assert_eq!(
+            pawn.piece
+                .mutation
+                .as_ref()
+                .map(|mutation| &mutation.condition),
+            Some(&MutationCondition::LocalRank(Rank::SIX))
+        );
+    }
+}
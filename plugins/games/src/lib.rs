@@ -1,4 +1,8 @@
 pub use chess;
+pub use layouts;
+
+mod bot;
+pub use bot::*;
 
 mod gameplay;
 pub use gameplay::*;
@@ -7,7 +11,8 @@ mod matchmaking;
 pub use matchmaking::*;
 
 pub mod components {
+    pub use super::bot::Bot;
     pub use super::gameplay::components::*;
-    pub use super::gameplay::Clock;
+    pub use super::gameplay::{Clock, Latency};
     pub use super::matchmaking::components::*;
 }
@@ -1,14 +1,21 @@
-use layouts::{FeaturedWildLayout, RandomWildLayout};
+use layouts::{
+    random_seed, ClassicalLayout, FeaturedWildLayout, HordeLayout, RandomWildLayout, ShuffleRules,
+    WildConfig,
+};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
+use thiserror::Error;
 
 use bevy_ecs::prelude::{Bundle, Component};
 #[cfg(feature = "reflect")]
 use bevy_reflect::prelude::Reflect;
 
-use crate::components::{Clock, PieceSet};
+use crate::{
+    components::{Clock, GameSeed, PieceSet, SpawnGame},
+    format_duration, Timing, VariantError, VariantRegistry,
+};
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
@@ -17,46 +24,235 @@ pub enum GameRequestVariant {
     FeaturedGameOne,
     FeaturedGameTwo,
     FeaturedGameThree,
-    Wild,
-    // TODO: configuration...?
+    // A random wild army, generated as configured
+    Wild(WildConfig),
+    // Chess960, by its standard position number (518 is the classical setup)
+    FischerRandom(u16),
+    // A random wild army, generated as configured, with its back rank shuffled by position number
+    ShuffledWild(u16, ShuffleRules, WildConfig),
+    // A different random wild army for each team
+    DifferentArmies(WildConfig),
+    // White's pawns against Black's classical army
+    Horde,
+    // A variant of the `VariantRegistry`, by id
+    Registered(String),
 }
 
 impl GameRequestVariant {
-    pub fn piece_set(&self) -> PieceSet {
-        PieceSet(match self {
-            GameRequestVariant::FeaturedGameOne => FeaturedWildLayout::One.pieces(),
-            GameRequestVariant::FeaturedGameTwo => FeaturedWildLayout::Two.pieces(),
-            GameRequestVariant::FeaturedGameThree => FeaturedWildLayout::Three.pieces(),
-            GameRequestVariant::Wild => RandomWildLayout::pieces(),
+    // Whether the variant's pieces are generated from a seed
+    pub fn is_random(&self) -> bool {
+        matches!(
+            self,
+            GameRequestVariant::Wild(_)
+                | GameRequestVariant::ShuffledWild(..)
+                | GameRequestVariant::DifferentArmies(_)
+        )
+    }
+
+    // The seed is ignored by variants that are not random
+    pub fn piece_set(
+        &self,
+        seed: u64,
+        registry: &VariantRegistry,
+    ) -> Result<PieceSet, VariantError> {
+        Ok(match self {
+            GameRequestVariant::FeaturedGameOne => FeaturedWildLayout::One.pieces().into(),
+            GameRequestVariant::FeaturedGameTwo => FeaturedWildLayout::Two.pieces().into(),
+            GameRequestVariant::FeaturedGameThree => FeaturedWildLayout::Three.pieces().into(),
+            GameRequestVariant::Wild(config) => {
+                RandomWildLayout::configured_pieces(seed, config).into()
+            }
+            GameRequestVariant::FischerRandom(position) => {
+                ClassicalLayout::fischer_random_pieces((*position).into()).into()
+            }
+            GameRequestVariant::ShuffledWild(position, rules, config) => {
+                RandomWildLayout::shuffled_pieces(seed, (*position).into(), *rules, config).into()
+            }
+            GameRequestVariant::DifferentArmies(config) => {
+                let (white, black) = RandomWildLayout::different_armies(seed, config);
+                PieceSet::asymmetric(white, black)
+            }
+            GameRequestVariant::Horde => {
+                PieceSet::asymmetric(HordeLayout::pieces(), ClassicalLayout::pieces())
+            }
+            GameRequestVariant::Registered(id) => registry
+                .get(id)
+                .ok_or_else(|| VariantError::Unknown(id.clone()))?
+                .piece_set
+                .clone(),
         })
     }
+
+    // Spawns a game of this variant, generating a new seed for random variants
+    // unless one is requested
+    pub fn spawn_game(
+        &self,
+        seed: Option<u64>,
+        registry: &VariantRegistry,
+    ) -> Result<SpawnGame, VariantError> {
+        if let GameRequestVariant::Registered(id) = self {
+            return registry
+                .get(id)
+                .map(|variant| variant.spawn_game())
+                .ok_or_else(|| VariantError::Unknown(id.clone()));
+        }
+        let seed = self.is_random().then(|| GameSeed {
+            seed: seed.unwrap_or_else(random_seed),
+            variant: self.clone(),
+        });
+        let piece_set = self.piece_set(seed.as_ref().map_or(0, |seed| seed.seed), registry)?;
+        Ok(SpawnGame::new(piece_set).with_seed(seed))
+    }
+
+    // The time control, as the variant plays it
+    pub fn clock(&self, clock: GameRequestClock, registry: &VariantRegistry) -> Clock {
+        match self {
+            GameRequestVariant::Registered(id) => registry
+                .get(id)
+                .map(|variant| variant.clock(clock))
+                .unwrap_or_else(|| clock.to_clock()),
+            _ => clock.to_clock(),
+        }
+    }
 }
 
+// The seed requested for a random variant, to replay an army that was generated before
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[derive(Component)]
 #[derive(Deserialize, Serialize)]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct GameRequestSeed(pub u64);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Component)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
 pub enum GameRequestClock {
     #[default]
     Bullet,
     Blitz,
     Rapid,
     Classical,
+    // Any base time, with the increment or delay of its timing
+    Custom {
+        time: Duration,
+        timing: Timing,
+    },
+}
+
+// The bounds of the clocks that the server accepts
+pub const MIN_CLOCK_TIME: Duration = Duration::from_secs(15);
+pub const MIN_PER_MOVE_TIME: Duration = Duration::from_secs(5);
+pub const MAX_CLOCK_TIME: Duration = Duration::from_secs(3 * 60 * 60);
+pub const MAX_CLOCK_INCREMENT: Duration = Duration::from_secs(180);
+
+// How far apart the clocks of two seeks may be for them to be matched
+const BASE_TIME_TOLERANCE: f32 = 0.2;
+const INCREMENT_TOLERANCE: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+#[derive(Deserialize, Serialize)]
+pub enum ClockRequestError {
+    #[error("A clock needs at least {} of base time", format_duration(*.0))]
+    TooShort(Duration),
+    #[error("A clock may have at most {} of base time", format_duration(*.0))]
+    TooLong(Duration),
+    #[error("A clock may add at most {}s per turn", .0.as_secs())]
+    IncrementTooLong(Duration),
 }
 
 impl GameRequestClock {
-    pub fn to_clock(self) -> Clock {
+    pub fn custom(time: Duration, timing: Timing) -> Self {
+        GameRequestClock::Custom { time, timing }
+    }
+
+    // The base time and timing of the clock
+    pub fn settings(self) -> (Duration, Timing) {
         match self {
-            GameRequestClock::Bullet => {
-                Clock::new(Duration::from_secs(120), Duration::from_secs(0))
-            }
-            GameRequestClock::Blitz => Clock::new(Duration::from_secs(300), Duration::from_secs(1)),
-            GameRequestClock::Rapid => Clock::new(Duration::from_secs(600), Duration::from_secs(5)),
-            GameRequestClock::Classical => {
-                Clock::new(Duration::from_secs(3600), Duration::from_secs(30))
-            }
+            GameRequestClock::Bullet => (Duration::from_secs(120), Timing::default()),
+            GameRequestClock::Blitz => (
+                Duration::from_secs(300),
+                Timing::Increment(Duration::from_secs(1)),
+            ),
+            GameRequestClock::Rapid => (
+                Duration::from_secs(600),
+                Timing::Increment(Duration::from_secs(5)),
+            ),
+            GameRequestClock::Classical => (
+                Duration::from_secs(3600),
+                Timing::Increment(Duration::from_secs(30)),
+            ),
+            GameRequestClock::Custom { time, timing } => (time, timing),
         }
     }
+
+    pub fn to_clock(self) -> Clock {
+        let (time, timing) = self.settings();
+        Clock::with_timing(time, timing)
+    }
+
+    // Whether the server accepts this clock
+    pub fn validate(self) -> Result<(), ClockRequestError> {
+        let (time, timing) = self.settings();
+        let min_time = match timing {
+            Timing::PerMove => MIN_PER_MOVE_TIME,
+            _ => MIN_CLOCK_TIME,
+        };
+        if time < min_time {
+            return Err(ClockRequestError::TooShort(min_time));
+        }
+        if time > MAX_CLOCK_TIME {
+            return Err(ClockRequestError::TooLong(MAX_CLOCK_TIME));
+        }
+        if timing_bonus(timing).is_some_and(|bonus| bonus > MAX_CLOCK_INCREMENT) {
+            return Err(ClockRequestError::IncrementTooLong(MAX_CLOCK_INCREMENT));
+        }
+        Ok(())
+    }
+
+    // The clock halfway between two compatible clocks, played by both seeks
+    pub fn midpoint(self, other: GameRequestClock) -> GameRequestClock {
+        if self == other {
+            return self;
+        }
+        let (time1, timing1) = self.settings();
+        let (time2, timing2) = other.settings();
+        let bonus = timing_bonus(timing1)
+            .zip(timing_bonus(timing2))
+            .map(|(bonus1, bonus2)| (bonus1 + bonus2) / 2)
+            .unwrap_or_default();
+        let timing = match timing1 {
+            Timing::Increment(_) => Timing::Increment(bonus),
+            Timing::Bronstein(_) => Timing::Bronstein(bonus),
+            Timing::Delay(_) => Timing::Delay(bonus),
+            timing => timing,
+        };
+        GameRequestClock::custom((time1 + time2) / 2, timing)
+    }
+
+    // Whether two seeks are close enough to be played with the same clock:
+    // the same kind of timing, with similar base times and increments
+    pub fn is_compatible(self, other: GameRequestClock) -> bool {
+        let (time1, timing1) = self.settings();
+        let (time2, timing2) = other.settings();
+        if std::mem::discriminant(&timing1) != std::mem::discriminant(&timing2) {
+            return false;
+        }
+        let bonus_difference = timing_bonus(timing1)
+            .zip(timing_bonus(timing2))
+            .map(|(bonus1, bonus2)| bonus1.max(bonus2) - bonus1.min(bonus2))
+            .unwrap_or_default();
+        time1.max(time2) - time1.min(time2) <= time1.max(time2).mul_f32(BASE_TIME_TOLERANCE)
+            && bonus_difference <= INCREMENT_TOLERANCE
+    }
+}
+
+// The increment or delay of a timing
+fn timing_bonus(timing: Timing) -> Option<Duration> {
+    match timing {
+        Timing::Increment(bonus) | Timing::Bronstein(bonus) | Timing::Delay(bonus) => Some(bonus),
+        Timing::Hourglass | Timing::PerMove => None,
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default)]
@@ -72,4 +268,53 @@ pub struct GameRequestBundle {
     pub request: GameRequest,
     pub variant: GameRequestVariant,
     pub clock: GameRequestClock,
+    pub seed: GameRequestSeed,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_clocks() {
+        let clock_secs = |seconds: u64, increment: u64| {
+            GameRequestClock::custom(
+                Duration::from_secs(seconds),
+                Timing::Increment(Duration::from_secs(increment)),
+            )
+        };
+        let clock = |minutes: u64, increment: u64| clock_secs(minutes * 60, increment);
+        assert!(clock(5, 1).is_compatible(GameRequestClock::Blitz));
+        assert!(clock(5, 3).is_compatible(clock(4, 1)));
+        assert!(!clock(5, 0).is_compatible(clock(3, 0)));
+        assert!(!clock(5, 0).is_compatible(clock(5, 5)));
+        assert!(!clock(5, 0).is_compatible(GameRequestClock::custom(
+            Duration::from_secs(300),
+            Timing::Delay(Duration::ZERO),
+        )));
+
+        assert_eq!(clock(5, 1).to_clock().increment(), Duration::from_secs(1));
+        assert_eq!(clock(5, 3).midpoint(clock(4, 1)), clock_secs(270, 2));
+        assert_eq!(
+            GameRequestClock::Blitz.midpoint(GameRequestClock::Blitz),
+            GameRequestClock::Blitz
+        );
+        assert_eq!(
+            clock(5, 1).midpoint(GameRequestClock::Blitz),
+            clock_secs(300, 1)
+        );
+        assert_eq!(GameRequestClock::Classical.validate(), Ok(()));
+        assert_eq!(
+            clock(0, 5).validate(),
+            Err(ClockRequestError::TooShort(MIN_CLOCK_TIME))
+        );
+        assert_eq!(
+            clock(200, 0).validate(),
+            Err(ClockRequestError::TooLong(MAX_CLOCK_TIME))
+        );
+        assert_eq!(
+            clock(5, 600).validate(),
+            Err(ClockRequestError::IncrementTooLong(MAX_CLOCK_INCREMENT))
+        );
+    }
 }
@@ -11,6 +11,8 @@ use bevy_ecs::{
 
 use bevy_replicon::prelude::*;
 
+use crate::{BotDifficulty, VariantRegistry};
+
 pub mod components;
 
 mod systems;
@@ -25,14 +27,20 @@ impl Plugin for MatchmakingPlugin {
     fn build(&self, app: &mut App) {
         app.add_client_event::<RequestJoinGameEvent>(ChannelKind::Ordered)
             .add_client_event::<LeaveGameEvent>(ChannelKind::Ordered)
+            .add_client_event::<RequestImportGameEvent>(ChannelKind::Ordered)
+            .add_server_event::<ImportGameFailedEvent>(ChannelKind::Ordered)
+            .add_server_event::<ClockRequestFailedEvent>(ChannelKind::Ordered)
             .replicate::<components::GameRequestVariant>()
             .replicate::<components::GameRequestClock>()
+            .replicate::<components::GameRequestSeed>()
             .replicate::<components::GameRequest>()
+            .init_resource::<VariantRegistry>()
             .configure_sets(Update, MatchmakingSystems.run_if(has_authority))
             .add_systems(
                 Update,
                 (
                     systems::handle_game_requests,
+                    systems::handle_import_requests,
                     systems::handle_leave_events,
                     systems::match_game_requests,
                     systems::handle_visibility.run_if(resource_exists::<ConnectedClients>),
@@ -50,7 +58,7 @@ impl Plugin for MatchmakingPlugin {
 pub enum GameOpponent {
     Online,
     Local,
-    AgainstBot,
+    AgainstBot(BotDifficulty),
     Analysis,
 }
 
@@ -61,9 +69,35 @@ pub struct RequestJoinGameEvent {
     // TODO: more configuration
     pub game: Option<components::GameRequestVariant>,
     pub clock: Option<components::GameRequestClock>,
+    // the seed of a random variant, to replay an army that was generated before
+    pub seed: Option<u64>,
     pub opponent: GameOpponent,
 }
 
+// Requests an analysis game replaying a game written as PGN
+#[derive(Clone)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct RequestImportGameEvent {
+    pub pgn: String,
+}
+
+// Tells a client why the game it asked to import could not be replayed
+#[derive(Clone, Debug)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct ImportGameFailedEvent {
+    pub reason: String,
+}
+
+// Tells a client why the clock of the game it requested was rejected
+#[derive(Clone, Debug)]
+#[derive(Event)]
+#[derive(Deserialize, Serialize)]
+pub struct ClockRequestFailedEvent {
+    pub error: components::ClockRequestError,
+}
+
 #[derive(Clone)]
 #[derive(Event)]
 #[derive(Deserialize, Serialize)]
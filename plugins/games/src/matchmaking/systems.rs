@@ -1,28 +1,52 @@
 use itertools::Itertools;
 
+use bevy_core::Name;
 use bevy_ecs::prelude::{
-    Commands, Entity, EventReader, Query, RemovedComponents, ResMut, With, Without,
+    Commands, Entity, EventReader, EventWriter, Query, RemovedComponents, Res, ResMut, With,
+    Without,
 };
-use bevy_replicon::prelude::{ConnectedClients, FromClient};
+use bevy_replicon::prelude::{ConnectedClients, FromClient, SendMode, ToClients};
 
 use replication::Client;
 
+use chess::team::Team;
+
 use crate::{
+    can_abort,
     components::{
-        GameRequest, GameRequestBundle, GameRequestClock, GameRequestVariant, InGame, Player,
-        SpawnGame,
+        ActionHistory, Analysis, CurrentTurn, GameOver, GameOverReason, GameRequest,
+        GameRequestBundle, GameRequestClock, GameRequestSeed, GameRequestVariant, InGame,
+        IsActiveGame, Player,
     },
     gameplay::components::Game,
+    Bot, GameRecord, VariantRegistry,
 };
 
-use super::{GameOpponent, LeaveGameEvent, RequestJoinGameEvent};
+use super::{
+    ClockRequestFailedEvent, GameOpponent, ImportGameFailedEvent, LeaveGameEvent,
+    RequestImportGameEvent, RequestJoinGameEvent,
+};
 
 pub(super) fn handle_game_requests(
     mut commands: Commands,
     mut join_requests: EventReader<FromClient<RequestJoinGameEvent>>,
+    mut clock_failed_writer: EventWriter<ToClients<ClockRequestFailedEvent>>,
     players: Query<(Entity, &Client)>,
+    registry: Res<VariantRegistry>,
 ) {
     for event in join_requests.read() {
+        if let Some(Err(error)) = event.event.clock.map(GameRequestClock::validate) {
+            #[cfg(feature = "log")]
+            bevy_log::warn!(
+                "Rejected the clock requested by client {}: {error}",
+                event.client_id.get()
+            );
+            clock_failed_writer.send(ToClients {
+                mode: SendMode::Direct(event.client_id),
+                event: ClockRequestFailedEvent { error },
+            });
+            continue;
+        }
         match event.event.opponent {
             GameOpponent::Online => {
                 let Some((player, _)) = players
@@ -36,6 +60,14 @@ pub(super) fn handle_game_requests(
                     );
                     continue;
                 };
+                // a registered variant that the server does not know can never be matched
+                if let Some(GameRequestVariant::Registered(id)) = &event.event.game {
+                    if registry.get(id).is_none() {
+                        #[cfg(feature = "log")]
+                        bevy_log::warn!("Player {player:?} requested unknown variant {id}");
+                        continue;
+                    }
+                }
                 #[cfg(feature = "log")]
                 bevy_log::info!(
                     "Player {:?} (client {}) seeking match...",
@@ -44,40 +76,139 @@ pub(super) fn handle_game_requests(
                 );
                 let mut player_builder = commands.entity(player);
                 player_builder.insert(GameRequest);
-                if let Some(game) = event.event.game {
+                if let Some(game) = event.event.game.clone() {
                     player_builder.insert(game);
                 }
                 if let Some(clock) = event.event.clock {
                     player_builder.insert(clock);
                 }
+                if let Some(seed) = event.event.seed {
+                    player_builder.insert(GameRequestSeed(seed));
+                }
             }
             GameOpponent::Local => {
-                let variant = event.event.game.unwrap_or_default();
+                let variant = event.event.game.clone().unwrap_or_default();
                 let clock = event.event.clock.as_ref();
-                let spawn_game = SpawnGame::new(variant.piece_set())
-                    .with_clock(clock.map(|requested_clock| requested_clock.to_clock()));
+                let spawn_game = match variant.spawn_game(event.event.seed, &registry) {
+                    Ok(spawn_game) => spawn_game,
+                    Err(_error) => {
+                        #[cfg(feature = "log")]
+                        bevy_log::warn!("Failed to start a local game: {_error}");
+                        continue;
+                    }
+                };
+                let spawn_game = spawn_game.with_clock(
+                    clock.map(|requested_clock| variant.clock(*requested_clock, &registry)),
+                );
                 #[cfg(feature = "log")]
                 bevy_log::info!(
                     "Starting a local game with variant {variant:?} and clock {clock:?}"
                 );
                 commands.trigger(spawn_game);
             }
-            GameOpponent::AgainstBot | GameOpponent::Analysis => {
-                unimplemented!("Can't play games against bots yet :(");
+            GameOpponent::AgainstBot(difficulty) => {
+                // local players do not have a client entity, so they get a new player entity
+                let player = players
+                    .iter()
+                    .find(|(_, player)| player.id == event.client_id)
+                    .map(|(player, _)| player)
+                    .unwrap_or_else(|| commands.spawn(Name::new("Player")).id());
+                let bot = commands
+                    .spawn((Name::new("Bot"), Bot::new(difficulty)))
+                    .id();
+
+                let variant = event.event.game.clone().unwrap_or_default();
+                let clock = event.event.clock.as_ref();
+                let spawn_game = match variant.spawn_game(event.event.seed, &registry) {
+                    Ok(spawn_game) => spawn_game,
+                    Err(_error) => {
+                        #[cfg(feature = "log")]
+                        bevy_log::warn!("Failed to start a game against a bot: {_error}");
+                        continue;
+                    }
+                };
+                let spawn_game = spawn_game.with_players(player, bot).with_clock(
+                    clock.map(|requested_clock| variant.clock(*requested_clock, &registry)),
+                );
+                #[cfg(feature = "log")]
+                bevy_log::info!(
+                    "Starting a game against a {difficulty:?} bot with variant {variant:?} and clock {clock:?}"
+                );
+                commands.trigger(spawn_game);
+            }
+            GameOpponent::Analysis => {
+                let variant = event.event.game.clone().unwrap_or_default();
+                let mut spawn_game = match variant.spawn_game(event.event.seed, &registry) {
+                    Ok(spawn_game) => spawn_game.analysis(),
+                    Err(_error) => {
+                        #[cfg(feature = "log")]
+                        bevy_log::warn!("Failed to start an analysis game: {_error}");
+                        continue;
+                    }
+                };
+                // a single player controls both teams;
+                // local players do not have a client entity, so both teams are left to the server
+                if let Some((player, _)) = players
+                    .iter()
+                    .find(|(_, player)| player.id == event.client_id)
+                {
+                    spawn_game = spawn_game.with_players(player, player);
+                }
+                #[cfg(feature = "log")]
+                bevy_log::info!("Starting an analysis game with variant {variant:?}");
+                commands.trigger(spawn_game);
             }
         }
     }
 }
 
+pub(super) fn handle_import_requests(
+    mut commands: Commands,
+    mut import_requests: EventReader<FromClient<RequestImportGameEvent>>,
+    mut failed_writer: EventWriter<ToClients<ImportGameFailedEvent>>,
+    players: Query<(Entity, &Client)>,
+) {
+    for FromClient { client_id, event } in import_requests.read() {
+        let record = match GameRecord::import(&event.pgn) {
+            Ok(record) => record,
+            Err(error) => {
+                #[cfg(feature = "log")]
+                bevy_log::warn!(
+                    "Failed to import game for client {}: {error}",
+                    client_id.get()
+                );
+                failed_writer.send(ToClients {
+                    mode: SendMode::Direct(*client_id),
+                    event: ImportGameFailedEvent {
+                        reason: error.to_string(),
+                    },
+                });
+                continue;
+            }
+        };
+
+        let mut spawn_game = record.analysis_game();
+        if let Some((player, _)) = players.iter().find(|(_, player)| player.id == *client_id) {
+            spawn_game = spawn_game.with_players(player, player);
+        }
+        #[cfg(feature = "log")]
+        bevy_log::info!("Starting an analysis game from an imported game");
+        commands.trigger(spawn_game);
+    }
+}
+
+// Leaving a game that is still being played forfeits it, or aborts it while that is allowed
+#[allow(clippy::type_complexity)]
 pub(super) fn handle_leave_events(
     mut commands: Commands,
     mut leave_requests: EventReader<FromClient<LeaveGameEvent>>,
-    players: Query<(Entity, &Client)>,
+    players: Query<(Entity, &Client, Option<&InGame>, Option<&Team>)>,
+    games: Query<(&ActionHistory, &CurrentTurn), (IsActiveGame, Without<Analysis>)>,
 ) {
     for event in leave_requests.read() {
-        if let Some((entity, client)) = players
+        if let Some((entity, client, in_game, team)) = players
             .iter()
-            .find(|(_, client)| client.id == event.client_id)
+            .find(|(_, client, _, _)| client.id == event.client_id)
         {
             #[cfg(feature = "log")]
             bevy_log::info!(
@@ -85,11 +216,35 @@ pub(super) fn handle_leave_events(
                 client.id.get()
             );
 
+            if let (Some(in_game), Some(team)) = (in_game, team) {
+                if let Ok((history, current_turn)) = games.get(in_game.0) {
+                    let game_over = if can_abort(*team, history, current_turn) {
+                        GameOver::aborted()
+                    } else {
+                        GameOver::win(team.get_next(), GameOverReason::Resignation)
+                    };
+                    commands.entity(in_game.0).insert(game_over);
+                }
+            }
             commands.entity(entity).remove::<InGame>();
         }
     }
 }
 
+// The clock of two seeks, if they are compatible; both play the clock halfway between theirs
+fn combine_clocks(
+    clock1: Option<&GameRequestClock>,
+    clock2: Option<&GameRequestClock>,
+) -> Option<Option<GameRequestClock>> {
+    match (clock1, clock2) {
+        (Some(clock1), Some(clock2)) => clock1
+            .is_compatible(*clock2)
+            .then(|| Some(clock1.midpoint(*clock2))),
+        (Some(clock), None) | (None, Some(clock)) => Some(Some(*clock)),
+        (None, None) => Some(None),
+    }
+}
+
 /// Compares combinations of tuples so that combinations with more "Some"s are handled first
 fn cmp_combinations<T, U, V>(
     pair1: &[(T, Option<U>, Option<V>)],
@@ -112,19 +267,19 @@ fn cmp_combinations<T, U, V>(
         .then(pair1_v_somes.cmp(&pair2_v_somes))
 }
 
-fn combine_equal<O: Copy + PartialEq>(
+fn combine_equal<O: Clone + PartialEq>(
     option1: Option<&O>,
     option2: Option<&O>,
 ) -> Option<Option<O>> {
     match (option1, option2) {
         (Some(value1), Some(value2)) => {
             if value1 == value2 {
-                Some(Some(*value1))
+                Some(Some(value1.clone()))
             } else {
                 None
             }
         }
-        (Some(value), None) | (None, Some(value)) => Some(Some(*value)),
+        (Some(value), None) | (None, Some(value)) => Some(Some(value.clone())),
         (None, None) => Some(None),
     }
 }
@@ -141,6 +296,8 @@ pub(super) fn match_game_requests(
         ),
         With<GameRequest>,
     >,
+    seeds: Query<&GameRequestSeed>,
+    registry: Res<VariantRegistry>,
 ) {
     let mut matched_entities: Vec<Entity> = vec![];
 
@@ -155,8 +312,29 @@ pub(super) fn match_game_requests(
 
         // because of the way the combinations are sorted, we know that this is still greedy even though we check xor first
         let variant = combine_equal(variant1, variant2);
-        let clock = combine_equal(clock1, clock2);
+        let clock = combine_clocks(clock1, clock2);
         if let (Some(variant), Some(clock)) = (variant, clock) {
+            let variant = variant.unwrap_or(GameRequestVariant::FeaturedGameOne);
+            // either player may have asked to replay a seed, but not two different seeds
+            let seed = if variant.is_random() {
+                combine_equal(seeds.get(entity1).ok(), seeds.get(entity2).ok())
+            } else {
+                Some(None)
+            };
+            let Some(seed) = seed.map(|seed| seed.map(|seed| seed.0)) else {
+                continue;
+            };
+            let spawn_game = match variant.spawn_game(seed, &registry) {
+                Ok(spawn_game) => spawn_game,
+                Err(_error) => {
+                    #[cfg(feature = "log")]
+                    bevy_log::warn!(
+                        "Failed to start online game for players {entity1:?} and {entity2:?}: {_error}"
+                    );
+                    continue;
+                }
+            };
+
             #[cfg(feature = "log")]
             bevy_log::info!(
                 "Starting online game for players {:?} and {:?}",
@@ -167,12 +345,9 @@ pub(super) fn match_game_requests(
             matched_entities.push(entity1);
             matched_entities.push(entity2);
 
-            let pieces = variant
-                .unwrap_or(GameRequestVariant::FeaturedGameOne)
-                .piece_set();
-            let spawn_game = SpawnGame::new(pieces)
+            let spawn_game = spawn_game
                 .with_players(entity1, entity2)
-                .with_clock(clock.map(|requested_clock| requested_clock.to_clock()));
+                .with_clock(clock.map(|requested_clock| variant.clock(requested_clock, &registry)));
             commands.trigger(spawn_game);
         }
     }
@@ -185,7 +360,7 @@ pub(super) fn match_game_requests(
 pub(super) fn despawn_empty_games(
     mut commands: Commands,
     games: Query<Entity, With<Game>>,
-    players: Query<&InGame, With<Player>>,
+    players: Query<&InGame, (With<Player>, Without<Bot>)>,
 ) {
     for game in games.iter() {
         if !players.iter().any(|in_game| in_game.0 == game) {
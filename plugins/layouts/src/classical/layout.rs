@@ -1,12 +1,16 @@
 use chess::{
     behavior::{CastlingBehavior, CastlingTarget, EnPassantBehavior, PieceBehaviors},
     board::{File, Rank, Square},
+    fen::Legend,
     pieces::{Mutation, MutationCondition, PieceDefinition, PieceIdentity, Royal},
 };
 
 use super::pieces;
 
-use crate::PieceSpecification;
+use crate::{
+    shuffle::{shuffled_files, BackRankSlot, ShuffleRules},
+    PieceSpecification,
+};
 
 pub struct ClassicalLayout;
 
@@ -40,6 +44,52 @@ impl ClassicalLayout {
             )
             .collect()
     }
+
+    // Chess960 (Fischer Random Chess) by its standard position number, 0 to 959.
+    // Position 518 is the classical setup.
+    pub fn fischer_random_pieces(position: usize) -> Vec<PieceSpecification> {
+        shuffled_files(position, ShuffleRules::CHESS960)
+            .map(|(file, slot)| {
+                let piece = match slot {
+                    BackRankSlot::Major => rook(),
+                    BackRankSlot::Minor1 => knight(),
+                    BackRankSlot::Minor2 => bishop(),
+                    BackRankSlot::Elite => queen(),
+                    BackRankSlot::King => king(),
+                };
+                PieceSpecification::new(piece, Square::new(file, Rank::ONE))
+            })
+            .chain(
+                (0..8)
+                    .map(File::from)
+                    .map(|file| PieceSpecification::new(pawn(), Square::new(file, Rank::TWO))),
+            )
+            .collect()
+    }
+
+    // The usual letters of the classical pieces, for position strings
+    pub fn legend() -> Legend {
+        Legend::default()
+            .with_piece('K', king())
+            .with_piece('Q', queen())
+            .with_piece('R', rook())
+            .with_piece('B', bishop())
+            .with_piece('N', knight())
+            .with_piece('P', pawn())
+    }
+
+    // A classical piece by its lower case name, such as `knight`
+    pub fn named_piece(name: &str) -> Option<PieceDefinition> {
+        Some(match name {
+            "king" => king(),
+            "queen" => queen(),
+            "rook" => rook(),
+            "bishop" => bishop(),
+            "knight" => knight(),
+            "pawn" => pawn(),
+            _ => return None,
+        })
+    }
 }
 
 fn king() -> PieceDefinition {
@@ -94,3 +144,116 @@ fn bishop() -> PieceDefinition {
 fn queen() -> PieceDefinition {
     PieceDefinition::new(pieces::queen().into(), PieceIdentity::Queen)
 }
+
+#[cfg(test)]
+mod tests {
+    use chess::{board::Board, position::Position, team::Team};
+
+    use super::*;
+
+    fn classical_position() -> Position {
+        let board = Board::chess_board();
+        let mut position = Position::new(board);
+        for team in [Team::White, Team::Black] {
+            for PieceSpecification {
+                piece,
+                start_square,
+            } in ClassicalLayout::pieces()
+            {
+                let square = start_square.reorient(team.orientation(), &board);
+                position.add_piece(piece, square, team);
+            }
+        }
+        position
+    }
+
+    #[test]
+    fn test_perft() {
+        let mut position = classical_position();
+        assert_eq!(position.perft(1), 20);
+        assert_eq!(position.perft(2), 400);
+        assert_eq!(position.perft(3), 8902);
+    }
+
+    #[test]
+    #[ignore = "slow without optimizations"]
+    fn test_perft_depth_4() {
+        assert_eq!(classical_position().perft(4), 197281);
+    }
+
+    // A position with White to move, from its ranks listed from the eighth to the first.
+    // Only the kings of the `castling` teams keep their castling rights.
+    fn position_of(ranks: [&str; 8], castling: &[Team]) -> Position {
+        let mut position = Position::new(Board::chess_board());
+        for (index, rank) in ranks.into_iter().enumerate() {
+            for (file, symbol) in rank.chars().enumerate() {
+                let team = if symbol.is_ascii_uppercase() {
+                    Team::White
+                } else {
+                    Team::Black
+                };
+                let mut piece = match symbol.to_ascii_lowercase() {
+                    'k' => king(),
+                    'q' => queen(),
+                    'r' => rook(),
+                    'b' => bishop(),
+                    'n' => knight(),
+                    'p' => pawn(),
+                    _ => continue,
+                };
+                if !castling.contains(&team) {
+                    piece.behaviors.castling = None;
+                }
+                let square = Square::new(File(file as u16), Rank(7 - index as u16));
+                position.add_piece(piece, square, team);
+            }
+        }
+        position
+    }
+
+    fn kiwipete() -> Position {
+        position_of(
+            [
+                "r...k..r", "p.ppqpb.", "bn..pnp.", "...PN...", ".p..P...", "..N..Q.p", "PPPBBPPP",
+                "R...K..R",
+            ],
+            &[Team::White, Team::Black],
+        )
+    }
+
+    // Positions from the Chess Programming Wiki, covering castling, en passant and promotion
+    #[test]
+    fn test_perft_positions() {
+        let mut kiwipete = kiwipete();
+        assert_eq!(kiwipete.perft(1), 48);
+        assert_eq!(kiwipete.perft(2), 2039);
+
+        let mut endgame = position_of(
+            [
+                "........", "..p.....", "...p....", "KP.....r", ".R...p.k", "........", "....P.P.",
+                "........",
+            ],
+            &[],
+        );
+        assert_eq!(endgame.perft(1), 14);
+        assert_eq!(endgame.perft(2), 191);
+        assert_eq!(endgame.perft(3), 2812);
+
+        let mut promotions = position_of(
+            [
+                "r...k..r", "Pppp.ppp", ".b...nbN", "nP......", "BBP.P...", "q....N..", "Pp.P..PP",
+                "R..Q.RK.",
+            ],
+            &[Team::Black],
+        );
+        assert_eq!(promotions.perft(1), 6);
+        assert_eq!(promotions.perft(2), 264);
+        assert_eq!(promotions.perft(3), 9467);
+    }
+
+    #[test]
+    #[ignore = "slow without optimizations"]
+    fn test_perft_kiwipete_depth_3() {
+        assert_eq!(kiwipete().perft(3), 97862);
+    }
+}
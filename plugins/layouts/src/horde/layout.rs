@@ -0,0 +1,33 @@
+use chess::{
+    board::{File, Rank, Square},
+    pattern::Pattern,
+    pieces::PieceDefinition,
+};
+
+use crate::{classical::pieces, ClassicalLayout, PieceSpecification};
+
+pub struct HordeLayout;
+
+impl HordeLayout {
+    // The horde, played by White. Black plays `ClassicalLayout::pieces`.
+    pub fn pieces() -> Vec<PieceSpecification> {
+        [Rank::ONE, Rank::TWO, Rank::THREE, Rank::FOUR]
+            .into_iter()
+            .flat_map(|rank| (0..8).map(move |file| Square::new(File::from(file), rank)))
+            .chain(
+                [File::B, File::C, File::F, File::G]
+                    .into_iter()
+                    .map(|file| Square::new(file, Rank::FIVE)),
+            )
+            .map(|square| PieceSpecification::new(pawn(), square))
+            .collect()
+    }
+}
+
+fn pawn() -> PieceDefinition {
+    let mut pawn = ClassicalLayout::named_piece("pawn").expect("pawn is a classical piece");
+    pawn.behaviors.pattern = Some(
+        pieces::pawn().with_pattern(Pattern::forward().range(2).only_from_local_rank(Rank::ONE)),
+    );
+    pawn
+}
@@ -0,0 +1,6 @@
+// Horde chess: White fields 36 pawns and no king against the classical army.
+// White loses once all of its pawns are captured.
+// N.B. Pawns on the first rank may also advance two squares, as in the usual rules.
+
+mod layout;
+pub use layout::HordeLayout;
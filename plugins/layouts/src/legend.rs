@@ -0,0 +1,126 @@
+use thiserror::Error;
+
+use chess::{
+    betza::{self, BetzaError},
+    fen::Legend,
+    pieces::{PieceDefinition, PieceIdentity, Royal},
+};
+
+use crate::ClassicalLayout;
+
+#[derive(Debug, Error)]
+pub enum LegendError {
+    #[error("Legend entries look like `A=NB`, not `{0}`")]
+    InvalidEntry(String),
+    #[error("`{0}` is not a piece name or a Betza string: {1}")]
+    InvalidPiece(String, #[source] BetzaError),
+    #[error("`{0}` is not a piece identity")]
+    InvalidIdentity(String),
+}
+
+// Reads a legend from entries such as `A=NB, M=queen, X=fmWfcF:pawn`, separated by commas or
+// whitespace. Pieces are the classical piece names or Betza strings.
+// Betza pieces take their identity from a name after a colon, or else from their letter when it
+// is a classical letter, falling back to a queen. A king identity also makes the piece royal.
+pub fn parse_legend(text: &str) -> Result<Legend, LegendError> {
+    let mut legend = Legend::default();
+    for entry in text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+    {
+        let invalid = || LegendError::InvalidEntry(entry.to_string());
+        let (symbol, piece) = entry.split_once('=').ok_or_else(invalid)?;
+        let mut symbol_chars = symbol.chars();
+        let (Some(symbol), None) = (symbol_chars.next(), symbol_chars.next()) else {
+            return Err(invalid());
+        };
+        if !symbol.is_ascii_alphabetic() {
+            return Err(invalid());
+        }
+        legend = legend.with_piece(symbol, parse_piece(symbol, piece)?);
+    }
+    Ok(legend)
+}
+
+fn parse_piece(symbol: char, text: &str) -> Result<PieceDefinition, LegendError> {
+    let (text, identity) = match text.split_once(':') {
+        Some((text, identity)) => (text, Some(identity)),
+        None => (text, None),
+    };
+    if identity.is_none() {
+        if let Some(piece) = ClassicalLayout::named_piece(text) {
+            return Ok(piece);
+        }
+    }
+
+    let behavior =
+        betza::parse(text).map_err(|error| LegendError::InvalidPiece(text.to_string(), error))?;
+    let identity = match identity {
+        Some(name) => identity_from_name(name)
+            .ok_or_else(|| LegendError::InvalidIdentity(name.to_string()))?,
+        None => identity_from_symbol(symbol),
+    };
+    Ok(PieceDefinition {
+        royal: (identity == PieceIdentity::King).then_some(Royal),
+        ..PieceDefinition::new(behavior.into(), identity)
+    })
+}
+
+fn identity_from_name(name: &str) -> Option<PieceIdentity> {
+    Some(match name {
+        "king" => PieceIdentity::King,
+        "queen" => PieceIdentity::Queen,
+        "rook" => PieceIdentity::Rook,
+        "bishop" => PieceIdentity::Bishop,
+        "knight" => PieceIdentity::Knight,
+        "pawn" => PieceIdentity::Pawn,
+        _ => return None,
+    })
+}
+
+fn identity_from_symbol(symbol: char) -> PieceIdentity {
+    match symbol.to_ascii_uppercase() {
+        'K' => PieceIdentity::King,
+        'R' => PieceIdentity::Rook,
+        'B' => PieceIdentity::Bishop,
+        'N' => PieceIdentity::Knight,
+        'P' => PieceIdentity::Pawn,
+        _ => PieceIdentity::Queen,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_legend() {
+        let legend = parse_legend("K=king, a=NB M=fmWfcF:pawn").unwrap();
+        assert_eq!(
+            legend.piece('k'),
+            ClassicalLayout::named_piece("king").as_ref()
+        );
+        let archbishop = legend.piece('A').unwrap();
+        assert_eq!(archbishop.identity, PieceIdentity::Queen);
+        assert_eq!(
+            archbishop
+                .behaviors
+                .pattern
+                .as_ref()
+                .unwrap()
+                .patterns
+                .len(),
+            2
+        );
+        assert_eq!(legend.piece('M').unwrap().identity, PieceIdentity::Pawn);
+
+        assert!(matches!(
+            parse_legend("AB=N"),
+            Err(LegendError::InvalidEntry(_))
+        ));
+        assert!(matches!(
+            parse_legend("A=dragon"),
+            Err(LegendError::InvalidPiece(..))
+        ));
+    }
+}
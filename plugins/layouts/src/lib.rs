@@ -4,12 +4,21 @@ use chess::{board::Square, pieces::PieceDefinition};
 
 mod classical;
 pub use classical::ClassicalLayout;
+mod horde;
+pub use horde::HordeLayout;
 mod knight_relay;
 pub use knight_relay::KnightRelayLayout;
+mod legend;
+pub use legend::{parse_legend, LegendError};
+pub mod shuffle;
+pub use shuffle::ShuffleRules;
 mod super_relay;
 pub use super_relay::SuperRelayLayout;
 mod wild;
-pub use wild::{ClassicWildLayout, FeaturedWildLayout, RandomWildLayout, WildPieceSet};
+pub use wild::{
+    army_value, random_seed, wild_rng, ClassicWildLayout, FeaturedWildLayout, RandomWildLayout,
+    WildConfig, WildPieceSet, WildPromotion, WildRng, WildTiers,
+};
 
 // Defines how to position a piece relative to a player's starting orientation
 #[derive(Clone, Debug, Default)]
@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "reflect")]
+use bevy_reflect::Reflect;
+
+use chess::board::File;
+
+// The "slots" of a back rank that can be shuffled.
+// These follow the naming of `WildPieceSet`, where the classical equivalents are
+// Major: rook, Minor1: knight, Minor2: bishop, Elite: queen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BackRankSlot {
+    Major,
+    Minor1,
+    Minor2,
+    Elite,
+    King,
+}
+
+// Constraints applied when shuffling a back rank.
+// With every rule enabled this describes Chess960 (Fischer Random Chess).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct ShuffleRules {
+    // the royal piece is placed somewhere between the two castling targets (Major pieces)
+    pub royal_between_castling_targets: bool,
+    // the two Minor2 pieces are placed on squares of opposite colors
+    // (classically, this keeps one bishop on each color)
+    pub minor2_opposite_colors: bool,
+}
+
+impl ShuffleRules {
+    pub const CHESS960: Self = ShuffleRules {
+        royal_between_castling_targets: true,
+        minor2_opposite_colors: true,
+    };
+
+    // The number of distinct positions available under these rules.
+    // Position numbers beyond this count wrap around.
+    pub fn position_count(&self) -> usize {
+        self.minor2_choices() * ELITE_CHOICES * MINOR1_CHOICES * self.royal_choices()
+    }
+
+    fn minor2_choices(&self) -> usize {
+        if self.minor2_opposite_colors {
+            // one of 4 light squares and one of 4 dark squares
+            16
+        } else {
+            // any pair of the 8 files
+            28
+        }
+    }
+
+    fn royal_choices(&self) -> usize {
+        if self.royal_between_castling_targets {
+            1
+        } else {
+            3
+        }
+    }
+}
+
+// the elite piece takes one of the 6 files left after placing Minor2
+const ELITE_CHOICES: usize = 6;
+// the Minor1 pair takes two of the 5 remaining files
+const MINOR1_CHOICES: usize = 10;
+
+// Builds a back rank (from the A file to the H file) for the given position number.
+//
+// Positions are numbered following Scharnagl's scheme for Chess960, so that with
+// `ShuffleRules::CHESS960` position 518 is the classical setup (RNBQKBNR).
+// Relaxed rules extend the same scheme with additional choices for Minor2 and the King.
+pub fn shuffled_back_rank(position: usize, rules: ShuffleRules) -> [BackRankSlot; 8] {
+    let mut slots: [Option<BackRankSlot>; 8] = [None; 8];
+    let mut n = position % rules.position_count();
+
+    // first place Minor2
+    if rules.minor2_opposite_colors {
+        // light squares (b, d, f, h) first, then dark squares (a, c, e, g)
+        let light = n % 4;
+        n /= 4;
+        let dark = n % 4;
+        n /= 4;
+        slots[light * 2 + 1] = Some(BackRankSlot::Minor2);
+        slots[dark * 2] = Some(BackRankSlot::Minor2);
+    } else {
+        let (first, second) = nth_pair(n % 28, 8);
+        n /= 28;
+        slots[first] = Some(BackRankSlot::Minor2);
+        slots[second] = Some(BackRankSlot::Minor2);
+    }
+
+    // then the elite piece on one of the remaining files
+    let elite = n % ELITE_CHOICES;
+    n /= ELITE_CHOICES;
+    place_on_nth_empty(&mut slots, elite, BackRankSlot::Elite);
+
+    // then both Minor1 pieces
+    let (first, second) = nth_pair(n % MINOR1_CHOICES, 5);
+    n /= MINOR1_CHOICES;
+    // place the later one first so that the earlier index is unaffected
+    place_on_nth_empty(&mut slots, second, BackRankSlot::Minor1);
+    place_on_nth_empty(&mut slots, first, BackRankSlot::Minor1);
+
+    // finally the king and castling targets on the last three files
+    let royal = if rules.royal_between_castling_targets {
+        1
+    } else {
+        n % 3
+    };
+    place_on_nth_empty(&mut slots, royal, BackRankSlot::King);
+    while slots.iter().any(Option::is_none) {
+        place_on_nth_empty(&mut slots, 0, BackRankSlot::Major);
+    }
+
+    slots.map(|slot| slot.unwrap())
+}
+
+// Iterates the (File, BackRankSlot) pairs of a shuffled back rank
+pub fn shuffled_files(
+    position: usize,
+    rules: ShuffleRules,
+) -> impl Iterator<Item = (File, BackRankSlot)> {
+    shuffled_back_rank(position, rules)
+        .into_iter()
+        .enumerate()
+        .map(|(file, slot)| (File(file as u16), slot))
+}
+
+// Finds the pair `(a, b)` with `a < b < count` at `index` in lexicographic order.
+// For `count = 5` this matches the knight table of Scharnagl's numbering.
+fn nth_pair(mut index: usize, count: usize) -> (usize, usize) {
+    for first in 0..count {
+        let remaining = count - first - 1;
+        if index < remaining {
+            return (first, first + 1 + index);
+        }
+        index -= remaining;
+    }
+    unreachable!("pair index out of range");
+}
+
+fn place_on_nth_empty(slots: &mut [Option<BackRankSlot>; 8], n: usize, slot: BackRankSlot) {
+    if let Some(empty) = slots.iter_mut().filter(|slot| slot.is_none()).nth(n) {
+        *empty = Some(slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn to_string(slots: [BackRankSlot; 8]) -> String {
+        slots
+            .into_iter()
+            .map(|slot| match slot {
+                BackRankSlot::Major => 'R',
+                BackRankSlot::Minor1 => 'N',
+                BackRankSlot::Minor2 => 'B',
+                BackRankSlot::Elite => 'Q',
+                BackRankSlot::King => 'K',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scharnagl_numbering() {
+        let rules = ShuffleRules::CHESS960;
+        assert_eq!(to_string(shuffled_back_rank(0, rules)), "BBQNNRKR");
+        assert_eq!(to_string(shuffled_back_rank(518, rules)), "RNBQKBNR");
+        assert_eq!(to_string(shuffled_back_rank(959, rules)), "RKRNNQBB");
+    }
+
+    #[test]
+    fn test_chess960_positions_are_distinct_and_valid() {
+        let rules = ShuffleRules::CHESS960;
+        assert_eq!(rules.position_count(), 960);
+
+        let positions = (0..960)
+            .map(|position| shuffled_back_rank(position, rules))
+            .collect::<HashSet<_>>();
+        assert_eq!(positions.len(), 960);
+
+        for slots in positions {
+            let find = |kind: BackRankSlot| {
+                slots
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, slot)| **slot == kind)
+                    .map(|(file, _)| file)
+                    .collect::<Vec<_>>()
+            };
+            let bishops = find(BackRankSlot::Minor2);
+            assert_ne!(bishops[0] % 2, bishops[1] % 2);
+            let rooks = find(BackRankSlot::Major);
+            let king = find(BackRankSlot::King)[0];
+            assert!(rooks[0] < king && king < rooks[1]);
+        }
+    }
+
+    #[test]
+    fn test_relaxed_rules() {
+        let rules = ShuffleRules::default();
+        assert_eq!(rules.position_count(), 5040);
+
+        let positions = (0..rules.position_count())
+            .map(|position| shuffled_back_rank(position, rules))
+            .collect::<HashSet<_>>();
+        assert_eq!(positions.len(), 5040);
+    }
+}
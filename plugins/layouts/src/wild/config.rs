@@ -0,0 +1,135 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "reflect")]
+use bevy_reflect::Reflect;
+
+use chess::behavior::PatternBehavior;
+
+use super::pieces::{
+    AdvancedBuilder, EliteBuilder, InfantryBuilder, LegendaryBuilder, MajorBuilder, MinorBuilder,
+};
+
+// The classes of behaviors that wild pieces may be drawn from, weakest first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct WildTiers {
+    pub infantry: bool,
+    pub minor: bool,
+    pub advanced: bool,
+    pub major: bool,
+    pub elite: bool,
+    pub legendary: bool,
+}
+
+impl Default for WildTiers {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl WildTiers {
+    pub const ALL: Self = WildTiers {
+        infantry: true,
+        minor: true,
+        advanced: true,
+        major: true,
+        elite: true,
+        legendary: true,
+    };
+
+    // A behavior from any of the allowed classes, or from any class if none are allowed
+    pub fn random_behavior(&self, rng: &mut impl Rng) -> PatternBehavior {
+        let allowed = [
+            self.infantry,
+            self.minor,
+            self.advanced,
+            self.major,
+            self.elite,
+            self.legendary,
+        ];
+        let mut tiers = (0..allowed.len())
+            .filter(|tier| allowed[*tier])
+            .collect::<Vec<_>>();
+        if tiers.is_empty() {
+            tiers = (0..allowed.len()).collect();
+        }
+        match tiers[rng.gen_range(0..tiers.len())] {
+            0 => InfantryBuilder::random_behavior(rng),
+            1 => MinorBuilder::random_behavior(rng),
+            2 => AdvancedBuilder::random_behavior(rng),
+            3 => MajorBuilder::random_behavior(rng),
+            4 => EliteBuilder::random_behavior(rng),
+            _ => LegendaryBuilder::random_behavior(rng),
+        }
+    }
+}
+
+// The pieces that wild pawns promote to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum WildPromotion {
+    // any of the distinct pieces of the army
+    #[default]
+    Army,
+    // only the strongest piece of the army
+    Elite,
+    // the classical queen, rook, bishop and knight
+    Classical,
+    // pawns do not promote
+    None,
+}
+
+// Describes how random wild armies are generated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct WildConfig {
+    // The range of the total value of the pieces other than the king and pawns,
+    // in percent of the value of the classical army
+    pub min_budget: u16,
+    pub max_budget: u16,
+    pub tiers: WildTiers,
+    // otherwise pawns and kings move as they do classically
+    pub random_pawns: bool,
+    pub random_kings: bool,
+    // How many different pieces make up the back rank, from 1 to 4.
+    // With fewer than 4, the weaker slots repeat the weakest generated piece.
+    pub slots: u8,
+    pub promotion: WildPromotion,
+}
+
+impl Default for WildConfig {
+    fn default() -> Self {
+        WildConfig {
+            min_budget: 90,
+            max_budget: 110,
+            tiers: WildTiers::ALL,
+            random_pawns: true,
+            random_kings: true,
+            slots: 4,
+            promotion: WildPromotion::Army,
+        }
+    }
+}
+
+impl WildConfig {
+    pub const MAX_SLOTS: u8 = 4;
+
+    pub fn slot_count(&self) -> usize {
+        usize::from(self.slots.clamp(1, Self::MAX_SLOTS))
+    }
+
+    // The army value to aim for and how far from it an army may be,
+    // given the value of the classical army
+    pub fn budget(&self, classical_value: i32) -> (i32, i32) {
+        let min = i32::from(self.min_budget.min(self.max_budget));
+        let max = i32::from(self.min_budget.max(self.max_budget));
+        (
+            classical_value * (min + max) / 200,
+            classical_value * (max - min) / 200,
+        )
+    }
+}
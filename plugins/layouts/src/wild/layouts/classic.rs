@@ -1,10 +1,15 @@
+use rand::Rng;
+
 use chess::{
     behavior::CastlingTarget,
     pieces::{PieceDefinition, PieceIdentity},
 };
 
 use crate::{
-    wild::pieces::{AdvancedBuilder, EliteBuilder, MajorBuilder, MinorBuilder, PieceBuilder},
+    wild::{
+        pieces::{AdvancedBuilder, EliteBuilder, MajorBuilder, MinorBuilder, PieceBuilder},
+        wild_rng,
+    },
     PieceSpecification,
 };
 
@@ -13,36 +18,47 @@ use super::{king, pawn, piece, WildPieceSet};
 pub struct ClassicWildLayout;
 
 impl ClassicWildLayout {
-    pub fn pieces() -> Vec<PieceSpecification> {
+    pub fn pieces(seed: u64) -> Vec<PieceSpecification> {
+        Self::piece_set(&mut wild_rng(seed)).build_layout()
+    }
+
+    pub fn piece_set(rng: &mut impl Rng) -> WildPieceSet {
         // pieces
         let major: PieceDefinition = piece(
-            MajorBuilder::random_behavior(),
+            MajorBuilder::random_behavior(rng),
             PieceIdentity::Rook,
             Some(CastlingTarget),
         );
-        let minor1 = piece(MinorBuilder::random_behavior(), PieceIdentity::Knight, None);
+        let minor1 = piece(
+            MinorBuilder::random_behavior(rng),
+            PieceIdentity::Knight,
+            None,
+        );
         let minor2 = piece(
-            AdvancedBuilder::random_behavior(),
+            AdvancedBuilder::random_behavior(rng),
             PieceIdentity::Bishop,
             None,
         );
-        let elite = piece(EliteBuilder::random_behavior(), PieceIdentity::Queen, None);
+        let elite = piece(
+            EliteBuilder::random_behavior(rng),
+            PieceIdentity::Queen,
+            None,
+        );
 
         // pawns
         let pawn_promotion_options =
             vec![major.clone(), minor1.clone(), minor2.clone(), elite.clone()];
-        let pawn = pawn(PieceBuilder::generate_pawn(), pawn_promotion_options);
+        let pawn = pawn(PieceBuilder::generate_pawn(rng), pawn_promotion_options);
         // king
-        let king = king(PieceBuilder::generate_king());
+        let king = king(PieceBuilder::generate_king(rng));
 
-        let piece_set = WildPieceSet {
+        WildPieceSet {
             elite,
             major,
             minor1,
             minor2,
             pawn,
             king,
-        };
-        piece_set.build_layout()
+        }
     }
 }
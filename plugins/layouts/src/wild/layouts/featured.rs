@@ -4,6 +4,7 @@ use chess::{
 };
 
 use crate::{
+    shuffle::ShuffleRules,
     wild::pieces::{
         AdvancedBuilder, EliteBuilder, InfantryBuilder, KingBuilder, MajorBuilder, MinorBuilder,
         PawnBuilder,
@@ -21,12 +22,19 @@ pub enum FeaturedWildLayout {
 
 impl FeaturedWildLayout {
     pub fn pieces(self) -> Vec<PieceSpecification> {
+        self.piece_set().build_layout()
+    }
+
+    pub fn shuffled_pieces(self, position: usize, rules: ShuffleRules) -> Vec<PieceSpecification> {
+        self.piece_set().build_shuffled_layout(position, rules)
+    }
+
+    pub fn piece_set(self) -> WildPieceSet {
         match self {
             FeaturedWildLayout::One => Self::one(),
             FeaturedWildLayout::Two => Self::two(),
             FeaturedWildLayout::Three => Self::three(),
         }
-        .build_layout()
     }
 
     fn one() -> WildPieceSet {
@@ -9,9 +9,12 @@ pub use classic::ClassicWildLayout;
 mod featured;
 pub use featured::FeaturedWildLayout;
 mod random;
-pub use random::RandomWildLayout;
+pub use random::{army_value, RandomWildLayout};
 
-use crate::PieceSpecification;
+use crate::{
+    shuffle::{shuffled_files, BackRankSlot, ShuffleRules},
+    PieceSpecification,
+};
 
 pub struct WildPieceSet {
     // "queen", the d file ranks 1/8
@@ -29,7 +32,7 @@ pub struct WildPieceSet {
 }
 
 impl WildPieceSet {
-    fn build_layout(self) -> Vec<PieceSpecification> {
+    pub fn build_layout(self) -> Vec<PieceSpecification> {
         [File::A, File::H]
             .into_iter()
             .map(move |file| {
@@ -52,6 +55,30 @@ impl WildPieceSet {
             }))
             .collect()
     }
+
+    // Places the back rank according to a shuffled position number instead of the
+    // classical files. Pawns remain on every file of rank 2.
+    pub fn build_shuffled_layout(
+        self,
+        position: usize,
+        rules: ShuffleRules,
+    ) -> Vec<PieceSpecification> {
+        shuffled_files(position, rules)
+            .map(|(file, slot)| {
+                let piece = match slot {
+                    BackRankSlot::Major => self.major.clone(),
+                    BackRankSlot::Minor1 => self.minor1.clone(),
+                    BackRankSlot::Minor2 => self.minor2.clone(),
+                    BackRankSlot::Elite => self.elite.clone(),
+                    BackRankSlot::King => self.king.clone(),
+                };
+                PieceSpecification::new(piece, Square::new(file, Rank::ONE))
+            })
+            .chain((0..8).map(File::from).map(|file| {
+                PieceSpecification::new(self.pawn.clone(), Square::new(file, Rank::TWO))
+            }))
+            .collect()
+    }
 }
 
 fn king(behavior: PatternBehavior) -> PieceDefinition {
@@ -70,7 +97,8 @@ fn king(behavior: PatternBehavior) -> PieceDefinition {
 fn pawn(behavior: PatternBehavior, options: Vec<PieceDefinition>) -> PieceDefinition {
     PieceDefinition {
         behaviors: behavior.into(),
-        mutation: Some(Mutation {
+        // without any options, pawns do not promote
+        mutation: (!options.is_empty()).then(|| Mutation {
             condition: MutationCondition::LocalRank(Rank::EIGHT),
             to_piece: options,
             ..Default::default()
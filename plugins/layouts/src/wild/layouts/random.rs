@@ -1,59 +1,291 @@
-use rand::{thread_rng, Rng};
+use std::sync::OnceLock;
+
+use rand::Rng;
 
 use chess::{
-    behavior::CastlingTarget,
+    behavior::{CastlingTarget, PatternBehavior},
+    board::Board,
     pieces::{PieceDefinition, PieceIdentity},
+    value::ValueEstimator,
 };
 
-use crate::{wild::pieces::PieceBuilder, PieceSpecification};
+use crate::{
+    shuffle::ShuffleRules,
+    wild::{
+        pieces::{KingBuilder, PawnBuilder},
+        wild_rng, WildConfig, WildPromotion,
+    },
+    ClassicalLayout, PieceSpecification,
+};
 
 use super::{king, pawn, piece, WildPieceSet};
 
+// Each attempt estimates the value of up to four new behaviors, and armies are generated
+// while a game is spawned, so the search gives up early and keeps the closest army.
+// With the default config, an army within the budget takes fewer than 20 attempts.
+const MAX_ATTEMPTS: usize = 32;
+
 pub struct RandomWildLayout;
 
 impl RandomWildLayout {
-    pub fn pieces() -> Vec<PieceSpecification> {
-        let mut rng = thread_rng();
-        let max_value: u32 = rng.gen_range(50..80);
-        let mut current_value: u32 = 0;
+    // The same seed always generates the same army
+    pub fn pieces(seed: u64) -> Vec<PieceSpecification> {
+        Self::configured_pieces(seed, &WildConfig::default())
+    }
+
+    pub fn configured_pieces(seed: u64, config: &WildConfig) -> Vec<PieceSpecification> {
+        Self::configured_piece_set(&mut wild_rng(seed), config).build_layout()
+    }
+
+    // Two armies from the same seed, for White and for Black
+    pub fn different_armies(
+        seed: u64,
+        config: &WildConfig,
+    ) -> (Vec<PieceSpecification>, Vec<PieceSpecification>) {
+        let mut rng = wild_rng(seed);
+        let white = Self::configured_piece_set(&mut rng, config).build_layout();
+        let black = Self::configured_piece_set(&mut rng, config).build_layout();
+        (white, black)
+    }
+
+    // The army of the seed and config, with its back rank shuffled by position number
+    pub fn shuffled_pieces(
+        seed: u64,
+        position: usize,
+        rules: ShuffleRules,
+        config: &WildConfig,
+    ) -> Vec<PieceSpecification> {
+        Self::configured_piece_set(&mut wild_rng(seed), config)
+            .build_shuffled_layout(position, rules)
+    }
+
+    // A random army about as strong as the classical army
+    pub fn piece_set(rng: &mut impl Rng) -> WildPieceSet {
+        Self::configured_piece_set(rng, &WildConfig::default())
+    }
+
+    // A random army within the budget of the config, relative to the classical army
+    pub fn configured_piece_set(rng: &mut impl Rng, config: &WildConfig) -> WildPieceSet {
+        let (target, tolerance) = config.budget(classical_army_value());
+        Self::balanced_piece_set(rng, config, target, tolerance)
+    }
+
+    // Generates armies until the total value of the pieces other than the king and pawns is
+    // within `tolerance` of `target`, keeping the closest army if none of them are.
+    // The strongest piece becomes the elite, followed by the major and the minor pieces.
+    pub fn balanced_piece_set(
+        rng: &mut impl Rng,
+        config: &WildConfig,
+        target: i32,
+        tolerance: i32,
+    ) -> WildPieceSet {
+        let board = Board::chess_board();
+        let mut values = ValueEstimator::default();
+        let mut best: Option<(i32, [PatternBehavior; 4])> = None;
+        for _ in 0..MAX_ATTEMPTS {
+            let mut behaviors = (0..config.slot_count())
+                .map(|_| config.tiers.random_behavior(rng))
+                .collect::<Vec<_>>();
+            behaviors.sort_by_cached_key(|behavior| {
+                std::cmp::Reverse(values.pattern_value(&board, behavior).value)
+            });
+            // with fewer slots, the remaining pieces repeat the weakest one
+            let behaviors: [PatternBehavior; 4] =
+                std::array::from_fn(|slot| behaviors[slot.min(behaviors.len() - 1)].clone());
+            let [elite, major, minor1, minor2] = behaviors
+                .each_ref()
+                .map(|behavior| values.pattern_value(&board, behavior).value);
+            let error = (elite + 2 * (major + minor1 + minor2) - target).abs();
+            let is_closest = match &best {
+                Some((best_error, _)) => error < *best_error,
+                None => true,
+            };
+            if is_closest {
+                best = Some((error, behaviors));
+            }
+            if error <= tolerance {
+                break;
+            }
+        }
+        let Some((_, [elite, major, minor1, minor2])) = best else {
+            unreachable!("at least one army is generated");
+        };
 
         // pieces
-        let major: PieceDefinition = piece(
-            PieceBuilder::random_behavior(max_value, &mut current_value),
-            PieceIdentity::Rook,
-            Some(CastlingTarget),
-        );
-        let minor1 = piece(
-            PieceBuilder::random_behavior(max_value, &mut current_value),
-            PieceIdentity::Knight,
-            None,
-        );
-        let minor2 = piece(
-            PieceBuilder::random_behavior(max_value, &mut current_value),
-            PieceIdentity::Bishop,
-            None,
-        );
-        let elite = piece(
-            PieceBuilder::random_behavior(max_value, &mut current_value),
-            PieceIdentity::Queen,
-            None,
-        );
+        let major: PieceDefinition = piece(major, PieceIdentity::Rook, Some(CastlingTarget));
+        let minor1 = piece(minor1, PieceIdentity::Knight, None);
+        let minor2 = piece(minor2, PieceIdentity::Bishop, None);
+        let elite = piece(elite, PieceIdentity::Queen, None);
 
         // pawns
-        let pawn_promotion_options =
-            vec![major.clone(), minor1.clone(), minor2.clone(), elite.clone()];
-        let pawn = pawn(PieceBuilder::generate_pawn(), pawn_promotion_options);
+        let pawn_promotion_options = match config.promotion {
+            WildPromotion::Army => {
+                let mut options: Vec<PieceDefinition> = vec![];
+                for option in [&major, &minor1, &minor2, &elite] {
+                    let pattern = &option.behaviors.pattern;
+                    if !options
+                        .iter()
+                        .any(|other| other.behaviors.pattern == *pattern)
+                    {
+                        options.push(option.clone());
+                    }
+                }
+                options
+            }
+            WildPromotion::Elite => vec![elite.clone()],
+            WildPromotion::Classical => ["queen", "rook", "bishop", "knight"]
+                .into_iter()
+                .filter_map(ClassicalLayout::named_piece)
+                .collect(),
+            WildPromotion::None => vec![],
+        };
+        let mut pawn = pawn(
+            if config.random_pawns {
+                PawnBuilder::random_pawn(rng)
+            } else {
+                PatternBehavior::default()
+            },
+            pawn_promotion_options,
+        );
+        if !config.random_pawns {
+            // including the double step and en passant captures
+            if let Some(classical) = ClassicalLayout::named_piece("pawn") {
+                pawn.behaviors = classical.behaviors;
+            }
+        }
         // king
-        let king = king(PieceBuilder::generate_king());
+        let king = king(if config.random_kings {
+            KingBuilder::random_king(rng)
+        } else {
+            KingBuilder::classical()
+        });
 
-        let piece_set = WildPieceSet {
+        WildPieceSet {
             elite,
             major,
             minor1,
             minor2,
             pawn,
             king,
+        }
+    }
+}
+
+// The value of the classical army, estimated once
+fn classical_army_value() -> i32 {
+    static VALUE: OnceLock<i32> = OnceLock::new();
+    *VALUE.get_or_init(|| army_value(&mut ValueEstimator::default(), &ClassicalLayout::pieces()))
+}
+
+// The total value of the pieces of a layout, other than its royal pieces and pawns
+pub fn army_value(values: &mut ValueEstimator, pieces: &[PieceSpecification]) -> i32 {
+    let board = Board::chess_board();
+    pieces
+        .iter()
+        .filter(|spec| spec.piece.royal.is_none() && spec.piece.identity != PieceIdentity::Pawn)
+        .map(|spec| values.piece_value(&board, &spec.piece))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balanced_piece_set() {
+        let mut values = ValueEstimator::default();
+        let target = army_value(&mut values, &ClassicalLayout::pieces());
+        assert_eq!(classical_army_value(), target);
+        let config = WildConfig::default();
+        let (target, tolerance) = config.budget(target);
+        let mut rng = wild_rng(0);
+        for _ in 0..3 {
+            let pieces = RandomWildLayout::balanced_piece_set(&mut rng, &config, target, tolerance)
+                .build_layout();
+            let value = army_value(&mut values, &pieces);
+            assert!(
+                (value - target).abs() <= tolerance,
+                "{value} is not within {tolerance} of {target}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_seeded_piece_set() {
+        let definitions = |pieces: Vec<PieceSpecification>| {
+            pieces
+                .into_iter()
+                .map(|spec| spec.piece)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            definitions(RandomWildLayout::pieces(37)),
+            definitions(RandomWildLayout::pieces(37))
+        );
+        // with this many piece classes, seeds that generate the same army are unlikely
+        assert!((0..4)
+            .map(|seed| definitions(RandomWildLayout::pieces(seed)))
+            .any(|pieces| pieces != definitions(RandomWildLayout::pieces(37))));
+    }
+
+    #[test]
+    fn test_configured_piece_set() {
+        let config = WildConfig {
+            random_pawns: false,
+            random_kings: false,
+            slots: 2,
+            promotion: WildPromotion::Elite,
+            ..Default::default()
         };
-        piece_set.build_layout()
+        let set = RandomWildLayout::configured_piece_set(&mut wild_rng(38), &config);
+        assert_eq!(set.minor1.behaviors.pattern, set.major.behaviors.pattern);
+        assert_eq!(set.minor2.behaviors.pattern, set.major.behaviors.pattern);
+        assert_eq!(
+            set.pawn.behaviors,
+            ClassicalLayout::named_piece("pawn").unwrap().behaviors
+        );
+        assert_eq!(
+            set.pawn.mutation.map(|mutation| mutation.to_piece),
+            Some(vec![set.elite.clone()])
+        );
+        assert_eq!(set.king.behaviors.pattern, Some(KingBuilder::classical()));
+    }
+
+    #[test]
+    fn test_configured_shuffled_pieces() {
+        let config = WildConfig {
+            random_kings: false,
+            slots: 1,
+            ..Default::default()
+        };
+        let pieces = RandomWildLayout::shuffled_pieces(38, 0, ShuffleRules::CHESS960, &config);
+        let set = RandomWildLayout::configured_piece_set(&mut wild_rng(38), &config);
+        // every piece of the back rank other than the king is the army's only piece
+        assert!(pieces
+            .iter()
+            .filter(|spec| spec.piece.royal.is_none() && spec.piece.identity != PieceIdentity::Pawn)
+            .all(|spec| spec.piece.behaviors.pattern == set.elite.behaviors.pattern));
+        assert!(pieces
+            .iter()
+            .filter(|spec| spec.piece.royal.is_some())
+            .all(|spec| spec.piece.behaviors.pattern == Some(KingBuilder::classical())));
+    }
+
+    #[test]
+    fn test_different_armies() {
+        let definitions = |pieces: Vec<PieceSpecification>| {
+            pieces
+                .into_iter()
+                .map(|spec| spec.piece)
+                .collect::<Vec<_>>()
+        };
+        let config = WildConfig::default();
+        let (white, black) = RandomWildLayout::different_armies(37, &config);
+        // White fields the army that the seed generates for both teams otherwise
+        assert_eq!(
+            definitions(white.clone()),
+            definitions(RandomWildLayout::configured_pieces(37, &config))
+        );
+        assert_ne!(definitions(white), definitions(black));
     }
 }
@@ -1,4 +1,25 @@
+mod config;
+pub use config::{WildConfig, WildPromotion, WildTiers};
 mod layouts;
-pub use layouts::{ClassicWildLayout, FeaturedWildLayout, RandomWildLayout, WildPieceSet};
+pub use layouts::{
+    army_value, ClassicWildLayout, FeaturedWildLayout, RandomWildLayout, WildPieceSet,
+};
 
 mod pieces;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// The generator behind every random wild layout.
+// Unlike `rand::rngs::StdRng`, its output for a seed does not change between platforms or
+// releases of `rand`, so a seed always generates the same army.
+pub type WildRng = ChaCha8Rng;
+
+pub fn wild_rng(seed: u64) -> WildRng {
+    WildRng::seed_from_u64(seed)
+}
+
+// A new seed for a random wild layout
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
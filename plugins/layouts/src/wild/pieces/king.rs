@@ -8,16 +8,15 @@ use chess::{
 use super::PieceBuilder;
 
 impl PieceBuilder {
-    pub fn generate_king() -> PatternBehavior {
-        KingBuilder::random_king()
+    pub fn generate_king(rng: &mut impl Rng) -> PatternBehavior {
+        KingBuilder::random_king(rng)
     }
 }
 
 pub struct KingBuilder;
 
 impl KingBuilder {
-    pub fn random_king() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
+    pub fn random_king(rng: &mut impl Rng) -> PatternBehavior {
         match rng.gen_range(0..=3) {
             0 => Self::classical(),
             1 => Self::frail(),
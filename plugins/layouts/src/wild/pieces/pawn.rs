@@ -8,17 +8,15 @@ use chess::{
 use super::PieceBuilder;
 
 impl PieceBuilder {
-    pub fn generate_pawn() -> PatternBehavior {
-        PawnBuilder::random_pawn()
+    pub fn generate_pawn(rng: &mut impl Rng) -> PatternBehavior {
+        PawnBuilder::random_pawn(rng)
     }
 }
 
 pub struct PawnBuilder;
 
 impl PawnBuilder {
-    pub fn random_pawn() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
-
+    pub fn random_pawn(rng: &mut impl Rng) -> PatternBehavior {
         match rng.gen_range(0..=5) {
             0 => Self::torpedo(),
             1 => Self::historic(),
@@ -1,34 +1,14 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 use chess::{
     behavior::PatternBehavior,
     pattern::{CapturePattern, CaptureRules, Pattern, RSymmetry, Step},
 };
 
-use super::PieceBuilder;
-
-impl PieceBuilder {
-    // TODO implement out a better strategy
-    pub fn random_behavior(max_value: u32, current_value: &mut u32) -> PatternBehavior {
-        let mut rng: rand::prelude::ThreadRng = thread_rng();
-        let new_cost = rng.gen_range(0u32..(max_value - *current_value));
-        *current_value += new_cost;
-        match new_cost {
-            0..=9 => InfantryBuilder::random_behavior(),
-            10..=19 => MinorBuilder::random_behavior(),
-            20..=29 => AdvancedBuilder::random_behavior(),
-            30..=39 => MajorBuilder::random_behavior(),
-            40..=49 => EliteBuilder::random_behavior(),
-            50..=u32::MAX => LegendaryBuilder::random_behavior(),
-        }
-    }
-}
-
 pub struct InfantryBuilder;
 
 impl InfantryBuilder {
-    pub fn random_behavior() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
+    pub fn random_behavior(rng: &mut impl Rng) -> PatternBehavior {
         match rng.gen_range(0..=4) {
             0 => Self::raven(),
             1 => Self::acolyte(),
@@ -76,8 +56,7 @@ impl InfantryBuilder {
 pub struct MinorBuilder;
 
 impl MinorBuilder {
-    pub fn random_behavior() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
+    pub fn random_behavior(rng: &mut impl Rng) -> PatternBehavior {
         match rng.gen_range(0..=6) {
             0 => Self::knight(),
             1 => Self::camel(),
@@ -155,8 +134,7 @@ impl MinorBuilder {
 pub struct AdvancedBuilder;
 
 impl AdvancedBuilder {
-    pub fn random_behavior() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
+    pub fn random_behavior(rng: &mut impl Rng) -> PatternBehavior {
         match rng.gen_range(0..=4) {
             0 => Self::bishop(),
             1 => Self::jester(),
@@ -199,8 +177,7 @@ impl AdvancedBuilder {
 pub struct MajorBuilder;
 
 impl MajorBuilder {
-    pub fn random_behavior() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
+    pub fn random_behavior(rng: &mut impl Rng) -> PatternBehavior {
         match rng.gen_range(0..=6) {
             0 => Self::rook(),
             1 => Self::cardinal(),
@@ -255,8 +232,7 @@ impl MajorBuilder {
 pub struct EliteBuilder;
 
 impl EliteBuilder {
-    pub fn random_behavior() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
+    pub fn random_behavior(rng: &mut impl Rng) -> PatternBehavior {
         match rng.gen_range(0..=4) {
             0 => Self::queen(),
             1 => Self::chancellor(),
@@ -304,8 +280,7 @@ impl EliteBuilder {
 pub struct LegendaryBuilder;
 
 impl LegendaryBuilder {
-    fn random_behavior() -> PatternBehavior {
-        let mut rng = rand::thread_rng();
+    pub fn random_behavior(rng: &mut impl Rng) -> PatternBehavior {
         #[allow(clippy::match_single_binding)]
         match rng.gen_range(0..=2) {
             _ => Self::dragon(),
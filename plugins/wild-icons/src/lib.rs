@@ -2,8 +2,8 @@ use std::marker::PhantomData;
 
 use bevy::{
     prelude::{
-        App, Changed, Commands, Component, Entity, In, IntoSystem, Local, Or, Plugin, PreUpdate,
-        Query,
+        App, Commands, Component, DetectChanges, Entity, In, IntoSystem, Local, Plugin, PreUpdate,
+        Query, Ref,
     },
     utils::HashMap,
 };
@@ -74,6 +74,7 @@ pub struct PieceIconKey {
     relays: Option<RelayBehavior>,
     team: Team,
     is_king: bool,
+    board_orientation: Orientation,
 }
 
 enum PieceIcon {
@@ -119,29 +120,42 @@ impl<System, Params> PieceIconPlugin<System, Params>
 where
     System: IntoSystem<(), Orientation, Params>,
 {
+    // Icons are drawn for the side the board is seen from,
+    // so every icon is replaced when the board turns
     #[allow(clippy::type_complexity)]
     fn attach_icons_system(
         In(board_orientation): In<Orientation>,
         mut commands: Commands,
-        piece_query: Query<
-            (
-                Entity,
-                &Team,
-                &PieceIdentity,
-                Option<&PatternBehavior>,
-                Option<&RelayBehavior>,
-                Option<&Royal>,
-            ),
-            Or<(Changed<PatternBehavior>, Changed<RelayBehavior>)>,
-        >,
+        piece_query: Query<(
+            Entity,
+            &Team,
+            &PieceIdentity,
+            Option<Ref<PatternBehavior>>,
+            Option<Ref<RelayBehavior>>,
+            Option<&Royal>,
+        )>,
         mut icons: Local<HashMap<PieceIconKey, PieceIcon>>,
+        mut last_orientation: Local<Option<Orientation>>,
     ) {
+        let turned = *last_orientation != Some(board_orientation);
+        *last_orientation = Some(board_orientation);
         for (entity, team, identity, patterns, relays, maybe_royal) in piece_query.iter() {
+            let changed = turned
+                || patterns
+                    .as_ref()
+                    .is_some_and(|patterns| patterns.is_changed())
+                || relays.as_ref().is_some_and(|relays| relays.is_changed());
+            if !changed || (patterns.is_none() && relays.is_none()) {
+                continue;
+            }
+            let patterns = patterns.as_deref();
+            let relays = relays.as_deref();
             let key = PieceIconKey {
                 patterns: patterns.cloned(),
                 relays: relays.cloned(),
                 team: *team,
                 is_king: maybe_royal.is_some(),
+                board_orientation,
             };
             let icon = if let Some(icon) = icons.get(&key) {
                 Some(icon)
@@ -27,16 +27,22 @@ pub(crate) fn wild_behavior_icon(
     </g>
 </svg>"#,
         piece_nodes(team, is_king),
-        behavior_nodes(
-            patterns,
-            match team {
-                Team::White => orientation,
-                Team::Black => orientation.flip(),
-            }
-        ),
+        behavior_nodes(patterns, team.orientation(), orientation),
     )
 }
 
+// A step of a piece facing `orientation`, as drawn on a board seen from the side of `view`,
+// with y pointing up the screen
+fn screen_step(step: (i16, i16), orientation: Orientation, view: Orientation) -> (i16, i16) {
+    let (x, y) = orientation.orient(step);
+    match view {
+        Orientation::Up => (x, y),
+        Orientation::Down => (-x, -y),
+        Orientation::Left => (y, -x),
+        Orientation::Right => (-y, x),
+    }
+}
+
 fn piece_nodes(team: Team, is_king: bool) -> String {
     if is_king {
         build_king_paths(team)
@@ -94,17 +100,13 @@ impl NodePosition {
         }
     }
 
-    pub fn calculate(step_x: i16, step_y: i16, radius: usize, orientation: Orientation) -> Self {
+    // positions a node along a step, given with y pointing up the screen
+    pub fn calculate(step_x: i16, step_y: i16, radius: usize) -> Self {
         let x: i32 = step_x.into();
         let y: i32 = step_y.into();
         let radius: i32 = radius as i32;
-        let y: i32 = y * match orientation {
-            Orientation::Up => -1,
-            Orientation::Down => 1,
-            _ => unimplemented!("Pieces on left and right are currently unimplemented"),
-        };
 
-        let dy = -(y * radius + y.signum());
+        let dy = y * radius + y.signum();
         let dx = x * radius + x.signum();
         let cy = 500 - dy * 100;
         let cx = 500 + dx * 100;
@@ -248,21 +250,27 @@ fn cross(position: &NodePosition, color_hex: &str) -> String {
     )
 }
 
-fn pattern_nodes(pattern: &Pattern, orientation: Orientation) -> String {
+fn pattern_nodes(pattern: &Pattern, orientation: Orientation, view: Orientation) -> String {
     let color_hex = match pattern.capture.map(|capture| capture.mode) {
         None => "#0000ff",
         Some(CaptureMode::CanCapture) => "#000000",
         Some(CaptureMode::MustCapture) => "#ff0000",
     };
 
-    let movements = pattern.scanner.step.movements();
+    let movements = pattern
+        .scanner
+        .step
+        .movements()
+        .into_iter()
+        .map(|step| screen_step(step, orientation, view))
+        .collect::<Vec<_>>();
     let is_en_passant = pattern
         .capture
         .is_some_and(|capture| matches!(capture.pattern, CapturePattern::CaptureInPassing));
     match pattern.scanner.range {
         None => movements
             .into_iter()
-            .map(|(x, y)| NodePosition::calculate(x, y, 1, orientation))
+            .map(|(x, y)| NodePosition::calculate(x, y, 1))
             .map(|node| {
                 let shape = arrow(&node, color_hex, is_en_passant);
                 if is_en_passant {
@@ -276,7 +284,7 @@ fn pattern_nodes(pattern: &Pattern, orientation: Orientation) -> String {
             .iter()
             .flat_map(move |(x, y)| {
                 let nodes = (1..=range.min(2))
-                    .map(|radius| NodePosition::calculate(*x, *y, radius, orientation))
+                    .map(|radius| NodePosition::calculate(*x, *y, radius))
                     .collect::<Vec<_>>();
                 let mut elements = nodes
                     .iter()
@@ -289,7 +297,7 @@ fn pattern_nodes(pattern: &Pattern, orientation: Orientation) -> String {
                     })
                     .collect::<Vec<_>>();
                 if range == 3 {
-                    let node: NodePosition = NodePosition::calculate(*x, *y, 3, orientation);
+                    let node: NodePosition = NodePosition::calculate(*x, *y, 3);
                     if is_en_passant {
                         elements.push(cross(&node, color_hex));
                     } else {
@@ -297,7 +305,7 @@ fn pattern_nodes(pattern: &Pattern, orientation: Orientation) -> String {
                     }
                 }
                 if range > 3 {
-                    let node: NodePosition = NodePosition::calculate(*x, *y, 3, orientation);
+                    let node: NodePosition = NodePosition::calculate(*x, *y, 3);
                     elements.push(circle_range_text(&node, range));
                 }
                 elements
@@ -308,10 +316,10 @@ fn pattern_nodes(pattern: &Pattern, orientation: Orientation) -> String {
 }
 
 // builds a set of symbols to decorate the piece tile with patterns that describe its behavior options
-fn behavior_nodes(patterns: &[Pattern], orientation: Orientation) -> String {
+fn behavior_nodes(patterns: &[Pattern], orientation: Orientation, view: Orientation) -> String {
     patterns
         .iter()
-        .map(|pattern| pattern_nodes(pattern, orientation))
+        .map(|pattern| pattern_nodes(pattern, orientation, view))
         .collect::<Vec<_>>()
         .join("\n")
 }